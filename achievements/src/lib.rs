@@ -0,0 +1,256 @@
+pub mod tracker;
+
+mod list;
+
+use std::collections::HashSet;
+
+use catcoin::{grant_catcoin, ledger::Reason, random_pulls::Rarity, Game};
+use chrono::{DateTime, Utc};
+use poise::serenity_prelude::UserId;
+use sqlx::{MySql, Pool, QueryBuilder};
+
+use common::{util::hhmmss, Error};
+
+pub use list::ACHIEVEMENTS;
+
+/// Where an achievement's progress is read from
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Source {
+    /// seconds spent seeding, for users with a linked steam
+    Seeding,
+    /// catcoin rewards pulled, optionally of only one rarity
+    Pulls(Option<Rarity>),
+    /// dominations over other players, for users with a linked steam
+    Dominations,
+    /// messages sent, ever
+    Yaps,
+    /// likes on the user's profile
+    Likes,
+    /// wins in a catcoin game
+    Wins(Game),
+    /// pugs won
+    PugWins,
+    /// catcoin currently held
+    Catcoin,
+}
+
+impl Source {
+    /// a query selecting every user's `uid` and progress `value`, and its bind if any
+    fn query(&self) -> (&'static str, Option<String>) {
+        match self {
+            Source::Seeding => (
+                "SELECT p.`uid`, CAST(s.`seconds_seeded` AS SIGNED) AS `value` FROM `profiles` p JOIN `seederboard` s ON s.`steamid` = p.`steamid`",
+                None,
+            ),
+            Source::Pulls(None) => (
+                "SELECT `uid`, COUNT(*) AS `value` FROM `catcoin_inv` GROUP BY `uid`",
+                None,
+            ),
+            Source::Pulls(Some(rarity)) => (
+                "SELECT i.`uid`, COUNT(*) AS `value` FROM `catcoin_inv` i JOIN `catcoin_reward` r ON r.`id` = i.`rid` WHERE r.`rarity` = ? GROUP BY i.`uid`",
+                Some(rarity.to_string()),
+            ),
+            Source::Dominations => (
+                "SELECT p.`uid`, CAST(SUM(r.`dominations`) AS SIGNED) AS `value` FROM `profiles` p JOIN (
+					SELECT `lt_steamid` AS `steamid`, `lt_dominations` AS `dominations` FROM `rivalries`
+					UNION ALL
+					SELECT `gt_steamid`, `gt_dominations` FROM `rivalries`
+				) r ON r.`steamid` = p.`steamid` GROUP BY p.`uid`",
+                None,
+            ),
+            Source::Yaps => (
+                "SELECT `uid`, CAST(`lifetime` AS SIGNED) AS `value` FROM `yapawards`",
+                None,
+            ),
+            Source::Likes => (
+                "SELECT `profile_uid` AS `uid`, CAST(`likes` AS SIGNED) AS `value` FROM `profile_votes_aggregate`",
+                None,
+            ),
+            Source::Wins(game) => (
+                "SELECT `uid`, CAST(`wins` AS SIGNED) AS `value` FROM `game_wins` WHERE `game` = ?",
+                Some(game.as_str().to_owned()),
+            ),
+            Source::PugWins => (
+                "SELECT `uid`, CAST(`wins` AS SIGNED) AS `value` FROM `pug_ratings`",
+                None,
+            ),
+            Source::Catcoin => (
+                "SELECT `uid`, CAST(`catcoin` AS SIGNED) AS `value` FROM `catcoin`",
+                None,
+            ),
+        }
+    }
+
+    /// every user's progress
+    pub async fn values(&self, pool: &Pool<MySql>) -> Result<Vec<(String, i64)>, Error> {
+        let (sql, bind) = self.query();
+        let mut q = sqlx::query_as::<_, (String, i64)>(sql);
+        if let Some(bind) = bind {
+            q = q.bind(bind);
+        }
+        Ok(q.fetch_all(pool).await?)
+    }
+
+    /// one user's progress
+    pub async fn value(&self, pool: &Pool<MySql>, uid: UserId) -> Result<i64, Error> {
+        let (sql, bind) = self.query();
+        let sql = format!("SELECT `value` FROM ({sql}) t WHERE t.`uid` = ?");
+        let mut q = sqlx::query_scalar::<_, Option<i64>>(&sql);
+        if let Some(bind) = bind {
+            q = q.bind(bind);
+        }
+        let value = q.bind(uid.to_string()).fetch_optional(pool).await?;
+        Ok(value.flatten().unwrap_or(0))
+    }
+
+    /// formats a progress value for display
+    pub fn format(&self, value: i64) -> String {
+        match self {
+            Source::Seeding => hhmmss(value.max(0) as u64),
+            _ => value.to_string(),
+        }
+    }
+}
+
+/// An unlockable achievement
+#[derive(Debug)]
+pub struct Achievement {
+    /// stored in the db
+    pub id: &'static str,
+    pub name: &'static str,
+    pub description: &'static str,
+    pub emoji: &'static str,
+    pub source: Source,
+    /// the progress needed to unlock
+    pub threshold: i64,
+    /// catcoin granted on unlock
+    pub reward: u64,
+}
+
+impl Achievement {
+    pub fn get(id: &str) -> Option<&'static Achievement> {
+        ACHIEVEMENTS.iter().find(|a| a.id == id)
+    }
+
+    /// `emoji **name**`
+    pub fn title(&self) -> String {
+        format!("{} **{}**", self.emoji, self.name)
+    }
+}
+
+/// An achievement a user has unlocked
+#[derive(Clone, Debug)]
+pub struct Unlock {
+    pub achievement: &'static Achievement,
+    pub unlocked_at: DateTime<Utc>,
+}
+
+/// the achievements a user has unlocked, oldest first
+pub async fn get_unlocked(pool: &Pool<MySql>, uid: UserId) -> Result<Vec<Unlock>, Error> {
+    let rows: Vec<(String, DateTime<Utc>)> = sqlx::query_as(
+        "SELECT `achievement`, `unlocked_at` FROM `achievements` WHERE `uid` = ? ORDER BY `unlocked_at` ASC",
+    )
+    .bind(uid.to_string())
+    .fetch_all(pool)
+    .await?;
+    Ok(rows
+        .into_iter()
+        .filter_map(|(id, unlocked_at)| {
+            Some(Unlock {
+                achievement: Achievement::get(&id)?,
+                unlocked_at,
+            })
+        })
+        .collect())
+}
+
+/// unlocks every achievement users have newly reached, granting their rewards.
+/// returns what was unlocked. achievements checked for the first time are unlocked for everyone
+/// who already qualified without a reward, and aren't returned
+pub async fn check_unlocks(
+    pool: &Pool<MySql>,
+) -> Result<Vec<(UserId, &'static Achievement)>, Error> {
+    let unlocked: HashSet<(String, String)> =
+        sqlx::query_as("SELECT `uid`, `achievement` FROM `achievements`")
+            .fetch_all(pool)
+            .await?
+            .into_iter()
+            .collect();
+    let backfilled: HashSet<String> =
+        sqlx::query_scalar("SELECT `achievement` FROM `achievement_backfills`")
+            .fetch_all(pool)
+            .await?
+            .into_iter()
+            .collect();
+
+    // each source is only queried once
+    let mut sources: Vec<(Source, Vec<(String, i64)>)> = vec![];
+    let mut new = vec![];
+    let mut backfill = vec![];
+    for achievement in ACHIEVEMENTS {
+        if !sources.iter().any(|(s, _)| *s == achievement.source) {
+            sources.push((achievement.source, achievement.source.values(pool).await?));
+        }
+        let (_, values) = sources
+            .iter()
+            .find(|(s, _)| *s == achievement.source)
+            .unwrap();
+        for (uid, value) in values {
+            if *value < achievement.threshold
+                || unlocked.contains(&(uid.clone(), achievement.id.to_owned()))
+            {
+                continue;
+            }
+            let Some(uid) = uid.parse::<u64>().ok().filter(|uid| *uid != 0) else {
+                continue;
+            };
+            if backfilled.contains(achievement.id) {
+                new.push((UserId::new(uid), achievement));
+            } else {
+                backfill.push((UserId::new(uid), achievement));
+            }
+        }
+    }
+    let unchecked: Vec<&str> = ACHIEVEMENTS
+        .iter()
+        .map(|a| a.id)
+        .filter(|id| !backfilled.contains(*id))
+        .collect();
+    if new.is_empty() && backfill.is_empty() && unchecked.is_empty() {
+        return Ok(new);
+    }
+
+    let mut tx = pool.begin().await?;
+    for chunk in new.chunks(1000).chain(backfill.chunks(1000)) {
+        let mut qb = QueryBuilder::new("INSERT IGNORE INTO `achievements` (`uid`, `achievement`)");
+        qb.push_values(chunk, |mut b, (uid, achievement)| {
+            b.push_bind(uid.to_string()).push_bind(achievement.id);
+        });
+        qb.build().execute(&mut *tx).await?;
+    }
+    if !unchecked.is_empty() {
+        let mut qb =
+            QueryBuilder::new("INSERT IGNORE INTO `achievement_backfills` (`achievement`)");
+        qb.push_values(&unchecked, |mut b, id| {
+            b.push_bind(*id);
+        });
+        qb.build().execute(&mut *tx).await?;
+    }
+    for (uid, achievement) in &new {
+        if achievement.reward > 0 {
+            grant_catcoin(
+                &mut *tx,
+                *uid,
+                achievement.reward,
+                Reason::Achievement,
+                Some(achievement.id.to_owned()),
+            )
+            .await?;
+        }
+    }
+    tx.commit().await?;
+    if !backfill.is_empty() {
+        log::info!("Backfilled {} achievements", backfill.len());
+    }
+    Ok(new)
+}
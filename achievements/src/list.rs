@@ -0,0 +1,240 @@
+use catcoin::{random_pulls::Rarity, Game};
+
+use crate::{Achievement, Source};
+
+const HOUR: i64 = 60 * 60;
+
+/// Every achievement. Ids are stored in the db, so never change or reuse one
+pub const ACHIEVEMENTS: &[Achievement] = &[
+    // seeding
+    Achievement {
+        id: "seed_1h",
+        name: "Sprout",
+        description: "Seed the servers for an hour",
+        emoji: "🌱",
+        source: Source::Seeding,
+        threshold: HOUR,
+        reward: 5,
+    },
+    Achievement {
+        id: "seed_24h",
+        name: "Gardener",
+        description: "Seed the servers for a full day",
+        emoji: "🌿",
+        source: Source::Seeding,
+        threshold: 24 * HOUR,
+        reward: 25,
+    },
+    Achievement {
+        id: "seed_100h",
+        name: "Evergreen",
+        description: "Seed the servers for 100 hours",
+        emoji: "🌳",
+        source: Source::Seeding,
+        threshold: 100 * HOUR,
+        reward: 100,
+    },
+    // catcoin pulls
+    Achievement {
+        id: "pulls_1",
+        name: "First Pull",
+        description: "Pull your first catcoin reward",
+        emoji: "🎁",
+        source: Source::Pulls(None),
+        threshold: 1,
+        reward: 0,
+    },
+    Achievement {
+        id: "pulls_25",
+        name: "Collector",
+        description: "Pull 25 catcoin rewards",
+        emoji: "🧺",
+        source: Source::Pulls(None),
+        threshold: 25,
+        reward: 10,
+    },
+    Achievement {
+        id: "pulls_100",
+        name: "Hoarder",
+        description: "Pull 100 catcoin rewards",
+        emoji: "🏺",
+        source: Source::Pulls(None),
+        threshold: 100,
+        reward: 50,
+    },
+    Achievement {
+        id: "pulls_fluffy",
+        name: "So Fluffy",
+        description: "Pull a Fluffy reward",
+        emoji: "☁️",
+        source: Source::Pulls(Some(Rarity::Fluffy)),
+        threshold: 1,
+        reward: 10,
+    },
+    Achievement {
+        id: "pulls_peak",
+        name: "Peak",
+        description: "Pull a Peak reward",
+        emoji: "🏔️",
+        source: Source::Pulls(Some(Rarity::Peak)),
+        threshold: 1,
+        reward: 25,
+    },
+    // dominations
+    Achievement {
+        id: "dom_1",
+        name: "Dominating",
+        description: "Dominate another player",
+        emoji: "😤",
+        source: Source::Dominations,
+        threshold: 1,
+        reward: 0,
+    },
+    Achievement {
+        id: "dom_50",
+        name: "Menace",
+        description: "Dominate other players 50 times",
+        emoji: "😈",
+        source: Source::Dominations,
+        threshold: 50,
+        reward: 25,
+    },
+    Achievement {
+        id: "dom_250",
+        name: "Nemesis",
+        description: "Dominate other players 250 times",
+        emoji: "👹",
+        source: Source::Dominations,
+        threshold: 250,
+        reward: 100,
+    },
+    // yapping
+    Achievement {
+        id: "yap_1000",
+        name: "Chatterbox",
+        description: "Send 1,000 messages",
+        emoji: "💬",
+        source: Source::Yaps,
+        threshold: 1_000,
+        reward: 5,
+    },
+    Achievement {
+        id: "yap_10000",
+        name: "Yapper",
+        description: "Send 10,000 messages",
+        emoji: "🗣️",
+        source: Source::Yaps,
+        threshold: 10_000,
+        reward: 25,
+    },
+    Achievement {
+        id: "yap_50000",
+        name: "Certified Yapper",
+        description: "Send 50,000 messages",
+        emoji: "📢",
+        source: Source::Yaps,
+        threshold: 50_000,
+        reward: 100,
+    },
+    // profile
+    Achievement {
+        id: "likes_10",
+        name: "Beloved",
+        description: "Get 10 likes on your profile",
+        emoji: "💖",
+        source: Source::Likes,
+        threshold: 10,
+        reward: 10,
+    },
+    Achievement {
+        id: "likes_50",
+        name: "Celebrity",
+        description: "Get 50 likes on your profile",
+        emoji: "🌟",
+        source: Source::Likes,
+        threshold: 50,
+        reward: 50,
+    },
+    // games
+    Achievement {
+        id: "duel_1",
+        name: "Duelist",
+        description: "Win a catcoin duel",
+        emoji: "🤺",
+        source: Source::Wins(Game::Duel),
+        threshold: 1,
+        reward: 0,
+    },
+    Achievement {
+        id: "duel_25",
+        name: "Fastest Paw",
+        description: "Win 25 catcoin duels",
+        emoji: "⚔️",
+        source: Source::Wins(Game::Duel),
+        threshold: 25,
+        reward: 25,
+    },
+    Achievement {
+        id: "poker_1",
+        name: "Card Shark",
+        description: "Win a game of poker",
+        emoji: "🃏",
+        source: Source::Wins(Game::Poker),
+        threshold: 1,
+        reward: 0,
+    },
+    Achievement {
+        id: "poker_25",
+        name: "High Roller",
+        description: "Win 25 games of poker",
+        emoji: "🎰",
+        source: Source::Wins(Game::Poker),
+        threshold: 25,
+        reward: 25,
+    },
+    Achievement {
+        id: "pug_1",
+        name: "Pugger",
+        description: "Win a pug",
+        emoji: "🐶",
+        source: Source::PugWins,
+        threshold: 1,
+        reward: 5,
+    },
+    Achievement {
+        id: "pug_25",
+        name: "Pug Champion",
+        description: "Win 25 pugs",
+        emoji: "🏆",
+        source: Source::PugWins,
+        threshold: 25,
+        reward: 50,
+    },
+    // wealth
+    Achievement {
+        id: "catcoin_1000",
+        name: "Catcoin Tycoon",
+        description: "Hold 1,000 catcoin at once",
+        emoji: "💰",
+        source: Source::Catcoin,
+        threshold: 1_000,
+        reward: 0,
+    },
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unique_ids() {
+        for (i, a) in ACHIEVEMENTS.iter().enumerate() {
+            assert!(a.id.len() <= 64);
+            assert!(
+                ACHIEVEMENTS[i + 1..].iter().all(|b| b.id != a.id),
+                "duplicate achievement id {}",
+                a.id
+            );
+        }
+    }
+}
@@ -0,0 +1,107 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use emoji::emoji;
+use poise::serenity_prelude::{
+    ChannelId, Color, CreateAllowedMentions, CreateEmbed, CreateMessage, Http, Mentionable, UserId,
+};
+use sqlx::{MySql, Pool};
+
+use crate::{check_unlocks, Achievement};
+
+/// how often achievement progress is checked
+const CHECK_INTERVAL: Duration = Duration::from_secs(5 * 60);
+/// the most users announced one by one per check, the rest are summed up in one message
+const MAX_ANNOUNCEMENTS: usize = 10;
+
+/// periodically unlocks achievements and announces them in the given channel
+pub fn spawn_achievement_tracker(pool: Pool<MySql>, http: Arc<Http>, channel: ChannelId) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+            let unlocks = match check_unlocks(&pool).await {
+                Ok(unlocks) => unlocks,
+                Err(e) => {
+                    log::error!("Could not check achievements: {e}");
+                    continue;
+                }
+            };
+            if unlocks.is_empty() {
+                continue;
+            }
+            log::info!("{} achievements unlocked", unlocks.len());
+
+            let mut by_user: HashMap<UserId, Vec<&Achievement>> = HashMap::new();
+            for (uid, achievement) in unlocks {
+                by_user.entry(uid).or_default().push(achievement);
+            }
+            let mut by_user: Vec<(UserId, Vec<&Achievement>)> = by_user.into_iter().collect();
+            let rest = by_user.split_off(MAX_ANNOUNCEMENTS.min(by_user.len()));
+            for (uid, achievements) in by_user {
+                let _ = channel
+                    .send_message(
+                        &http,
+                        CreateMessage::new()
+                            .content(uid.mention().to_string())
+                            .embed(unlock_embed(&achievements))
+                            .allowed_mentions(CreateAllowedMentions::new().empty_users()),
+                    )
+                    .await
+                    .inspect_err(|e| log::error!("Could not announce achievement: {e}"));
+            }
+            if !rest.is_empty() {
+                let _ = channel
+                    .send_message(&http, CreateMessage::new().embed(summary_embed(&rest)))
+                    .await
+                    .inspect_err(|e| log::error!("Could not announce achievements: {e}"));
+            }
+        }
+    });
+}
+
+/// how many of the remaining users unlocked each achievement
+fn summary_embed(rest: &[(UserId, Vec<&Achievement>)]) -> CreateEmbed {
+    let mut counts: Vec<(&Achievement, usize)> = vec![];
+    for achievement in rest.iter().flat_map(|(_, a)| a) {
+        match counts.iter_mut().find(|(a, _)| a.id == achievement.id) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((achievement, 1)),
+        }
+    }
+    CreateEmbed::new()
+        .title(format!(
+            "🏅 {} more users unlocked achievements!",
+            rest.len()
+        ))
+        .description(
+            counts
+                .iter()
+                .map(|(a, count)| format!("{} - **{}** users", a.title(), count))
+                .collect::<Vec<String>>()
+                .join("\n"),
+        )
+        .color(Color::GOLD)
+}
+
+fn unlock_embed(achievements: &[&Achievement]) -> CreateEmbed {
+    CreateEmbed::new()
+        .title(if achievements.len() == 1 {
+            "🏅 Achievement unlocked!"
+        } else {
+            "🏅 Achievements unlocked!"
+        })
+        .description(
+            achievements
+                .iter()
+                .map(|a| {
+                    let mut line = format!("{} - {}", a.title(), a.description);
+                    if a.reward > 0 {
+                        line += &format!(" **+{}** {}", a.reward, emoji("catcoin"));
+                    }
+                    line
+                })
+                .collect::<Vec<String>>()
+                .join("\n"),
+        )
+        .color(Color::GOLD)
+}
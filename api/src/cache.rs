@@ -0,0 +1,83 @@
+use std::{
+    collections::HashMap,
+    future::Future,
+    net::IpAddr,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use chrono::{TimeDelta, Utc};
+use serde_json::Value;
+use tokio::sync::RwLock;
+
+use common::{util::LeakyBucket, Error};
+
+/// Json responses cached for a fixed time
+#[derive(Clone, Default)]
+pub struct TtlCache {
+    entries: Arc<RwLock<HashMap<String, (Instant, Value)>>>,
+}
+
+impl TtlCache {
+    /// the cached value for the key, otherwise the result of `f`, cached for `ttl`
+    pub async fn get_or_try_insert<F, Fut>(
+        &self,
+        key: &str,
+        ttl: Duration,
+        f: F,
+    ) -> Result<Value, Error>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<Value, Error>>,
+    {
+        if let Some((expires, value)) = self.entries.read().await.get(key) {
+            if *expires > Instant::now() {
+                return Ok(value.clone());
+            }
+        }
+        let value = f().await?;
+        self.entries
+            .write()
+            .await
+            .insert(key.to_owned(), (Instant::now() + ttl, value.clone()));
+        Ok(value)
+    }
+
+    /// drops expired entries
+    pub async fn prune(&self) {
+        let now = Instant::now();
+        self.entries
+            .write()
+            .await
+            .retain(|_, (expires, _)| *expires > now);
+    }
+}
+
+/// Per-ip request limits
+#[derive(Clone, Default)]
+pub struct RateLimiter {
+    buckets: Arc<RwLock<HashMap<IpAddr, LeakyBucket>>>,
+}
+
+impl RateLimiter {
+    /// a burst of 30 requests, refilling at 60 per minute
+    fn bucket() -> LeakyBucket {
+        LeakyBucket::new(30., 60., 1.)
+    }
+
+    /// Ok if the ip may make a request, otherwise how long until it can
+    pub async fn try_request(&self, ip: IpAddr) -> Result<(), TimeDelta> {
+        self.buckets
+            .write()
+            .await
+            .entry(ip)
+            .or_insert_with(Self::bucket)
+            .try_afford_one()
+    }
+
+    /// forgets ips that haven't made a request recently
+    pub async fn prune(&self) {
+        let cutoff = Utc::now() - TimeDelta::try_minutes(10).unwrap();
+        self.buckets.write().await.retain(|_, b| b.last > cutoff);
+    }
+}
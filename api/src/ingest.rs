@@ -0,0 +1,269 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    net::SocketAddr,
+    sync::Arc,
+};
+
+use axum::{
+    body::Bytes,
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::post,
+    Json, Router,
+};
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use serde_json::json;
+use sha2::Sha256;
+use tokio::sync::RwLock;
+
+use crate::ApiState;
+
+/// how far the request timestamp may drift from ours, in seconds
+const MAX_CLOCK_SKEW: i64 = 300;
+/// most events accepted in a single request
+const MAX_BATCH: usize = 500;
+/// event ids remembered per server for deduplication
+const RECENT_IDS: usize = 8192;
+
+/// http log ingest, for servers that can't reliably send udp logs.
+///
+/// `POST /ingest/logs` with the headers
+/// - `X-Server`: the server's address, as configured in the bot
+/// - `X-Timestamp`: the current unix time in seconds
+/// - `X-Signature`: hex hmac-sha256 of `{timestamp}.{body}`, keyed with the server's ingest secret
+///
+/// and a json body of `{ "events": [{ "id": 123, "type": "line", "line": "..." }, ...] }`.
+/// ids must be unique per server (e.g. random 64 bit values) so retried batches aren't broadcast twice
+pub fn router() -> Router<ApiState> {
+    Router::new().route("/logs", post(ingest_logs))
+}
+
+/// The most recent event ids seen from a server
+#[derive(Default)]
+pub struct RecentIds {
+    order: VecDeque<u64>,
+    seen: HashSet<u64>,
+}
+
+impl RecentIds {
+    /// records the id, returning false if it was already seen
+    pub fn insert(&mut self, id: u64) -> bool {
+        if !self.seen.insert(id) {
+            return false;
+        }
+        self.order.push_back(id);
+        if self.order.len() > RECENT_IDS {
+            if let Some(old) = self.order.pop_front() {
+                self.seen.remove(&old);
+            }
+        }
+        true
+    }
+}
+
+pub type SeenEvents = Arc<RwLock<HashMap<SocketAddr, RecentIds>>>;
+
+#[derive(Deserialize)]
+struct Player {
+    name: String,
+    userid: i32,
+    steamid: String,
+    #[serde(default)]
+    team: String,
+}
+
+impl Player {
+    fn tag(&self) -> String {
+        format!(
+            "\"{}<{}><{}><{}>\"",
+            self.name, self.userid, self.steamid, self.team
+        )
+    }
+}
+
+/// A game event, either a raw log line or a structured event converted to one
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Event {
+    Line {
+        line: String,
+    },
+    Chat {
+        player: Player,
+        message: String,
+        #[serde(default)]
+        team_only: bool,
+    },
+    Connected {
+        player: Player,
+        address: String,
+    },
+    Disconnected {
+        player: Player,
+        reason: String,
+    },
+}
+
+impl Event {
+    /// the srcds log line for this event
+    fn into_line(self) -> String {
+        match self {
+            Event::Line { line } => line,
+            Event::Chat {
+                player,
+                message,
+                team_only,
+            } => format!(
+                "{} {} \"{}\"",
+                player.tag(),
+                if team_only { "say_team" } else { "say" },
+                message
+            ),
+            Event::Connected { player, address } => {
+                format!("{} connected, address \"{}\"", player.tag(), address)
+            }
+            Event::Disconnected { player, reason } => {
+                format!("{} disconnected (reason \"{}\")", player.tag(), reason)
+            }
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct Entry {
+    id: u64,
+    #[serde(flatten)]
+    event: Event,
+}
+
+#[derive(Deserialize)]
+struct Batch {
+    events: Vec<Entry>,
+}
+
+/// checks the hex hmac-sha256 signature of `{timestamp}.{body}`
+fn verify_signature(secret: &str, timestamp: &str, body: &[u8], signature: &str) -> bool {
+    let Ok(signature) = hex::decode(signature.trim()) else {
+        return false;
+    };
+    let Ok(mut mac) = Hmac::<Sha256>::new_from_slice(secret.as_bytes()) else {
+        return false;
+    };
+    mac.update(timestamp.as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    mac.verify_slice(&signature).is_ok()
+}
+
+fn error(status: StatusCode, msg: &str) -> Response {
+    (status, Json(json!({ "error": msg }))).into_response()
+}
+
+async fn ingest_logs(State(state): State<ApiState>, headers: HeaderMap, body: Bytes) -> Response {
+    let header = |name: &str| headers.get(name).and_then(|h| h.to_str().ok());
+
+    let Some(server) = header("x-server")
+        .and_then(|addr| addr.parse::<SocketAddr>().ok())
+        .and_then(|addr| state.servers.get(&addr))
+    else {
+        return error(StatusCode::NOT_FOUND, "Unknown server");
+    };
+    let Some(secret) = &server.ingest_secret else {
+        return error(StatusCode::FORBIDDEN, "Ingest is disabled for this server");
+    };
+    let (Some(timestamp), Some(signature)) = (header("x-timestamp"), header("x-signature")) else {
+        return error(StatusCode::UNAUTHORIZED, "Missing signature");
+    };
+    if !verify_signature(secret, timestamp, &body, signature) {
+        log::warn!(
+            "Rejected log ingest with a bad signature for {}",
+            server.addr
+        );
+        return error(StatusCode::UNAUTHORIZED, "Bad signature");
+    }
+    let fresh = timestamp
+        .parse::<i64>()
+        .is_ok_and(|t| (Utc::now().timestamp() - t).abs() <= MAX_CLOCK_SKEW);
+    if !fresh {
+        return error(StatusCode::UNAUTHORIZED, "Stale timestamp");
+    }
+
+    let batch: Batch = match serde_json::from_slice(&body) {
+        Ok(batch) => batch,
+        Err(e) => return error(StatusCode::BAD_REQUEST, &format!("Invalid body: {e}")),
+    };
+    if batch.events.len() > MAX_BATCH {
+        return error(StatusCode::PAYLOAD_TOO_LARGE, "Too many events");
+    }
+
+    // filter duplicates before broadcasting, so the lock isn't held across callbacks
+    let total = batch.events.len();
+    let lines: Vec<String> = {
+        let mut seen = state.ingest_seen.write().await;
+        let recent = seen.entry(server.addr).or_default();
+        batch
+            .events
+            .into_iter()
+            .filter(|entry| recent.insert(entry.id))
+            .map(|entry| entry.event.into_line())
+            .collect()
+    };
+    let accepted = lines.len();
+    for line in lines {
+        state.log_receiver.ingest_line(server.addr, &line).await;
+    }
+
+    Json(json!({ "accepted": accepted, "duplicates": total - accepted })).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dedupes_recent_ids() {
+        let mut recent = RecentIds::default();
+        assert!(recent.insert(1));
+        assert!(!recent.insert(1));
+        for id in 2..=RECENT_IDS as u64 + 1 {
+            assert!(recent.insert(id));
+        }
+        // the oldest id has been forgotten
+        assert!(recent.insert(1));
+    }
+
+    #[test]
+    fn verifies_signatures() {
+        let body = br#"{"events":[]}"#;
+        let mut mac = Hmac::<Sha256>::new_from_slice(b"secret").unwrap();
+        mac.update(b"1700000000.");
+        mac.update(body);
+        let signature = hex::encode(mac.finalize().into_bytes());
+
+        assert!(verify_signature("secret", "1700000000", body, &signature));
+        assert!(!verify_signature("secret", "1700000001", body, &signature));
+        assert!(!verify_signature("other", "1700000000", body, &signature));
+        assert!(!verify_signature("secret", "1700000000", body, "not hex"));
+    }
+
+    #[test]
+    fn converts_events_to_lines() {
+        let batch: Batch = serde_json::from_str(
+            r#"{"events":[
+				{"id":1,"type":"chat","player":{"name":"cat","userid":2,"steamid":"[U:1:1]","team":"Red"},"message":"meow","team_only":true},
+				{"id":2,"type":"line","line":"World triggered \"Round_Start\""}
+			]}"#,
+        )
+        .unwrap();
+        let lines: Vec<String> = batch
+            .events
+            .into_iter()
+            .map(|e| e.event.into_line())
+            .collect();
+        assert_eq!(lines[0], r#""cat<2><[U:1:1]><Red>" say_team "meow""#);
+        assert_eq!(lines[1], r#"World triggered "Round_Start""#);
+    }
+}
@@ -1,107 +1,281 @@
+pub mod cache;
+mod ingest;
+pub mod v1;
+mod web;
+
 use rand::prelude::*;
 use std::{
     collections::HashMap,
     hash::Hash,
-    net::{Ipv4Addr, ToSocketAddrs},
-    sync::Arc,
-    time::Duration,
+    net::{Ipv4Addr, SocketAddr, ToSocketAddrs},
+    path::PathBuf,
+    sync::{Arc, OnceLock},
+    time::{Duration, Instant},
 };
 
-use common::{util::parse_env, Error};
+use common::{metrics, util::parse_env, Error};
 
 use axum::{
     self,
-    extract::{RawQuery, State},
-    http::StatusCode,
+    body::Body,
+    extract::{Path, RawQuery, State},
+    http::{header, HeaderMap, StatusCode},
     response::{self, IntoResponse, Response},
     routing::get,
     Router,
 };
 use chrono::{DateTime, TimeDelta, Utc};
+use hmac::{Hmac, Mac};
+use poise::serenity_prelude as serenity;
+use sha2::Sha256;
+use sqlx::{MySql, Pool};
 use steam_connect as steam;
+use tf2::{logs::LogReceiver, Server};
 use tokio::{self, net::TcpListener, sync::RwLock};
+use tokio_util::io::ReaderStream;
+
+/// how long a steam link code is valid for
+const LINK_CODE_TTL: TimeDelta = TimeDelta::minutes(10);
 
 #[derive(Clone, PartialEq, Eq, Hash)]
 pub struct LinkCode(pub String);
 
+/// A local file temporarily served at /files/<token>
+#[derive(Clone)]
+pub struct SharedFile {
+    pub path: PathBuf,
+    pub filename: String,
+    pub expires: DateTime<Utc>,
+}
+
 #[derive(Clone)]
 pub struct ApiState {
     public_url: String,
-    pub link_codes: Arc<RwLock<HashMap<LinkCode, (u64, DateTime<Utc>)>>>,
+    pool: Pool<MySql>,
+    servers: HashMap<SocketAddr, Server>,
+    cache: cache::TtlCache,
+    /// bearer token required to scrape /metrics. /metrics is off without one
+    metrics_token: Option<String>,
+    rate_limiter: cache::RateLimiter,
+    /// the discord cache and guild, used to show member names on the web pages
+    discord: Arc<OnceLock<(Arc<serenity::Cache>, serenity::GuildId)>>,
+    log_receiver: LogReceiver,
+    ingest_seen: ingest::SeenEvents,
+    pub shared_files: Arc<RwLock<HashMap<String, SharedFile>>>,
 }
 
 impl ApiState {
-    pub async fn gen_link_code(&self, steamid64: u64) -> (LinkCode, DateTime<Utc>) {
+    /// issues a code the user can enter in discord to link the given steam account
+    pub async fn gen_link_code(&self, steamid64: u64) -> Result<(LinkCode, DateTime<Utc>), Error> {
         const CHARS: &'static str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ123456789";
-        let existing_codes = self
-            .link_codes
-            .read()
-            .await
-            .keys()
-            .map(|m| m.0.clone())
-            .collect::<Vec<String>>();
-
-        let new_code = loop {
+        let expires_at = Utc::now() + LINK_CODE_TTL;
+        loop {
             let code: String = (0..6)
                 .flat_map(|_| CHARS.chars().nth(thread_rng().gen_range(0..CHARS.len())))
                 .collect();
-            if !existing_codes.contains(&code) {
-                break code;
+            // retry on the rare collision with an outstanding code
+            let inserted = sqlx::query!(
+                "INSERT IGNORE INTO `steam_link_codes` (`code`, `steamid64`, `expires_at`) VALUES (?, ?, ?)",
+                code,
+                steamid64,
+                expires_at
+            )
+            .execute(&self.pool)
+            .await?
+            .rows_affected();
+            if inserted > 0 {
+                return Ok((LinkCode(code), expires_at));
+            }
+        }
+    }
+
+    /// serves a local file for a limited time, returning the download url.
+    /// the file is deleted once it expires
+    pub async fn share_file(&self, path: PathBuf, filename: String, ttl: TimeDelta) -> String {
+        let token: String = thread_rng()
+            .sample_iter(&rand::distributions::Alphanumeric)
+            .take(24)
+            .map(char::from)
+            .collect();
+        self.prune_shared_files().await;
+        self.shared_files.write().await.insert(
+            token.clone(),
+            SharedFile {
+                path,
+                filename,
+                expires: Utc::now() + ttl,
+            },
+        );
+        format!("{}/files/{}", self.public_url, token)
+    }
+
+    /// stops serving expired shared files and deletes them
+    pub async fn prune_shared_files(&self) {
+        let now = Utc::now();
+        let mut files = self.shared_files.write().await;
+        let expired: Vec<String> = files
+            .iter()
+            .filter(|(_, f)| f.expires < now)
+            .map(|(t, _)| t.clone())
+            .collect();
+        for token in expired {
+            if let Some(file) = files.remove(&token) {
+                let _ = tokio::fs::remove_file(&file.path)
+                    .await
+                    .inspect_err(|e| log::error!("Could not remove shared file: {e}"));
             }
+        }
+    }
+
+    /// gives the web pages access to discord member names, once the client is built
+    pub fn set_discord(&self, cache: Arc<serenity::Cache>, guild: serenity::GuildId) {
+        let _ = self.discord.set((cache, guild));
+    }
+
+    /// the display name of a discord user, if they're cached
+    fn display_name(&self, uid: &str) -> String {
+        let Some((cache, guild)) = self.discord.get() else {
+            return "unknown user".to_owned();
+        };
+        let Ok(uid) = uid.parse::<serenity::UserId>() else {
+            return "unknown user".to_owned();
         };
-        let issued_at = Utc::now();
-        let code = LinkCode(new_code);
+        if let Some(member) = cache.member(*guild, uid) {
+            return member.display_name().to_owned();
+        }
+        cache
+            .user(uid)
+            .map(|u| u.display_name().to_owned())
+            .unwrap_or("unknown user".to_owned())
+    }
 
-        let mut v = self.link_codes.write().await;
-        v.insert(code.clone(), (steamid64, issued_at));
-        (code, issued_at)
+    /// the avatar of a discord user, if they're cached
+    fn avatar_url(&self, uid: &str) -> Option<String> {
+        let (cache, guild) = self.discord.get()?;
+        let uid = uid.parse::<serenity::UserId>().ok()?;
+        if let Some(member) = cache.member(*guild, uid) {
+            return Some(member.face());
+        }
+        cache.user(uid).map(|u| u.face())
     }
 
     pub fn link_url(&self) -> String {
         format!("{}/steam-link", self.public_url)
     }
 
+    /// redeems a link code, returning the steamid64 it was issued for. codes can only be used once
     pub async fn try_link_user(&self, code: String) -> Result<u64, Error> {
-        // check if code is valid
-        let mut codes = self.link_codes.write().await;
-        let code = LinkCode(code);
-        let v = codes.get(&code);
-
-        match v {
-            None => {
-                return Err("Code not found".into());
-            }
-            Some((steamid64, issued_at)) => {
-                let now = Utc::now();
-                if issued_at
-                    .checked_add_signed(TimeDelta::try_minutes(10).unwrap())
-                    .is_some_and(|expires| expires < now)
-                {
-                    codes.remove(&code);
-                    Err("Code has expired".into())
-                } else {
-                    Ok(*steamid64)
-                }
-            }
+        let code = code.trim().to_uppercase();
+        let mut tx = self.pool.begin().await?;
+        let Some(row) = sqlx::query!(
+            "SELECT `steamid64`, `expires_at` FROM `steam_link_codes` WHERE `code` = ? FOR UPDATE",
+            code
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        else {
+            return Err("Code not found".into());
+        };
+        sqlx::query!("DELETE FROM `steam_link_codes` WHERE `code` = ?", code)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        if row.expires_at < Utc::now() {
+            Err("Code has expired".into())
+        } else {
+            Ok(row.steamid64)
         }
     }
 }
 
-pub async fn init() -> Result<ApiState, Error> {
+/// deletes expired link codes
+async fn prune_link_codes(pool: &Pool<MySql>) -> Result<u64, Error> {
+    Ok(sqlx::query!(
+        "DELETE FROM `steam_link_codes` WHERE `expires_at` < ?",
+        Utc::now()
+    )
+    .execute(pool)
+    .await?
+    .rows_affected())
+}
+
+/// how often servers are sampled for map stats
+const MAP_STATS_INTERVAL: Duration = Duration::from_secs(60);
+
+pub async fn init(
+    pool: Pool<MySql>,
+    servers: HashMap<SocketAddr, Server>,
+    log_receiver: LogReceiver,
+) -> Result<ApiState, Error> {
     let ip: Ipv4Addr = parse_env("HTTP_IP");
     let public_url: String = parse_env("HTTP_PUBLIC_URL");
     let port: u16 = parse_env("HTTP_PORT");
 
     let state = ApiState {
         public_url,
-        link_codes: Arc::new(RwLock::new(HashMap::new())),
+        pool: pool.clone(),
+        servers,
+        cache: cache::TtlCache::default(),
+        metrics_token: std::env::var("METRICS_TOKEN")
+            .ok()
+            .filter(|t| !t.is_empty()),
+        rate_limiter: cache::RateLimiter::default(),
+        discord: Arc::new(OnceLock::new()),
+        log_receiver,
+        ingest_seen: Arc::new(RwLock::new(HashMap::new())),
+        shared_files: Arc::new(RwLock::new(HashMap::new())),
     };
 
     let app = Router::new()
         .route("/steam-link", get(steam_link))
         .route("/steam-callback", get(steam_callback))
+        .route("/files/:token", get(shared_file))
+        .route("/metrics", get(metrics_page))
+        .nest("/v1", v1::router(state.clone()))
+        .nest("/ingest", ingest::router())
+        .merge(web::router(state.clone()))
         .with_state(state.clone());
 
+    {
+        let state = state.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(MAP_STATS_INTERVAL);
+            loop {
+                interval.tick().await;
+                for server in state.servers.values() {
+                    let Ok(game_state) = server.controller.write().await.status().await else {
+                        continue;
+                    };
+                    let _ = v1::record_map_sample(&state.pool, &game_state, MAP_STATS_INTERVAL)
+                        .await
+                        .inspect_err(|e| log::error!("Could not record map stats: {e}"));
+                }
+                let start = Instant::now();
+                if sqlx::query("SELECT 1").execute(&state.pool).await.is_ok() {
+                    metrics::DB_LATENCY
+                        .observe(&[("query", "ping")], start.elapsed().as_secs_f64());
+                }
+                state.cache.prune().await;
+                state.rate_limiter.prune().await;
+            }
+        });
+    }
+
+    {
+        let state = state.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(60));
+            loop {
+                interval.tick().await;
+                let _ = prune_link_codes(&pool)
+                    .await
+                    .inspect_err(|e| log::error!("Could not prune link codes: {e}"));
+                state.prune_shared_files().await;
+            }
+        });
+    }
+
     tokio::spawn(async move {
         let mut interval = tokio::time::interval(Duration::from_secs(10));
         loop {
@@ -116,9 +290,13 @@ pub async fn init() -> Result<ApiState, Error> {
                 continue;
             };
             log::info!("HTTP listener bound to {}", socket_addr);
-            let _ = axum::serve(listener, app.clone())
-                .await
-                .inspect_err(|e| log::error!("axum server error: {e}"));
+            let _ = axum::serve(
+                listener,
+                app.clone()
+                    .into_make_service_with_connect_info::<SocketAddr>(),
+            )
+            .await
+            .inspect_err(|e| log::error!("axum server error: {e}"));
         }
     });
 
@@ -172,14 +350,111 @@ async fn steam_callback(State(state): State<ApiState>, RawQuery(query): RawQuery
         return response::Redirect::to("/steam-link").into_response();
     };
     let id = v.claim_id();
-    let (code, issued_at) = state.gen_link_code(id).await;
+    let (code, expires_at) = match state.gen_link_code(id).await {
+        Ok(code) => code,
+        Err(e) => {
+            log::error!("Could not generate link code: {e}");
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                response_doc("Could not generate a link code. Try again later."),
+            )
+                .into_response();
+        }
+    };
     (
         StatusCode::OK,
         response_doc(format!(
-            "Your steam link code is <tt>{}</tt>. It will be valid for 10 minutes ({}).",
+            "Your steam link code is <tt>{}</tt>. It will be valid for {} minutes ({}).",
             code.0,
-            (issued_at + chrono::Duration::try_minutes(10).unwrap()).format("%d/%m/%Y %H:%M")
+            LINK_CODE_TTL.num_minutes(),
+            expires_at.format("%d/%m/%Y %H:%M")
         )),
     )
         .into_response()
 }
+
+async fn shared_file(State(state): State<ApiState>, Path(token): Path<String>) -> Response {
+    let file = state
+        .shared_files
+        .read()
+        .await
+        .get(&token)
+        .cloned()
+        .filter(|f| f.expires > Utc::now());
+    let Some(file) = file else {
+        return (
+            StatusCode::NOT_FOUND,
+            response_doc("File not found or expired."),
+        )
+            .into_response();
+    };
+    let Ok(handle) = tokio::fs::File::open(&file.path).await else {
+        return (
+            StatusCode::NOT_FOUND,
+            response_doc("File not found or expired."),
+        )
+            .into_response();
+    };
+    (
+        [
+            (header::CONTENT_TYPE, "application/octet-stream".to_owned()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!(
+                    "attachment; filename=\"{}\"",
+                    file.filename.replace('"', "")
+                ),
+            ),
+        ],
+        Body::from_stream(ReaderStream::new(handle)),
+    )
+        .into_response()
+}
+
+/// prometheus metrics, behind a bearer token
+/// compares a presented bearer token in constant time, so timing doesn't leak how much matched
+fn token_matches(token: &str, presented: &str) -> bool {
+    let mac = |data: &str| {
+        let mut mac = Hmac::<Sha256>::new_from_slice(token.as_bytes()).ok()?;
+        mac.update(data.as_bytes());
+        Some(mac)
+    };
+    let (Some(expected), Some(presented)) = (mac(token), mac(presented)) else {
+        return false;
+    };
+    presented
+        .verify_slice(&expected.finalize().into_bytes())
+        .is_ok()
+}
+
+async fn metrics_page(State(state): State<ApiState>, headers: HeaderMap) -> Response {
+    let Some(token) = &state.metrics_token else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    let authorized = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.strip_prefix("Bearer "))
+        .is_some_and(|t| token_matches(token, t));
+    if !authorized {
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        metrics::render(),
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_matches() {
+        assert!(token_matches("s3cret", "s3cret"));
+        assert!(!token_matches("s3cret", "s3cre"));
+        assert!(!token_matches("s3cret", "s3cret!"));
+        assert!(!token_matches("s3cret", ""));
+    }
+}
@@ -0,0 +1,467 @@
+use std::{
+    net::{IpAddr, SocketAddr},
+    time::Duration,
+};
+
+use axum::{
+    extract::{ConnectInfo, Path, Query, Request, State},
+    http::{header, HeaderMap, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use poise::serenity_prelude::UserId;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sqlx::{MySql, Pool};
+use tf2::{GameState, NextMap, Server, TimeLeft};
+
+use common::Error;
+
+use crate::ApiState;
+
+/// how long each endpoint's response is cached
+const SERVERS_TTL: Duration = Duration::from_secs(15);
+const LEADERBOARD_TTL: Duration = Duration::from_secs(60);
+const PROFILE_TTL: Duration = Duration::from_secs(30);
+/// the most rows a leaderboard can return
+const MAX_LIMIT: i64 = 100;
+
+pub fn router(state: ApiState) -> Router<ApiState> {
+    Router::new()
+        .route("/servers", get(servers))
+        .route("/seederboard", get(seederboard))
+        .route("/catcoin/top", get(catcoin_top))
+        .route("/profiles/:discord_id", get(profile))
+        .route("/maps/stats", get(map_stats))
+        .layer(middleware::from_fn_with_state(state, rate_limit))
+}
+
+/// the client's ip. proxy headers are only trusted from localhost
+pub fn client_ip(peer: SocketAddr, headers: &HeaderMap) -> IpAddr {
+    if !peer.ip().is_loopback() {
+        return peer.ip();
+    }
+    headers
+        .get("x-forwarded-for")
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.split(',').next())
+        .and_then(|ip| ip.trim().parse().ok())
+        .unwrap_or(peer.ip())
+}
+
+pub(crate) async fn rate_limit(
+    State(state): State<ApiState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let ip = client_ip(peer, request.headers());
+    match state.rate_limiter.try_request(ip).await {
+        Ok(()) => next.run(request).await,
+        Err(wait) => (
+            StatusCode::TOO_MANY_REQUESTS,
+            [(header::RETRY_AFTER, (wait.num_seconds() + 1).to_string())],
+            Json(json!({ "error": "Too many requests" })),
+        )
+            .into_response(),
+    }
+}
+
+fn error(status: StatusCode, msg: &str) -> Response {
+    (status, Json(json!({ "error": msg }))).into_response()
+}
+
+/// responds with the json, or a 500 if it could not be fetched
+fn respond(value: Result<Value, Error>) -> Response {
+    match value {
+        Ok(value) => Json(value).into_response(),
+        Err(e) => {
+            log::error!("api error: {e}");
+            error(StatusCode::INTERNAL_SERVER_ERROR, "Internal server error")
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct Limit {
+    limit: Option<i64>,
+}
+
+impl Limit {
+    fn get(&self) -> i64 {
+        self.limit.unwrap_or(10).clamp(1, MAX_LIMIT)
+    }
+}
+
+#[derive(Serialize)]
+pub struct PlayerJson {
+    pub name: String,
+    pub connected_seconds: u64,
+}
+
+#[derive(Serialize)]
+pub struct ServerJson {
+    pub name: String,
+    pub address: String,
+    pub online: bool,
+    pub map: Option<String>,
+    pub player_count: usize,
+    pub max_players: Option<i32>,
+    pub players: Vec<PlayerJson>,
+    pub timeleft: Option<String>,
+    pub nextmap: Option<String>,
+}
+
+impl ServerJson {
+    fn new(server: &Server, state: Option<GameState>) -> Self {
+        let mut json = ServerJson {
+            name: server.name.clone(),
+            address: server.addr.to_string(),
+            online: state.is_some(),
+            map: None,
+            player_count: 0,
+            max_players: None,
+            players: vec![],
+            timeleft: None,
+            nextmap: None,
+        };
+        if let Some(state) = state {
+            json.map = Some(state.map);
+            json.player_count = state.players.len();
+            json.max_players = Some(state.max_players);
+            json.players = state
+                .players
+                .into_iter()
+                .map(|p| PlayerJson {
+                    name: p.name,
+                    connected_seconds: p.connected.as_secs(),
+                })
+                .collect();
+            json.timeleft = state.timeleft.map(|t| match t {
+                TimeLeft::LastRound => "last round".to_owned(),
+                TimeLeft::Time { remaining, .. } => remaining,
+            });
+            json.nextmap = state.nextmap.map(|n| match n {
+                NextMap::PendingVote => "pending vote".to_owned(),
+                NextMap::Map(map) => map,
+            });
+        }
+        json
+    }
+}
+
+/// the current state of every public server
+pub async fn get_servers(state: &ApiState) -> Result<Vec<ServerJson>, Error> {
+    let mut servers: Vec<&Server> = state.servers.values().filter(|s| s.show_status).collect();
+    servers.sort_by(|a, b| a.name.cmp(&b.name));
+    let mut out = vec![];
+    for server in servers {
+        let game_state = tokio::time::timeout(Duration::from_secs(5), async {
+            server.controller.write().await.status().await
+        })
+        .await
+        .ok()
+        .and_then(|s| s.ok());
+        out.push(ServerJson::new(server, game_state));
+    }
+    Ok(out)
+}
+
+async fn servers(State(state): State<ApiState>) -> Response {
+    respond(
+        state
+            .cache
+            .get_or_try_insert("servers", SERVERS_TTL, || async {
+                Ok(serde_json::to_value(get_servers(&state).await?)?)
+            })
+            .await,
+    )
+}
+
+#[derive(Serialize, sqlx::FromRow)]
+pub struct SeederJson {
+    pub rank: i64,
+    pub steamid: String,
+    pub name: Option<String>,
+    pub seconds_seeded: i64,
+}
+
+/// the top seeders, named where their name has been seen in the logs
+pub async fn get_seederboard(pool: &Pool<MySql>, limit: i64) -> Result<Vec<SeederJson>, Error> {
+    Ok(sqlx::query_as(
+        r#"
+		SELECT CAST(RANK() OVER (ORDER BY s.`seconds_seeded` DESC) AS SIGNED) AS `rank`, s.`steamid`, p.`name`, s.`seconds_seeded`
+		FROM `seederboard` s LEFT JOIN `rivalry_players` p ON p.`steamid` = s.`steamid`
+		ORDER BY s.`seconds_seeded` DESC LIMIT ?
+	"#,
+    )
+    .bind(limit)
+    .fetch_all(pool)
+    .await?)
+}
+
+async fn seederboard(State(state): State<ApiState>, Query(limit): Query<Limit>) -> Response {
+    let limit = limit.get();
+    respond(
+        state
+            .cache
+            .get_or_try_insert(&format!("seederboard/{limit}"), LEADERBOARD_TTL, || async {
+                Ok(serde_json::to_value(
+                    get_seederboard(&state.pool, limit).await?,
+                )?)
+            })
+            .await,
+    )
+}
+
+#[derive(Serialize)]
+pub struct WalletJson {
+    pub discord_id: String,
+    pub catcoin: i64,
+}
+
+async fn catcoin_top(State(state): State<ApiState>) -> Response {
+    respond(
+        state
+            .cache
+            .get_or_try_insert("catcoin/top", LEADERBOARD_TTL, || async {
+                let top: Vec<WalletJson> = catcoin::get_top(&state.pool)
+                    .await?
+                    .into_iter()
+                    .map(|w| WalletJson {
+                        discord_id: w.uid,
+                        catcoin: w.catcoin,
+                    })
+                    .collect();
+                Ok(serde_json::to_value(top)?)
+            })
+            .await,
+    )
+}
+
+#[derive(Serialize)]
+pub struct VotesJson {
+    pub likes: i64,
+    pub dislikes: i64,
+}
+
+#[derive(Serialize)]
+pub struct SeedingJson {
+    pub rank: i64,
+    pub seconds_seeded: i64,
+}
+
+#[derive(Serialize)]
+pub struct DominationsJson {
+    pub dominations: i64,
+    pub dominated: i64,
+}
+
+#[derive(Serialize)]
+pub struct PugRatingJson {
+    pub rating: f64,
+    pub games: i32,
+    pub wins: i32,
+    pub losses: i32,
+    pub ties: i32,
+}
+
+#[derive(Serialize)]
+pub struct ProfileJson {
+    pub discord_id: String,
+    pub title: String,
+    pub description: Option<String>,
+    pub url: Option<String>,
+    pub image: Option<String>,
+    pub color: Option<u32>,
+    pub classes: Vec<String>,
+    pub favorite_map: Option<String>,
+    pub views: u64,
+    pub catcoin: i64,
+    pub achievements: Vec<&'static str>,
+    pub steamid: Option<String>,
+    /// absent if the user hides their votes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub votes: Option<VotesJson>,
+    /// absent if the user hides their stats
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seeding: Option<SeedingJson>,
+    /// absent if the user hides their stats
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pug_rating: Option<PugRatingJson>,
+    /// absent if the user hides their dominations
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dominations: Option<DominationsJson>,
+}
+
+/// a user's public profile, leaving out whatever they've chosen to hide
+pub async fn get_profile(pool: &Pool<MySql>, uid: UserId) -> Result<Option<ProfileJson>, Error> {
+    let Some(profile) = ::profile::find_user_profile(pool, uid).await? else {
+        return Ok(None);
+    };
+    let mut json = ProfileJson {
+        discord_id: profile.uid.clone(),
+        title: profile.title.clone(),
+        description: profile.description.clone(),
+        url: profile.url.clone(),
+        image: profile.image.clone(),
+        color: profile.color,
+        classes: profile
+            .get_classes()
+            .into_iter()
+            .map(|c| c.to_string())
+            .collect(),
+        favorite_map: profile.favorite_map.clone(),
+        views: profile.views,
+        catcoin: catcoin::get_catcoin(pool, uid).await?.catcoin,
+        achievements: achievements::get_unlocked(pool, uid)
+            .await?
+            .into_iter()
+            .map(|u| u.achievement.id)
+            .collect(),
+        steamid: profile.steamid.clone(),
+        votes: None,
+        seeding: None,
+        pug_rating: None,
+        dominations: None,
+    };
+    if profile.hide_votes == 0 {
+        let votes = ::profile::vote::get_profile_votes(pool, uid).await?;
+        json.votes = Some(VotesJson {
+            likes: votes.likes,
+            dislikes: votes.dislikes,
+        });
+    }
+    if profile.hide_stats == 0 {
+        json.pug_rating = pugs::get_rating(pool, uid).await?.map(|r| PugRatingJson {
+            rating: r.rating,
+            games: r.games,
+            wins: r.wins,
+            losses: r.losses,
+            ties: r.ties,
+        });
+    }
+    if let Some(steamid) = &profile.steamid {
+        if profile.hide_stats == 0 {
+            json.seeding = sqlx::query_as::<_, (i64, i64)>(
+                r#"
+				SELECT `rank`, `seconds_seeded` FROM (
+					SELECT `steamid`, `seconds_seeded`, CAST(RANK() OVER (ORDER BY `seconds_seeded` DESC) AS SIGNED) AS `rank` FROM `seederboard`
+				) t WHERE `steamid` = ?
+			"#,
+            )
+            .bind(steamid)
+            .fetch_optional(pool)
+            .await?
+            .map(|(rank, seconds_seeded)| SeedingJson {
+                rank,
+                seconds_seeded,
+            });
+        }
+        if profile.hide_dominations == 0 {
+            json.dominations = sqlx::query_as::<_, (Option<i64>, Option<i64>)>(
+                r#"
+				SELECT
+					CAST(SUM(CASE WHEN `lt_steamid` = ? THEN `lt_dominations` ELSE `gt_dominations` END) AS SIGNED),
+					CAST(SUM(CASE WHEN `lt_steamid` = ? THEN `gt_dominations` ELSE `lt_dominations` END) AS SIGNED)
+				FROM `rivalries` WHERE `lt_steamid` = ? OR `gt_steamid` = ?
+			"#,
+            )
+            .bind(steamid)
+            .bind(steamid)
+            .bind(steamid)
+            .bind(steamid)
+            .fetch_optional(pool)
+            .await?
+            .map(|(dominations, dominated)| DominationsJson {
+                dominations: dominations.unwrap_or(0),
+                dominated: dominated.unwrap_or(0),
+            });
+        }
+    }
+    Ok(Some(json))
+}
+
+async fn profile(State(state): State<ApiState>, Path(discord_id): Path<String>) -> Response {
+    let Some(uid) = discord_id.parse::<u64>().ok().filter(|id| *id != 0) else {
+        return error(StatusCode::BAD_REQUEST, "Invalid discord id");
+    };
+    let value = state
+        .cache
+        .get_or_try_insert(&format!("profiles/{uid}"), PROFILE_TTL, || async {
+            Ok(serde_json::to_value(
+                get_profile(&state.pool, UserId::new(uid)).await?,
+            )?)
+        })
+        .await;
+    match value {
+        Ok(Value::Null) => error(StatusCode::NOT_FOUND, "Profile not found"),
+        value => respond(value),
+    }
+}
+
+#[derive(Serialize, sqlx::FromRow)]
+pub struct MapStatsJson {
+    pub map: String,
+    pub seconds_played: i64,
+    pub player_seconds: i64,
+    pub peak_players: i32,
+    pub last_played: chrono::DateTime<chrono::Utc>,
+}
+
+/// the most played maps
+pub async fn get_map_stats(pool: &Pool<MySql>, limit: i64) -> Result<Vec<MapStatsJson>, Error> {
+    Ok(sqlx::query_as(
+        "SELECT `map`, `seconds_played`, `player_seconds`, `peak_players`, `last_played` FROM `map_stats` ORDER BY `player_seconds` DESC LIMIT ?",
+    )
+    .bind(limit)
+    .fetch_all(pool)
+    .await?)
+}
+
+async fn map_stats(State(state): State<ApiState>, Query(limit): Query<Limit>) -> Response {
+    let limit = limit.get();
+    respond(
+        state
+            .cache
+            .get_or_try_insert(&format!("maps/stats/{limit}"), LEADERBOARD_TTL, || async {
+                Ok(serde_json::to_value(
+                    get_map_stats(&state.pool, limit).await?,
+                )?)
+            })
+            .await,
+    )
+}
+
+/// adds a sample of a server's state to the map stats
+pub async fn record_map_sample(
+    pool: &Pool<MySql>,
+    state: &GameState,
+    interval: Duration,
+) -> Result<(), Error> {
+    let players = state.players.len() as i64;
+    if players == 0 {
+        return Ok(());
+    }
+    let seconds = interval.as_secs() as i64;
+    sqlx::query!(
+        r#"
+		INSERT INTO `map_stats` (`map`, `seconds_played`, `player_seconds`, `peak_players`)
+		VALUES (?, ?, ?, ?)
+		ON DUPLICATE KEY UPDATE
+			`seconds_played` = `seconds_played` + VALUES(`seconds_played`),
+			`player_seconds` = `player_seconds` + VALUES(`player_seconds`),
+			`peak_players` = GREATEST(`peak_players`, VALUES(`peak_players`)),
+			`last_played` = CURRENT_TIMESTAMP
+	"#,
+        state.map,
+        seconds,
+        seconds * players,
+        players
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
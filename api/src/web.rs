@@ -0,0 +1,263 @@
+use std::time::Duration;
+
+use askama::Template;
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    middleware,
+    response::{Html, IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use poise::serenity_prelude::UserId;
+use serde_json::Value;
+use sqlx::{MySql, Pool};
+
+use common::{util::hhmmss, Error};
+
+use crate::{
+    response_doc,
+    v1::{self, ServerJson},
+    ApiState,
+};
+
+/// how long rendered pages are cached
+const SERVERS_TTL: Duration = Duration::from_secs(15);
+const PAGE_TTL: Duration = Duration::from_secs(60);
+/// rows shown on each leaderboard
+const LEADERBOARD_SIZE: i64 = 25;
+
+pub fn router(state: ApiState) -> Router<ApiState> {
+    Router::new()
+        .route("/", get(servers))
+        .route("/servers", get(servers))
+        .route("/seederboard", get(seederboard))
+        .route("/catcoin", get(catcoin))
+        .route("/stocks", get(stocks))
+        .route("/profiles/:discord_id", get(profile))
+        .layer(middleware::from_fn_with_state(state, v1::rate_limit))
+}
+
+/// serves a rendered page from the cache, rendering it if needed
+async fn page<F, Fut>(state: &ApiState, key: &str, ttl: Duration, render: F) -> Response
+where
+    F: FnOnce() -> Fut,
+    Fut: std::future::Future<Output = Result<Option<String>, Error>>,
+{
+    let html = state
+        .cache
+        .get_or_try_insert(&format!("page/{key}"), ttl, || async {
+            Ok(render().await?.map(Value::String).unwrap_or(Value::Null))
+        })
+        .await;
+    match html {
+        Ok(Value::String(html)) => Html(html).into_response(),
+        Ok(_) => (StatusCode::NOT_FOUND, response_doc("Not found.")).into_response(),
+        Err(e) => {
+            log::error!("Could not render {key}: {e}");
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                response_doc("Something went wrong."),
+            )
+                .into_response()
+        }
+    }
+}
+
+#[derive(Template)]
+#[template(path = "servers.html")]
+struct ServersPage {
+    servers: Vec<ServerJson>,
+}
+
+async fn servers(State(state): State<ApiState>) -> Response {
+    page(&state, "servers", SERVERS_TTL, || async {
+        let servers = v1::get_servers(&state).await?;
+        Ok(Some(ServersPage { servers }.render()?))
+    })
+    .await
+}
+
+struct SeederRow {
+    rank: i64,
+    name: String,
+    steamid: String,
+    time: String,
+}
+
+#[derive(Template)]
+#[template(path = "seederboard.html")]
+struct SeederboardPage {
+    seeders: Vec<SeederRow>,
+}
+
+async fn seederboard(State(state): State<ApiState>) -> Response {
+    page(&state, "seederboard", PAGE_TTL, || async {
+        let seeders = v1::get_seederboard(&state.pool, LEADERBOARD_SIZE)
+            .await?
+            .into_iter()
+            .map(|s| SeederRow {
+                rank: s.rank,
+                name: s.name.unwrap_or(s.steamid.clone()),
+                steamid: s.steamid,
+                time: hhmmss(s.seconds_seeded.max(0) as u64),
+            })
+            .collect();
+        Ok(Some(SeederboardPage { seeders }.render()?))
+    })
+    .await
+}
+
+/// a user in a leaderboard, linking to their profile
+struct UserRow {
+    rank: usize,
+    discord_id: String,
+    name: String,
+    value: i64,
+}
+
+#[derive(Template)]
+#[template(path = "catcoin.html")]
+struct CatcoinPage {
+    wallets: Vec<UserRow>,
+}
+
+async fn catcoin(State(state): State<ApiState>) -> Response {
+    page(&state, "catcoin", PAGE_TTL, || async {
+        let wallets = catcoin::get_top(&state.pool)
+            .await?
+            .into_iter()
+            .enumerate()
+            .map(|(i, w)| UserRow {
+                rank: i + 1,
+                name: state.display_name(&w.uid),
+                discord_id: w.uid,
+                value: w.catcoin,
+            })
+            .collect();
+        Ok(Some(CatcoinPage { wallets }.render()?))
+    })
+    .await
+}
+
+#[derive(sqlx::FromRow)]
+struct CompanyRow {
+    name: String,
+    tag: String,
+    logo: String,
+    price: i32,
+    total_shares: i32,
+    market_cap: i64,
+}
+
+#[derive(Template)]
+#[template(path = "stocks.html")]
+struct StocksPage {
+    companies: Vec<CompanyRow>,
+    investors: Vec<UserRow>,
+}
+
+async fn get_stock_market(
+    pool: &Pool<MySql>,
+) -> Result<(Vec<CompanyRow>, Vec<(String, i64)>), Error> {
+    let companies = sqlx::query_as(
+        "SELECT `name`, `tag`, `logo`, `price`, `total_shares`, CAST(`price` * `total_shares` AS SIGNED) AS `market_cap` FROM `catcoin_company` ORDER BY `market_cap` DESC",
+    )
+    .fetch_all(pool)
+    .await?;
+    let investors = sqlx::query_as(
+        r#"
+		SELECT s.`uid`, CAST(SUM(s.`shares_owned` * c.`price`) AS SIGNED) AS `value`
+		FROM `catcoin_user_shares` s JOIN `catcoin_company` c ON c.`id` = s.`company_id`
+		WHERE s.`shares_owned` > 0
+		GROUP BY s.`uid` ORDER BY `value` DESC LIMIT ?
+	"#,
+    )
+    .bind(LEADERBOARD_SIZE)
+    .fetch_all(pool)
+    .await?;
+    Ok((companies, investors))
+}
+
+async fn stocks(State(state): State<ApiState>) -> Response {
+    page(&state, "stocks", PAGE_TTL, || async {
+        let (companies, investors) = get_stock_market(&state.pool).await?;
+        let investors = investors
+            .into_iter()
+            .enumerate()
+            .map(|(i, (uid, value))| UserRow {
+                rank: i + 1,
+                name: state.display_name(&uid),
+                discord_id: uid,
+                value,
+            })
+            .collect();
+        Ok(Some(
+            StocksPage {
+                companies,
+                investors,
+            }
+            .render()?,
+        ))
+    })
+    .await
+}
+
+struct AchievementRow {
+    emoji: &'static str,
+    name: &'static str,
+    description: &'static str,
+}
+
+#[derive(Template)]
+#[template(path = "profile.html")]
+struct ProfilePage {
+    name: String,
+    avatar: Option<String>,
+    title: String,
+    color: String,
+    profile: v1::ProfileJson,
+    achievements: Vec<AchievementRow>,
+    seeding: Option<String>,
+}
+
+async fn profile(State(state): State<ApiState>, Path(discord_id): Path<String>) -> Response {
+    let Some(uid) = discord_id.parse::<u64>().ok().filter(|id| *id != 0) else {
+        return (StatusCode::NOT_FOUND, response_doc("Profile not found.")).into_response();
+    };
+    page(&state, &format!("profiles/{uid}"), PAGE_TTL, || async {
+        let Some(mut profile) = v1::get_profile(&state.pool, UserId::new(uid)).await? else {
+            return Ok(None);
+        };
+        // links are user provided, so only allow plain web urls
+        let is_web_url = |url: &String| url.starts_with("https://") || url.starts_with("http://");
+        profile.url = profile.url.filter(is_web_url);
+        profile.image = profile.image.filter(is_web_url);
+        let name = state.display_name(&profile.discord_id);
+        Ok(Some(
+            ProfilePage {
+                avatar: state.avatar_url(&profile.discord_id),
+                title: profile.title.replace('%', &name),
+                color: format!("#{:06x}", profile.color.unwrap_or(0x5865F2)),
+                achievements: profile
+                    .achievements
+                    .iter()
+                    .filter_map(|id| achievements::Achievement::get(id))
+                    .map(|a| AchievementRow {
+                        emoji: a.emoji,
+                        name: a.name,
+                        description: a.description,
+                    })
+                    .collect(),
+                seeding: profile
+                    .seeding
+                    .as_ref()
+                    .map(|s| format!("{} (#{})", hhmmss(s.seconds_seeded.max(0) as u64), s.rank)),
+                name,
+                profile,
+            }
+            .render()?,
+        ))
+    })
+    .await
+}
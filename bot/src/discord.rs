@@ -8,9 +8,10 @@ use poise::serenity_prelude::{self as serenity, ChannelId, GuildId, Mentionable,
 use serenity::CreateMessage;
 
 use api::ApiState;
-use common::{Error, util::parse_env};
+use common::{Error, metrics, util::parse_env};
 use genimg::GenImg;
 use steam::SteamIDClient;
+use tf2::cfgs::{CfgConfig, CfgIndex};
 use tf2::{Server, logs, wacky};
 use yapawards::{self, YapTracker};
 
@@ -25,7 +26,7 @@ mod commands;
 
 use crate::discord::new_user::NSFWBets;
 
-use self::commands::ReminderManager;
+use self::commands::{PermissionPolicy, ReminderManager};
 use self::media_cooldown::CooldownMessage;
 mod emojirank;
 mod media_cooldown;
@@ -36,6 +37,7 @@ mod on_delete;
 mod on_message;
 mod on_react;
 mod player_count;
+mod reports;
 
 pub type Context<'a> = poise::Context<'a, PoiseData, Error>;
 pub type ApplicationContext<'a> = poise::ApplicationContext<'a, PoiseData, Error>;
@@ -47,8 +49,10 @@ pub struct PoiseData {
     pub guild_id: GuildId,
     /// identify the pug server. tkgp specific
     pub pug_server: SocketAddr,
-    /// list of pug cfgs available for use
-    pub pug_cfgs: Vec<String>,
+    /// cfgs available on the pug server
+    pub pug_cfgs: Arc<RwLock<CfgIndex>>,
+    /// players queued for the next pug
+    pub pug_queue: Arc<RwLock<commands::PugQueue>>,
     /// image spam prevention
     pub media_cooldown: Arc<RwLock<media_cooldown::MediaCooldown>>,
     media_cooldown_sender: OnceCell<Sender<CooldownMessage>>,
@@ -60,6 +64,9 @@ pub struct PoiseData {
     /// Shared api state
     pub api_state: ApiState,
 
+    /// tags bought from the catcoin shop, shown in the chat relay
+    pub relay_tags: logs::RelayTags,
+
     /// Yap tracker
     pub yap_tracker: Arc<RwLock<YapTracker>>,
 
@@ -93,7 +100,7 @@ pub struct PoiseData {
     /// channel where users who left are logged
     pub leaver_log_channel: ChannelId,
     /// mod channel id
-    pub _mod_channel: ChannelId,
+    pub mod_channel: ChannelId,
     /// age verification channel
     pub _birthday_channel: ChannelId,
     /// for posting stock market info daily
@@ -103,6 +110,10 @@ pub struct PoiseData {
     pub seeder_cooldown: Arc<RwLock<HashMap<SocketAddr, DateTime<Utc>>>>,
     /// \ !remindme
     pub reminders: Arc<RwLock<ReminderManager>>,
+    /// role requirements for commands and rcon
+    pub permissions: Arc<RwLock<PermissionPolicy>>,
+    /// directory sourcetv demos are recorded to, relative to the server root
+    pub demo_dir: String,
     /// Bot database pool
     pub local_pool: Pool<MySql>,
     /// Sourcebans database pool
@@ -173,6 +184,20 @@ impl PoiseData {
     }
 }
 
+/// counts framework errors before passing them to poise's default handler
+async fn on_error(error: poise::FrameworkError<'_, PoiseData, Error>) {
+    let kind = match &error {
+        poise::FrameworkError::Command { .. } => "command",
+        poise::FrameworkError::EventHandler { .. } => "event",
+        poise::FrameworkError::Setup { .. } => "setup",
+        _ => "framework",
+    };
+    metrics::DISCORD_ERRORS.inc(&[("kind", kind)]);
+    if let Err(e) = poise::builtins::on_error(error).await {
+        log::error!("Could not handle framework error: {e}");
+    }
+}
+
 /// handle discord events
 async fn event_handler(
     ctx: &serenity::Context,
@@ -297,7 +322,6 @@ async fn event_handler(
 pub async fn start_bot(
     log_receiver: logs::LogReceiver,
     servers: HashMap<SocketAddr, Server>,
-    api_state: ApiState,
 ) -> Result<(), Error> {
     let bot_token: String = parse_env("BOT_TOKEN");
 
@@ -318,8 +342,12 @@ pub async fn start_bot(
     let stock_market_channel = ChannelId::new(parse_env("STOCK_MARKET_CHANNEL_ID"));
     let yapawards_channel = ChannelId::new(parse_env("YAPAWARDS_CHANNEL_ID"));
     let logstf_channel = ChannelId::new(parse_env("LOGSTF_CHANNEL_ID"));
+    let pug_channel = ChannelId::new(parse_env("PUG_CHANNEL_ID"));
+    let achievements_channel = ChannelId::new(parse_env("ACHIEVEMENTS_CHANNEL_ID"));
 
     let steamid_myid: u64 = parse_env("STEAMID_MYID");
+    let demo_dir: String = parse_env("DEMO_DIR");
+    let demo_retention_days: i64 = parse_env("DEMO_RETENTION_DAYS");
 
     let db_url: String = parse_env("DATABASE_URL");
     let sb_db_url: String = parse_env("SB_DATABASE_URL");
@@ -332,6 +360,9 @@ pub async fn start_bot(
         .inspect_err(|e| log::error!("failed to migrate: {e:?}"));
     log::info!("DB Migrated.");
 
+    log::info!("Spawning HTTP API listener...");
+    let api_state = api::init(local_pool.clone(), servers.clone(), log_receiver.clone()).await?;
+
     let sb_pool = Pool::<MySql>::connect(&sb_db_url).await?;
     log::info!("Connected to sourcebans pool.");
 
@@ -350,24 +381,46 @@ pub async fn start_bot(
         .next()
         .expect("Could not resolve PUG server address.");
 
+    let pug_cfg_config = match std::env::var("PUG_CFG_JSON") {
+        Ok(path) if !path.is_empty() => CfgConfig::load(&path)?,
+        _ => CfgConfig::default(),
+    };
+    let pug_cfgs = Arc::new(RwLock::new(CfgIndex::new(pug_cfg_config)));
+    if let Some(server) = servers.get(&pug_server) {
+        commands::spawn_pug_cfg_refresh_thread(server.clone(), pug_cfgs.clone());
+    }
+
     let reminders = Arc::new(RwLock::new(
         ReminderManager::new_with_init(&local_pool).await?,
     ));
 
+    let permissions = Arc::new(RwLock::new(
+        PermissionPolicy::new_with_init(&local_pool, mod_role).await?,
+    ));
+
     let yap_tracker = Arc::new(RwLock::new(yapawards::YapTracker::new()));
 
+    let relay_tags: logs::RelayTags = Arc::new(RwLock::new(HashMap::new()));
+    commands::refresh_relay_tags(&local_pool, &relay_tags).await?;
+
     let framework = {
+        let api_state = api_state.clone();
         let watcher = watcher.clone();
         let servers = servers.clone();
         let local_pool = local_pool.clone();
         let sb_pool = sb_pool.clone();
         let pug_server = pug_server.clone();
+        let pug_cfgs = pug_cfgs.clone();
         let reminders = reminders.clone();
         let yap_tracker = yap_tracker.clone();
+        let relay_tags = relay_tags.clone();
+        let demo_dir = demo_dir.clone();
         poise::Framework::builder()
             .options(poise::FrameworkOptions {
                 commands: commands::ALL.iter().map(|f| f()).collect(),
                 event_handler: |a, b, c, d| Box::pin(event_handler(a, b, c, d)),
+                on_error: |e| Box::pin(on_error(e)),
+                command_check: Some(|ctx| Box::pin(commands::permissions::check(ctx))),
                 prefix_options: PrefixFrameworkOptions {
                     prefix: Some("!".to_owned()),
                     ..Default::default()
@@ -389,24 +442,13 @@ pub async fn start_bot(
                         )),
                         guild_id: guild,
                         reminders,
-                        pug_cfgs: [
-                            "rgl_off",
-                            "mge",
-                            "arena",
-                            "rgl_7s_koth_bo5",
-                            "rgl_6s_koth_scrim",
-                            "rgl_6s_koth_bo5",
-                            "rgl_6s_5cp_scrim",
-                            "rgl_6s_5cp_match_pro",
-                            "tfcl_off",
-                            "tfcl_UD_ultiduo",
-                        ]
-                        .into_iter()
-                        .map(str::to_owned)
-                        .collect(),
+                        permissions,
+                        pug_cfgs,
                         catcoin_spam_filter: Arc::new(RwLock::new(catcoin::SpamFilter::new())),
                         pug_server,
+                        pug_queue: Arc::new(RwLock::new(commands::PugQueue::new())),
                         api_state,
+                        relay_tags,
                         emoji_rank: watcher.clone(),
                         seeder_role,
                         horny_role,
@@ -422,11 +464,12 @@ pub async fn start_bot(
                         yap_tracker,
                         nsfwbets: Arc::new(RwLock::new(NSFWBets::new())),
                         leaver_log_channel,
-                        _mod_channel: mod_channel,
+                        mod_channel,
                         _birthday_channel: birthday_channel,
                         stock_market_channel,
                         media_cooldown_sender: OnceCell::new(),
                         seeder_cooldown: Arc::new(RwLock::new(HashMap::new())),
+                        demo_dir,
                         local_pool,
                         _sb_pool: sb_pool,
                         steamid_client: SteamIDClient::new(
@@ -452,6 +495,7 @@ pub async fn start_bot(
         .framework(framework)
         .await
         .expect("Could not initialize client.");
+    api_state.set_discord(client.cache.clone(), guild);
 
     for (_addr, server) in servers.iter() {
         player_count::spawn_player_count_thread(server.clone(), client.http.clone());
@@ -462,6 +506,50 @@ pub async fn start_bot(
         servers.clone(),
         local_pool.clone(),
         client.http.clone(),
+        relay_tags.clone(),
+    )
+    .await;
+
+    {
+        let history = logs::ChatHistory::subscribe(&log_receiver, 50).await;
+        reports::spawn_report_thread(
+            log_receiver.clone(),
+            servers.clone(),
+            history,
+            local_pool.clone(),
+            demo_dir.clone(),
+            client.http.clone(),
+            mod_channel,
+        )
+        .await;
+    }
+    commands::spawn_demo_prune_thread(
+        servers.clone(),
+        local_pool.clone(),
+        demo_dir.clone(),
+        demo_retention_days,
+    );
+
+    rivalries::spawn_rivalry_tracker(
+        log_receiver.clone(),
+        servers.clone(),
+        local_pool.clone(),
+        client.http.clone(),
+    )
+    .await;
+
+    achievements::tracker::spawn_achievement_tracker(
+        local_pool.clone(),
+        client.http.clone(),
+        achievements_channel,
+    );
+
+    pugs::spawn_result_tracker(
+        log_receiver.clone(),
+        pug_server,
+        local_pool.clone(),
+        client.http.clone(),
+        pug_channel,
     )
     .await;
 
@@ -485,6 +573,8 @@ pub async fn start_bot(
         client.http.clone(),
     );
     emojirank::spawn_flush_thread(watcher.clone(), local_pool.clone());
+    catcoin::escrow::spawn_escrow_thread(local_pool.clone(), client.http.clone());
+    catcoin::auction::spawn_auction_thread(local_pool.clone(), client.http.clone());
     commands::spawn_reminder_thread(
         client.http.clone(),
         local_pool.clone(),
@@ -504,6 +594,18 @@ pub async fn start_bot(
         .expect("Wacky server dose not exist.");
 
     sched.add(wacky::start_job(wacky_server.clone())).await?;
+    sched
+        .add(commands::shop_expiry_job(
+            client.http.clone(),
+            guild,
+            servers.clone(),
+            local_pool.clone(),
+            relay_tags,
+        ))
+        .await?;
+    sched
+        .add(commands::economy_snapshot_job(local_pool.clone()))
+        .await?;
     sched.add(wacky::end_job(wacky_server.clone())).await?;
     sched
         .add(yapawards::start_job(
@@ -516,6 +618,10 @@ pub async fn start_bot(
     yapawards::init(yap_tracker, &local_pool);
     logstf::init(
         &local_pool,
+        std::env::var("LOGSTF_BASE_URL")
+            .ok()
+            .filter(|s| !s.is_empty())
+            .unwrap_or("https://logs.tf".to_owned()),
         steamid_myid,
         client.http.clone(),
         logstf_channel,
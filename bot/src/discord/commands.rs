@@ -51,6 +51,15 @@ pub use teamcaptain::*;
 mod catcoin;
 pub use catcoin::*;
 
+mod catcoinadmin;
+pub use catcoinadmin::*;
+
+mod shop;
+pub use shop::*;
+
+mod auction;
+pub use auction::*;
+
 mod seederboard;
 pub use seederboard::*;
 
@@ -60,6 +69,24 @@ pub use profile::*;
 mod stocks;
 pub use stocks::*;
 
+mod pugstats;
+pub use pugstats::*;
+
+mod pugrank;
+pub use pugrank::*;
+
+mod rivals;
+pub use rivals::*;
+
+mod achievements;
+pub use achievements::*;
+
+mod demos;
+pub use demos::{demos, pin_latest_demo, spawn_demo_prune_thread};
+
+pub mod permissions;
+pub use permissions::{PermissionPolicy, permissions};
+
 use stats::psychostats;
 
 use poise::serenity_prelude::{
@@ -77,6 +104,10 @@ pub static ALL: &[fn() -> poise::Command<PoiseData, Error>] = &[
     bibleverse,
     classes,
     catcoin,
+    catcoinadmin,
+    shop,
+    auction,
+    auctions,
     || poise::Command {
         slash_action: remindme_slash().slash_action,
         ..remindme()
@@ -88,6 +119,7 @@ pub static ALL: &[fn() -> poise::Command<PoiseData, Error>] = &[
     delete_server,
     get_profile,
     link,
+    unlink,
     fixpulls,
     teamcaptain,
     wacky,
@@ -99,6 +131,8 @@ pub static ALL: &[fn() -> poise::Command<PoiseData, Error>] = &[
     emojitop,
     birthday_modal,
     pug,
+    pugqueue,
+    pugresult,
     donate,
     //dihh,
     rcon,
@@ -126,6 +160,14 @@ pub static ALL: &[fn() -> poise::Command<PoiseData, Error>] = &[
     tf2ungag,
     get_videos,
     pingpugs,
+    permissions,
+    demos,
+    pugstats,
+    pugrank,
+    rivals,
+    rivalry,
+    rivalgraph,
+    achievements,
 ];
 
 /// ping pugs
@@ -728,7 +770,7 @@ pub async fn playercap(
     ctx.defer().await?;
     let re = Regex::new(r#""maxplayers" is "(\d+)""#).unwrap();
 
-    let server = ctx.data().server(&server)?;
+    let server = rcon_server(ctx, &server).await?;
 
     let min: i32 = 24;
     let max = server.controller.write().await.run("maxplayers").await?;
@@ -770,7 +812,7 @@ pub async fn rcon(
     #[description = "The command to send."] cmd: String,
     #[description = "Hide the reply?"] hide_reply: Option<bool>,
 ) -> Result<(), Error> {
-    let reply = rcon_user_output(&output_servers(ctx, server)?, cmd).await;
+    let reply = rcon_user_output(&output_servers(ctx, server).await?, cmd).await;
     let hide_reply = hide_reply.unwrap_or(false);
     ctx.send(CreateReply::default().ephemeral(hide_reply).content(reply))
         .await?;
@@ -791,7 +833,7 @@ pub async fn snipers(
         "sm_classrestrict_blu_snipers {0}; sm_classrestrict_red_snipers {0}",
         limit
     );
-    let reply = rcon_user_output(&output_servers(ctx, Some(server))?, cmd).await;
+    let reply = rcon_user_output(&output_servers(ctx, Some(server)).await?, cmd).await;
     let hide_reply = hide_reply.unwrap_or(false);
     ctx.send(CreateReply::default().ephemeral(hide_reply).content(reply))
         .await?;
@@ -817,7 +859,7 @@ pub async fn bhop(
         if enabled { 1 } else { 0 },
         if autohop { 1 } else { 0 }
     );
-    let server = ctx.data().server(&server)?;
+    let server = rcon_server(ctx, &server).await?;
     let _ = rcon_user_output(&[server], cmd).await;
     let reply = if enabled {
         format!(
@@ -897,7 +939,7 @@ pub async fn respawntimes(
             if instant { "0" } else { "1" },
         ),
     };
-    let reply = rcon_user_output(&output_servers(ctx, server)?, cmd).await;
+    let reply = rcon_user_output(&output_servers(ctx, server).await?, cmd).await;
     ctx.send(CreateReply::default().content(reply)).await?;
 
     Ok(())
@@ -0,0 +1,71 @@
+use poise::serenity_prelude::{self as serenity, Color, CreateEmbed, CreateEmbedFooter};
+
+use super::util::paginate;
+use crate::discord::Context;
+use ::achievements::{ACHIEVEMENTS, Source, get_unlocked};
+use common::Error;
+
+/// achievements listed per page
+const PAGE_SIZE: usize = 8;
+
+/// Browse your achievements, or someone else's.
+#[poise::command(slash_command, user_cooldown = 10)]
+pub async fn achievements(
+    ctx: Context<'_>,
+    #[description = "The user whose achievements to view"] user: Option<serenity::User>,
+) -> Result<(), Error> {
+    let pool = &ctx.data().local_pool;
+    let user: &serenity::User = user.as_ref().unwrap_or(ctx.author());
+
+    let unlocked = get_unlocked(pool, user.id).await?;
+    // progress is only needed for locked achievements, and each source is queried once
+    let mut progress: Vec<(Source, i64)> = vec![];
+    let mut lines = vec![];
+    for achievement in ACHIEVEMENTS {
+        let line = match unlocked.iter().find(|u| u.achievement.id == achievement.id) {
+            Some(unlock) => format!(
+                "✅ {} - {}\n-# unlocked <t:{}:d>",
+                achievement.title(),
+                achievement.description,
+                unlock.unlocked_at.timestamp()
+            ),
+            None => {
+                let value = match progress.iter().find(|(s, _)| *s == achievement.source) {
+                    Some((_, value)) => *value,
+                    None => {
+                        let value = achievement.source.value(pool, user.id).await?;
+                        progress.push((achievement.source, value));
+                        value
+                    }
+                };
+                format!(
+                    "🔒 {} - {}\n-# `{}` / `{}`",
+                    achievement.title(),
+                    achievement.description,
+                    achievement.source.format(value.min(achievement.threshold)),
+                    achievement.source.format(achievement.threshold)
+                )
+            }
+        };
+        lines.push(line);
+    }
+    let pages: Vec<String> = lines.chunks(PAGE_SIZE).map(|c| c.join("\n")).collect();
+
+    let embed = |page: usize| {
+        CreateEmbed::new()
+            .title(format!("🏅 {}'s achievements", user.name))
+            .description(pages[page].clone())
+            .color(Color::GOLD)
+            .footer(CreateEmbedFooter::new(format!(
+                "{} / {} unlocked | Page {} / {}",
+                unlocked.len(),
+                ACHIEVEMENTS.len(),
+                page + 1,
+                pages.len()
+            )))
+    };
+    paginate(ctx, pages.len(), false, |page| {
+        std::future::ready(Ok(embed(page)))
+    })
+    .await
+}
@@ -0,0 +1,251 @@
+use chrono::TimeDelta;
+use poise::serenity_prelude::{
+    AutocompleteChoice, Color, CreateAllowedMentions, CreateEmbed, CreateEmbedFooter, Mentionable,
+};
+use poise::{self, CreateReply};
+
+use super::util::paginate;
+use crate::discord::Context;
+use ::catcoin::auction::{self, Auction, BidOutcome};
+use ::catcoin::inventory::search_inventory;
+use common::Error;
+use emoji::emoji;
+
+/// auctions shown per page of /auctions
+const AUCTIONS_PAGE_SIZE: i64 = 10;
+/// how long an auction runs if no duration is given
+const DEFAULT_HOURS: u32 = 24;
+
+/// Auction off your pulls
+#[poise::command(
+    slash_command,
+    subcommands("sell", "bid", "buynow"),
+    subcommand_required
+)]
+pub async fn auction(_: Context<'_>) -> Result<(), Error> {
+    Ok(()) // never run
+}
+
+async fn pulls_autocomplete(ctx: Context<'_>, partial: &str) -> Vec<AutocompleteChoice> {
+    search_inventory(&ctx.data().local_pool, ctx.author().id, partial)
+        .await
+        .unwrap_or_default()
+        .into_iter()
+        .map(|pull| {
+            AutocompleteChoice::new(
+                format!(
+                    "{} {} #{}",
+                    pull.reward.rarity, pull.reward.name, pull.number
+                ),
+                pull.id,
+            )
+        })
+        .collect()
+}
+
+async fn auctions_autocomplete(ctx: Context<'_>, partial: &str) -> Vec<AutocompleteChoice> {
+    auction::get_open(&ctx.data().local_pool, 25, 0)
+        .await
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|a| {
+            a.pull
+                .reward
+                .name
+                .to_lowercase()
+                .contains(&partial.to_lowercase())
+        })
+        .map(|a| {
+            AutocompleteChoice::new(
+                format!(
+                    "#{} {} {} #{} ({})",
+                    a.id,
+                    a.pull.reward.rarity,
+                    a.pull.reward.name,
+                    a.pull.number,
+                    a.min_bid()
+                ),
+                a.id,
+            )
+        })
+        .collect()
+}
+
+/// Put one of your pulls up for auction
+#[poise::command(slash_command, user_cooldown = 10)]
+async fn sell(
+    ctx: Context<'_>,
+    #[description = "The pull to sell"]
+    #[autocomplete = "pulls_autocomplete"]
+    pull: i32,
+    #[description = "The lowest bid accepted"]
+    #[min = 1]
+    start_price: u64,
+    #[description = "A price anyone can pay to end the auction right away"]
+    #[min = 1]
+    buy_now: Option<u64>,
+    #[description = "How many hours the auction runs, 24 by default"]
+    #[min = 1]
+    #[max = 168]
+    hours: Option<u32>,
+) -> Result<(), Error> {
+    if buy_now.is_some_and(|price| price < start_price) {
+        ctx.send(
+            CreateReply::default()
+                .content("The buy now price can't be below the starting price.")
+                .ephemeral(true),
+        )
+        .await?;
+        return Ok(());
+    }
+    let pool = &ctx.data().local_pool;
+    let hours = hours.unwrap_or(DEFAULT_HOURS);
+    let Some(id) = auction::list(
+        pool,
+        ctx.author().id,
+        pull,
+        start_price,
+        buy_now,
+        TimeDelta::hours(hours.into()),
+    )
+    .await?
+    else {
+        ctx.send(
+            CreateReply::default()
+                .content("You don't own that pull, or it's already up for auction.")
+                .ephemeral(true),
+        )
+        .await?;
+        return Ok(());
+    };
+    let auction = auction::get(pool, id)
+        .await?
+        .ok_or("Auction went missing.")?;
+    ctx.send(
+        CreateReply::default()
+            .content(format!("{} started an auction!", ctx.author().mention()))
+            .embed(
+                CreateEmbed::new()
+                    .color(Color::GOLD)
+                    .title(format!("{} Auction", emoji("catcoin")))
+                    .description(auction.line()),
+            ),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Bid on an auction
+#[poise::command(slash_command, user_cooldown = 5)]
+async fn bid(
+    ctx: Context<'_>,
+    #[description = "The auction to bid on"]
+    #[autocomplete = "auctions_autocomplete"]
+    id: u64,
+    #[description = "How much to bid"]
+    #[min = 1]
+    amount: u64,
+) -> Result<(), Error> {
+    let outcome = auction::bid(&ctx.data().local_pool, id, ctx.author().id, amount).await?;
+    on_outcome(ctx, outcome).await
+}
+
+/// Buy an auction outright at its buy now price, while bidding is below it
+#[poise::command(slash_command, user_cooldown = 5)]
+async fn buynow(
+    ctx: Context<'_>,
+    #[description = "The auction to buy"]
+    #[autocomplete = "auctions_autocomplete"]
+    id: u64,
+) -> Result<(), Error> {
+    let outcome = auction::buy_now(&ctx.data().local_pool, id, ctx.author().id).await?;
+    on_outcome(ctx, outcome).await
+}
+
+/// replies to a bid or buy, and lets anyone outbid know
+async fn on_outcome(ctx: Context<'_>, outcome: BidOutcome) -> Result<(), Error> {
+    let reply = match outcome {
+        BidOutcome::Placed { auction, outbid } => {
+            if let Some((uid, refunded)) = outbid {
+                auction::notify_outbid(ctx.http(), &auction, uid, refunded).await;
+            }
+            ctx.send(
+                CreateReply::default()
+                    .content(format!(
+                        "{} is the highest bidder!\n{}",
+                        ctx.author().mention(),
+                        auction.line()
+                    ))
+                    .allowed_mentions(CreateAllowedMentions::new()),
+            )
+            .await?;
+            return Ok(());
+        }
+        BidOutcome::Bought { settlement, outbid } => {
+            if let Some((uid, refunded)) = outbid {
+                auction::notify_bought_out(ctx.http(), &settlement.auction, uid, refunded).await;
+            }
+            auction::notify_settled(ctx.http(), &settlement).await;
+            let auction = &settlement.auction;
+            ctx.send(CreateReply::default().content(format!(
+                "{} bought **{}** {} `#{}` for **{}** {}!",
+                ctx.author().mention(),
+                auction.pull.reward.rarity,
+                auction.pull.reward.name,
+                auction.pull.number,
+                settlement.price,
+                emoji("catcoin")
+            )))
+            .await?;
+            return Ok(());
+        }
+        BidOutcome::TooLow(min) => {
+            format!("You have to bid at least **{}** {}.", min, emoji("catcoin"))
+        }
+        BidOutcome::Closed => "That auction has ended.".to_owned(),
+        BidOutcome::NoBuyNow => "That auction can't be bought outright.".to_owned(),
+        BidOutcome::OwnAuction => "You can't bid on your own auction!".to_owned(),
+        BidOutcome::CantAfford => format!("You do not have enough catcoin {}.", emoji("catcoin")),
+    };
+    ctx.send(CreateReply::default().content(reply).ephemeral(true))
+        .await?;
+    Ok(())
+}
+
+/// Browse open auctions
+#[poise::command(slash_command, user_cooldown = 10)]
+pub async fn auctions(ctx: Context<'_>) -> Result<(), Error> {
+    let pool = &ctx.data().local_pool;
+
+    let total = auction::count_open(pool).await?;
+    if total == 0 {
+        ctx.send(
+            CreateReply::default()
+                .ephemeral(true)
+                .content("There are no auctions right now."),
+        )
+        .await?;
+        return Ok(());
+    }
+    let pages = (total + AUCTIONS_PAGE_SIZE - 1) / AUCTIONS_PAGE_SIZE;
+
+    paginate(ctx, pages as usize, false, move |page| async move {
+        let open =
+            auction::get_open(pool, AUCTIONS_PAGE_SIZE, page as i64 * AUCTIONS_PAGE_SIZE).await?;
+        Ok(CreateEmbed::new()
+            .color(Color::GOLD)
+            .title(format!("{} Auctions", emoji("catcoin")))
+            .description(
+                open.iter()
+                    .map(Auction::line)
+                    .collect::<Vec<String>>()
+                    .join("\n"),
+            )
+            .footer(CreateEmbedFooter::new(format!(
+                "Page {} / {} | /auction bid to bid",
+                page + 1,
+                pages
+            ))))
+    })
+    .await
+}
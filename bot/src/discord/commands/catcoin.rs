@@ -1,27 +1,49 @@
 use futures::TryFutureExt;
 use rand::prelude::*;
-use std::time::Duration;
+use sqlx::{MySql, Pool};
+use std::{collections::BTreeMap, time::Duration};
+use tokio_cron_scheduler::{Job, JobBuilder};
+
+use chrono::{TimeDelta, Utc};
 
 use emoji::emoji;
 use poise::{
-    self,
+    self, CreateReply, Modal,
     serenity_prelude::{
-        self as serenity, parse_message_url, ComponentInteractionCollector, CreateActionRow,
-        CreateAllowedMentions, CreateButton, CreateEmbed, CreateEmbedFooter,
-        CreateInteractionResponseMessage, CreateMessage, Mentionable, ReactionType,
+        self as serenity, ButtonStyle, ComponentInteractionCollector, CreateActionRow,
+        CreateAllowedMentions, CreateAttachment, CreateButton, CreateEmbed, CreateEmbedFooter,
+        CreateInteractionResponseMessage, CreateMessage, CreateSelectMenu, CreateSelectMenuKind,
+        CreateSelectMenuOption, EditInteractionResponse, Mentionable, ReactionType,
+        parse_message_url,
     },
-    CreateReply,
 };
 
-use crate::{discord::Context, Error};
+use super::util::paginate;
+use crate::{Error, discord::Context};
 use catcoin::{
-    get_catcoin, get_top, grant_catcoin, inventory::claim_old_pull,
-    inventory::CatcoinPullMessageData, inventory::PaginatedInventory, spend_catcoin, transact,
-    CatcoinWallet,
+    CatcoinWallet, collection, collection::Collection, collection::award_sets, economy, escrow,
+    get_catcoin, get_top, inventory::CatcoinPull, inventory::CatcoinPullMessageData,
+    inventory::PaginatedInventory, inventory::claim_old_pull, ledger, ledger::Reason, odds,
+    odds::Chance, random_pulls::Rarity, trade, trade::TradeOffer, transact,
 };
+use common::discord::execute_modal_generic;
 
 /// TKGP catcoin related stuff :3
-#[poise::command(slash_command, subcommands("balance", "top", "pay", "drop", "inv"))]
+#[poise::command(
+    slash_command,
+    subcommands(
+        "balance",
+        "top",
+        "pay",
+        "drop",
+        "inv",
+        "collection",
+        "history",
+        "trade",
+        "odds",
+        "economy"
+    )
+)]
 pub async fn catcoin(_: Context<'_>) -> Result<(), Error> {
     Ok(())
 }
@@ -46,28 +68,28 @@ pub async fn inv(
     let page_buttons = |pages: &Vec<PaginatedInventory>, page: usize| {
         let mut v = vec![];
         if page > 0 {
-            v.push(CreateButton::new(prev_id.clone()).emoji(ReactionType::Unicode("⬅️".to_owned())));
+            v.push(
+                CreateButton::new(prev_id.clone()).emoji(ReactionType::Unicode("⬅️".to_owned())),
+            );
         }
         if pages[page].has_next() {
-            v.push(CreateButton::new(next_id.clone()).emoji(ReactionType::Unicode("➡️".to_owned())));
+            v.push(
+                CreateButton::new(next_id.clone()).emoji(ReactionType::Unicode("➡️".to_owned())),
+            );
         }
         v.push(CreateButton::new(close_id.clone()).emoji(ReactionType::Unicode("❌".to_owned())));
         vec![CreateActionRow::Buttons(v)]
     };
 
     // initial response
-    let rh = ctx
-        .send(
-            CreateReply::default()
-                .embed(
-                    pages[page]
-                        .to_embed(ctx.serenity_context(), ctx.data().guild_id)
-                        .await?
-                        .footer(CreateEmbedFooter::new(format!("Page {}", page + 1))),
-                )
-                .components(page_buttons(&pages, page)),
-        )
-        .await?;
+    let (embed, gallery) = inv_page(ctx, &pages[page], page).await?;
+    let mut reply = CreateReply::default()
+        .embed(embed)
+        .components(page_buttons(&pages, page));
+    if let Some(gallery) = gallery {
+        reply = reply.attachment(gallery);
+    }
+    let rh = ctx.send(reply).await?;
 
     while let Some(mci) = ComponentInteractionCollector::new(ctx)
         .channel_id(ctx.channel_id())
@@ -75,43 +97,29 @@ pub async fn inv(
         .filter(move |mci| mci.data.custom_id.starts_with(&uuid.to_string()))
         .await
     {
-        if mci.data.custom_id == prev_id {
-            page = (page - 1).max(0);
-            rh.edit(
-                ctx,
-                CreateReply::default()
-                    .embed(
-                        pages[page]
-                            .to_embed(ctx.serenity_context(), ctx.data().guild_id)
-                            .await?
-                            .footer(CreateEmbedFooter::new(format!("Page {}", page + 1))),
-                    )
-                    .components(page_buttons(&pages, page)),
-            )
-            .await?;
-            mci.create_response(ctx, serenity::CreateInteractionResponse::Acknowledge)
-                .await?;
-        } else if mci.data.custom_id == next_id {
-            // if page is not fetched yet,
-            if (page + 1) >= pages.len() {
-                // fetch it
-                pages.push(pages[page].next(&ctx.data().local_pool).await?.unwrap());
+        if mci.data.custom_id == prev_id || mci.data.custom_id == next_id {
+            if mci.data.custom_id == prev_id {
+                page = (page - 1).max(0);
+            } else {
+                // if page is not fetched yet,
+                if (page + 1) >= pages.len() {
+                    // fetch it
+                    pages.push(pages[page].next(&ctx.data().local_pool).await?.unwrap());
+                }
+                page = page + 1;
             }
-            page = page + 1;
-            rh.edit(
-                ctx,
-                CreateReply::default()
-                    .embed(
-                        pages[page]
-                            .to_embed(ctx.serenity_context(), ctx.data().guild_id)
-                            .await?
-                            .footer(CreateEmbedFooter::new(format!("Page {}", page + 1))),
-                    )
-                    .components(page_buttons(&pages, page)),
-            )
-            .await?;
+            // drawing the gallery can take a moment
             mci.create_response(ctx, serenity::CreateInteractionResponse::Acknowledge)
                 .await?;
+            let (embed, gallery) = inv_page(ctx, &pages[page], page).await?;
+            let edit = EditInteractionResponse::new()
+                .embed(embed)
+                .components(page_buttons(&pages, page));
+            let edit = match gallery {
+                Some(gallery) => edit.new_attachment(gallery),
+                None => edit.clear_attachments(),
+            };
+            mci.edit_response(ctx, edit).await?;
         } else if mci.data.custom_id == close_id {
             rh.delete(ctx).await?;
             mci.create_response(ctx, serenity::CreateInteractionResponse::Acknowledge)
@@ -123,6 +131,158 @@ pub async fn inv(
     Ok(())
 }
 
+/// an inventory page's embed and gallery image
+async fn inv_page(
+    ctx: Context<'_>,
+    inventory: &PaginatedInventory,
+    page: usize,
+) -> Result<(CreateEmbed, Option<CreateAttachment>), Error> {
+    let mut embed = inventory
+        .to_embed(ctx.serenity_context(), ctx.data().guild_id)
+        .await?
+        .footer(CreateEmbedFooter::new(format!("Page {}", page + 1)));
+    let gallery = inventory
+        .render()
+        .await?
+        .map(|png| CreateAttachment::bytes(png, "inventory.png"));
+    if gallery.is_some() {
+        embed = embed.attachment("inventory.png");
+    }
+    Ok((embed, gallery))
+}
+
+/// whole percent of `total`, 0 if there's nothing
+fn percent(owned: usize, total: usize) -> usize {
+    if total == 0 { 0 } else { owned * 100 / total }
+}
+
+/// See which rewards you've collected, or someone else's.
+#[poise::command(slash_command, user_cooldown = 10)]
+async fn collection(
+    ctx: Context<'_>,
+    #[description = "The user who's collection to show"] user: Option<serenity::User>,
+) -> Result<(), Error> {
+    ctx.defer().await?;
+    let uuid = ctx.id();
+    let pool = &ctx.data().local_pool;
+    let user: &serenity::User = user.as_ref().unwrap_or(ctx.author());
+
+    // sets finished through trades or auctions get paid out here
+    let awarded = award_sets(pool, ctx.author().id).await?;
+    let collection = Collection::get(pool, user.id).await?;
+    let sets = collection::get_sets(pool, false).await?;
+    let mut rarity = Rarity::Common;
+
+    let embed = |rarity: Rarity| {
+        let (owned, total) = collection.completion(None);
+        let tiers: Vec<String> = Rarity::ALL
+            .iter()
+            .map(|r| {
+                let (owned, total) = collection.completion(Some(*r));
+                format!("**{}** {}/{} ({}%)", r, owned, total, percent(owned, total))
+            })
+            .collect();
+        let entries: Vec<String> = collection
+            .of_rarity(rarity)
+            .iter()
+            .map(|e| e.line())
+            .collect();
+        let mut embed = CreateEmbed::new()
+            .color(rarity.color())
+            .title(format!(
+                "{}'s collection - {}/{} ({}%)",
+                user.display_name(),
+                owned,
+                total,
+                percent(owned, total)
+            ))
+            .description(format!("{}\n\n{}", tiers.join(" | "), entries.join("\n")))
+            .attachment("collection.png");
+        if !sets.is_empty() {
+            let lines: Vec<String> = sets.iter().map(|s| s.line(&collection)).collect();
+            embed = embed.field("Sets", lines.join("\n"), false);
+        }
+        embed
+    };
+    let rarity_buttons = |rarity: Rarity| {
+        vec![CreateActionRow::Buttons(
+            Rarity::ALL
+                .iter()
+                .map(|r| {
+                    CreateButton::new(format!("{uuid}-{r}"))
+                        .label(r.to_string())
+                        .style(if *r == rarity {
+                            ButtonStyle::Primary
+                        } else {
+                            ButtonStyle::Secondary
+                        })
+                })
+                .collect(),
+        )]
+    };
+    let awarded: Vec<String> = awarded
+        .iter()
+        .map(|set| {
+            format!(
+                "You completed **{}**! {} **+{}**",
+                set.name,
+                emoji("catcoin"),
+                set.bonus
+            )
+        })
+        .collect();
+    let mut reply = CreateReply::default()
+        .embed(embed(rarity))
+        .attachment(CreateAttachment::bytes(
+            collection.render(rarity).await?,
+            "collection.png",
+        ))
+        .components(rarity_buttons(rarity));
+    if !awarded.is_empty() {
+        reply = reply.content(awarded.join("\n"));
+    }
+    let rh = ctx.send(reply).await?;
+
+    while let Some(mci) = ComponentInteractionCollector::new(ctx)
+        .channel_id(ctx.channel_id())
+        .timeout(Duration::from_secs(120))
+        .filter(move |mci| mci.data.custom_id.starts_with(&uuid.to_string()))
+        .await
+    {
+        let Some(picked) = Rarity::ALL
+            .into_iter()
+            .find(|r| mci.data.custom_id == format!("{uuid}-{r}"))
+        else {
+            continue;
+        };
+        rarity = picked;
+        // drawing the grid can take a moment
+        mci.create_response(ctx, serenity::CreateInteractionResponse::Acknowledge)
+            .await?;
+        mci.edit_response(
+            ctx,
+            EditInteractionResponse::new()
+                .embed(embed(rarity))
+                .new_attachment(CreateAttachment::bytes(
+                    collection.render(rarity).await?,
+                    "collection.png",
+                ))
+                .components(rarity_buttons(rarity)),
+        )
+        .await?;
+    }
+    let _ = rh
+        .edit(
+            ctx,
+            CreateReply::default()
+                .embed(embed(rarity))
+                .components(vec![]),
+        )
+        .await;
+
+    Ok(())
+}
+
 /// Claim an old pull
 #[poise::command(slash_command, user_cooldown = 3)]
 async fn _claim(
@@ -186,6 +346,11 @@ async fn _claim(
     Ok(())
 }
 
+/// how long a drop can be claimed for
+const DROP_TIMEOUT: Duration = Duration::from_secs(120);
+/// how long a drop stays in escrow, in case the bot goes away before it times out
+const DROP_TTL: TimeDelta = TimeDelta::seconds(180);
+
 /// Drop catcoin in the chat for anyone fast enough to claim.
 #[poise::command(slash_command, user_cooldown = 30)]
 async fn drop(
@@ -205,9 +370,19 @@ async fn drop(
     }
 
     let uuid = ctx.id();
+    let reference = format!("drop-{uuid}");
 
-    // first expend the catcoin
-    match spend_catcoin(&ctx.data().local_pool, ctx.author().id, amount).await {
+    // first hold the catcoin
+    match escrow::hold(
+        &ctx.data().local_pool,
+        ctx.author().id,
+        amount,
+        Reason::Drop,
+        &reference,
+        DROP_TTL,
+    )
+    .await
+    {
         Ok(false) => {
             ctx.send(CreateReply::default().ephemeral(true).content(format!(
                 "You do not have **{}** {}",
@@ -246,22 +421,26 @@ async fn drop(
     let embed = CreateEmbed::new()
         .color(serenity::Color::from_rgb(random(), random(), random()))
         .title(msg);
-    let button = CreateActionRow::Buttons(vec![CreateButton::new(format!("{uuid}-claim"))
-        .label(format!("{amount}"))
-        .emoji(
-            emoji("catcoin")
-                .parse::<ReactionType>()
-                .expect("Could not parse catcoin emoji as ReactionType"),
-        )]);
+    let button = CreateActionRow::Buttons(vec![
+        CreateButton::new(format!("{uuid}-claim"))
+            .label(format!("{amount}"))
+            .emoji(
+                emoji("catcoin")
+                    .parse::<ReactionType>()
+                    .expect("Could not parse catcoin emoji as ReactionType"),
+            ),
+    ]);
 
     let rh = ctx
         .send(CreateReply::default().embed(embed).components(vec![button]))
         .await?;
+    let sent = rh.message().await?;
+    escrow::attach_message(&ctx.data().local_pool, &reference, sent.channel_id, sent.id).await?;
 
     // wait for first interaction
     while let Some(mci) = ComponentInteractionCollector::new(ctx)
         .channel_id(ctx.channel_id())
-        .timeout(Duration::from_secs(120))
+        .timeout(DROP_TIMEOUT)
         .filter(move |mci| mci.data.custom_id.starts_with(&uuid.to_string()))
         .await
     {
@@ -277,6 +456,25 @@ async fn drop(
             .await?;
             continue;
         }
+        let claimed = escrow::release(
+            &ctx.data().local_pool,
+            &reference,
+            mci.user.id,
+            Reason::Drop,
+        )
+        .await?;
+        if claimed == 0 {
+            mci.create_response(
+                &ctx,
+                serenity::CreateInteractionResponse::Message(
+                    CreateInteractionResponseMessage::new()
+                        .ephemeral(true)
+                        .content("This drop has already expired."),
+                ),
+            )
+            .await?;
+            return Ok(());
+        }
         rh.edit(
             ctx,
             CreateReply::default()
@@ -290,12 +488,18 @@ async fn drop(
                 .components(vec![]),
         )
         .await?;
-        grant_catcoin(&ctx.data().local_pool, mci.user.id, amount).await?;
         mci.create_response(ctx, serenity::CreateInteractionResponse::Acknowledge)
             .await?;
         return Ok(());
     }
 
+    // the escrow thread may have beaten us to it
+    if escrow::refund(&ctx.data().local_pool, &reference)
+        .await?
+        .is_empty()
+    {
+        return Ok(());
+    }
     rh.delete(ctx).await?;
     ctx.channel_id()
         .send_message(
@@ -308,7 +512,6 @@ async fn drop(
             )),
         )
         .await?;
-    grant_catcoin(&ctx.data().local_pool, ctx.author().id, amount).await?;
 
     Ok(())
 }
@@ -390,6 +593,183 @@ async fn balance(ctx: Context<'_>) -> Result<(), Error> {
     Ok(())
 }
 
+/// See how likely pulls, trips, duels and poker are
+#[poise::command(slash_command, user_cooldown = 10)]
+async fn odds(ctx: Context<'_>) -> Result<(), Error> {
+    let odds = odds::odds();
+    let since_rare = odds::get_pity(&ctx.data().local_pool, ctx.author().id).await?;
+    let channel = ctx.channel_id();
+
+    let chances = Chance::ALL
+        .iter()
+        .map(|&chance| {
+            let p = odds.probability(chance, channel);
+            if p > 0.0 {
+                format!("**{}** 1 in {:.0} messages", chance.as_str(), 1.0 / p)
+            } else {
+                format!("**{}** never", chance.as_str())
+            }
+        })
+        .collect::<Vec<String>>()
+        .join("\n");
+    let rarities = odds
+        .rarities
+        .iter()
+        .map(|r| {
+            format!(
+                "**{}** {:.2}% - {} ± {} {}",
+                r.rarity,
+                odds.rarity_probability(r.rarity) * 100.0,
+                r.catcoin_mean,
+                r.catcoin_std_dev,
+                emoji("catcoin")
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("\n");
+    let pity = if odds.pity == 0 {
+        "Off".to_owned()
+    } else if odds.pity_due(since_rare) {
+        "Your next pull is a Rare or better!".to_owned()
+    } else {
+        format!(
+            "{}/{} pulls since your last Rare or better",
+            since_rare, odds.pity
+        )
+    };
+
+    let multiplier = odds.multiplier(channel);
+    let mut embed = CreateEmbed::new()
+        .title(format!("{} Catcoin Odds", emoji("catcoin")))
+        .field("Per message here", chances, false)
+        .field("Pulls", rarities, false)
+        .field("Pity", pity, false)
+        .color(Rarity::Fluffy.color());
+    if multiplier != 1.0 {
+        embed = embed.footer(CreateEmbedFooter::new(format!(
+            "This channel is boosted x{multiplier}"
+        )));
+    }
+    ctx.send(CreateReply::default().embed(embed).ephemeral(true))
+        .await?;
+    Ok(())
+}
+
+/// days shown by /catcoin economy if none are given
+const ECONOMY_DAYS: u32 = 30;
+/// money sources listed by /catcoin economy
+const ECONOMY_FLOWS: usize = 8;
+
+/// See how the catcoin economy is doing
+#[poise::command(slash_command, user_cooldown = 30)]
+async fn economy(
+    ctx: Context<'_>,
+    #[description = "How many days to look back, 30 by default"]
+    #[min = 1]
+    #[max = 365]
+    days: Option<u32>,
+) -> Result<(), Error> {
+    let days = days.unwrap_or(ECONOMY_DAYS);
+    let pool = &ctx.data().local_pool;
+    let snapshots = economy::get_snapshots(pool, days).await?;
+    let (Some(first), Some(latest)) = (snapshots.first(), snapshots.last()) else {
+        ctx.send(
+            CreateReply::default()
+                .content("There are no economy snapshots yet, check back tomorrow.")
+                .ephemeral(true),
+        )
+        .await?;
+        return Ok(());
+    };
+    ctx.defer().await?;
+
+    let flows = economy::get_flows(pool, days).await?;
+    let minted: u64 = flows.iter().map(|f| f.minted).sum();
+    let burned: u64 = flows.iter().map(|f| f.burned).sum();
+    let flow_lines: Vec<String> = flows
+        .iter()
+        .take(ECONOMY_FLOWS)
+        .map(|f| {
+            format!(
+                "**{}** {:+} (+{} / -{})",
+                f.reason.describe(),
+                f.net(),
+                f.minted,
+                f.burned
+            )
+        })
+        .collect();
+    // discord rejects empty field values
+    let sources = if flow_lines.is_empty() {
+        "None".to_owned()
+    } else {
+        flow_lines.join("\n")
+    };
+    let embed = CreateEmbed::new()
+        .title(format!("{} Catcoin Economy", emoji("catcoin")))
+        .description(format!(
+            "Last {} days, as of <t:{}:d>",
+            days,
+            latest
+                .day
+                .and_hms_opt(0, 0, 0)
+                .unwrap_or_default()
+                .and_utc()
+                .timestamp()
+        ))
+        .field(
+            "Supply",
+            format!(
+                "{} {} ({:+})",
+                latest.supply,
+                emoji("catcoin"),
+                latest.supply - first.supply
+            ),
+            true,
+        )
+        .field("Gini", format!("{:.3}", latest.gini), true)
+        .field("Active wallets", latest.active_wallets.to_string(), true)
+        .field("Minted", minted.to_string(), true)
+        .field("Burned", burned.to_string(), true)
+        .field("Sources", sources, false)
+        .attachment("economy.png")
+        .color(Rarity::Fluffy.color());
+
+    let png = {
+        let snapshots = snapshots.clone();
+        tokio::task::spawn_blocking(move || economy::draw_economy_chart(&snapshots)).await??
+    };
+    ctx.send(
+        CreateReply::default()
+            .embed(embed)
+            .attachment(CreateAttachment::bytes(png, "economy.png")),
+    )
+    .await?;
+    Ok(())
+}
+
+/// snapshots the catcoin economy every day just after midnight UTC
+pub fn economy_snapshot_job(pool: Pool<MySql>) -> Job {
+    JobBuilder::new()
+        .with_timezone(chrono_tz::UTC)
+        .with_cron_job_type()
+        .with_schedule("0 5 0 * * *")
+        .unwrap()
+        .with_run_async(Box::new(move |_uuid, _l| {
+            let pool = pool.clone();
+            Box::pin(async move {
+                let Some(yesterday) = Utc::now().date_naive().pred_opt() else {
+                    return;
+                };
+                let _ = economy::take_snapshot(&pool, yesterday)
+                    .await
+                    .inspect_err(|e| log::error!("Could not snapshot the economy: {e}"));
+            })
+        }))
+        .build()
+        .unwrap()
+}
+
 /// Check top catcoin wallets
 #[poise::command(slash_command)]
 async fn top(ctx: Context<'_>) -> Result<(), Error> {
@@ -429,3 +809,429 @@ async fn top(ctx: Context<'_>) -> Result<(), Error> {
     ctx.send(CreateReply::default().embed(embed)).await?;
     Ok(())
 }
+
+/// ledger entries shown per page of /catcoin history
+const HISTORY_PAGE_SIZE: i64 = 10;
+
+/// Browse your catcoin transaction history
+#[poise::command(slash_command, user_cooldown = 10)]
+async fn history(ctx: Context<'_>) -> Result<(), Error> {
+    let pool = &ctx.data().local_pool;
+    let uid = ctx.author().id;
+
+    let total = ledger::count_history(pool, uid).await?;
+    if total == 0 {
+        ctx.send(
+            CreateReply::default()
+                .ephemeral(true)
+                .content(format!("You have no {} history yet.", emoji("catcoin"))),
+        )
+        .await?;
+        return Ok(());
+    }
+    let pages = (total + HISTORY_PAGE_SIZE - 1) / HISTORY_PAGE_SIZE;
+
+    paginate(ctx, pages as usize, true, move |page| async move {
+        let entries = ledger::get_history(
+            pool,
+            uid,
+            HISTORY_PAGE_SIZE,
+            page as i64 * HISTORY_PAGE_SIZE,
+        )
+        .await?;
+        Ok(CreateEmbed::new()
+            .title(format!("{} Catcoin history", emoji("catcoin")))
+            .description(
+                entries
+                    .iter()
+                    .map(|e| e.line(uid))
+                    .collect::<Vec<String>>()
+                    .join("\n"),
+            )
+            .footer(CreateEmbedFooter::new(format!(
+                "Page {} / {}",
+                page + 1,
+                pages
+            ))))
+    })
+    .await
+}
+
+/// how long a trade waits for either side before it's called off
+const TRADE_TIMEOUT: Duration = Duration::from_secs(300);
+
+#[derive(Debug, Modal)]
+#[name = "Offer catcoin"]
+struct TradeCatcoinModal {
+    #[name = "How much catcoin to offer"]
+    #[placeholder = "0"]
+    #[min_length = 1]
+    #[max_length = 12]
+    pub amount: String,
+}
+
+/// one side of a trade being put together
+struct TradeSide {
+    user: serenity::User,
+    pages: Vec<PaginatedInventory>,
+    page: usize,
+    pulls: BTreeMap<i32, CatcoinPull>,
+    catcoin: u64,
+    confirmed: bool,
+}
+
+impl TradeSide {
+    async fn new(pool: &Pool<MySql>, user: serenity::User) -> Result<Self, Error> {
+        Ok(Self {
+            pages: vec![PaginatedInventory::get(pool, user.id).await?],
+            user,
+            page: 0,
+            pulls: BTreeMap::new(),
+            catcoin: 0,
+            confirmed: false,
+        })
+    }
+
+    fn offer(&self) -> TradeOffer {
+        TradeOffer {
+            uid: self.user.id,
+            pulls: self.pulls.keys().copied().collect(),
+            catcoin: self.catcoin,
+        }
+    }
+
+    /// this side's offer as an embed field
+    fn field(&self) -> (String, String, bool) {
+        let mut lines: Vec<String> = self
+            .pulls
+            .values()
+            .map(|pull| {
+                format!(
+                    "**{}** {} `#{}`",
+                    pull.reward.rarity, pull.reward.name, pull.number
+                )
+            })
+            .collect();
+        if self.catcoin > 0 {
+            lines.push(format!("**{}** {}", self.catcoin, emoji("catcoin")));
+        }
+        if lines.is_empty() {
+            lines.push("*nothing yet*".to_owned());
+        }
+        let status = if self.confirmed { "✅" } else { "⏳" };
+        (
+            format!("{} {}", status, self.user.display_name()),
+            lines.join("\n"),
+            true,
+        )
+    }
+
+    /// select menu for the current inventory page, plus page buttons
+    fn picker(&self, uuid: u64) -> Vec<CreateActionRow> {
+        let page = &self.pages[self.page];
+        let mut rows = vec![];
+        if !page.pulls.is_empty() {
+            let options: Vec<CreateSelectMenuOption> = page
+                .pulls
+                .iter()
+                .map(|pull| {
+                    CreateSelectMenuOption::new(
+                        format!(
+                            "{} {} #{}",
+                            pull.reward.rarity, pull.reward.name, pull.number
+                        ),
+                        pull.id.to_string(),
+                    )
+                    .default_selection(self.pulls.contains_key(&pull.id))
+                })
+                .collect();
+            let max = options.len() as u8;
+            rows.push(CreateActionRow::SelectMenu(
+                CreateSelectMenu::new(
+                    format!("{uuid}-pick"),
+                    CreateSelectMenuKind::String { options },
+                )
+                .placeholder("Pick pulls to offer")
+                .min_values(0)
+                .max_values(max),
+            ));
+        }
+        rows.push(CreateActionRow::Buttons(vec![
+            CreateButton::new(format!("{uuid}-pickprev"))
+                .emoji(ReactionType::Unicode("⬅️".to_owned()))
+                .disabled(self.page == 0),
+            CreateButton::new(format!("{uuid}-picknext"))
+                .emoji(ReactionType::Unicode("➡️".to_owned()))
+                .disabled(!page.has_next()),
+        ]));
+        rows
+    }
+}
+
+/// Trade pulls and catcoin with someone
+#[poise::command(slash_command, user_cooldown = 30)]
+async fn trade(
+    ctx: Context<'_>,
+    #[description = "Who to trade with"] user: serenity::User,
+) -> Result<(), Error> {
+    if user.id == ctx.author().id || user.bot {
+        ctx.send(
+            CreateReply::default()
+                .content("You can't trade with them!")
+                .ephemeral(true),
+        )
+        .await?;
+        return Ok(());
+    }
+    let uuid = ctx.id();
+    let pool = &ctx.data().local_pool;
+    let mut sides = [
+        TradeSide::new(pool, ctx.author().clone()).await?,
+        TradeSide::new(pool, user.clone()).await?,
+    ];
+
+    let offer_id = format!("{uuid}-offer");
+    let coin_id = format!("{uuid}-coin");
+    let confirm_id = format!("{uuid}-confirm");
+    let cancel_id = format!("{uuid}-cancel");
+    let pick_id = format!("{uuid}-pick");
+    let pickprev_id = format!("{uuid}-pickprev");
+    let picknext_id = format!("{uuid}-picknext");
+
+    let summary = |sides: &[TradeSide; 2]| {
+        CreateEmbed::new()
+            .color(serenity::Color::GOLD)
+            .title("Catcoin trade")
+            .description("Pick your offer, then both confirm.")
+            .fields(sides.iter().map(TradeSide::field))
+    };
+    let buttons = vec![CreateActionRow::Buttons(vec![
+        CreateButton::new(offer_id.clone()).label("Offer pulls"),
+        CreateButton::new(coin_id.clone())
+            .label("Offer catcoin")
+            .emoji(
+                emoji("catcoin")
+                    .parse::<ReactionType>()
+                    .expect("Could not parse catcoin emoji as ReactionType"),
+            ),
+        CreateButton::new(confirm_id.clone())
+            .label("Confirm")
+            .style(ButtonStyle::Success),
+        CreateButton::new(cancel_id.clone())
+            .label("Cancel")
+            .style(ButtonStyle::Danger),
+    ])];
+
+    let rh = ctx
+        .send(
+            CreateReply::default()
+                .content(format!(
+                    "{} wants to trade with {}!",
+                    ctx.author().mention(),
+                    user.mention()
+                ))
+                .embed(summary(&sides))
+                .components(buttons.clone())
+                .allowed_mentions(CreateAllowedMentions::new().all_users(true)),
+        )
+        .await?;
+
+    let mut agreed = false;
+    while let Some(mci) = ComponentInteractionCollector::new(ctx)
+        .channel_id(ctx.channel_id())
+        .timeout(TRADE_TIMEOUT)
+        .filter(move |mci| mci.data.custom_id.starts_with(&uuid.to_string()))
+        .await
+    {
+        let Some(i) = sides.iter().position(|s| s.user.id == mci.user.id) else {
+            mci.create_response(
+                ctx,
+                serenity::CreateInteractionResponse::Message(
+                    CreateInteractionResponseMessage::new()
+                        .ephemeral(true)
+                        .content("This isn't your trade!"),
+                ),
+            )
+            .await?;
+            continue;
+        };
+        let custom_id = &mci.data.custom_id;
+        if *custom_id == offer_id {
+            mci.create_response(
+                ctx,
+                serenity::CreateInteractionResponse::Message(
+                    CreateInteractionResponseMessage::new()
+                        .ephemeral(true)
+                        .content("Pick the pulls you want to offer.")
+                        .components(sides[i].picker(uuid)),
+                ),
+            )
+            .await?;
+            continue;
+        } else if *custom_id == pickprev_id || *custom_id == picknext_id {
+            let side = &mut sides[i];
+            if *custom_id == pickprev_id {
+                side.page = side.page.saturating_sub(1);
+            } else if side.pages[side.page].has_next() {
+                // if page is not fetched yet, fetch it
+                if side.page + 1 >= side.pages.len() {
+                    let next = side.pages[side.page].next(pool).await?.unwrap();
+                    side.pages.push(next);
+                }
+                side.page += 1;
+            }
+            mci.create_response(
+                ctx,
+                serenity::CreateInteractionResponse::UpdateMessage(
+                    CreateInteractionResponseMessage::new().components(side.picker(uuid)),
+                ),
+            )
+            .await?;
+            continue;
+        } else if *custom_id == pick_id {
+            let serenity::ComponentInteractionDataKind::StringSelect { values } = &mci.data.kind
+            else {
+                continue;
+            };
+            let side = &mut sides[i];
+            // the menu only shows one page, so only that page's picks are replaced
+            for pull in side.pages[side.page].pulls.iter() {
+                if values.contains(&pull.id.to_string()) {
+                    side.pulls.insert(pull.id, pull.clone());
+                } else {
+                    side.pulls.remove(&pull.id);
+                }
+            }
+            mci.create_response(ctx, serenity::CreateInteractionResponse::Acknowledge)
+                .await?;
+        } else if *custom_id == coin_id {
+            let Some(response) = execute_modal_generic::<TradeCatcoinModal, _>(
+                ctx.serenity_context(),
+                |resp| mci.create_response(ctx, resp),
+                mci.id.to_string(),
+                None,
+                Some(Duration::from_secs(120)),
+            )
+            .await?
+            else {
+                continue;
+            };
+            let amount = TradeCatcoinModal::parse(response.data.clone())?
+                .amount
+                .trim()
+                .parse::<u64>();
+            let balance = get_catcoin(pool, mci.user.id).await?.catcoin.max(0) as u64;
+            match amount {
+                Ok(amount) if amount <= balance => {
+                    sides[i].catcoin = amount;
+                    response
+                        .create_response(ctx, serenity::CreateInteractionResponse::Acknowledge)
+                        .await?;
+                }
+                _ => {
+                    response
+                        .create_response(
+                            ctx,
+                            serenity::CreateInteractionResponse::Message(
+                                CreateInteractionResponseMessage::new()
+                                    .ephemeral(true)
+                                    .content(format!(
+                                        "You can offer up to **{}** {}.",
+                                        balance,
+                                        emoji("catcoin")
+                                    )),
+                            ),
+                        )
+                        .await?;
+                    continue;
+                }
+            }
+        } else if *custom_id == confirm_id {
+            if sides.iter().all(|s| s.offer().is_empty()) {
+                mci.create_response(
+                    ctx,
+                    serenity::CreateInteractionResponse::Message(
+                        CreateInteractionResponseMessage::new()
+                            .ephemeral(true)
+                            .content("Nothing has been offered yet!"),
+                    ),
+                )
+                .await?;
+                continue;
+            }
+            sides[i].confirmed = true;
+            mci.create_response(ctx, serenity::CreateInteractionResponse::Acknowledge)
+                .await?;
+            if sides.iter().all(|s| s.confirmed) {
+                agreed = true;
+                break;
+            }
+            rh.edit(
+                ctx,
+                CreateReply::default()
+                    .embed(summary(&sides))
+                    .components(buttons.clone()),
+            )
+            .await?;
+            continue;
+        } else if *custom_id == cancel_id {
+            mci.create_response(ctx, serenity::CreateInteractionResponse::Acknowledge)
+                .await?;
+            rh.edit(
+                ctx,
+                CreateReply::default()
+                    .content(format!("{} called off the trade.", mci.user.mention()))
+                    .embed(summary(&sides))
+                    .components(vec![]),
+            )
+            .await?;
+            return Ok(());
+        } else {
+            continue;
+        }
+
+        // an offer changed, so both have to confirm again
+        for side in sides.iter_mut() {
+            side.confirmed = false;
+        }
+        rh.edit(
+            ctx,
+            CreateReply::default()
+                .embed(summary(&sides))
+                .components(buttons.clone()),
+        )
+        .await?;
+    }
+
+    if !agreed {
+        rh.edit(
+            ctx,
+            CreateReply::default()
+                .content("The trade timed out.")
+                .embed(summary(&sides))
+                .components(vec![]),
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let content = match trade::execute(pool, &sides[0].offer(), &sides[1].offer()).await? {
+        Some(id) => format!(
+            "Trade `#{}` between {} and {} is done!",
+            id,
+            sides[0].user.mention(),
+            sides[1].user.mention()
+        ),
+        None => "Someone no longer has what they offered, so the trade is off.".to_owned(),
+    };
+    rh.edit(
+        ctx,
+        CreateReply::default()
+            .content(content)
+            .embed(summary(&sides))
+            .components(vec![]),
+    )
+    .await?;
+
+    Ok(())
+}
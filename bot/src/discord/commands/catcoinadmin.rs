@@ -0,0 +1,448 @@
+use chrono::{TimeDelta, Utc};
+use poise::serenity_prelude::{
+    self as serenity, AutocompleteChoice, Color, CreateEmbed, Mentionable,
+};
+use poise::{self, CreateReply};
+
+use crate::discord::Context;
+use ::catcoin::{
+    collection, ledger,
+    odds::{self, Chance},
+    random_pulls::Rarity,
+    rewards,
+    shop::{self, ItemKind},
+};
+use common::Error;
+
+/// discrepancies listed by /catcoinadmin reconcile
+const MAX_LISTED: usize = 20;
+/// largest reward image accepted, in bytes
+const MAX_IMAGE_SIZE: u32 = 8 * 1024 * 1024;
+const INVALID_IMAGE: &str = "That file is too big or isn't an image.";
+
+/// Catcoin economy tools for mods
+#[poise::command(
+    slash_command,
+    subcommands(
+        "reconcile",
+        "shopadd",
+        "shopedit",
+        "setadd",
+        "setedit",
+        "chance",
+        "rarity",
+        "pity",
+        "multiplier",
+        "reward"
+    ),
+    subcommand_required
+)]
+pub async fn catcoinadmin(_: Context<'_>) -> Result<(), Error> {
+    Ok(()) // never run
+}
+
+/// Check that every wallet balance matches its ledger entries
+#[poise::command(slash_command)]
+async fn reconcile(ctx: Context<'_>) -> Result<(), Error> {
+    ctx.defer_ephemeral().await?;
+    let discrepancies = ledger::reconcile(&ctx.data().local_pool).await?;
+
+    let embed = if discrepancies.is_empty() {
+        CreateEmbed::new()
+            .title("✅ Ledger reconciled")
+            .description("Every wallet matches the ledger.")
+            .color(Color::DARK_GREEN)
+    } else {
+        let mut lines: Vec<String> = discrepancies
+            .iter()
+            .take(MAX_LISTED)
+            .map(|d| {
+                format!(
+                    "<@{}> wallet `{}`, ledger `{}` ({:+})",
+                    d.uid,
+                    d.balance,
+                    d.ledger,
+                    d.balance - d.ledger
+                )
+            })
+            .collect();
+        if discrepancies.len() > MAX_LISTED {
+            lines.push(format!("...and {} more", discrepancies.len() - MAX_LISTED));
+        }
+        CreateEmbed::new()
+            .title(format!(
+                "⚠️ {} wallets don't match the ledger",
+                discrepancies.len()
+            ))
+            .description(lines.join("\n"))
+            .color(Color::RED)
+    };
+    ctx.send(CreateReply::default().ephemeral(true).embed(embed))
+        .await?;
+    Ok(())
+}
+
+/// Put a new item up for sale in the catcoin shop
+#[poise::command(slash_command)]
+async fn shopadd(
+    ctx: Context<'_>,
+    #[description = "What the item does"] kind: ItemKind,
+    #[description = "The item's name"]
+    #[max_length = 64]
+    name: String,
+    #[description = "Shown in /shop list"]
+    #[max_length = 255]
+    description: String,
+    #[description = "The price in catcoin"]
+    #[min = 1]
+    price: u64,
+    #[description = "How many days the perk lasts. Leave empty for one-time items"]
+    #[min = 1]
+    days: Option<u32>,
+) -> Result<(), Error> {
+    if kind != ItemKind::MapNomination && days.is_none() {
+        ctx.send(
+            CreateReply::default()
+                .content("Only map nominations can be one-time, give the perk a duration.")
+                .ephemeral(true),
+        )
+        .await?;
+        return Ok(());
+    }
+    let id = shop::add_item(
+        &ctx.data().local_pool,
+        &name,
+        &description,
+        kind,
+        price,
+        days,
+    )
+    .await?;
+    ctx.send(
+        CreateReply::default()
+            .content(format!("Added **{name}** `#{id}` to the shop."))
+            .ephemeral(true),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Change the price or availability of a catcoin shop item
+#[poise::command(slash_command)]
+async fn shopedit(
+    ctx: Context<'_>,
+    #[description = "The item's id, from /shop list"] id: u32,
+    #[description = "The new price in catcoin"]
+    #[min = 1]
+    price: Option<u64>,
+    #[description = "Whether the item can be bought"] enabled: Option<bool>,
+) -> Result<(), Error> {
+    let content = if shop::edit_item(&ctx.data().local_pool, id, price, enabled).await? {
+        format!("Updated shop item `#{id}`.")
+    } else {
+        format!("There's no shop item `#{id}`.")
+    };
+    ctx.send(CreateReply::default().content(content).ephemeral(true))
+        .await?;
+    Ok(())
+}
+
+/// Add a collection set that pays a bonus once a user owns all of it
+#[poise::command(slash_command)]
+async fn setadd(
+    ctx: Context<'_>,
+    #[description = "The set's name"]
+    #[max_length = 64]
+    name: String,
+    #[description = "The bonus in catcoin"]
+    #[min = 1]
+    bonus: u64,
+    #[description = "Make it every reward of this rarity"] rarity: Option<Rarity>,
+    #[description = "Or every reward whose name contains this"] matching: Option<String>,
+) -> Result<(), Error> {
+    if rarity.is_some() == matching.is_some() {
+        ctx.send(
+            CreateReply::default()
+                .content("Give the set either a rarity or a name to match.")
+                .ephemeral(true),
+        )
+        .await?;
+        return Ok(());
+    }
+    let id = collection::add_set(
+        &ctx.data().local_pool,
+        &name,
+        bonus,
+        rarity,
+        matching.as_deref(),
+    )
+    .await?;
+    ctx.send(
+        CreateReply::default()
+            .content(format!("Added the **{name}** `#{id}` set."))
+            .ephemeral(true),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Change the bonus or availability of a collection set
+#[poise::command(slash_command)]
+async fn setedit(
+    ctx: Context<'_>,
+    #[description = "The set's id, from /catcoin collection"] id: u32,
+    #[description = "The new bonus in catcoin"]
+    #[min = 1]
+    bonus: Option<u64>,
+    #[description = "Whether the set pays out"] enabled: Option<bool>,
+) -> Result<(), Error> {
+    let content = if collection::edit_set(&ctx.data().local_pool, id, bonus, enabled).await? {
+        format!("Updated set `#{id}`.")
+    } else {
+        format!("There's no set `#{id}`.")
+    };
+    ctx.send(CreateReply::default().content(content).ephemeral(true))
+        .await?;
+    Ok(())
+}
+
+/// Change how often something happens, as one in n messages
+#[poise::command(slash_command)]
+async fn chance(
+    ctx: Context<'_>,
+    #[description = "What to change"] chance: Chance,
+    #[description = "It happens once in this many messages. 0 turns it off"] one_in: u32,
+) -> Result<(), Error> {
+    odds::set_chance(&ctx.data().local_pool, chance, one_in).await?;
+    let content = if one_in == 0 {
+        format!("Turned off **{}**.", chance.as_str())
+    } else {
+        format!(
+            "**{}** now happens once in {one_in} messages.",
+            chance.as_str()
+        )
+    };
+    ctx.send(CreateReply::default().content(content).ephemeral(true))
+        .await?;
+    Ok(())
+}
+
+/// Change how likely a rarity is, or what it pays
+#[poise::command(slash_command)]
+async fn rarity(
+    ctx: Context<'_>,
+    #[description = "The rarity to change"] rarity: Rarity,
+    #[description = "How likely it is compared to the other rarities"] weight: Option<u32>,
+    #[description = "Average catcoin paid per pull"]
+    #[min = 0]
+    mean: Option<f64>,
+    #[description = "How much the catcoin paid varies"]
+    #[min = 0]
+    std_dev: Option<f64>,
+) -> Result<(), Error> {
+    odds::set_rarity_odds(&ctx.data().local_pool, rarity, weight, mean, std_dev).await?;
+    let odds = odds::odds();
+    let content = match odds.rarity(rarity) {
+        Some(r) => format!(
+            "**{}** is now {:.2}% of pulls, paying {} ± {}.",
+            rarity,
+            odds.rarity_probability(rarity) * 100.0,
+            r.catcoin_mean,
+            r.catcoin_std_dev
+        ),
+        None => format!("There are no odds for **{rarity}**."),
+    };
+    ctx.send(CreateReply::default().content(content).ephemeral(true))
+        .await?;
+    Ok(())
+}
+
+/// Change how many pulls without a Rare or better guarantee one
+#[poise::command(slash_command)]
+async fn pity(
+    ctx: Context<'_>,
+    #[description = "Pulls until a Rare or better is guaranteed. 0 turns pity off"] pulls: u32,
+) -> Result<(), Error> {
+    odds::set_pity(&ctx.data().local_pool, pulls).await?;
+    let content = if pulls == 0 {
+        "Turned off pity.".to_owned()
+    } else {
+        format!("A Rare or better is now guaranteed after {pulls} pulls without one.")
+    };
+    ctx.send(CreateReply::default().content(content).ephemeral(true))
+        .await?;
+    Ok(())
+}
+
+/// Boost every chance in a channel
+#[poise::command(slash_command)]
+async fn multiplier(
+    ctx: Context<'_>,
+    #[description = "The channel to boost"] channel: serenity::GuildChannel,
+    #[description = "How many times more likely everything is. 1 removes the boost"]
+    #[min = 0]
+    multiplier: f64,
+    #[description = "How many hours the boost lasts. Leave empty to keep it"]
+    #[min = 1]
+    hours: Option<u32>,
+) -> Result<(), Error> {
+    let pool = &ctx.data().local_pool;
+    let content = if multiplier == 1.0 {
+        odds::remove_multiplier(pool, channel.id).await?;
+        format!("Removed the boost in {}.", channel.mention())
+    } else {
+        let expires_at = hours.map(|hours| Utc::now() + TimeDelta::hours(hours.into()));
+        odds::set_multiplier(pool, channel.id, multiplier, expires_at).await?;
+        match expires_at {
+            Some(at) => format!(
+                "Everything in {} is now x{} as likely until <t:{}:f>.",
+                channel.mention(),
+                multiplier,
+                at.timestamp()
+            ),
+            None => format!(
+                "Everything in {} is now x{} as likely.",
+                channel.mention(),
+                multiplier
+            ),
+        }
+    };
+    ctx.send(CreateReply::default().content(content).ephemeral(true))
+        .await?;
+    Ok(())
+}
+
+/// Manage the rewards that can be pulled
+#[poise::command(
+    slash_command,
+    subcommands("reward_add", "reward_edit", "reward_retire"),
+    subcommand_required
+)]
+async fn reward(_: Context<'_>) -> Result<(), Error> {
+    Ok(()) // never run
+}
+
+/// every reward, retired ones included so they can be brought back
+async fn rewards_autocomplete(ctx: Context<'_>, partial: &str) -> Vec<AutocompleteChoice> {
+    rewards::search_rewards(&ctx.data().local_pool, partial, 25)
+        .await
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(r, retired)| {
+            let name = format!("{} {}", r.rarity, r.name);
+            let name = if retired {
+                format!("{name} (retired)")
+            } else {
+                name
+            };
+            AutocompleteChoice::new(name, r.id)
+        })
+        .collect()
+}
+
+/// Add a reward that can be pulled
+#[poise::command(slash_command, rename = "add")]
+async fn reward_add(
+    ctx: Context<'_>,
+    #[description = "The reward's name"]
+    #[max_length = 128]
+    name: String,
+    #[description = "How rare it is"] rarity: Rarity,
+    #[description = "A png of the reward"] image: serenity::Attachment,
+) -> Result<(), Error> {
+    ctx.defer_ephemeral().await?;
+    let image = if image.size <= MAX_IMAGE_SIZE {
+        rewards::decode_image(image.download().await?).await?
+    } else {
+        None
+    };
+    let Some(image) = image else {
+        ctx.send(
+            CreateReply::default()
+                .content(INVALID_IMAGE)
+                .ephemeral(true),
+        )
+        .await?;
+        return Ok(());
+    };
+    let id = rewards::add_reward(&ctx.data().local_pool, &name, rarity, image).await?;
+    ctx.send(
+        CreateReply::default()
+            .content(format!("Added the {rarity} reward **{name}** `#{id}`."))
+            .ephemeral(true),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Change a reward's name, rarity or image
+#[poise::command(slash_command, rename = "edit")]
+async fn reward_edit(
+    ctx: Context<'_>,
+    #[description = "The reward to change"]
+    #[autocomplete = "rewards_autocomplete"]
+    id: i32,
+    #[description = "The new name"]
+    #[max_length = 128]
+    name: Option<String>,
+    #[description = "The new rarity"] rarity: Option<Rarity>,
+    #[description = "A new png of the reward"] image: Option<serenity::Attachment>,
+) -> Result<(), Error> {
+    ctx.defer_ephemeral().await?;
+    let image = match image {
+        Some(image) if image.size <= MAX_IMAGE_SIZE => {
+            rewards::decode_image(image.download().await?)
+                .await?
+                .map(Some)
+        }
+        Some(_) => None,
+        None => Some(None),
+    };
+    let Some(image) = image else {
+        ctx.send(
+            CreateReply::default()
+                .content(INVALID_IMAGE)
+                .ephemeral(true),
+        )
+        .await?;
+        return Ok(());
+    };
+    let content = if rewards::edit_reward(
+        &ctx.data().local_pool,
+        id,
+        name.as_deref(),
+        rarity,
+        image,
+    )
+    .await?
+    {
+        format!("Updated reward `#{id}`.")
+    } else {
+        format!("There's no reward `#{id}`.")
+    };
+    ctx.send(CreateReply::default().content(content).ephemeral(true))
+        .await?;
+    Ok(())
+}
+
+/// Stop a reward from being pulled. Pulls people already own are kept
+#[poise::command(slash_command, rename = "retire")]
+async fn reward_retire(
+    ctx: Context<'_>,
+    #[description = "The reward to retire"]
+    #[autocomplete = "rewards_autocomplete"]
+    id: i32,
+    #[description = "Set to false to bring a retired reward back"] retired: Option<bool>,
+) -> Result<(), Error> {
+    let retired = retired.unwrap_or(true);
+    let content = if !rewards::retire_reward(&ctx.data().local_pool, id, retired).await? {
+        format!("There's no reward `#{id}`.")
+    } else if retired {
+        format!("Retired reward `#{id}`.")
+    } else {
+        format!("Reward `#{id}` can be pulled again.")
+    };
+    ctx.send(CreateReply::default().content(content).ephemeral(true))
+        .await?;
+    Ok(())
+}
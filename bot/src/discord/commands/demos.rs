@@ -0,0 +1,274 @@
+use std::collections::{HashMap, HashSet};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use chrono::{TimeDelta, Utc};
+use poise::serenity_prelude::{CreateAttachment, CreateEmbed};
+use poise::{self, CreateReply};
+use sqlx::{MySql, Pool};
+
+use super::util::servers_autocomplete;
+use crate::discord::Context;
+use common::{Error, util::hhmmss};
+use tf2::Server;
+use tf2::demos::{list_demos, read_header};
+
+/// demos listed by /demos list
+const LIST_COUNT: usize = 10;
+/// largest demo uploaded directly as an attachment, larger ones are linked
+const MAX_ATTACHMENT_SIZE: usize = 10 * 1024 * 1024;
+
+/// Browse SourceTV demos recorded on the servers
+#[poise::command(
+    slash_command,
+    subcommands("list", "fetch", "pin", "unpin"),
+    subcommand_required
+)]
+pub async fn demos(_: Context<'_>) -> Result<(), Error> {
+    Ok(()) // never run
+}
+
+/// the names of all pinned demos on a server
+async fn pinned(pool: &Pool<MySql>, server: &Server) -> Result<HashSet<String>, Error> {
+    Ok(sqlx::query!(
+        "SELECT `name` FROM `demo_pins` WHERE `server` = ?",
+        server.name
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|r| r.name)
+    .collect())
+}
+
+/// pins a demo, exempting it from pruning
+pub async fn pin_demo(
+    pool: &Pool<MySql>,
+    server: &Server,
+    name: &str,
+    reason: &str,
+) -> Result<(), Error> {
+    sqlx::query!(
+        "INSERT IGNORE INTO `demo_pins` (`server`, `name`, `reason`) VALUES (?, ?, ?)",
+        server.name,
+        name,
+        reason
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// pins the most recent (usually still recording) demo on the server
+pub async fn pin_latest_demo(
+    pool: &Pool<MySql>,
+    server: &Server,
+    dir: &str,
+    reason: &str,
+) -> Result<(), Error> {
+    let demos = list_demos(server, dir).await?;
+    let latest = demos.first().ok_or("No demos on the server")?;
+    pin_demo(pool, server, &latest.name, reason).await
+}
+
+/// periodically deletes unpinned demos older than the retention period
+pub fn spawn_demo_prune_thread(
+    servers: HashMap<SocketAddr, Server>,
+    pool: Pool<MySql>,
+    dir: String,
+    retention_days: i64,
+) {
+    let mut interval = tokio::time::interval(Duration::from_secs(6 * 60 * 60));
+    tokio::spawn(async move {
+        loop {
+            interval.tick().await;
+            let cutoff = Utc::now() - TimeDelta::try_days(retention_days).unwrap();
+            for server in servers.values() {
+                let Ok(demos) = list_demos(server, &dir)
+                    .await
+                    .inspect_err(|e| log::error!("Could not list demos on {}: {e}", server.name))
+                else {
+                    continue;
+                };
+                let Ok(pins) = pinned(&pool, server).await else {
+                    continue;
+                };
+                let mut count = 0;
+                for demo in demos {
+                    if pins.contains(&demo.name) || demo.modified.is_none_or(|m| m > cutoff) {
+                        continue;
+                    }
+                    if let Err(e) = server.files.delete_file(&demo.path(&dir)).await {
+                        log::error!(
+                            "Could not delete demo {} on {}: {e}",
+                            demo.name,
+                            server.name
+                        );
+                    } else {
+                        count += 1;
+                    }
+                }
+                if count > 0 {
+                    log::info!("Pruned {} demos on {}", count, server.name);
+                }
+            }
+        }
+    });
+}
+
+/// rejects names that would escape the demo directory
+fn validate_name(name: &str) -> Result<(), Error> {
+    if !name.ends_with(".dem") || name.contains(['/', '\\']) || name.contains("..") {
+        return Err("Invalid demo name.".into());
+    }
+    Ok(())
+}
+
+/// List recent demos on a server
+#[poise::command(slash_command)]
+async fn list(
+    ctx: Context<'_>,
+    #[description = "The server to list demos of"]
+    #[autocomplete = "servers_autocomplete"]
+    server: String,
+) -> Result<(), Error> {
+    ctx.defer().await?;
+    let server = ctx.data().server(&server)?;
+    let dir = &ctx.data().demo_dir;
+    let demos = list_demos(server, dir).await?;
+    let pins = pinned(&ctx.data().local_pool, server).await?;
+
+    let mut lines = vec![];
+    for demo in demos.iter().take(LIST_COUNT) {
+        let (map, duration) = match read_header(server, &demo.path(dir)).await {
+            Ok(header) if header.playback_time > 0.0 => {
+                (header.map, hhmmss(header.playback_time as u64))
+            }
+            Ok(header) => (header.map, "recording".to_owned()),
+            Err(_) => ("?".to_owned(), "?".to_owned()),
+        };
+        lines.push(format!(
+            "{}`{}`\n`{}` | {} | `{:.1} MB` | `{}`",
+            if pins.contains(&demo.name) {
+                "📌 "
+            } else {
+                ""
+            },
+            demo.name,
+            map,
+            demo.modified
+                .map(|m| format!("<t:{}:f>", m.timestamp()))
+                .unwrap_or("?".to_owned()),
+            demo.size as f64 / (1024.0 * 1024.0),
+            duration,
+        ));
+    }
+    if lines.is_empty() {
+        lines.push("No demos recorded.".to_owned());
+    }
+
+    ctx.send(
+        CreateReply::default().embed(
+            CreateEmbed::new()
+                .title(format!("{} Demos ({} total)", server.emoji, demos.len()))
+                .description(lines.join("\n")),
+        ),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Download a demo from a server
+#[poise::command(slash_command)]
+async fn fetch(
+    ctx: Context<'_>,
+    #[description = "The server the demo is on"]
+    #[autocomplete = "servers_autocomplete"]
+    server: String,
+    #[description = "The demo file name"] name: String,
+) -> Result<(), Error> {
+    validate_name(&name)?;
+    ctx.defer_ephemeral().await?;
+    let server = ctx.data().server(&server)?;
+    let path = format!("{}/{}", ctx.data().demo_dir.trim_end_matches('/'), name);
+    let local = PathBuf::from("cache/demos").join(format!("{}-{}", server.addr.port(), name));
+    tokio::fs::create_dir_all("cache/demos").await?;
+    let size = match server.files.download_file(&path, &local).await {
+        Ok(size) => size,
+        Err(e) => {
+            let _ = tokio::fs::remove_file(&local).await;
+            return Err(e);
+        }
+    };
+
+    if size as usize <= MAX_ATTACHMENT_SIZE {
+        let bytes = tokio::fs::read(&local).await?;
+        let _ = tokio::fs::remove_file(&local).await;
+        ctx.send(
+            CreateReply::default()
+                .content(format!("{} `{}`", server.emoji, name))
+                .attachment(CreateAttachment::bytes(bytes, name)),
+        )
+        .await?;
+        return Ok(());
+    }
+
+    // too big to attach, serve it from the api instead
+    let url = ctx
+        .data()
+        .api_state
+        .share_file(local, name.clone(), TimeDelta::try_hours(24).unwrap())
+        .await;
+    ctx.send(CreateReply::default().content(format!(
+        "{} `{}` ({:.1} MB): {}\nThis link expires in 24 hours.",
+        server.emoji,
+        name,
+        size as f64 / (1024.0 * 1024.0),
+        url
+    )))
+    .await?;
+    Ok(())
+}
+
+/// Pin a demo so it is never pruned
+#[poise::command(slash_command)]
+async fn pin(
+    ctx: Context<'_>,
+    #[description = "The server the demo is on"]
+    #[autocomplete = "servers_autocomplete"]
+    server: String,
+    #[description = "The demo file name"] name: String,
+    #[description = "Why the demo is being kept"] reason: Option<String>,
+) -> Result<(), Error> {
+    validate_name(&name)?;
+    let server = ctx.data().server(&server)?;
+    let reason = reason.unwrap_or(format!("Pinned by {}", ctx.author().name));
+    pin_demo(&ctx.data().local_pool, server, &name, &reason).await?;
+    ctx.say(format!("📌 Pinned `{}` on {}", name, server.emoji))
+        .await?;
+    Ok(())
+}
+
+/// Unpin a demo, allowing it to be pruned
+#[poise::command(slash_command)]
+async fn unpin(
+    ctx: Context<'_>,
+    #[description = "The server the demo is on"]
+    #[autocomplete = "servers_autocomplete"]
+    server: String,
+    #[description = "The demo file name"] name: String,
+) -> Result<(), Error> {
+    validate_name(&name)?;
+    let server = ctx.data().server(&server)?;
+    sqlx::query!(
+        "DELETE FROM `demo_pins` WHERE `server` = ? AND `name` = ?",
+        server.name,
+        name
+    )
+    .execute(&ctx.data().local_pool)
+    .await?;
+    ctx.say(format!("Unpinned `{}` on {}", name, server.emoji))
+        .await?;
+    Ok(())
+}
@@ -1,8 +1,10 @@
-use crate::{discord::Context, Error};
+use crate::{Error, discord::Context};
 use poise;
 use poise::CreateReply;
 use tf2::{banid, rcon_user_output};
 
+use super::demos::pin_latest_demo;
+use super::permissions::can_rcon;
 use super::util::{
     rcon_and_reply, servers_autocomplete, steam_id_autocomplete, users_autocomplete,
 };
@@ -22,7 +24,11 @@ pub async fn tf2ban(
 ) -> Result<(), Error> {
     let reason = reason.unwrap_or("undesirable".to_owned());
     let cmd = format!("sm_ban \"{}\" {} {}", username, minutes, reason);
-    rcon_and_reply(ctx, server, cmd).await
+    rcon_and_reply(ctx, server.clone(), cmd).await?;
+    if let Some(server) = &server {
+        pin_ban_demo(ctx, server, &username).await;
+    }
+    Ok(())
 }
 
 /// Raw ban (when sourcemod is down)
@@ -38,7 +44,24 @@ pub async fn tf2banraw(
     #[description = "Time to ban them for, in minutes"] minutes: u32,
 ) -> Result<(), Error> {
     let cmd = format!("banid \"{}\" {} kick", minutes, user);
-    rcon_and_reply(ctx, Some(server), cmd).await
+    rcon_and_reply(ctx, Some(server.clone()), cmd).await?;
+    pin_ban_demo(ctx, &server, &user).await;
+    Ok(())
+}
+
+/// keeps the demo of the ban from being pruned
+async fn pin_ban_demo(ctx: Context<'_>, server: &str, user: &str) {
+    let Ok(server) = ctx.data().server(server) else {
+        return;
+    };
+    let _ = pin_latest_demo(
+        &ctx.data().local_pool,
+        server,
+        &ctx.data().demo_dir,
+        &format!("Ban of {} by {}", user, ctx.author().name),
+    )
+    .await
+    .inspect_err(|e| log::error!("Could not pin ban demo: {e}"));
 }
 
 /// Raw kick (when sourcemod is down)
@@ -66,11 +89,18 @@ pub async fn tf2banid(
     #[description = "Time to ban them for, in minutes"] minutes: u32,
     #[description = "The reason for the ban"] reason: Option<String>,
 ) -> Result<(), Error> {
+    // the ban runs on every server, so it needs rcon on all of them
+    let servers = ctx.data().servers.values().collect::<Vec<&tf2::Server>>();
+    for server in &servers {
+        if !can_rcon(ctx, &server.name).await {
+            return Err(format!("You are not allowed to use RCON on {}.", server.emoji).into());
+        }
+    }
     ctx.defer().await?;
     let result = banid(
         &ctx.data().steamid_client,
         &id,
-        &ctx.data().servers.values().collect::<Vec<&tf2::Server>>(),
+        &servers,
         minutes,
         &reason.unwrap_or("undesirable".to_owned()),
     )
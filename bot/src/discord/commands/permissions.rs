@@ -0,0 +1,506 @@
+use std::collections::{HashMap, HashSet};
+
+use poise::serenity_prelude::{self as serenity, AutocompleteChoice, CreateEmbed, RoleId};
+use poise::{self, CreateReply};
+use sqlx::{MySql, Pool};
+
+use super::util::servers_autocomplete;
+use crate::discord::{Context, PoiseData};
+use common::Error;
+
+/// commands that are restricted to the mod role unless overridden in the db
+const MOD_ONLY: &[&str] = &[
+    "rcon",
+    "playercap",
+    "purge",
+    "botsay",
+    "givepro",
+    "pug",
+    "pugqueue format",
+    "pugresult",
+    "map",
+    "wacky",
+    "snipers",
+    "bhop",
+    "respawntimes",
+    "tf2ban",
+    "tf2banraw",
+    "tf2banid",
+    "tf2unban",
+    "tf2kick",
+    "tf2kickraw",
+    "tf2mute",
+    "tf2unmute",
+    "tf2gag",
+    "tf2ungag",
+    "spawn_duel",
+    "spawn_poker",
+    "get_videos",
+    "fixpulls",
+    "catcoinadmin reconcile",
+    "catcoinadmin shopadd",
+    "catcoinadmin shopedit",
+    "catcoinadmin setadd",
+    "catcoinadmin setedit",
+    "catcoinadmin chance",
+    "catcoinadmin rarity",
+    "catcoinadmin pity",
+    "catcoinadmin multiplier",
+    "catcoinadmin reward add",
+    "catcoinadmin reward edit",
+    "catcoinadmin reward retire",
+    "permissions",
+    "link force",
+    "link detach",
+    "link history",
+    "demos fetch",
+    "demos pin",
+    "demos unpin",
+];
+
+/// Maps commands and servers to the roles allowed to use them
+pub struct PermissionPolicy {
+    mod_role: RoleId,
+    /// command qualified name -> allowed roles. overrides MOD_ONLY
+    commands: HashMap<String, HashSet<RoleId>>,
+    /// server name -> roles allowed to send rcon to it. unrestricted if absent
+    servers: HashMap<String, HashSet<RoleId>>,
+}
+
+impl PermissionPolicy {
+    pub async fn new_with_init(pool: &Pool<MySql>, mod_role: RoleId) -> Result<Self, Error> {
+        let mut commands: HashMap<String, HashSet<RoleId>> = HashMap::new();
+        for row in sqlx::query!("SELECT `command`, `rid` FROM `command_permissions`")
+            .fetch_all(pool)
+            .await?
+        {
+            commands
+                .entry(row.command)
+                .or_default()
+                .insert(RoleId::new(row.rid.parse()?));
+        }
+
+        let mut servers: HashMap<String, HashSet<RoleId>> = HashMap::new();
+        for row in sqlx::query!("SELECT `server`, `rid` FROM `rcon_permissions`")
+            .fetch_all(pool)
+            .await?
+        {
+            servers
+                .entry(row.server)
+                .or_default()
+                .insert(RoleId::new(row.rid.parse()?));
+        }
+
+        Ok(Self {
+            mod_role,
+            commands,
+            servers,
+        })
+    }
+
+    /// the roles allowed to run this command, walking up to parent commands. None if unrestricted
+    pub fn rule(&self, command: &str) -> Option<HashSet<RoleId>> {
+        let mut name = command;
+        loop {
+            if let Some(roles) = self.commands.get(name) {
+                return Some(roles.clone());
+            }
+            if MOD_ONLY.contains(&name) {
+                return Some(HashSet::from([self.mod_role]));
+            }
+            name = name.rsplit_once(' ')?.0;
+        }
+    }
+
+    /// checks if a member with the given roles can run the command
+    pub fn can_run(&self, command: &str, roles: &[RoleId], everyone: RoleId) -> bool {
+        match self.rule(command) {
+            None => true,
+            Some(allowed) => {
+                allowed.contains(&everyone) || roles.iter().any(|r| allowed.contains(r))
+            }
+        }
+    }
+
+    /// checks if a member with the given roles can send rcon to the server
+    pub fn can_rcon(&self, server: &str, roles: &[RoleId], everyone: RoleId) -> bool {
+        match self.servers.get(server) {
+            None => true,
+            Some(allowed) => {
+                allowed.contains(&everyone) || roles.iter().any(|r| allowed.contains(r))
+            }
+        }
+    }
+
+    /// allow a role to run a command. returns true if the command was unrestricted,
+    /// in which case it's now restricted to just that role
+    pub async fn allow(
+        &mut self,
+        pool: &Pool<MySql>,
+        command: &str,
+        role: RoleId,
+    ) -> Result<bool, Error> {
+        // materialize the inherited rule so that allowing a role doesn't open it to everyone else
+        let rule = self.rule(command);
+        let restricted = rule.is_none();
+        let mut roles = rule.unwrap_or_default();
+        roles.insert(role);
+        self.set(pool, command, roles).await?;
+        Ok(restricted)
+    }
+
+    /// revoke a role's access to a command
+    pub async fn revoke(
+        &mut self,
+        pool: &Pool<MySql>,
+        command: &str,
+        role: RoleId,
+    ) -> Result<(), Error> {
+        let mut roles = self
+            .rule(command)
+            .ok_or("This command is not restricted.")?;
+        if !roles.remove(&role) {
+            return Err("That role cannot use this command already.".into());
+        }
+        if roles.is_empty() {
+            return Err("Cannot remove the last role. Use /permissions reset instead.".into());
+        }
+        self.set(pool, command, roles).await
+    }
+
+    /// remove db overrides for a command, falling back to the defaults
+    pub async fn reset(&mut self, pool: &Pool<MySql>, command: &str) -> Result<(), Error> {
+        sqlx::query!(
+            "DELETE FROM `command_permissions` WHERE `command` = ?",
+            command
+        )
+        .execute(pool)
+        .await?;
+        self.commands.remove(command);
+        Ok(())
+    }
+
+    async fn set(
+        &mut self,
+        pool: &Pool<MySql>,
+        command: &str,
+        roles: HashSet<RoleId>,
+    ) -> Result<(), Error> {
+        let mut tx = pool.begin().await?;
+        sqlx::query!(
+            "DELETE FROM `command_permissions` WHERE `command` = ?",
+            command
+        )
+        .execute(&mut *tx)
+        .await?;
+        for role in &roles {
+            sqlx::query!(
+                "INSERT INTO `command_permissions` (`command`, `rid`) VALUES (?, ?)",
+                command,
+                role.to_string()
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        self.commands.insert(command.to_owned(), roles);
+        Ok(())
+    }
+
+    /// restrict rcon on a server to the role (and any others already allowed)
+    pub async fn allow_rcon(
+        &mut self,
+        pool: &Pool<MySql>,
+        server: &str,
+        role: RoleId,
+    ) -> Result<(), Error> {
+        sqlx::query!(
+            "INSERT IGNORE INTO `rcon_permissions` (`server`, `rid`) VALUES (?, ?)",
+            server,
+            role.to_string()
+        )
+        .execute(pool)
+        .await?;
+        self.servers
+            .entry(server.to_owned())
+            .or_default()
+            .insert(role);
+        Ok(())
+    }
+
+    /// remove a role from a server's rcon list. the server is unrestricted once the list is empty
+    pub async fn revoke_rcon(
+        &mut self,
+        pool: &Pool<MySql>,
+        server: &str,
+        role: RoleId,
+    ) -> Result<(), Error> {
+        sqlx::query!(
+            "DELETE FROM `rcon_permissions` WHERE `server` = ? AND `rid` = ?",
+            server,
+            role.to_string()
+        )
+        .execute(pool)
+        .await?;
+        if let Some(roles) = self.servers.get_mut(server) {
+            roles.remove(&role);
+            if roles.is_empty() {
+                self.servers.remove(server);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// the roles of the invoking member, empty outside of the guild
+async fn author_roles(ctx: Context<'_>) -> Vec<RoleId> {
+    ctx.author_member()
+        .await
+        .map(|m| m.roles.clone())
+        .unwrap_or_default()
+}
+
+/// global poise check, denies commands the author's roles don't allow
+pub async fn check(ctx: Context<'_>) -> Result<bool, Error> {
+    let command = &ctx.command().qualified_name;
+    let roles = author_roles(ctx).await;
+    let everyone = ctx.data().guild_id.everyone_role();
+    let allowed = ctx
+        .data()
+        .permissions
+        .read()
+        .await
+        .can_run(command, &roles, everyone);
+    if !allowed {
+        log::info!("Denied /{} to {}", command, ctx.author().name);
+        ctx.send(
+            CreateReply::default()
+                .ephemeral(true)
+                .content(format!("You do not have permission to use `/{}`.", command)),
+        )
+        .await?;
+    }
+    Ok(allowed)
+}
+
+/// checks if the author may send rcon to the server
+pub async fn can_rcon(ctx: Context<'_>, server: &str) -> bool {
+    let roles = author_roles(ctx).await;
+    ctx.data().permissions.read().await.can_rcon(
+        server,
+        &roles,
+        ctx.data().guild_id.everyone_role(),
+    )
+}
+
+fn qualified_names(commands: &[poise::Command<PoiseData, Error>], out: &mut Vec<String>) {
+    for command in commands {
+        out.push(command.qualified_name.clone());
+        qualified_names(&command.subcommands, out);
+    }
+}
+
+async fn commands_autocomplete(ctx: Context<'_>, partial: &str) -> Vec<AutocompleteChoice> {
+    let mut names = vec![];
+    qualified_names(&ctx.framework().options().commands, &mut names);
+    names
+        .into_iter()
+        .filter(|name| name.contains(&partial.to_lowercase()))
+        .take(25)
+        .map(|name| AutocompleteChoice::new(name.clone(), name))
+        .collect()
+}
+
+fn mention_roles(roles: &HashSet<RoleId>, everyone: RoleId) -> String {
+    roles
+        .iter()
+        .map(|r| {
+            if *r == everyone {
+                "@everyone".to_owned()
+            } else {
+                format!("<@&{}>", r.get())
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+/// View or edit which roles may use bot commands
+#[poise::command(
+    slash_command,
+    subcommands("view", "allow", "revoke", "reset", "rcon_allow", "rcon_revoke"),
+    subcommand_required
+)]
+pub async fn permissions(_: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// View the current permission policy
+#[poise::command(slash_command)]
+pub async fn view(ctx: Context<'_>) -> Result<(), Error> {
+    let mut names = vec![];
+    qualified_names(&ctx.framework().options().commands, &mut names);
+    names.sort();
+
+    let everyone = ctx.data().guild_id.everyone_role();
+    let policy = ctx.data().permissions.read().await;
+
+    let commands = names
+        .iter()
+        .filter_map(|name| {
+            let roles = policy.rule(name)?;
+            Some(format!("`/{}`: {}", name, mention_roles(&roles, everyone)))
+        })
+        .collect::<Vec<String>>()
+        .join("\n");
+    let servers = ctx
+        .data()
+        .servers
+        .values()
+        .map(|s| match policy.servers.get(&s.name) {
+            Some(roles) => format!(
+                "{} `{}`: {}",
+                s.emoji,
+                s.name,
+                mention_roles(roles, everyone)
+            ),
+            None => format!("{} `{}`: unrestricted", s.emoji, s.name),
+        })
+        .collect::<Vec<String>>()
+        .join("\n");
+
+    ctx.send(
+        CreateReply::default().ephemeral(true).embed(
+            CreateEmbed::new()
+                .title("Permission policy")
+                .description(common::util::truncate(&commands, 4000))
+                .field("RCON", servers, false),
+        ),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Allow a role to use a command
+#[poise::command(slash_command)]
+pub async fn allow(
+    ctx: Context<'_>,
+    #[description = "The command (eg. `rcon` or `map add`)"]
+    #[autocomplete = "commands_autocomplete"]
+    command: String,
+    #[description = "The role to allow"] role: serenity::Role,
+) -> Result<(), Error> {
+    let restricted = ctx
+        .data()
+        .permissions
+        .write()
+        .await
+        .allow(&ctx.data().local_pool, &command, role.id)
+        .await?;
+    let content = if restricted {
+        format!(
+            "`/{}` was open to everyone. It is now restricted to {} only.",
+            command, role.name
+        )
+    } else {
+        format!("Allowed {} to use `/{}`.", role.name, command)
+    };
+    ctx.send(CreateReply::default().ephemeral(true).content(content))
+        .await?;
+    Ok(())
+}
+
+/// Revoke a role's access to a command
+#[poise::command(slash_command)]
+pub async fn revoke(
+    ctx: Context<'_>,
+    #[description = "The command (eg. `rcon` or `map add`)"]
+    #[autocomplete = "commands_autocomplete"]
+    command: String,
+    #[description = "The role to revoke"] role: serenity::Role,
+) -> Result<(), Error> {
+    ctx.data()
+        .permissions
+        .write()
+        .await
+        .revoke(&ctx.data().local_pool, &command, role.id)
+        .await?;
+    ctx.send(
+        CreateReply::default()
+            .ephemeral(true)
+            .content(format!("Revoked {}'s access to `/{}`.", role.name, command)),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Reset a command to its default permissions
+#[poise::command(slash_command)]
+pub async fn reset(
+    ctx: Context<'_>,
+    #[description = "The command (eg. `rcon` or `map add`)"]
+    #[autocomplete = "commands_autocomplete"]
+    command: String,
+) -> Result<(), Error> {
+    ctx.data()
+        .permissions
+        .write()
+        .await
+        .reset(&ctx.data().local_pool, &command)
+        .await?;
+    ctx.send(
+        CreateReply::default()
+            .ephemeral(true)
+            .content(format!("Reset `/{}` to its default permissions.", command)),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Restrict RCON on a server to a role
+#[poise::command(slash_command)]
+pub async fn rcon_allow(
+    ctx: Context<'_>,
+    #[description = "The server"]
+    #[autocomplete = "servers_autocomplete"]
+    server: String,
+    #[description = "The role to allow"] role: serenity::Role,
+) -> Result<(), Error> {
+    let server = ctx.data().server(&server)?;
+    ctx.data()
+        .permissions
+        .write()
+        .await
+        .allow_rcon(&ctx.data().local_pool, &server.name, role.id)
+        .await?;
+    ctx.send(CreateReply::default().ephemeral(true).content(format!(
+        "Allowed {} to use RCON on {}.",
+        role.name, server.emoji
+    )))
+    .await?;
+    Ok(())
+}
+
+/// Remove a role from a server's RCON list
+#[poise::command(slash_command)]
+pub async fn rcon_revoke(
+    ctx: Context<'_>,
+    #[description = "The server"]
+    #[autocomplete = "servers_autocomplete"]
+    server: String,
+    #[description = "The role to revoke"] role: serenity::Role,
+) -> Result<(), Error> {
+    let server = ctx.data().server(&server)?;
+    ctx.data()
+        .permissions
+        .write()
+        .await
+        .revoke_rcon(&ctx.data().local_pool, &server.name, role.id)
+        .await?;
+    ctx.send(CreateReply::default().ephemeral(true).content(format!(
+        "Revoked {}'s RCON access on {}.",
+        role.name, server.emoji
+    )))
+    .await?;
+    Ok(())
+}
@@ -1,23 +1,28 @@
 use std::time::Duration;
 
 use poise::{
+    CreateReply,
     serenity_prelude::{
         self as serenity, ButtonStyle, ComponentInteraction, ComponentInteractionCollector,
-        CreateActionRow, CreateButton, CreateInteractionResponse, CreateInteractionResponseMessage,
-        CreateSelectMenu, CreateSelectMenuKind, CreateSelectMenuOption, GetMessages, ReactionType,
+        CreateActionRow, CreateAttachment, CreateButton, CreateEmbed, CreateInteractionResponse,
+        CreateInteractionResponseMessage, CreateSelectMenu, CreateSelectMenuKind,
+        CreateSelectMenuOption, GetMessages, Mentionable, ReactionType,
     },
-    CreateReply,
 };
 
+use super::shop::profile_background;
 use crate::discord::Context;
 
-use catcoin::get_catcoin;
-use common::{discord::get_steam_link_content, Error};
+use achievements::get_unlocked;
+use catcoin::{get_catcoin, inventory::PaginatedInventory};
+use common::{Error, discord::get_steam_link_content};
 use profile::{
-    get_user_profile, view_profile,
+    UserProfile, get_user_profile,
+    link::{LinkAction, LinkEvent, get_link_history, link_steam, unlink_steam},
+    view_profile,
     vote::{self, get_profile_votes},
-    UserProfile,
 };
+use pugs::get_rating;
 use steam::SteamProfileData;
 
 #[derive(Debug, poise::Modal)]
@@ -48,6 +53,7 @@ pub async fn get_profile(
 pub async fn profile(
     ctx: Context<'_>,
     #[description = "The user to retrieve"] member: Option<serenity::Member>,
+    #[description = "Render the profile as a shareable image"] card: Option<bool>,
 ) -> Result<(), Error> {
     let member = if let Some(member) = member {
         member
@@ -62,12 +68,62 @@ pub async fn profile(
         .await?;
         return Ok(());
     };
-    send_profile(ctx, member).await
+    if card.unwrap_or(false) {
+        send_card(ctx, member).await
+    } else {
+        send_profile(ctx, member).await
+    }
+}
+
+/// renders a profile as a shareable image
+async fn send_card(ctx: Context<'_>, member: serenity::Member) -> Result<(), Error> {
+    ctx.defer().await?;
+    let pool = &ctx.data().local_pool;
+    let profile = get_user_profile(pool, member.user.id).await?;
+    let steam_data = if let Some(ref steamid) = profile.steamid {
+        SteamProfileData::get(pool, &ctx.data().steamid_client, steamid).await?
+    } else {
+        None
+    };
+    let catcoin = get_catcoin(pool, member.user.id).await?;
+    let pug_rating = get_rating(pool, member.user.id).await?;
+    let pulls = PaginatedInventory::get(pool, member.user.id).await?.pulls;
+    let unlocked = get_unlocked(pool, member.user.id).await?;
+    let background = profile_background(ctx.data(), member.user.id).await;
+
+    let png = profile
+        .to_card(
+            &member.user,
+            member.display_name().to_owned(),
+            steam_data,
+            catcoin,
+            pug_rating,
+            pulls,
+            unlocked,
+            background,
+        )
+        .await?;
+    ctx.send(CreateReply::default().attachment(CreateAttachment::bytes(
+        png,
+        format!("{}.png", member.user.name),
+    )))
+    .await?;
+    Ok(())
+}
+
+/// Manage TKGP steam links
+#[poise::command(
+    slash_command,
+    subcommands("steam", "status", "force", "detach", "history"),
+    subcommand_required
+)]
+pub async fn link(_: Context<'_>) -> Result<(), Error> {
+    Ok(()) // never run
 }
 
 /// Link your steam account to TKGP
 #[poise::command(slash_command)]
-pub async fn link(ctx: Context<'_>) -> Result<(), Error> {
+async fn steam(ctx: Context<'_>) -> Result<(), Error> {
     let (embed, row) = get_steam_link_content(&ctx.data().api_state.link_url());
     ctx.send(CreateReply::default().embed(embed).components(row))
         .await?;
@@ -75,6 +131,128 @@ pub async fn link(ctx: Context<'_>) -> Result<(), Error> {
     Ok(())
 }
 
+/// Check which steam account is linked to you
+#[poise::command(slash_command)]
+async fn status(ctx: Context<'_>) -> Result<(), Error> {
+    let profile = get_user_profile(&ctx.data().local_pool, ctx.author().id).await?;
+    let content = match profile.steamid {
+        Some(steamid) => format!(
+            "🔗 Linked to `{}` (https://steamcommunity.com/profiles/{}). Use /unlink to remove it.",
+            steamid, steamid
+        ),
+        None => "You have no steam account linked. Use /link steam to link one.".to_owned(),
+    };
+    ctx.send(CreateReply::default().content(content).ephemeral(true))
+        .await?;
+    Ok(())
+}
+
+/// Link a steam account to a user, detaching it from anyone else
+#[poise::command(slash_command)]
+async fn force(
+    ctx: Context<'_>,
+    #[description = "The user to link"] user: serenity::User,
+    #[description = "Their steam profile url or id"] steam: String,
+) -> Result<(), Error> {
+    let pool = &ctx.data().local_pool;
+    let profile = ctx
+        .data()
+        .steamid_client
+        .lookup(&steam)
+        .await?
+        .into_iter()
+        .next()
+        .ok_or("Could not find that steam account.")?;
+    let content = match link_steam(
+        pool,
+        user.id,
+        &profile.steam3,
+        LinkAction::ForceLink,
+        ctx.author().id,
+    )
+    .await
+    {
+        Ok(()) => format!("Linked {} to {}", user.mention(), profile.steamidurl),
+        Err(e) => format!("Could not link: {e}"),
+    };
+    ctx.send(CreateReply::default().content(content).ephemeral(true))
+        .await?;
+    Ok(())
+}
+
+/// Remove a user's steam link
+#[poise::command(slash_command)]
+async fn detach(
+    ctx: Context<'_>,
+    #[description = "The user to unlink"] user: serenity::User,
+) -> Result<(), Error> {
+    let unlinked = unlink_steam(
+        &ctx.data().local_pool,
+        user.id,
+        LinkAction::ForceUnlink,
+        ctx.author().id,
+    )
+    .await?;
+    let content = match unlinked {
+        Some(steamid) => format!("Unlinked `{}` from {}", steamid, user.mention()),
+        None => format!("{} has no steam account linked.", user.mention()),
+    };
+    ctx.send(CreateReply::default().content(content).ephemeral(true))
+        .await?;
+    Ok(())
+}
+
+/// View the steam link history of a user
+#[poise::command(slash_command)]
+async fn history(
+    ctx: Context<'_>,
+    #[description = "The user to look up"] user: serenity::User,
+) -> Result<(), Error> {
+    let events = get_link_history(&ctx.data().local_pool, user.id, 20).await?;
+    let description = if events.is_empty() {
+        "`No history`".to_owned()
+    } else {
+        events
+            .iter()
+            .map(LinkEvent::line)
+            .collect::<Vec<String>>()
+            .join("\n")
+    };
+    ctx.send(
+        CreateReply::default()
+            .embed(
+                CreateEmbed::new()
+                    .title(format!("🔗 Steam link history of {}", user.name))
+                    .description(description),
+            )
+            .ephemeral(true),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Unlink your steam account from TKGP
+#[poise::command(slash_command)]
+pub async fn unlink(ctx: Context<'_>) -> Result<(), Error> {
+    let unlinked = unlink_steam(
+        &ctx.data().local_pool,
+        ctx.author().id,
+        LinkAction::Unlink,
+        ctx.author().id,
+    )
+    .await?;
+    let content = match unlinked {
+        Some(steamid) => format!(
+            "Unlinked `{}`. Do /link steam again if you change your mind ^-^",
+            steamid
+        ),
+        None => "You have no steam account linked.".to_owned(),
+    };
+    ctx.send(CreateReply::default().content(content).ephemeral(true))
+        .await?;
+    Ok(())
+}
+
 async fn send_profile(ctx: Context<'_>, member: serenity::Member) -> Result<(), Error> {
     ctx.defer().await?;
     let uuid = ctx.id();
@@ -119,6 +297,8 @@ async fn send_profile(ctx: Context<'_>, member: serenity::Member) -> Result<(),
         None
     };
     let mut catcoin = get_catcoin(&ctx.data().local_pool, member.user.id).await?;
+    let mut pug_rating = get_rating(&ctx.data().local_pool, member.user.id).await?;
+    let mut unlocked = get_unlocked(&ctx.data().local_pool, member.user.id).await?;
 
     // buttons
     let buttons = vec![
@@ -156,6 +336,8 @@ async fn send_profile(ctx: Context<'_>, member: serenity::Member) -> Result<(),
                             votes.clone(),
                             steam_data.clone(),
                             catcoin.clone(),
+                            pug_rating.clone(),
+                            unlocked.clone(),
                         )
                         .await?,
                 )
@@ -193,6 +375,8 @@ async fn send_profile(ctx: Context<'_>, member: serenity::Member) -> Result<(),
                                 votes,
                                 steam_data.clone(),
                                 catcoin.clone(),
+                                pug_rating.clone(),
+                                unlocked.clone(),
                             )
                             .await?,
                     ),
@@ -223,6 +407,8 @@ async fn send_profile(ctx: Context<'_>, member: serenity::Member) -> Result<(),
                 None
             };
             catcoin = get_catcoin(&ctx.data().local_pool, member.user.id).await?;
+            pug_rating = get_rating(&ctx.data().local_pool, member.user.id).await?;
+            unlocked = get_unlocked(&ctx.data().local_pool, member.user.id).await?;
             mci.create_response(
                 &ctx,
                 CreateInteractionResponse::UpdateMessage(
@@ -234,6 +420,8 @@ async fn send_profile(ctx: Context<'_>, member: serenity::Member) -> Result<(),
                                 votes,
                                 steam_data.clone(),
                                 catcoin.clone(),
+                                pug_rating.clone(),
+                                unlocked.clone(),
                             )
                             .await?,
                     ),
@@ -1,11 +1,313 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+
 use crate::discord::Context;
 use common::Error;
 
 use poise;
-use poise::CreateReply;
+use poise::serenity_prelude::{
+    self as serenity, ButtonStyle, ComponentInteractionCollector, ComponentInteractionDataKind,
+    CreateActionRow, CreateAllowedMentions, CreateButton, CreateEmbed, CreateInteractionResponse,
+    CreateInteractionResponseMessage, CreateMessage, CreateSelectMenu, CreateSelectMenuOption,
+    EditMessage, Mentionable, UserId,
+};
+use poise::{ChoiceParameter, CreateReply};
+use rand::{distributions::Alphanumeric, prelude::*};
+use tf2::cfgs::{CfgIndex, list_cfgs};
+use tf2::{Server, TF2Class};
+use tokio::sync::RwLock;
 
+use super::teamcaptain::{Draft, DraftLobby, DraftPlayer, run_draft};
 use super::util::*;
 
+/// how long players have to ready up once the queue fills
+const READY_TIMEOUT: Duration = Duration::from_secs(60);
+/// how long the map vote stays open
+const MAP_VOTE_TIMEOUT: Duration = Duration::from_secs(30);
+/// maps offered in the map vote
+const MAP_VOTE_CHOICES: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, poise::ChoiceParameter)]
+pub enum PugFormat {
+    #[name = "6s"]
+    Sixes,
+    #[name = "Prolander"]
+    Prolander,
+    #[name = "Ultiduo"]
+    Ultiduo,
+}
+
+impl PugFormat {
+    pub fn team_size(&self) -> usize {
+        match self {
+            PugFormat::Sixes => 6,
+            PugFormat::Prolander => 7,
+            PugFormat::Ultiduo => 2,
+        }
+    }
+
+    /// whether the map can be played in this format
+    pub fn allows_map(&self, map: &str) -> bool {
+        match self {
+            PugFormat::Sixes => {
+                (map.starts_with("cp_") || map.starts_with("koth_")) && !map.contains("ultiduo")
+            }
+            PugFormat::Prolander => map.starts_with("koth_") && !map.contains("ultiduo"),
+            PugFormat::Ultiduo => map.contains("ultiduo"),
+        }
+    }
+}
+
+/// A player waiting in the pug queue
+#[derive(Clone)]
+pub struct QueuedPlayer {
+    pub uid: UserId,
+    pub name: String,
+    pub classes: Vec<TF2Class>,
+}
+
+impl QueuedPlayer {
+    fn classes_text(&self) -> String {
+        self.classes
+            .iter()
+            .map(|c| c.to_string())
+            .collect::<Vec<String>>()
+            .join(", ")
+    }
+}
+
+/// The pug queue. Fills up to the format's player count, then launches a pug
+pub struct PugQueue {
+    pub format: PugFormat,
+    pub players: Vec<QueuedPlayer>,
+    /// set while a full queue is going through ready check / draft / map vote
+    pub launching: bool,
+}
+
+impl PugQueue {
+    pub fn new() -> Self {
+        Self {
+            format: PugFormat::Sixes,
+            players: vec![],
+            launching: false,
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        self.format.team_size() * 2
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.players.len() >= self.size()
+    }
+
+    /// adds or updates a player in the queue
+    pub fn join(&mut self, player: QueuedPlayer) -> Result<(), Error> {
+        if self.launching {
+            return Err("A pug is launching right now, try again in a minute.".into());
+        }
+        if let Some(existing) = self.players.iter_mut().find(|p| p.uid == player.uid) {
+            *existing = player;
+            return Ok(());
+        }
+        if self.is_full() {
+            return Err("The queue is full.".into());
+        }
+        self.players.push(player);
+        Ok(())
+    }
+
+    /// removes a player from the queue, returning whether they were in it
+    pub fn leave(&mut self, uid: UserId) -> bool {
+        let len = self.players.len();
+        self.players.retain(|p| p.uid != uid);
+        self.players.len() != len
+    }
+
+    pub fn to_embed(&self) -> CreateEmbed {
+        let list = self
+            .players
+            .iter()
+            .map(|p| {
+                if p.classes.is_empty() {
+                    p.uid.mention().to_string()
+                } else {
+                    format!(
+                        "{} {}",
+                        p.uid.mention(),
+                        p.classes.iter().map(|c| c.emoji()).collect::<String>()
+                    )
+                }
+            })
+            .collect::<Vec<String>>()
+            .join("\n");
+        CreateEmbed::new()
+            .title(format!(
+                "{} pug queue ({}/{})",
+                self.format.name(),
+                self.players.len(),
+                self.size()
+            ))
+            .description(if list.is_empty() {
+                "Nobody is queued. `/pugqueue join` to join!".to_owned()
+            } else {
+                list
+            })
+    }
+}
+
+/// parses class names like "scout demo med"
+fn parse_classes(s: &str) -> Vec<TF2Class> {
+    let mut classes = vec![];
+    for word in s.split(|c: char| c.is_whitespace() || c == ',') {
+        let word = word.trim().to_lowercase();
+        if word.is_empty() {
+            continue;
+        }
+        if let Some(class) = TF2Class::all()
+            .into_iter()
+            .find(|c| c.to_string().starts_with(&word))
+        {
+            if !classes.contains(&class) {
+                classes.push(class);
+            }
+        }
+    }
+    classes
+}
+
+/// how often the pug server's cfgs are rescanned
+const CFG_REFRESH_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// rescans the cfgs on the pug server
+pub async fn refresh_pug_cfgs(server: &Server, index: &RwLock<CfgIndex>) -> Result<(), Error> {
+    let files = list_cfgs(server).await?;
+    index.write().await.update(files);
+    Ok(())
+}
+
+/// periodically rescans the cfgs on the pug server
+pub fn spawn_pug_cfg_refresh_thread(server: Server, index: Arc<RwLock<CfgIndex>>) {
+    let mut interval = tokio::time::interval(CFG_REFRESH_INTERVAL);
+    tokio::spawn(async move {
+        loop {
+            interval.tick().await;
+            let _ = refresh_pug_cfgs(&server, &index)
+                .await
+                .inspect_err(|e| log::error!("Could not refresh pug cfgs: {e}"));
+        }
+    });
+}
+
+/// The pug queue
+#[poise::command(
+    slash_command,
+    subcommands("join", "leave", "view", "format"),
+    subcommand_required
+)]
+pub async fn pugqueue(_: Context<'_>) -> Result<(), Error> {
+    Ok(()) // never run
+}
+
+/// Join the pug queue
+#[poise::command(slash_command)]
+async fn join(
+    ctx: Context<'_>,
+    #[description = "Classes you want to play (eg. \"scout demo\"). Defaults to your /profile classes"]
+    classes: Option<String>,
+) -> Result<(), Error> {
+    let classes = match classes {
+        Some(classes) => parse_classes(&classes),
+        None => ::profile::get_user_profile(&ctx.data().local_pool, ctx.author().id)
+            .await
+            .map(|p| p.get_classes())
+            .unwrap_or_default(),
+    };
+    let name = match ctx.author_member().await {
+        Some(m) => m.display_name().to_owned(),
+        None => ctx.author().name.clone(),
+    };
+    let player = QueuedPlayer {
+        uid: ctx.author().id,
+        name,
+        classes,
+    };
+
+    let full = {
+        let mut queue = ctx.data().pug_queue.write().await;
+        queue.join(player)?;
+        ctx.send(CreateReply::default().embed(queue.to_embed()))
+            .await?;
+        queue.is_full()
+    };
+    if full {
+        spawn_launch(ctx)?;
+    }
+    Ok(())
+}
+
+/// Leave the pug queue
+#[poise::command(slash_command)]
+async fn leave(ctx: Context<'_>) -> Result<(), Error> {
+    let mut queue = ctx.data().pug_queue.write().await;
+    if queue.launching {
+        ctx.send(
+            CreateReply::default()
+                .content("The pug is launching! Don't ready up if you can't play.")
+                .ephemeral(true),
+        )
+        .await?;
+        return Ok(());
+    }
+    if !queue.leave(ctx.author().id) {
+        ctx.send(
+            CreateReply::default()
+                .content("You are not in the queue.")
+                .ephemeral(true),
+        )
+        .await?;
+        return Ok(());
+    }
+    ctx.send(CreateReply::default().embed(queue.to_embed()))
+        .await?;
+    Ok(())
+}
+
+/// View the pug queue
+#[poise::command(slash_command)]
+async fn view(ctx: Context<'_>) -> Result<(), Error> {
+    let queue = ctx.data().pug_queue.read().await;
+    ctx.send(CreateReply::default().embed(queue.to_embed()))
+        .await?;
+    Ok(())
+}
+
+/// Change the pug queue format
+#[poise::command(slash_command)]
+async fn format(
+    ctx: Context<'_>,
+    #[description = "The pug format"] format: PugFormat,
+) -> Result<(), Error> {
+    let full = {
+        let mut queue = ctx.data().pug_queue.write().await;
+        if queue.launching {
+            return Err("Cannot change the format while a pug is launching.".into());
+        }
+        queue.format = format;
+        // players past the new size are dropped from the end of the queue
+        let size = queue.size();
+        queue.players.truncate(size);
+        ctx.send(CreateReply::default().embed(queue.to_embed()))
+            .await?;
+        queue.is_full()
+    };
+    if full {
+        spawn_launch(ctx)?;
+    }
+    Ok(())
+}
+
 /// Control the pug / scrim server (tkgp6)
 #[poise::command(slash_command)]
 pub async fn pug(
@@ -18,12 +320,16 @@ pub async fn pug(
     cfg: String,
 ) -> Result<(), Error> {
     ctx.defer().await?;
-    if !ctx.data().pug_cfgs.contains(&cfg) {
-        ctx.send(CreateReply::default().content("That cfg file does not exist!"))
-            .await?;
-        return Ok(());
-    }
     let ps = ctx.data().pug_server()?;
+    if !ctx.data().pug_cfgs.read().await.contains(&cfg) {
+        // it may have been installed since the last refresh
+        refresh_pug_cfgs(ps, &ctx.data().pug_cfgs).await?;
+        if !ctx.data().pug_cfgs.read().await.contains(&cfg) {
+            ctx.send(CreateReply::default().content("That cfg file does not exist!"))
+                .await?;
+            return Ok(());
+        }
+    }
     if !ps.maps().await?.contains(&map) {
         ctx.send(CreateReply::default().content("That map does not exist!"))
             .await?;
@@ -38,3 +344,441 @@ pub async fn pug(
     ctx.send(CreateReply::default().content(response)).await?;
     Ok(())
 }
+
+#[derive(Debug, Clone, Copy, poise::ChoiceParameter)]
+pub enum PugWinner {
+    #[name = "RED"]
+    Red,
+    #[name = "BLU"]
+    Blu,
+    #[name = "Tie"]
+    Tie,
+}
+
+/// Record the result of a pug, updating everyone's rating
+#[poise::command(slash_command)]
+pub async fn pugresult(
+    ctx: Context<'_>,
+    #[description = "The winning team"] winner: PugWinner,
+    #[description = "The pug number (default: the latest unfinished pug)"] pug: Option<i32>,
+    #[description = "RED's final score"] red_score: Option<i32>,
+    #[description = "BLU's final score"] blu_score: Option<i32>,
+) -> Result<(), Error> {
+    let pool = &ctx.data().local_pool;
+    let draft_id = match pug {
+        Some(id) => id,
+        None => pugs::latest_open_draft(pool, chrono::TimeDelta::try_days(1).unwrap())
+            .await?
+            .ok_or("No unfinished pugs in the last day.")?,
+    };
+    let winner = match winner {
+        PugWinner::Red => Some(pugs::Team::Red),
+        PugWinner::Blu => Some(pugs::Team::Blu),
+        PugWinner::Tie => None,
+    };
+    let scores = red_score.zip(blu_score);
+    let changes = pugs::record_result(pool, draft_id, winner, scores).await?;
+    ctx.send(
+        CreateReply::default()
+            .embed(pugs::result_embed(draft_id, winner, scores, &changes))
+            .allowed_mentions(CreateAllowedMentions::new().empty_users()),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Everything a pug launch needs, owned so it can run after the command that filled the queue
+struct PugLaunch {
+    lobby: DraftLobby,
+    queue: Arc<RwLock<PugQueue>>,
+    cfgs: Arc<RwLock<CfgIndex>>,
+    server: Server,
+}
+
+/// launches the full queue in the background, so the ready check, draft and map vote don't
+/// hold up the interaction that filled it
+fn spawn_launch(ctx: Context<'_>) -> Result<(), Error> {
+    let pug = PugLaunch {
+        lobby: DraftLobby::new(&ctx),
+        queue: ctx.data().pug_queue.clone(),
+        cfgs: ctx.data().pug_cfgs.clone(),
+        server: ctx.data().pug_server()?.clone(),
+    };
+    tokio::spawn(async move {
+        let _ = launch(&pug)
+            .await
+            .inspect_err(|e| log::error!("Could not launch pug: {e}"));
+    });
+    Ok(())
+}
+
+/// runs a full queue through ready check, draft, map vote and server setup
+async fn launch(pug: &PugLaunch) -> Result<(), Error> {
+    let (players, format) = {
+        let mut queue = pug.queue.write().await;
+        if queue.launching {
+            return Ok(());
+        }
+        queue.launching = true;
+        (queue.players.clone(), queue.format)
+    };
+
+    let result = run_launch(pug, players, format).await;
+
+    let mut queue = pug.queue.write().await;
+    queue.launching = false;
+    match result {
+        // the drafted players leave the queue
+        Ok(Some(draft)) => queue
+            .players
+            .retain(|p| !draft.red.contains(&p.uid) && !draft.blu.contains(&p.uid)),
+        // unready players were kicked from the queue
+        Ok(None) => (),
+        // something broke mid draft. keep everyone queued
+        Err(e) => {
+            log::error!("Pug launch failed: {e}");
+            pug.lobby
+                .channel
+                .send_message(
+                    &pug.lobby.ctx,
+                    CreateMessage::new()
+                        .content(format!("Pug launch failed: {e}\nThe queue was kept."))
+                        .embed(queue.to_embed()),
+                )
+                .await?;
+        }
+    }
+    Ok(())
+}
+
+async fn run_launch(
+    pug: &PugLaunch,
+    players: Vec<QueuedPlayer>,
+    format: PugFormat,
+) -> Result<Option<Draft>, Error> {
+    let lobby = &pug.lobby;
+    let unready = ready_check(lobby, &players).await?;
+    if !unready.is_empty() {
+        let mut queue = pug.queue.write().await;
+        queue.players.retain(|p| !unready.contains(&p.uid));
+        lobby
+            .channel
+            .send_message(
+                &lobby.ctx,
+                CreateMessage::new()
+                    .content(format!(
+                        "Removed from the queue for not readying up: {}",
+                        unready
+                            .iter()
+                            .map(|u| u.mention().to_string())
+                            .collect::<Vec<String>>()
+                            .join(" ")
+                    ))
+                    .embed(queue.to_embed()),
+            )
+            .await?;
+        return Ok(None);
+    }
+
+    let (red_captain, blu_captain) = pick_captains(&players)?;
+    lobby
+        .channel
+        .send_message(
+            &lobby.ctx,
+            CreateMessage::new().content(format!(
+                "Everyone is ready! Captains are 🔴 {} and 🔵 {}.",
+                red_captain.mention(),
+                blu_captain.mention()
+            )),
+        )
+        .await?;
+    let draft_players = players
+        .iter()
+        .map(|p| DraftPlayer {
+            uid: p.uid,
+            name: p.name.clone(),
+            note: Some(p.classes_text()).filter(|s| !s.is_empty()),
+        })
+        .collect();
+    let draft = run_draft(lobby, red_captain, blu_captain, draft_players).await?;
+
+    let map = map_vote(lobby, &pug.server, &players, format).await?;
+    let cfg = {
+        let cfgs = pug.cfgs.read().await;
+        match cfgs.find(format.name(), &map) {
+            Some(cfg) => cfg.name.clone(),
+            None => {
+                return Err(
+                    format!("No {} cfg for `{}` on the pug server.", format.name(), map).into(),
+                );
+            }
+        }
+    };
+    let password: String = thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(8)
+        .map(char::from)
+        .collect();
+
+    let ps = &pug.server;
+    ps.controller
+        .write()
+        .await
+        .run(&format!(
+            "exec {cfg}; sv_password \"{password}\"; changelevel {map}"
+        ))
+        .await?;
+    let _ = pugs::mark_launched(&lobby.pool, draft.id, &map)
+        .await
+        .inspect_err(|e| log::error!("Could not mark pug #{} launched: {e}", draft.id));
+
+    send_connect_dms(lobby, &draft, &map, ps.addr, &password).await;
+
+    lobby
+        .channel
+        .send_message(
+            &lobby.ctx,
+            CreateMessage::new()
+                .content(format!(
+                    "Pug is set up on {} `{}` with `{}.cfg`! Check your DMs for the connect link.",
+                    ps.emoji, map, cfg
+                ))
+                .embed(draft.to_embed()),
+        )
+        .await?;
+
+    Ok(Some(draft))
+}
+
+/// asks every player to ready up. returns the players that didn't
+async fn ready_check(lobby: &DraftLobby, players: &[QueuedPlayer]) -> Result<Vec<UserId>, Error> {
+    let uuid = lobby.id;
+    let ready_id = format!("{uuid}-ready");
+    let mut pending: HashSet<UserId> = players.iter().map(|p| p.uid).collect();
+
+    let closes = chrono::Utc::now().timestamp() + READY_TIMEOUT.as_secs() as i64;
+    let content = |pending: &HashSet<UserId>| {
+        format!(
+            "The pug queue is full! Ready up <t:{}:R>:\n{}",
+            closes,
+            pending
+                .iter()
+                .map(|u| u.mention().to_string())
+                .collect::<Vec<String>>()
+                .join(" ")
+        )
+    };
+    let mut msg = lobby
+        .channel
+        .send_message(
+            &lobby.ctx,
+            CreateMessage::new()
+                .content(content(&pending))
+                .components(vec![CreateActionRow::Buttons(vec![
+                    CreateButton::new(ready_id.clone())
+                        .label("Ready")
+                        .style(ButtonStyle::Success),
+                ])]),
+        )
+        .await?;
+
+    let deadline = tokio::time::Instant::now() + READY_TIMEOUT;
+    while !pending.is_empty() {
+        let remaining = deadline.saturating_duration_since(tokio::time::Instant::now());
+        if remaining.is_zero() {
+            break;
+        }
+        let ready_id = ready_id.clone();
+        let Some(mci) = ComponentInteractionCollector::new(&lobby.ctx)
+            .message_id(msg.id)
+            .timeout(remaining)
+            .filter(move |mci| mci.data.custom_id == ready_id)
+            .await
+        else {
+            break;
+        };
+        if !pending.remove(&mci.user.id) {
+            mci.create_response(
+                &lobby.ctx,
+                CreateInteractionResponse::Message(
+                    CreateInteractionResponseMessage::new()
+                        .content("You're not in this pug!")
+                        .ephemeral(true),
+                ),
+            )
+            .await?;
+            continue;
+        }
+        mci.create_response(
+            &lobby.ctx,
+            CreateInteractionResponse::UpdateMessage(
+                CreateInteractionResponseMessage::new().content(content(&pending)),
+            ),
+        )
+        .await?;
+    }
+
+    msg.edit(
+        &lobby.ctx,
+        EditMessage::new()
+            .content(if pending.is_empty() {
+                "Everyone is ready!".to_owned()
+            } else {
+                "Ready check failed.".to_owned()
+            })
+            .components(vec![]),
+    )
+    .await?;
+    Ok(pending.into_iter().collect())
+}
+
+/// picks two random captains
+fn pick_captains(players: &[QueuedPlayer]) -> Result<(UserId, UserId), Error> {
+    let mut rng = thread_rng();
+    let mut uids: Vec<UserId> = players.iter().map(|p| p.uid).collect();
+    uids.shuffle(&mut rng);
+    let mut iter = uids.into_iter();
+    match (iter.next(), iter.next()) {
+        (Some(red), Some(blu)) => Ok((red, blu)),
+        _ => Err("Not enough players for captains.".into()),
+    }
+}
+
+/// lets the players vote on a map for the format. ties are broken randomly
+async fn map_vote(
+    lobby: &DraftLobby,
+    server: &Server,
+    players: &[QueuedPlayer],
+    format: PugFormat,
+) -> Result<String, Error> {
+    let uuid = lobby.id;
+    let vote_id = format!("{uuid}-mapvote");
+
+    let maps: Vec<String> = server
+        .maps()
+        .await?
+        .into_iter()
+        .filter(|m| format.allows_map(m))
+        .collect::<Vec<String>>()
+        .choose_multiple(&mut thread_rng(), MAP_VOTE_CHOICES)
+        .cloned()
+        .collect();
+    if maps.is_empty() {
+        return Err(format!("No {} maps on the pug server.", format.name()).into());
+    }
+    if maps.len() == 1 {
+        return Ok(maps[0].clone());
+    }
+
+    let menu = CreateSelectMenu::new(
+        vote_id.clone(),
+        serenity::CreateSelectMenuKind::String {
+            options: maps
+                .iter()
+                .map(|m| CreateSelectMenuOption::new(m, m))
+                .collect(),
+        },
+    )
+    .placeholder("Vote for a map!");
+    let mut msg = lobby
+        .channel
+        .send_message(
+            &lobby.ctx,
+            CreateMessage::new()
+                .content(format!(
+                    "Map vote! Closes <t:{}:R>",
+                    chrono::Utc::now().timestamp() + MAP_VOTE_TIMEOUT.as_secs() as i64
+                ))
+                .components(vec![CreateActionRow::SelectMenu(menu)]),
+        )
+        .await?;
+
+    let eligible: HashSet<UserId> = players.iter().map(|p| p.uid).collect();
+    let mut votes: HashMap<UserId, String> = HashMap::new();
+    let deadline = tokio::time::Instant::now() + MAP_VOTE_TIMEOUT;
+    while votes.len() < eligible.len() {
+        let remaining = deadline.saturating_duration_since(tokio::time::Instant::now());
+        if remaining.is_zero() {
+            break;
+        }
+        let vote_id = vote_id.clone();
+        let Some(mci) = ComponentInteractionCollector::new(&lobby.ctx)
+            .message_id(msg.id)
+            .timeout(remaining)
+            .filter(move |mci| mci.data.custom_id == vote_id)
+            .await
+        else {
+            break;
+        };
+        let reply = match (&mci.data.kind, eligible.contains(&mci.user.id)) {
+            (ComponentInteractionDataKind::StringSelect { values }, true) => {
+                let map = values.first().ok_or("No map chosen")?.clone();
+                let reply = format!("Voted for `{}`.", map);
+                votes.insert(mci.user.id, map);
+                reply
+            }
+            (_, false) => "You're not in this pug!".to_owned(),
+            _ => "Invalid vote.".to_owned(),
+        };
+        mci.create_response(
+            &lobby.ctx,
+            CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new()
+                    .content(reply)
+                    .ephemeral(true),
+            ),
+        )
+        .await?;
+    }
+
+    let mut tally: HashMap<&String, usize> = maps.iter().map(|m| (m, 0)).collect();
+    for map in votes.values() {
+        *tally.entry(map).or_default() += 1;
+    }
+    let most = tally.values().copied().max().unwrap_or(0);
+    let winner = tally
+        .into_iter()
+        .filter(|(_, count)| *count == most)
+        .map(|(map, _)| map.clone())
+        .collect::<Vec<String>>()
+        .choose(&mut thread_rng())
+        .cloned()
+        .ok_or("No map won the vote")?;
+
+    msg.edit(
+        &lobby.ctx,
+        EditMessage::new()
+            .content(format!(
+                "`{}` won the map vote with {} vote(s)!",
+                winner, most
+            ))
+            .components(vec![]),
+    )
+    .await?;
+    Ok(winner)
+}
+
+/// DMs every drafted player their team and the server connect info
+async fn send_connect_dms(
+    lobby: &DraftLobby,
+    draft: &Draft,
+    map: &str,
+    addr: std::net::SocketAddr,
+    password: &str,
+) {
+    let teams = [("🔴 RED", &draft.red), ("🔵 BLU", &draft.blu)];
+    for (team, uids) in teams {
+        for uid in uids.iter() {
+            let _ = uid
+                .direct_message(
+                    &lobby.ctx,
+                    CreateMessage::new().content(format!(
+                        "You're on **{}** for the pug on `{}`!\nsteam://connect/{}/{}\nOr in console: `connect {}; password \"{}\"`",
+                        team, map, addr, password, addr, password
+                    )),
+                )
+                .await
+                .inspect_err(|e| log::error!("Could not DM pug player {}: {e}", uid));
+        }
+    }
+}
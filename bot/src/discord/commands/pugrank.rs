@@ -0,0 +1,52 @@
+use crate::discord::Context;
+use common::Error;
+use poise::{
+    self, CreateReply,
+    serenity_prelude::{Color, CreateAllowedMentions, CreateEmbed, CreateEmbedFooter},
+};
+use pugs::get_leaderboard;
+
+/// rated pugs required to show up on the leaderboard
+const MIN_GAMES: i32 = 3;
+
+/// View the pug rating leaderboard
+#[poise::command(slash_command, global_cooldown = 5)]
+pub async fn pugrank(ctx: Context<'_>) -> Result<(), Error> {
+    let ratings = get_leaderboard(&ctx.data().local_pool, MIN_GAMES, 10).await?;
+
+    let leaderboard = ratings
+        .iter()
+        .enumerate()
+        .map(|(i, r)| {
+            format!(
+                "{}. <@{}> - `{:.0}` _({})_",
+                i + 1,
+                r.uid,
+                r.rating,
+                r.record()
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("\n");
+
+    let embed = CreateEmbed::new()
+        .title("Pug rating leaderboard 🏆")
+        .description(if leaderboard.is_empty() {
+            "No rated pugs yet.".to_owned()
+        } else {
+            leaderboard
+        })
+        .footer(CreateEmbedFooter::new(format!(
+            "Players with at least {} rated pugs.",
+            MIN_GAMES
+        )))
+        .color(Color::GOLD);
+
+    ctx.send(
+        CreateReply::default()
+            .embed(embed)
+            .allowed_mentions(CreateAllowedMentions::new().empty_users().empty_roles()),
+    )
+    .await?;
+    Ok(())
+}
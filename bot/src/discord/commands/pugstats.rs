@@ -0,0 +1,111 @@
+use crate::discord::Context;
+use common::Error;
+use logstf::stats::{PugStats, get_linked_uids, get_pug_stats};
+use poise::{
+    self, ChoiceParameter, CreateReply,
+    serenity_prelude::{Color, CreateAllowedMentions, CreateEmbed, CreateEmbedFooter},
+};
+
+/// games required to show up on the leaderboard
+const MIN_GAMES: i32 = 3;
+
+#[derive(Debug, poise::ChoiceParameter)]
+pub enum PugStat {
+    #[name = "Damage per minute"]
+    Dpm,
+    #[name = "Kills per death"]
+    Kd,
+    #[name = "Win rate"]
+    Winrate,
+    #[name = "Heals"]
+    Heals,
+    #[name = "Ubers"]
+    Ubers,
+    #[name = "Games played"]
+    Games,
+}
+
+impl PugStat {
+    fn value(&self, s: &PugStats) -> f32 {
+        match self {
+            PugStat::Dpm => s.dpm(),
+            PugStat::Kd => s.kd(),
+            PugStat::Winrate => s.winrate() * 100.0,
+            PugStat::Heals => s.heals as f32,
+            PugStat::Ubers => s.ubers as f32,
+            PugStat::Games => s.games as f32,
+        }
+    }
+
+    fn format(&self, v: f32) -> String {
+        match self {
+            PugStat::Kd => format!("{:.2}", v),
+            PugStat::Winrate => format!("{:.1}%", v),
+            _ => format!("{:.0}", v),
+        }
+    }
+}
+
+/// View the pug leaderboard, from posted logs.tf logs
+#[poise::command(slash_command, global_cooldown = 5)]
+pub async fn pugstats(
+    ctx: Context<'_>,
+    #[description = "The stat to rank by (default: DPM)"] stat: Option<PugStat>,
+) -> Result<(), Error> {
+    ctx.defer().await?;
+    let stat = stat.unwrap_or(PugStat::Dpm);
+
+    let mut stats = get_pug_stats(&ctx.data().local_pool, MIN_GAMES).await?;
+    stats.sort_by(|a, b| {
+        stat.value(b)
+            .partial_cmp(&stat.value(a))
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    stats.truncate(10);
+
+    let linked = get_linked_uids(
+        &ctx.data().local_pool,
+        stats.iter().map(|s| s.steamid.clone()).collect(),
+    )
+    .await?;
+
+    let leaderboard = stats
+        .iter()
+        .enumerate()
+        .map(|(i, s)| {
+            format!(
+                "{}. `{}`{} - `{}` _({} games)_",
+                i + 1,
+                s.name,
+                linked
+                    .get(&s.steamid)
+                    .map(|uid| format!(" <@{}>", uid))
+                    .unwrap_or_default(),
+                stat.format(stat.value(s)),
+                s.games
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("\n");
+
+    let embed = CreateEmbed::new()
+        .title(format!("Pug leaderboard: {}", stat.name()))
+        .description(if leaderboard.is_empty() {
+            "No pugs recorded yet.".to_owned()
+        } else {
+            leaderboard
+        })
+        .footer(CreateEmbedFooter::new(format!(
+            "Players with at least {} pugs.",
+            MIN_GAMES
+        )))
+        .color(Color::DARK_GREEN);
+
+    ctx.send(
+        CreateReply::default()
+            .embed(embed)
+            .allowed_mentions(CreateAllowedMentions::new().empty_users().empty_roles()),
+    )
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,151 @@
+use poise::serenity_prelude::{
+    AutocompleteChoice, Color, CreateAllowedMentions, CreateAttachment, CreateEmbed,
+};
+use poise::{self, CreateReply};
+
+use crate::discord::Context;
+use common::{Error, util::strip_markdown};
+use rivalries::{Side, get_rivalries, get_rivalry, get_top_rivalries, graph::draw_rivalry_graph};
+
+/// rivals listed per category by /rivals
+const RIVALS_COUNT: usize = 5;
+/// rivalries drawn in /rivalgraph
+const GRAPH_RIVALRIES: i64 = 12;
+
+/// players seen in the server logs
+async fn rivals_autocomplete(ctx: Context<'_>, partial: &str) -> Vec<AutocompleteChoice> {
+    rivalries::search_players(&ctx.data().local_pool, partial)
+        .await
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(steamid, name)| AutocompleteChoice::new(format!("{} {}", name, steamid), steamid))
+        .collect()
+}
+
+/// the steamid to look up, defaulting to the author's linked account
+async fn player_or_self(ctx: Context<'_>, player: Option<String>) -> Result<String, Error> {
+    if let Some(player) = player {
+        return Ok(player);
+    }
+    ::profile::get_user_profile(&ctx.data().local_pool, ctx.author().id)
+        .await?
+        .steamid
+        .ok_or("Pick a player, or link your steam with /link steam to see your own rivals.".into())
+}
+
+fn side_line(s: &Side) -> String {
+    format!(
+        "**{}** `{}` kills / `{}` deaths, `{}` - `{}` dominations",
+        strip_markdown(&s.rival_name),
+        s.kills,
+        s.deaths,
+        s.dominations,
+        s.dominated
+    )
+}
+
+/// View a player's nemeses and favorite victims
+#[poise::command(slash_command, global_cooldown = 5)]
+pub async fn rivals(
+    ctx: Context<'_>,
+    #[description = "The player (default: your linked steam)"]
+    #[autocomplete = "rivals_autocomplete"]
+    player: Option<String>,
+) -> Result<(), Error> {
+    let steamid = player_or_self(ctx, player).await?;
+    let sides: Vec<Side> = get_rivalries(&ctx.data().local_pool, &steamid)
+        .await?
+        .iter()
+        .map(|r| r.side(&steamid))
+        .collect();
+    let Some(name) = sides.first().map(|s| s.name.clone()) else {
+        ctx.say("No rivals recorded for that player.").await?;
+        return Ok(());
+    };
+
+    let top = |key: fn(&Side) -> (i32, i32)| {
+        let mut sides: Vec<&Side> = sides.iter().filter(|s| key(*s) > (0, 0)).collect();
+        sides.sort_by_key(|s| std::cmp::Reverse(key(*s)));
+        let list = sides
+            .into_iter()
+            .take(RIVALS_COUNT)
+            .map(side_line)
+            .collect::<Vec<String>>()
+            .join("\n");
+        if list.is_empty() {
+            "`None`".to_owned()
+        } else {
+            list
+        }
+    };
+
+    let embed = CreateEmbed::new()
+        .title(format!("⚔️ Rivals of {}", name))
+        .field("😈 Nemeses", top(|s| (s.dominated, s.deaths)), false)
+        .field("🎯 Victims", top(|s| (s.dominations, s.kills)), false)
+        .color(Color::DARK_RED);
+    ctx.send(CreateReply::default().embed(embed)).await?;
+    Ok(())
+}
+
+/// View the head-to-head record between two players
+#[poise::command(slash_command, global_cooldown = 5)]
+pub async fn rivalry(
+    ctx: Context<'_>,
+    #[description = "The first player"]
+    #[autocomplete = "rivals_autocomplete"]
+    a: String,
+    #[description = "The second player"]
+    #[autocomplete = "rivals_autocomplete"]
+    b: String,
+) -> Result<(), Error> {
+    let Some(rivalry) = get_rivalry(&ctx.data().local_pool, &a, &b).await? else {
+        ctx.say("Those players have no history.").await?;
+        return Ok(());
+    };
+    let (a, b) = (rivalry.side(&a), rivalry.side(&b));
+    let leader = match &rivalry.leader {
+        Some(leader) if *leader == a.steamid => {
+            format!("**{}** has the upper hand.", strip_markdown(&a.name))
+        }
+        Some(_) => format!("**{}** has the upper hand.", strip_markdown(&b.name)),
+        None => "Nobody has the upper hand... yet.".to_owned(),
+    };
+    let column = |s: &Side| {
+        format!(
+            "🔫 Kills: `{}`\n⚔️ Dominations: `{}`\n🔁 Revenges: `{}`",
+            s.kills, s.dominations, s.revenges
+        )
+    };
+    let embed = CreateEmbed::new()
+        .title(format!(
+            "{} vs {}",
+            strip_markdown(&a.name),
+            strip_markdown(&b.name)
+        ))
+        .description(leader)
+        .field(a.name.clone(), column(&a), true)
+        .field(b.name.clone(), column(&b), true)
+        .color(Color::ORANGE);
+    ctx.send(CreateReply::default().embed(embed)).await?;
+    Ok(())
+}
+
+/// View a graph of the server's top rivalries
+#[poise::command(slash_command, global_cooldown = 30)]
+pub async fn rivalgraph(ctx: Context<'_>) -> Result<(), Error> {
+    ctx.defer().await?;
+    let rivalries = get_top_rivalries(&ctx.data().local_pool, GRAPH_RIVALRIES).await?;
+    if rivalries.is_empty() {
+        ctx.say("No rivalries recorded yet.").await?;
+        return Ok(());
+    }
+    let png = tokio::task::spawn_blocking(move || draw_rivalry_graph(&rivalries)).await??;
+    ctx.send(
+        CreateReply::default()
+            .attachment(CreateAttachment::bytes(png, "rivalries.png"))
+            .allowed_mentions(CreateAllowedMentions::new().empty_users()),
+    )
+    .await?;
+    Ok(())
+}
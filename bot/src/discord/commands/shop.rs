@@ -0,0 +1,441 @@
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use poise::serenity_prelude::{
+    self as serenity, AutocompleteChoice, Color, CreateEmbed, EditRole, GuildId, Mentionable,
+};
+use poise::{self, CreateReply};
+use sqlx::{MySql, Pool};
+use tokio_cron_scheduler::{Job, JobBuilder};
+
+use super::util::servers_autocomplete;
+use crate::discord::{Context, PoiseData};
+use ::catcoin::shop::{self, ItemKind, Purchase, ShopItem};
+use common::Error;
+use emoji::emoji;
+use profile::get_user_profile;
+use tf2::{Server, logs::RelayTags};
+
+/// sourcemod admins file reserved slots are granted in
+const ADMINS_FILE: &str = "tf/addons/sourcemod/configs/admins_simple.ini";
+/// longest relay tag that can be bought
+const MAX_TAG_LEN: usize = 12;
+
+/// Spend catcoin on perks
+#[poise::command(slash_command, subcommands("list", "buy"), subcommand_required)]
+pub async fn shop(_: Context<'_>) -> Result<(), Error> {
+    Ok(()) // never run
+}
+
+/// See what's for sale
+#[poise::command(slash_command, user_cooldown = 5)]
+async fn list(ctx: Context<'_>) -> Result<(), Error> {
+    let items = shop::get_items(&ctx.data().local_pool, false).await?;
+    let description = if items.is_empty() {
+        "The shop is empty right now.".to_owned()
+    } else {
+        items
+            .iter()
+            .map(ShopItem::line)
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    };
+    let embed = CreateEmbed::new()
+        .title(format!("{} Catcoin shop", emoji("catcoin")))
+        .description(description)
+        .color(Color::GOLD);
+    ctx.send(CreateReply::default().embed(embed)).await?;
+    Ok(())
+}
+
+async fn items_autocomplete(ctx: Context<'_>, partial: &str) -> Vec<AutocompleteChoice> {
+    shop::get_items(&ctx.data().local_pool, false)
+        .await
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|item| item.name.to_lowercase().contains(&partial.to_lowercase()))
+        .map(|item| AutocompleteChoice::new(format!("{} ({})", item.name, item.price), item.id))
+        .collect()
+}
+
+/// Buy a perk from the shop
+#[poise::command(slash_command, user_cooldown = 10)]
+async fn buy(
+    ctx: Context<'_>,
+    #[description = "What to buy"]
+    #[autocomplete = "items_autocomplete"]
+    item: u32,
+    #[description = "Color, tag, image url or map, if needed"] input: Option<String>,
+    #[description = "The server to nominate a map on"]
+    #[autocomplete = "servers_autocomplete"]
+    server: Option<String>,
+) -> Result<(), Error> {
+    let pool = &ctx.data().local_pool;
+    let Some(item) = shop::get_item(pool, item).await?.filter(|i| i.enabled) else {
+        ctx.send(
+            CreateReply::default()
+                .content("That item isn't for sale.")
+                .ephemeral(true),
+        )
+        .await?;
+        return Ok(());
+    };
+
+    let input = input.map(|i| i.trim().to_owned());
+    if let Err(e) = check_input(ctx, &item, input.as_deref(), server.as_deref()).await {
+        ctx.send(CreateReply::default().content(e).ephemeral(true))
+            .await?;
+        return Ok(());
+    }
+
+    if let Some(active) = shop::get_active(pool, ctx.author().id, item.kind).await? {
+        let until = active
+            .expires_at
+            .map(|t| format!(" until <t:{}:f>", t.timestamp()))
+            .unwrap_or_default();
+        ctx.send(
+            CreateReply::default()
+                .content(format!("You already have a **{}**{}.", item.name, until))
+                .ephemeral(true),
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let steamid = if item.kind.needs_steam() {
+        let Some(steamid) = get_user_profile(pool, ctx.author().id).await?.steamid else {
+            ctx.send(
+                CreateReply::default()
+                    .content("You need to `/link steam` your account first.")
+                    .ephemeral(true),
+            )
+            .await?;
+            return Ok(());
+        };
+        Some(steamid)
+    } else {
+        None
+    };
+
+    ctx.defer_ephemeral().await?;
+    let Some(purchase) = shop::buy(pool, ctx.author().id, &item, input, steamid).await? else {
+        ctx.send(CreateReply::default().ephemeral(true).content(format!(
+            "You do not have **{}** {}",
+            item.price,
+            emoji("catcoin")
+        )))
+        .await?;
+        return Ok(());
+    };
+
+    let result = match give_perk(ctx, &purchase, server.as_deref()).await {
+        Ok(result) => result,
+        Err(e) => {
+            log::error!("Could not give shop perk {}: {e}", purchase.id);
+            shop::refund(pool, &purchase).await?;
+            ctx.send(CreateReply::default().ephemeral(true).content(format!(
+                "Could not give you **{}**, refunded **{}** {}: `{e}`",
+                item.name,
+                item.price,
+                emoji("catcoin")
+            )))
+            .await?;
+            return Ok(());
+        }
+    };
+
+    let until = purchase
+        .expires_at
+        .map(|t| format!("\nExpires <t:{}:R>.", t.timestamp()))
+        .unwrap_or_default();
+    let embed = CreateEmbed::new()
+        .title(format!("Bought {}", item.name))
+        .description(format!(
+            "{} paid **{}** {}.\n{}{}",
+            ctx.author().mention(),
+            item.price,
+            emoji("catcoin"),
+            result,
+            until
+        ))
+        .color(Color::DARK_GREEN);
+    ctx.send(CreateReply::default().ephemeral(true).embed(embed))
+        .await?;
+    Ok(())
+}
+
+/// parses a hex color like `#ff66cc`
+fn parse_color(s: &str) -> Option<u32> {
+    let hex = s.strip_prefix('#').unwrap_or(s);
+    if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    u32::from_str_radix(hex, 16).ok()
+}
+
+fn is_valid_tag(tag: &str) -> bool {
+    !tag.is_empty()
+        && tag.chars().count() <= MAX_TAG_LEN
+        && tag
+            .chars()
+            .all(|c| c.is_alphanumeric() || " -_!?".contains(c))
+}
+
+/// makes sure the buyer gave what the item needs before taking their catcoin
+async fn check_input(
+    ctx: Context<'_>,
+    item: &ShopItem,
+    input: Option<&str>,
+    server: Option<&str>,
+) -> Result<(), String> {
+    let input = match (item.kind.input_hint(), input) {
+        (Some(hint), None) => return Err(format!("**{}** needs {}.", item.name, hint)),
+        (_, input) => input.unwrap_or_default(),
+    };
+    match item.kind {
+        ItemKind::ColorRole if parse_color(input).is_none() => {
+            Err(format!("`{input}` is not a hex color, e.g. `#ff66cc`."))
+        }
+        ItemKind::ProfileBackground if !profile::card::is_background_url(input) => Err(
+            "The background has to be an image uploaded to discord (a `cdn.discordapp.com` link)."
+                .to_owned(),
+        ),
+        ItemKind::RelayTag if !is_valid_tag(input) => Err(format!(
+            "Tags can be up to {MAX_TAG_LEN} letters, numbers, spaces and `-_!?`."
+        )),
+        ItemKind::MapNomination => {
+            let Some(server) = server else {
+                return Err("Pick the server to nominate the map on.".to_owned());
+            };
+            let server = ctx.data().server(server).map_err(|e| e.to_string())?;
+            let maps = server.maps().await.map_err(|e| e.to_string())?;
+            if !maps.iter().any(|m| m == input) {
+                return Err(format!("`{input}` isn't in {}'s map cycle.", server.name));
+            }
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// the highest position a role the bot creates can have
+fn top_role_position(ctx: Context<'_>) -> Option<u16> {
+    let guild = ctx.guild()?;
+    let me = guild.members.get(&ctx.cache().current_user().id)?;
+    me.roles
+        .iter()
+        .filter_map(|r| guild.roles.get(r))
+        .map(|r| r.position)
+        .max()
+}
+
+/// applies a purchase, returning a line describing what happened
+async fn give_perk(
+    ctx: Context<'_>,
+    purchase: &Purchase,
+    server: Option<&str>,
+) -> Result<String, Error> {
+    let data = ctx.data();
+    let input = purchase.input.clone().unwrap_or_default();
+    match purchase.kind {
+        ItemKind::ColorRole => {
+            let color = parse_color(&input).ok_or("Invalid color")?;
+            let mut role = EditRole::new()
+                .name(format!("{} ✨", ctx.author().name))
+                .colour(color);
+            // just below the bot's own role, so the color shows over the buyer's other roles
+            if let Some(position) = top_role_position(ctx) {
+                role = role.position(position.saturating_sub(1).max(1));
+            }
+            let role = data.guild_id.create_role(ctx, role).await?;
+            let given: Result<(), Error> = async {
+                data.guild_id
+                    .member(ctx, purchase.uid)
+                    .await?
+                    .add_role(ctx, role.id)
+                    .await?;
+                shop::set_role(&data.local_pool, purchase.id, role.id).await?;
+                Ok(())
+            }
+            .await;
+            // the purchase is refunded, so don't leave the role behind
+            if let Err(e) = given {
+                let _ = data.guild_id.delete_role(ctx, role.id).await;
+                return Err(e);
+            }
+            Ok(format!("Enjoy your new color, {}!", role.mention()))
+        }
+        ItemKind::ProfileBackground => {
+            Ok("Your background will show on your next `/profile card: True`.".to_owned())
+        }
+        ItemKind::RelayTag => {
+            refresh_relay_tags(&data.local_pool, &data.relay_tags).await?;
+            Ok(format!("Your chat in the relay is now tagged `[{input}]`."))
+        }
+        ItemKind::MapNomination => {
+            let server = data.server(server.ok_or("No server")?)?;
+            let output = server
+                .controller
+                .write()
+                .await
+                .run(&format!("sm_nominate_addmap {input}"))
+                .await?;
+            Ok(format!(
+                "Nominated `{input}` on {}. {}",
+                server.emoji,
+                output.trim()
+            ))
+        }
+        ItemKind::ReservedSlot => {
+            let steamid = purchase
+                .steamid
+                .as_deref()
+                .ok_or("No linked steam account")?;
+            for server in data.servers.values() {
+                if let Err(e) = grant_reserved_slot(server, steamid).await {
+                    // the purchase is refunded, so take back the slots already given
+                    for server in data.servers.values() {
+                        let _ = revoke_reserved_slot(server, steamid)
+                            .await
+                            .inspect_err(|e| log::error!("Could not revoke reserved slot: {e}"));
+                    }
+                    return Err(e);
+                }
+            }
+            Ok(format!("`{steamid}` has a reserved slot on every server."))
+        }
+    }
+}
+
+/// the line in the admins file giving the steam account a reserved slot.
+/// kept separate from any admin line the account already has
+fn reserved_slot_line(steamid: &str) -> String {
+    format!("\"{steamid}\" \"a\" // catcoin shop")
+}
+
+async fn grant_reserved_slot(server: &Server, steamid: &str) -> Result<(), Error> {
+    let line = reserved_slot_line(steamid);
+    server
+        .files
+        .add_or_edit_line(ADMINS_FILE, &line, &line)
+        .await?;
+    server
+        .controller
+        .write()
+        .await
+        .run("sm_reloadadmins")
+        .await?;
+    Ok(())
+}
+
+async fn revoke_reserved_slot(server: &Server, steamid: &str) -> Result<(), Error> {
+    if server
+        .files
+        .remove_lines(ADMINS_FILE, &reserved_slot_line(steamid))
+        .await?
+    {
+        server
+            .controller
+            .write()
+            .await
+            .run("sm_reloadadmins")
+            .await?;
+    }
+    Ok(())
+}
+
+/// reloads the relay tags of every active relay tag perk
+pub async fn refresh_relay_tags(pool: &Pool<MySql>, tags: &RelayTags) -> Result<(), Error> {
+    let active: HashMap<String, String> = shop::get_all_active(pool, ItemKind::RelayTag)
+        .await?
+        .into_iter()
+        .filter_map(|p| Some((p.steamid?, p.input?)))
+        .collect();
+    *tags.write().await = active;
+    Ok(())
+}
+
+/// takes away the perk of an expired purchase
+async fn take_perk(
+    http: &serenity::Http,
+    guild: GuildId,
+    servers: &HashMap<SocketAddr, Server>,
+    purchase: &Purchase,
+) -> Result<(), Error> {
+    match purchase.kind {
+        ItemKind::ColorRole => {
+            if let Some(role_id) = purchase.role_id {
+                if let Err(e) = guild.delete_role(http, role_id).await {
+                    // already deleted by hand
+                    log::info!("Could not delete color role {role_id}: {e}");
+                }
+            }
+        }
+        ItemKind::ReservedSlot => {
+            if let Some(steamid) = &purchase.steamid {
+                for server in servers.values() {
+                    revoke_reserved_slot(server, steamid).await?;
+                }
+            }
+        }
+        // the relay tags are refreshed after every run
+        ItemKind::RelayTag | ItemKind::ProfileBackground | ItemKind::MapNomination => (),
+    }
+    Ok(())
+}
+
+/// removes expired shop perks every 10 minutes
+pub fn shop_expiry_job(
+    http: Arc<serenity::Http>,
+    guild: GuildId,
+    servers: HashMap<SocketAddr, Server>,
+    pool: Pool<MySql>,
+    tags: RelayTags,
+) -> Job {
+    JobBuilder::new()
+        .with_timezone(chrono_tz::US::Eastern)
+        .with_cron_job_type()
+        .with_schedule("0 */10 * * * *")
+        .unwrap()
+        .with_run_async(Box::new(move |_uuid, _l| {
+            let http = http.clone();
+            let servers = servers.clone();
+            let pool = pool.clone();
+            let tags = tags.clone();
+            Box::pin(async move {
+                let expired = match shop::get_expired(&pool).await {
+                    Ok(expired) => expired,
+                    Err(e) => {
+                        log::error!("Could not fetch expired shop perks: {e}");
+                        return;
+                    }
+                };
+                for purchase in expired {
+                    match take_perk(&http, guild, &servers, &purchase).await {
+                        Ok(()) => {
+                            let _ = shop::mark_expired(&pool, purchase.id)
+                                .await
+                                .inspect_err(|e| log::error!("Could not expire perk: {e}"));
+                        }
+                        // retried on the next run
+                        Err(e) => log::error!("Could not take shop perk {}: {e}", purchase.id),
+                    }
+                }
+                let _ = refresh_relay_tags(&pool, &tags)
+                    .await
+                    .inspect_err(|e| log::error!("Could not refresh relay tags: {e}"));
+            })
+        }))
+        .build()
+        .unwrap()
+}
+
+/// the active profile background of a user, if they bought one
+pub async fn profile_background(data: &PoiseData, uid: serenity::UserId) -> Option<String> {
+    shop::get_active(&data.local_pool, uid, ItemKind::ProfileBackground)
+        .await
+        .ok()
+        .flatten()
+        .and_then(|p| p.input)
+}
@@ -2,17 +2,17 @@ use rand::prelude::*;
 use std::{str::FromStr, time::Duration};
 
 use poise::{
-    self,
+    self, CreateReply,
     serenity_prelude::{
-        self as serenity, ChannelType, ComponentInteraction, ComponentInteractionCollector,
-        ComponentInteractionDataKind, CreateActionRow, CreateEmbed, CreateInteractionResponse,
-        CreateInteractionResponseMessage, CreateMessage, CreateSelectMenu, CreateSelectMenuOption,
-        Member, Mentionable, UserId,
+        self as serenity, ChannelId, ChannelType, ComponentInteraction,
+        ComponentInteractionCollector, ComponentInteractionDataKind, CreateActionRow, CreateEmbed,
+        CreateInteractionResponse, CreateInteractionResponseMessage, CreateMessage,
+        CreateSelectMenu, CreateSelectMenuOption, Member, Mentionable, UserId,
     },
-    CreateReply,
 };
+use sqlx::{MySql, Pool};
 
-use crate::{discord::Context, Error};
+use crate::{Error, discord::Context};
 
 struct PromptResponse<T> {
     pub mci: ComponentInteraction,
@@ -29,12 +29,12 @@ impl PromptResponse<String> {
 }
 
 async fn prompt(
-    ctx: &Context<'_>,
+    lobby: &DraftLobby,
     msg: &str,
     user: UserId,
     options: Vec<CreateSelectMenuOption>,
 ) -> Result<PromptResponse<String>, Error> {
-    let uuid = ctx.id();
+    let uuid = lobby.id;
 
     // send msg
     let menu = CreateSelectMenu::new(
@@ -42,20 +42,24 @@ async fn prompt(
         serenity::CreateSelectMenuKind::String { options },
     );
     let row = vec![CreateActionRow::SelectMenu(menu)];
-    ctx.channel_id()
-        .send_message(ctx, CreateMessage::new().components(row).content(msg))
+    lobby
+        .channel
+        .send_message(
+            &lobby.ctx,
+            CreateMessage::new().components(row).content(msg),
+        )
         .await?;
 
     // listen for responses
-    while let Some(mci) = ComponentInteractionCollector::new(ctx)
-        .channel_id(ctx.channel_id())
+    while let Some(mci) = ComponentInteractionCollector::new(&lobby.ctx)
+        .channel_id(lobby.channel)
         .timeout(Duration::from_secs(60))
         .filter(move |mci| mci.data.custom_id.starts_with(&uuid.to_string()))
         .await
     {
         if mci.user.id != user {
             mci.create_response(
-                ctx,
+                &lobby.ctx,
                 CreateInteractionResponse::Message(
                     CreateInteractionResponseMessage::new()
                         .content("Not you!! >_<")
@@ -76,26 +80,259 @@ async fn prompt(
     return Err("/teamcaptain response missing, aborted.".into());
 }
 
+/// Where a draft takes place. owns everything it needs so a draft can outlive the command
+/// that started it
+#[derive(Clone)]
+pub struct DraftLobby {
+    pub ctx: serenity::Context,
+    pub channel: ChannelId,
+    /// unique per draft, prefixes the ids of its menus
+    pub id: u64,
+    pub pool: Pool<MySql>,
+}
+
+impl DraftLobby {
+    pub fn new(ctx: &Context<'_>) -> Self {
+        Self {
+            ctx: ctx.serenity_context().clone(),
+            channel: ctx.channel_id(),
+            id: ctx.id(),
+            pool: ctx.data().local_pool.clone(),
+        }
+    }
+}
+
+/// A player that can be picked in a draft
+pub struct DraftPlayer {
+    pub uid: UserId,
+    pub name: String,
+    /// shown under the name in the pick menu
+    pub note: Option<String>,
+}
+
+impl From<&Member> for DraftPlayer {
+    fn from(m: &Member) -> Self {
+        Self {
+            uid: m.user.id,
+            name: m.display_name().to_owned(),
+            note: None,
+        }
+    }
+}
+
+/// The result of a completed draft, stored for rating once the pug is over
+#[derive(Clone, Debug)]
+pub struct Draft {
+    pub id: i32,
+    /// `None` when the teams were balanced by rating
+    pub red_captain: Option<UserId>,
+    pub blu_captain: Option<UserId>,
+    /// every pick in order, excluding the captains
+    pub picks: Vec<UserId>,
+    pub red: Vec<UserId>,
+    pub blu: Vec<UserId>,
+}
+
+impl Draft {
+    pub fn to_embed(&self) -> CreateEmbed {
+        let list = |team: &Vec<UserId>| {
+            team.iter()
+                .map(|uid| uid.mention().to_string())
+                .collect::<Vec<String>>()
+                .join("\n")
+        };
+        CreateEmbed::new()
+            .title(format!("Pug #{}", self.id))
+            .field("🔴 RED", list(&self.red), true)
+            .field("🔵 BLU", list(&self.blu), true)
+    }
+}
+
+async fn update_after_choice(
+    lobby: &DraftLobby,
+    mci: &ComponentInteraction,
+    msg: String,
+) -> Result<(), serenity::prelude::SerenityError> {
+    mci.create_response(
+        &lobby.ctx,
+        CreateInteractionResponse::UpdateMessage(
+            CreateInteractionResponseMessage::new()
+                .content(msg)
+                .components(vec![]),
+        ),
+    )
+    .await
+}
+
+fn options(players: &Vec<DraftPlayer>) -> Vec<CreateSelectMenuOption> {
+    players
+        .iter()
+        .map(|p| {
+            let mut option = CreateSelectMenuOption::new(&p.name, p.uid.to_string());
+            if let Some(note) = &p.note {
+                option = option.description(note);
+            }
+            option
+        })
+        .collect()
+}
+
+/// has the captains alternate picking from the players, then posts the teams
+pub async fn run_draft(
+    lobby: &DraftLobby,
+    red_captain: UserId,
+    blu_captain: UserId,
+    mut players: Vec<DraftPlayer>,
+) -> Result<Draft, Error> {
+    players.retain(|p| p.uid != red_captain && p.uid != blu_captain);
+
+    let mut red: Vec<UserId> = vec![red_captain];
+    let mut blu: Vec<UserId> = vec![blu_captain];
+    let mut picks: Vec<UserId> = vec![];
+    let mut pick_red: bool = random();
+
+    loop {
+        if players.len() == 0 {
+            break;
+        }
+        let captain = if pick_red { red_captain } else { blu_captain };
+        let color = if pick_red { "🔴 RED" } else { "🔵 BLU" };
+        let team: &mut Vec<UserId> = if pick_red { &mut red } else { &mut blu };
+        if players.len() == 1 {
+            let pick = players.first().unwrap().uid;
+            lobby
+                .channel
+                .send_message(
+                    &lobby.ctx,
+                    CreateMessage::new()
+                        .content(format!("<@{}> ({}) chose: <@{}>", captain, color, pick)),
+                )
+                .await?;
+            team.push(pick);
+            picks.push(pick);
+            players.clear();
+        } else {
+            let pick: PromptResponse<UserId> = prompt(
+                lobby,
+                &format!("<@{}> Your pick! :3", captain),
+                captain,
+                options(&players),
+            )
+            .await?
+            .parse()?;
+            update_after_choice(
+                lobby,
+                &pick.mci,
+                format!("<@{}> ({}) chose: <@{}>", captain, color, pick.data),
+            )
+            .await?;
+            players.retain(|p| p.uid != pick.data);
+            team.push(pick.data);
+            picks.push(pick.data);
+        };
+        pick_red = !pick_red;
+    }
+
+    let id = pugs::save_draft(
+        &lobby.pool,
+        Some((red_captain, blu_captain)),
+        &picks,
+        &red,
+        &blu,
+    )
+    .await?;
+    let draft = Draft {
+        id,
+        red_captain: Some(red_captain),
+        blu_captain: Some(blu_captain),
+        picks,
+        red,
+        blu,
+    };
+
+    lobby
+        .channel
+        .send_message(
+            &lobby.ctx,
+            CreateMessage::default()
+                .content("Teams have been selected!")
+                .embed(draft.to_embed()),
+        )
+        .await?;
+
+    Ok(draft)
+}
+
+/// splits the players into two teams of near equal pug rating
+pub async fn run_balanced(lobby: &DraftLobby, players: Vec<DraftPlayer>) -> Result<Draft, Error> {
+    let uids: Vec<UserId> = players.iter().map(|p| p.uid).collect();
+    let ratings = pugs::get_ratings(&lobby.pool, &uids).await?;
+    let rated: Vec<(UserId, f64)> = uids.iter().map(|uid| (*uid, ratings[uid])).collect();
+    let (red, blu) = pugs::rating::balance(&rated);
+    let average = |team: &Vec<(UserId, f64)>| {
+        pugs::rating::team_rating(&team.iter().map(|p| p.1).collect::<Vec<f64>>())
+    };
+    let (red_rating, blu_rating) = (average(&red), average(&blu));
+    let red: Vec<UserId> = red.into_iter().map(|p| p.0).collect();
+    let blu: Vec<UserId> = blu.into_iter().map(|p| p.0).collect();
+
+    let id = pugs::save_draft(&lobby.pool, None, &[], &red, &blu).await?;
+    let draft = Draft {
+        id,
+        red_captain: None,
+        blu_captain: None,
+        picks: vec![],
+        red,
+        blu,
+    };
+
+    lobby
+        .channel
+        .send_message(
+            &lobby.ctx,
+            CreateMessage::default()
+                .content("Teams have been balanced!")
+                .embed(draft.to_embed().description(format!(
+                    "Average rating: 🔴 `{:.0}` - `{:.0}` 🔵",
+                    red_rating, blu_rating
+                ))),
+        )
+        .await?;
+
+    Ok(draft)
+}
+
 /// Set up pug team captains and display prompts for them to pick users
 #[poise::command(slash_command)]
 pub async fn teamcaptain(
     ctx: Context<'_>,
     #[description = "Pug lobby voice channel with all the players"] channel: serenity::GuildChannel,
+    #[description = "Split the teams by pug rating instead of picking captains"] balanced: Option<
+        bool,
+    >,
 ) -> Result<(), Error> {
     if !matches!(channel.kind, ChannelType::Voice) {
         ctx.reply(format!("Channel {} is not a voice channel!", channel.name))
             .await?;
         return Ok(());
     }
-    let mut members = channel.members(ctx)?;
-    if members.len() < 2 {
+    let mut players: Vec<DraftPlayer> = channel.members(ctx)?.iter().map(Into::into).collect();
+    if players.len() < 2 {
         ctx.send(
             CreateReply::default().content("Must be at least 2 people in the VC to be captains!"),
         )
         .await?;
         return Ok(());
     }
+    if balanced.unwrap_or(false) {
+        ctx.defer().await?;
+        run_balanced(&DraftLobby::new(&ctx), players).await?;
+        ctx.send(CreateReply::default().content(":white_check_mark:"))
+            .await?;
+        return Ok(());
+    }
     let invoker = ctx.author();
+    let lobby = DraftLobby::new(&ctx);
 
     ctx.send(
         CreateReply::default()
@@ -104,137 +341,38 @@ pub async fn teamcaptain(
     )
     .await?;
 
-    async fn update_after_choice(
-        ctx: &Context<'_>,
-        mci: &ComponentInteraction,
-        msg: String,
-    ) -> Result<(), serenity::prelude::SerenityError> {
-        mci.create_response(
-            ctx,
-            CreateInteractionResponse::UpdateMessage(
-                CreateInteractionResponseMessage::new()
-                    .content(msg)
-                    .components(vec![]),
-            ),
-        )
-        .await
-    }
-
     // prompt for red captain
-    let options = |members: &Vec<Member>| -> Vec<CreateSelectMenuOption> {
-        members
-            .iter()
-            .map(|m| CreateSelectMenuOption::new(m.display_name(), m.user.id.to_string()))
-            .collect()
-    };
     let red_captain: PromptResponse<UserId> = prompt(
-        &ctx,
+        &lobby,
         &format!("<@{}> Pick the RED team captain.", invoker.id),
         invoker.id,
-        options(&members),
+        options(&players),
     )
     .await?
     .parse()?;
     update_after_choice(
-        &ctx,
+        &lobby,
         &red_captain.mci,
-        format!("🔴 RED team captain: <@{}>", red_captain.data.clone()),
+        format!("🔴 RED team captain: <@{}>", red_captain.data),
     )
     .await?;
-    members.retain(|m| m.user.id != red_captain.data);
+    players.retain(|p| p.uid != red_captain.data);
     let blu_captain: PromptResponse<UserId> = prompt(
-        &ctx,
+        &lobby,
         &format!("<@{}> Pick the BLU team captain.", invoker.id),
         invoker.id,
-        options(&members),
+        options(&players),
     )
     .await?
     .parse()?;
     update_after_choice(
-        &ctx,
+        &lobby,
         &blu_captain.mci,
-        format!("🔵 BLU team captain: <@{}>", blu_captain.data.clone()),
+        format!("🔵 BLU team captain: <@{}>", blu_captain.data),
     )
     .await?;
-    members.retain(|m| m.user.id != blu_captain.data);
-
-    let mut red: Vec<UserId> = vec![red_captain.data.clone()];
-    let mut blu: Vec<UserId> = vec![blu_captain.data.clone()];
-    let mut pick_red: bool = random();
-
-    loop {
-        if members.len() == 0 {
-            break;
-        }
-        let captain = if pick_red {
-            red_captain.data
-        } else {
-            blu_captain.data
-        };
-        let color = if pick_red { "🔴 RED" } else { "🔵 BLU" };
-        let team: &mut Vec<UserId> = if pick_red { &mut red } else { &mut blu };
-        if members.len() == 1 {
-            let pick = members.first().unwrap().user.id;
-            ctx.channel_id()
-                .send_message(
-                    ctx,
-                    CreateMessage::new().content(format!(
-                        "<@{}> ({}) chose: <@{}>",
-                        captain,
-                        color,
-                        pick.clone()
-                    )),
-                )
-                .await?;
-            team.push(pick);
-            members.clear();
-        } else {
-            let pick: PromptResponse<UserId> = prompt(
-                &ctx,
-                &format!("<@{}> Your pick! :3", captain),
-                captain,
-                options(&members),
-            )
-            .await?
-            .parse()?;
-            update_after_choice(
-                &ctx,
-                &pick.mci,
-                format!("<@{}> ({}) chose: <@{}>", captain, color, pick.data.clone()),
-            )
-            .await?;
-            members.retain(|m| m.user.id != pick.data);
-            team.push(pick.data);
-        };
-        pick_red = !pick_red;
-    }
-
-    let embed = CreateEmbed::new() //
-        .field(
-            "🔴 RED",
-            red.into_iter()
-                .map(|uid| uid.mention().to_string())
-                .collect::<Vec<String>>()
-                .join("\n"),
-            true,
-        )
-        .field(
-            "🔵 BLU",
-            blu.into_iter()
-                .map(|uid| uid.mention().to_string())
-                .collect::<Vec<String>>()
-                .join("\n"),
-            true,
-        );
 
-    ctx.channel_id()
-        .send_message(
-            &ctx,
-            CreateMessage::default()
-                .content("Teams have been selected!")
-                .embed(embed),
-        )
-        .await?;
+    run_draft(&lobby, red_captain.data, blu_captain.data, players).await?;
 
     Ok(())
 }
@@ -1,19 +1,42 @@
+use poise::CreateReply;
 use poise::serenity_prelude as serenity;
-use serenity::AutocompleteChoice;
+use serenity::{
+    AutocompleteChoice, ComponentInteractionCollector, CreateActionRow, CreateButton, CreateEmbed,
+    ReactionType,
+};
 use std::time::Duration;
 use tokio::sync::mpsc;
 
+use super::permissions::can_rcon;
 use crate::discord::Context;
 
 use common::Error;
-use tf2::{rcon_user_output, Server};
+use tf2::{Server, rcon_user_output};
 
-pub fn output_servers(ctx: Context<'_>, addr: Option<String>) -> Result<Vec<&Server>, Error> {
-    Ok(if let Some(addr) = addr {
-        vec![ctx.data().server(&addr)?]
-    } else {
-        ctx.data().servers.values().collect()
-    })
+/// the servers to send rcon to, respecting per-server rcon permissions
+pub async fn output_servers(ctx: Context<'_>, addr: Option<String>) -> Result<Vec<&Server>, Error> {
+    if let Some(addr) = addr {
+        return Ok(vec![rcon_server(ctx, &addr).await?]);
+    }
+    let mut servers = vec![];
+    for server in ctx.data().servers.values() {
+        if can_rcon(ctx, &server.name).await {
+            servers.push(server);
+        }
+    }
+    if servers.is_empty() {
+        return Err("You are not allowed to use RCON on any server.".into());
+    }
+    Ok(servers)
+}
+
+/// fetch a server by name, erroring if the author may not send rcon to it
+pub async fn rcon_server<'a>(ctx: Context<'a>, name: &str) -> Result<&'a Server, Error> {
+    let server = ctx.data().server(name)?;
+    if !can_rcon(ctx, &server.name).await {
+        return Err(format!("You are not allowed to use RCON on {}.", server.emoji).into());
+    }
+    Ok(server)
 }
 
 pub async fn rcon_and_reply(
@@ -21,8 +44,73 @@ pub async fn rcon_and_reply(
     server: Option<String>,
     cmd: String,
 ) -> Result<(), Error> {
-    ctx.say(rcon_user_output(&output_servers(ctx, server)?, cmd).await)
+    ctx.say(rcon_user_output(&output_servers(ctx, server).await?, cmd).await)
+        .await?;
+    Ok(())
+}
+
+/// Sends an embed with previous/next buttons, building each page with `page`.
+/// The buttons are removed after two minutes without a click
+pub async fn paginate<F, Fut>(
+    ctx: Context<'_>,
+    pages: usize,
+    ephemeral: bool,
+    mut page: F,
+) -> Result<(), Error>
+where
+    F: FnMut(usize) -> Fut,
+    Fut: Future<Output = Result<CreateEmbed, Error>>,
+{
+    let uuid = ctx.id();
+    let prev_id = format!("{uuid}-prev");
+    let next_id = format!("{uuid}-next");
+    let page_buttons = |current: usize| {
+        vec![CreateActionRow::Buttons(vec![
+            CreateButton::new(prev_id.clone())
+                .emoji(ReactionType::Unicode("⬅️".to_owned()))
+                .disabled(current == 0),
+            CreateButton::new(next_id.clone())
+                .emoji(ReactionType::Unicode("➡️".to_owned()))
+                .disabled(current + 1 >= pages),
+        ])]
+    };
+
+    let mut current = 0;
+    let mut embed = page(current).await?;
+    let rh = ctx
+        .send(
+            CreateReply::default()
+                .ephemeral(ephemeral)
+                .embed(embed.clone())
+                .components(page_buttons(current)),
+        )
         .await?;
+
+    while let Some(mci) = ComponentInteractionCollector::new(ctx)
+        .channel_id(ctx.channel_id())
+        .timeout(Duration::from_secs(120))
+        .filter(move |mci| mci.data.custom_id.starts_with(&uuid.to_string()))
+        .await
+    {
+        if mci.data.custom_id == prev_id {
+            current = current.saturating_sub(1);
+        } else if mci.data.custom_id == next_id {
+            current = (current + 1).min(pages.saturating_sub(1));
+        }
+        embed = page(current).await?;
+        rh.edit(
+            ctx,
+            CreateReply::default()
+                .embed(embed.clone())
+                .components(page_buttons(current)),
+        )
+        .await?;
+        mci.create_response(ctx, serenity::CreateInteractionResponse::Acknowledge)
+            .await?;
+    }
+    let _ = rh
+        .edit(ctx, CreateReply::default().embed(embed).components(vec![]))
+        .await;
     Ok(())
 }
 
@@ -102,12 +190,21 @@ pub async fn pug_maps_autocomplete(ctx: Context<'_>, partial: &str) -> Vec<Autoc
         .collect()
 }
 
+/// Returns the cfgs found on the pug server
 pub async fn pug_cfgs_autocomplete(ctx: Context<'_>, partial: &str) -> Vec<AutocompleteChoice> {
+    let partial = partial.to_lowercase();
     ctx.data()
         .pug_cfgs
+        .read()
+        .await
+        .all()
         .iter()
-        .filter(|&cfg| cfg.to_lowercase().contains(&partial.to_lowercase()))
-        .map(|cfg| AutocompleteChoice::new(cfg.clone(), cfg.clone()))
+        .filter(|cfg| {
+            cfg.name.to_lowercase().contains(&partial)
+                || cfg.label.to_lowercase().contains(&partial)
+        })
+        .take(25)
+        .map(|cfg| AutocompleteChoice::new(cfg.display(), cfg.name.clone()))
         .collect()
 }
 
@@ -2,7 +2,7 @@ use sqlx::{self, MySql, Pool};
 use std::{collections::HashMap, sync::Arc};
 use tokio::{sync::RwLock, time};
 
-use common::Error;
+use common::{Error, metrics};
 
 pub struct EmojiUsage {
     eid: String,
@@ -23,12 +23,16 @@ pub fn spawn_flush_thread(watcher: Arc<RwLock<EmojiWatcher>>, pool: Pool<MySql>)
         loop {
             interval.tick().await;
 
-            let _ = watcher
+            let start = time::Instant::now();
+            let flushed = watcher
                 .write()
                 .await
                 .flush(&pool)
                 .await
                 .inspect_err(|e| log::error!("Could not flush emoji watcher: {e}"));
+            if flushed.is_ok() {
+                metrics::flushed("emoji_flush", start.elapsed());
+            }
         }
     });
 }
@@ -3,7 +3,7 @@ use std::{
     time::{SystemTime, UNIX_EPOCH},
 };
 
-use catcoin::{get_catcoin, grant_catcoin, spend_catcoin};
+use catcoin::{get_catcoin, grant_catcoin, ledger::Reason, spend_catcoin};
 use poise::{
     Modal,
     serenity_prelude::{
@@ -176,7 +176,15 @@ impl NSFWBets {
 
         // Spend the coin
         if !bet.has_bet_already(bettor_id) {
-            if !spend_catcoin(local_pool, bettor_id, bet.wager).await? {
+            if !spend_catcoin(
+                local_pool,
+                bettor_id,
+                bet.wager,
+                Reason::Bet,
+                Some(bet.message_id.to_string()),
+            )
+            .await?
+            {
                 return Ok(BetResult::Error(
                     "You are way too broke to bet on this user.".to_string(),
                 ));
@@ -208,7 +216,14 @@ impl NSFWBets {
         // If only one person bet, silently refund them and return None (no gambling message)
         if bet.bets.len() == 1 {
             for (bettor, _) in &bet.bets {
-                grant_catcoin(local_pool, *bettor, bet.wager).await?;
+                grant_catcoin(
+                    local_pool,
+                    *bettor,
+                    bet.wager,
+                    Reason::Refund,
+                    Some(message_id.to_string()),
+                )
+                .await?;
             }
             self.disable_betting_button(ctx, channel_id, message_id)
                 .await?;
@@ -221,7 +236,14 @@ impl NSFWBets {
             let pool_coin: u64 = bet.wager * bet.bets.len() as u64;
 
             // Grant winner the coins
-            grant_catcoin(local_pool, winner_id, pool_coin).await?;
+            grant_catcoin(
+                local_pool,
+                winner_id,
+                pool_coin,
+                Reason::Bet,
+                Some(message_id.to_string()),
+            )
+            .await?;
 
             let result = NSFWWinResult {
                 user_id,
@@ -263,7 +285,14 @@ impl NSFWBets {
         // If only one person bet, silently refund them and return None (no message)
         if bet.bets.len() == 1 {
             for (bettor, _) in &bet.bets {
-                grant_catcoin(local_pool, *bettor, bet.wager).await?;
+                grant_catcoin(
+                    local_pool,
+                    *bettor,
+                    bet.wager,
+                    Reason::Refund,
+                    Some(message_id.to_string()),
+                )
+                .await?;
             }
             self.disable_betting_button(ctx, channel_id, message_id)
                 .await?;
@@ -286,7 +315,14 @@ impl NSFWBets {
 
         // Award each winner
         for winner in &winners {
-            grant_catcoin(local_pool, *winner, win_amount).await?;
+            grant_catcoin(
+                local_pool,
+                *winner,
+                win_amount,
+                Reason::Bet,
+                Some(message_id.to_string()),
+            )
+            .await?;
         }
 
         let result = HourPassedResult {
@@ -316,7 +352,14 @@ impl NSFWBets {
 
             // Refund all bettors
             for (bettor, _) in &bet.bets {
-                grant_catcoin(local_pool, *bettor, bet.wager).await?;
+                grant_catcoin(
+                    local_pool,
+                    *bettor,
+                    bet.wager,
+                    Reason::Refund,
+                    Some(message_id.to_string()),
+                )
+                .await?;
             }
 
             // Disable the button and remove pool
@@ -11,12 +11,13 @@ use profile::{
         toggle_class, update_profile_column,
     },
     get_user_profile,
+    link::{LinkAction, link_steam},
 };
 use serenity::ComponentInteraction;
 
 use common::{Error, discord::execute_modal_generic};
 
-use crate::discord::new_user;
+use crate::discord::{new_user, reports};
 
 use super::{
     PoiseData,
@@ -33,11 +34,19 @@ pub async fn dispatch(
         id if id.starts_with("stock-market.") => {
             stocks::interaction_dispatch(ctx, &data.local_pool, mci).await?
         }
+        id if id.starts_with("report.") => reports::report_button(ctx, data, mci).await?,
         "birthday.submit" => birthday_check::submit_button(ctx, data, mci).await?,
         "profile.edit.select" => match &mci.data.kind {
             ComponentInteractionDataKind::StringSelect { values } => {
                 let choice = values.first().ok_or("No choice")?;
-                dispatch_profile_edit(ctx, mci, &data.local_pool, &data.api_state, choice).await?;
+                dispatch_profile_edit(
+                    ctx,
+                    mci,
+                    &data.local_pool,
+                    &data.api_state.link_url(),
+                    choice,
+                )
+                .await?;
             }
             _ => {
                 mci.create_response(&ctx, CreateInteractionResponse::Acknowledge)
@@ -158,14 +167,24 @@ pub async fn dispatch(
                     .first()
                     .ok_or("No profile found for the returned steamid.")?;
 
-                sqlx::query!("INSERT INTO `profiles` (`uid`, `steamid`) VALUES (?, ?) ON DUPLICATE KEY UPDATE `steamid` = ?", mci.user.id.to_string(), profile.steam3, profile.steam3).execute(&data.local_pool).await?;
-
+                let content = match link_steam(
+                    &data.local_pool,
+                    mci.user.id,
+                    &profile.steam3,
+                    LinkAction::Link,
+                    mci.user.id,
+                )
+                .await
+                {
+                    Ok(()) => format!("Successfully linked to {}", profile.steamidurl),
+                    Err(e) => format!("Could not link: {e}"),
+                };
                 response
                     .create_response(
                         ctx,
                         CreateInteractionResponse::Message(
                             CreateInteractionResponseMessage::new()
-                                .content(format!("Successfully linked to {}", profile.steamidurl))
+                                .content(content)
                                 .ephemeral(true),
                         ),
                     )
@@ -0,0 +1,328 @@
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use chrono::{DateTime, TimeDelta, Utc};
+use poise::serenity_prelude::{
+    self as serenity, ButtonStyle, ChannelId, ComponentInteraction, CreateActionRow,
+    CreateAllowedMentions, CreateButton, CreateEmbed, CreateEmbedFooter, CreateInteractionResponse,
+    CreateInteractionResponseMessage, CreateMessage, Mentionable,
+};
+use sqlx::{MySql, Pool};
+use srcds_log_parser::MessageType;
+
+use common::{Error, util::strip_markdown};
+use tf2::{
+    Server, banid,
+    logs::{ChatHistory, LogReceiver},
+    rcon_user_output,
+    report::{Report, find_player, parse_report},
+};
+
+use super::{PoiseData, commands::pin_latest_demo};
+
+/// chat lines attached to each report
+const CONTEXT_LINES: usize = 15;
+/// how long a player must wait between reports
+const REPORT_COOLDOWN_SECS: i64 = 60;
+/// ban length from the report ban button, in minutes
+const REPORT_BAN_MINUTES: u32 = 1440;
+
+/// forwards in-game `!report` and `@admin` chat messages to the mod channel
+pub async fn spawn_report_thread(
+    log_receiver: LogReceiver,
+    servers: HashMap<SocketAddr, Server>,
+    history: ChatHistory,
+    pool: Pool<MySql>,
+    demo_dir: String,
+    ctx: Arc<serenity::Http>,
+    mod_channel: ChannelId,
+) {
+    let (tx, mut rx) = tokio::sync::mpsc::channel(100);
+    log_receiver
+        .subscribe(Box::new(move |from, _msg, parsed| {
+            let MessageType::ChatMessage {
+                from: user,
+                message,
+                ..
+            } = parsed
+            else {
+                return;
+            };
+            let Some(report) = parse_report(message) else {
+                return;
+            };
+            let tx = tx.clone();
+            let reporter = (user.name.clone(), user.steamid.clone());
+            tokio::spawn(async move {
+                let _ = tx.send((from, reporter, report)).await;
+            });
+        }))
+        .await;
+
+    tokio::spawn(async move {
+        let cooldown = TimeDelta::try_seconds(REPORT_COOLDOWN_SECS).unwrap();
+        let mut cooldowns: HashMap<String, DateTime<Utc>> = HashMap::new();
+        while let Some((from, (name, steamid), report)) = rx.recv().await {
+            let Some(server) = servers.get(&from) else {
+                continue;
+            };
+            let now = Utc::now();
+            if cooldowns
+                .get(&steamid)
+                .is_some_and(|last| now - *last < cooldown)
+            {
+                let _ = acknowledge(server, &steamid, "Please wait before reporting again.").await;
+                continue;
+            }
+            // forget reporters whose cooldown is over
+            cooldowns.retain(|_, last| now - *last < cooldown);
+            cooldowns.insert(steamid.clone(), now);
+
+            let _ = pin_latest_demo(&pool, server, &demo_dir, &format!("Report by {}", name))
+                .await
+                .inspect_err(|e| log::error!("Could not pin reported demo: {e}"));
+
+            let _ = post_report(
+                &ctx,
+                server,
+                &history,
+                mod_channel,
+                &name,
+                &steamid,
+                &report,
+            )
+            .await
+            .inspect_err(|e| log::error!("Could not post report: {e}"));
+        }
+    });
+}
+
+/// private message a player in-game
+async fn acknowledge(server: &Server, steamid: &str, msg: &str) -> Result<(), Error> {
+    let cmd = format!("sm_psay \"#{}\" \"{}\"", steamid, msg.replace('"', "'"));
+    server.controller.write().await.run(&cmd).await?;
+    Ok(())
+}
+
+async fn post_report(
+    ctx: &serenity::Http,
+    server: &Server,
+    history: &ChatHistory,
+    mod_channel: ChannelId,
+    reporter_name: &str,
+    reporter_steamid: &str,
+    report: &Report,
+) -> Result<(), Error> {
+    let state = server.controller.write().await.status().await?;
+    let target = report
+        .target
+        .as_ref()
+        .and_then(|name| find_player(&state.players, name));
+
+    let reported = match (&report.target, target) {
+        (_, Some(player)) => format!("**{}** `{}`", strip_markdown(&player.name), player.id),
+        (Some(name), None) => format!("Could not resolve `{}`", strip_markdown(name)),
+        (None, None) => "_Admin call_".to_owned(),
+    };
+    let chat = history
+        .last(server.addr, CONTEXT_LINES)
+        .into_iter()
+        .map(|(_, line)| line)
+        .collect::<Vec<String>>()
+        .join("\n");
+
+    let embed = CreateEmbed::new()
+        .title(format!("{} Player report", server.emoji))
+        .description(common::util::truncate(&chat, 4000))
+        .field(
+            "Reporter",
+            format!(
+                "**{}** `{}`",
+                strip_markdown(reporter_name),
+                reporter_steamid
+            ),
+            true,
+        )
+        .field("Reported", reported, true)
+        .field(
+            "Server",
+            format!("{} `{}` on `{}`", server.emoji, server.name, state.map),
+            false,
+        )
+        .field(
+            "Reason",
+            if report.reason.is_empty() {
+                "_None given_".to_owned()
+            } else {
+                strip_markdown(&report.reason)
+            },
+            false,
+        )
+        .footer(CreateEmbedFooter::new(format!(
+            "{}/{} players",
+            state.players.len(),
+            state.max_players
+        )))
+        .timestamp(serenity::Timestamp::now());
+
+    let mut buttons = vec![];
+    if let Some(player) = target {
+        buttons.push(
+            CreateButton::new(format!("report.kick|{}|{}", server.addr, player.id))
+                .label("Kick")
+                .style(ButtonStyle::Primary),
+        );
+        buttons.push(
+            CreateButton::new(format!("report.ban|{}|{}", server.addr, player.id))
+                .label("Ban (1d)")
+                .style(ButtonStyle::Danger),
+        );
+    }
+    buttons.push(
+        CreateButton::new("report.dismiss")
+            .label("Dismiss")
+            .style(ButtonStyle::Secondary),
+    );
+
+    mod_channel
+        .send_message(
+            ctx,
+            CreateMessage::new()
+                .embed(embed)
+                .components(vec![CreateActionRow::Buttons(buttons)])
+                .allowed_mentions(CreateAllowedMentions::new().empty_roles().empty_users()),
+        )
+        .await?;
+
+    acknowledge(
+        server,
+        reporter_steamid,
+        "Your report was sent to the moderators. Thank you!",
+    )
+    .await?;
+    Ok(())
+}
+
+/// handles the kick / ban / dismiss buttons on a report
+pub async fn report_button(
+    ctx: &serenity::Context,
+    data: &PoiseData,
+    mci: &ComponentInteraction,
+) -> Result<(), Error> {
+    let reply = |content: String| {
+        CreateInteractionResponse::Message(
+            CreateInteractionResponseMessage::new()
+                .content(content)
+                .ephemeral(true),
+        )
+    };
+
+    let mut parts = mci.data.custom_id.split('|');
+    let action = parts.next().ok_or("Invalid report button")?;
+    let command = match action {
+        "report.kick" => "tf2kick",
+        "report.ban" => "tf2ban",
+        _ => "tf2kick",
+    };
+
+    let roles = mci
+        .member
+        .as_ref()
+        .map(|m| m.roles.clone())
+        .unwrap_or_default();
+    let everyone = data.guild_id.everyone_role();
+    if !data
+        .permissions
+        .read()
+        .await
+        .can_run(command, &roles, everyone)
+    {
+        mci.create_response(ctx, reply("You cannot act on reports.".to_owned()))
+            .await?;
+        return Ok(());
+    }
+
+    let closed_by = |what: &str| {
+        CreateInteractionResponse::UpdateMessage(
+            CreateInteractionResponseMessage::new()
+                .components(vec![])
+                .content(format!("{} by {}", what, mci.user.mention())),
+        )
+    };
+
+    if action == "report.dismiss" {
+        mci.create_response(ctx, closed_by("Dismissed")).await?;
+        return Ok(());
+    }
+
+    let addr: SocketAddr = parts.next().ok_or("No server in report button")?.parse()?;
+    let steamid = parts.next().ok_or("No steamid in report button")?;
+    let server = data.servers.get(&addr).ok_or("Server not found")?;
+    // bans run on every server, so they need rcon on all of them
+    let targets: Vec<&Server> = match action {
+        "report.ban" => data.servers.values().collect(),
+        _ => vec![server],
+    };
+    let denied = {
+        let policy = data.permissions.read().await;
+        targets
+            .iter()
+            .find(|s| !policy.can_rcon(&s.name, &roles, everyone))
+            .copied()
+    };
+    if let Some(denied) = denied {
+        mci.create_response(
+            ctx,
+            reply(format!(
+                "You are not allowed to use RCON on {}.",
+                denied.emoji
+            )),
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let output = match action {
+        "report.kick" => {
+            rcon_user_output(&[server], format!("kickid \"{}\" Reported", steamid)).await
+        }
+        "report.ban" => {
+            let _ = pin_latest_demo(
+                &data.local_pool,
+                server,
+                &data.demo_dir,
+                &format!("Ban of {} by {}", steamid, mci.user.name),
+            )
+            .await
+            .inspect_err(|e| log::error!("Could not pin banned demo: {e}"));
+            banid(
+                &data.steamid_client,
+                steamid,
+                &targets,
+                REPORT_BAN_MINUTES,
+                "Reported",
+            )
+            .await
+        }
+        _ => return Err("Invalid report button".into()),
+    };
+
+    mci.create_response(
+        ctx,
+        closed_by(if action == "report.kick" {
+            "Kicked"
+        } else {
+            "Banned"
+        }),
+    )
+    .await?;
+    mci.create_followup(
+        ctx,
+        serenity::CreateInteractionResponseFollowup::new()
+            .content(output)
+            .ephemeral(true),
+    )
+    .await?;
+    Ok(())
+}
@@ -45,6 +45,7 @@ async fn main() -> Result<(), Error> {
         "stocks",
         "tf2",
         "yapawards",
+        "achievements",
         "genimg",
     ] {
         log_config = log_config.level_for(module, log::LevelFilter::Debug);
@@ -100,6 +101,9 @@ async fn main() -> Result<(), Error> {
         allow_seed: true,
         control_mapfile: true,
         wacky_server: true,
+        ingest_secret: std::env::var("LOG_INGEST_SECRET_4")
+            .ok()
+            .filter(|s| !s.is_empty()),
     }
     .build()
     .await
@@ -120,6 +124,9 @@ async fn main() -> Result<(), Error> {
         allow_seed: true,
         control_mapfile: true,
         wacky_server: false,
+        ingest_secret: std::env::var("LOG_INGEST_SECRET_5")
+            .ok()
+            .filter(|s| !s.is_empty()),
     }
     .build()
     .await
@@ -140,6 +147,9 @@ async fn main() -> Result<(), Error> {
         allow_seed: false,
         control_mapfile: false,
         wacky_server: false,
+        ingest_secret: std::env::var("LOG_INGEST_SECRET_6")
+            .ok()
+            .filter(|s| !s.is_empty()),
     }
     .build()
     .await
@@ -155,13 +165,11 @@ async fn main() -> Result<(), Error> {
     log::info!("Launching UDP log receiver...");
     let logs_addr: Ipv4Addr = parse_env("SRCDS_LOG_ADDR");
     let logs_port: u16 = parse_env("SRCDS_LOG_PORT");
-    let log_receiver = LogReceiver::connect(logs_addr, logs_port)
+    let server_names = servers.values().map(|s| (s.addr, s.name.clone())).collect();
+    let log_receiver = LogReceiver::connect(logs_addr, logs_port, server_names)
         .await
         .expect("Could not bind log receiver");
 
-    log::info!("Spawning HTTP API listener...");
-    let api_state = api::init().await.expect("Could not spawn api.");
-
     log::info!("Starting discord bot...");
-    discord::start_bot(log_receiver, servers, api_state).await
+    discord::start_bot(log_receiver, servers).await
 }
@@ -10,15 +10,24 @@ use poise::serenity_prelude::{
     CreateSelectMenuKind, CreateSelectMenuOption, EditMessage, Mentionable, Message, User, UserId,
 };
 
+use chrono::TimeDelta;
 use common::Error;
 use rand::{thread_rng, Rng};
 use rand_distr::{Distribution, Normal};
 use sqlx::{MySql, Pool};
 
 use crate::{format_rank_tie, Deck, Hand};
-use catcoin::{get_catcoin, grant_catcoin, spend_catcoin};
+use catcoin::{
+    escrow, get_catcoin,
+    ledger::Reason,
+    odds::{odds, Chance},
+    record_win, Game,
+};
 use emoji::emoji;
 
+/// how long wagers stay in escrow once a game starts
+const WAGER_TTL: TimeDelta = TimeDelta::minutes(30);
+
 pub async fn on_message(
     ctx: &serenity::Context,
     pool: &Pool<MySql>,
@@ -28,7 +37,7 @@ pub async fn on_message(
         let mut rng = thread_rng();
 
         // should message spawn a game?
-        if !rng.gen_ratio(1, 2000) {
+        if !odds().roll(Chance::Poker, msg.channel_id, &mut rng) {
             return Ok(());
         }
 
@@ -274,23 +283,8 @@ impl PokerLobby {
         msg: &Message,
         reason: &str,
     ) -> Result<(), Error> {
-        // Refund catcoin to players who joined if wagers were deducted
-        // (Only applies when game has started but selections aren't done)
-        if self.is_ready() && self.draw_timeout.is_some() && !self.are_selections_done() {
-            let mut tx = pool.begin().await?;
-
-            // Refund player 1
-            if let Some(player1) = &self.player1 {
-                grant_catcoin(&mut *tx, player1.id, self.wager).await?;
-            }
-
-            // Refund player 2
-            if let Some(player2) = &self.player2 {
-                grant_catcoin(&mut *tx, player2.id, self.wager).await?;
-            }
-
-            tx.commit().await?;
-
+        // Refund whatever wagers are still held in escrow
+        if !escrow::refund(pool, &self.uuid).await?.is_empty() {
             // Send abort notification message to channel
             msg.channel_id
                 .send_message(
@@ -463,7 +457,16 @@ impl PokerLobby {
 
         let mut tx = pool.begin().await?;
 
-        if !spend_catcoin(&mut *tx, self.player1.as_ref().unwrap().id, self.wager).await? {
+        if !escrow::hold(
+            &mut *tx,
+            self.player1.as_ref().unwrap().id,
+            self.wager,
+            Reason::Poker,
+            &self.uuid,
+            WAGER_TTL,
+        )
+        .await?
+        {
             tx.rollback().await?;
             msg.channel_id
                 .send_message(
@@ -478,7 +481,16 @@ impl PokerLobby {
             return Ok(false);
         }
 
-        if !spend_catcoin(&mut *tx, self.player2.as_ref().unwrap().id, self.wager).await? {
+        if !escrow::hold(
+            &mut *tx,
+            self.player2.as_ref().unwrap().id,
+            self.wager,
+            Reason::Poker,
+            &self.uuid,
+            WAGER_TTL,
+        )
+        .await?
+        {
             tx.rollback().await?;
             msg.channel_id
                 .send_message(
@@ -726,11 +738,12 @@ pub async fn create_poker_game(
     uuid: u64,
     wager: u64,
 ) -> Result<(), Error> {
-    let mut poker = PokerLobby::new(wager, &uuid.to_string());
+    // also the escrow reference, so it can't collide with a trip from the same message
+    let mut poker = PokerLobby::new(wager, &format!("poker-{uuid}"));
 
-    let join_id = format!("{uuid}-join");
-    let view_id = format!("{uuid}-view");
-    let select_id = format!("{uuid}-select");
+    let join_id = format!("{}-join", poker.uuid);
+    let view_id = format!("{}-view", poker.uuid);
+    let select_id = format!("{}-select", poker.uuid);
 
     let mut msg = channel
         .send_message(
@@ -783,7 +796,7 @@ pub async fn create_poker_game(
         let interaction = ComponentInteractionCollector::new(ctx)
             .channel_id(channel)
             .timeout(collector_timeout)
-            .filter(move |mci| mci.data.custom_id.starts_with(&uuid.to_string()))
+            .filter(move |mci| mci.data.custom_id.starts_with(&format!("poker-{uuid}-")))
             .await;
 
         // Check timeouts and auto-select for players who didn't make a choice in time
@@ -856,6 +869,7 @@ pub async fn create_poker_game(
                             .components(poker.to_components()),
                     )
                     .await?;
+                escrow::attach_message(pool, &poker.uuid, msg.channel_id, msg.id).await?;
             } else {
                 update_embed(ctx, &mut msg, &poker).await?;
             }
@@ -958,24 +972,31 @@ pub async fn create_poker_game(
     let comparison = player1_hand.compare(player2_hand);
 
     let winner_id: Option<UserId> = match comparison {
-        Ordering::Greater => {
-            let winner = poker.player1.as_ref().unwrap().id;
-            grant_catcoin(pool, winner, wager * 2).await?;
-            Some(winner)
+        Ordering::Greater => Some(poker.player1.as_ref().unwrap().id),
+        Ordering::Less => Some(poker.player2.as_ref().unwrap().id),
+        Ordering::Equal => None,
+    };
+    match winner_id {
+        Some(winner) => {
+            if escrow::release(pool, &poker.uuid, winner, Reason::Poker).await? == 0 {
+                // the wagers were refunded from under us, so nobody won anything
+                poker
+                    .abort_game(ctx, pool, &msg, "the wagers expired, game void")
+                    .await?;
+                return Ok(());
+            }
         }
-        Ordering::Less => {
-            let winner = poker.player2.as_ref().unwrap().id;
-            grant_catcoin(pool, winner, wager * 2).await?;
-            Some(winner)
+        // a tie, so everyone gets their wager back
+        None => {
+            escrow::refund(pool, &poker.uuid).await?;
         }
-        Ordering::Equal => {
-            let a = poker.player2.as_ref().unwrap().id;
-            let b = poker.player1.as_ref().unwrap().id;
-            grant_catcoin(pool, a, wager).await?;
-            grant_catcoin(pool, b, wager).await?;
-            None
-        }
-    };
+    }
+
+    if let Some(winner) = winner_id {
+        let _ = record_win(pool, winner, Game::Poker)
+            .await
+            .inspect_err(|e| log::error!("Could not record poker win: {e}"));
+    }
 
     // Display winner announcement and hands in the second message
     poker
@@ -0,0 +1,524 @@
+use std::{sync::Arc, time::Duration};
+
+use chrono::{DateTime, NaiveDateTime, TimeDelta, Utc};
+use emoji::emoji;
+use poise::serenity_prelude::{self as serenity, CreateMessage};
+use sqlx::{MySql, MySqlConnection, Pool, Transaction};
+
+use common::Error;
+
+use crate::{escrow, inventory::CatcoinPull, ledger::Reason, random_pulls::Reward};
+
+/// how often ended auctions are settled
+const SETTLE_INTERVAL: Duration = Duration::from_secs(60);
+/// how long bids stay in escrow past the end of an auction, in case settling is late
+const BID_GRACE: TimeDelta = TimeDelta::days(1);
+
+/// A pull up for auction
+#[derive(Clone, Debug)]
+pub struct Auction {
+    pub id: u64,
+    pub seller: serenity::UserId,
+    pub pull: CatcoinPull,
+    pub start_price: u64,
+    pub buy_now: Option<u64>,
+    /// the highest bid so far, held in escrow
+    pub bid: Option<u64>,
+    pub bidder: Option<serenity::UserId>,
+    pub ends_at: DateTime<Utc>,
+}
+
+impl Auction {
+    /// the lowest bid that would be accepted
+    pub fn min_bid(&self) -> u64 {
+        self.bid.map(|b| b + 1).unwrap_or(self.start_price)
+    }
+
+    /// the buy now price, None once bidding has reached it
+    pub fn buy_now_price(&self) -> Option<u64> {
+        self.buy_now
+            .filter(|price| self.bid.is_none_or(|bid| bid < *price))
+    }
+
+    /// a short summary for discord
+    pub fn line(&self) -> String {
+        let bid = match self.bid {
+            Some(bid) => format!("bid **{}** {}", bid, emoji("catcoin")),
+            None => format!("starts at **{}** {}", self.start_price, emoji("catcoin")),
+        };
+        let buy_now = self
+            .buy_now_price()
+            .map(|price| format!(" | buy now **{}** {}", price, emoji("catcoin")))
+            .unwrap_or_default();
+        format!(
+            "`#{}` **{}** {} `#{}` - {}{} | ends <t:{}:R>",
+            self.id,
+            self.pull.reward.rarity,
+            self.pull.reward.name,
+            self.pull.number,
+            bid,
+            buy_now,
+            self.ends_at.timestamp()
+        )
+    }
+}
+
+/// escrow reference bids on an auction are held under
+fn reference(id: u64) -> String {
+    format!("auction-{id}")
+}
+
+#[derive(sqlx::FromRow)]
+struct AuctionRow {
+    id: u64,
+    seller_uid: String,
+    inv_id: i32,
+    start_price: u64,
+    buy_now: Option<u64>,
+    bid: Option<u64>,
+    bidder_uid: Option<String>,
+    ends_at: DateTime<Utc>,
+    number: i32,
+    catcoin: i32,
+    inv_created_at: NaiveDateTime,
+    inv_updated_at: NaiveDateTime,
+    rid: i32,
+    name: String,
+    file: String,
+    rarity: String,
+}
+
+impl AuctionRow {
+    fn into_auction(self) -> Option<Auction> {
+        let seller: serenity::UserId = self.seller_uid.parse().ok()?;
+        Some(Auction {
+            id: self.id,
+            seller,
+            pull: CatcoinPull {
+                id: self.inv_id,
+                uid: seller,
+                reward: Reward {
+                    id: self.rid,
+                    name: self.name,
+                    file: self.file,
+                    rarity: self.rarity.into(),
+                },
+                number: self.number,
+                catcoin: self.catcoin,
+                created_at: self.inv_created_at,
+                updated_at: self.inv_updated_at,
+            },
+            start_price: self.start_price,
+            buy_now: self.buy_now,
+            bid: self.bid,
+            bidder: self.bidder_uid.and_then(|b| b.parse().ok()),
+            ends_at: self.ends_at,
+        })
+    }
+}
+
+const SELECT_AUCTION: &str = r#"
+	SELECT 	a.id, a.seller_uid, a.inv_id, a.start_price, a.buy_now, a.bid, a.bidder_uid, a.ends_at,
+			i.number, i.catcoin, i.created_at AS inv_created_at, i.updated_at AS inv_updated_at,
+			r.id AS rid, r.name, r.file, r.rarity
+	FROM `catcoin_auction` a
+	INNER JOIN `catcoin_inv` i ON a.inv_id = i.id
+	INNER JOIN `catcoin_reward` r ON i.rid = r.id
+"#;
+
+/// What happened to a bid
+#[derive(Clone, Debug)]
+pub enum BidOutcome {
+    /// the bid is the highest. `outbid` is who it beat and what they got back
+    Placed {
+        auction: Auction,
+        outbid: Option<(serenity::UserId, u64)>,
+    },
+    /// the auction was bought outright. `outbid` is the bid it beat and what they got back
+    Bought {
+        settlement: Settlement,
+        outbid: Option<(serenity::UserId, u64)>,
+    },
+    /// the bid has to be at least this much
+    TooLow(u64),
+    /// the auction has ended or doesn't exist
+    Closed,
+    /// the auction has no buy now price, or bidding has passed it
+    NoBuyNow,
+    OwnAuction,
+    CantAfford,
+}
+
+/// How an auction ended
+#[derive(Clone, Debug)]
+pub struct Settlement {
+    pub auction: Auction,
+    /// None if nobody bid
+    pub winner: Option<serenity::UserId>,
+    pub price: u64,
+}
+
+/// Puts a pull up for auction, returning its id.
+/// None if the user doesn't own the pull or it's already up for auction
+pub async fn list(
+    pool: &Pool<MySql>,
+    seller: serenity::UserId,
+    inv_id: i32,
+    start_price: u64,
+    buy_now: Option<u64>,
+    duration: TimeDelta,
+) -> Result<Option<u64>, Error> {
+    let mut tx = pool.begin().await?;
+    let owned = sqlx::query!(
+        "SELECT `id` FROM `catcoin_inv` WHERE `id` = ? AND `uid` = ? FOR UPDATE",
+        inv_id,
+        seller.get()
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+    let listed = sqlx::query!(
+        "SELECT `id` FROM `catcoin_auction` WHERE `inv_id` = ? AND NOT `settled`",
+        inv_id
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+    if owned.is_none() || listed.is_some() {
+        tx.rollback().await?;
+        return Ok(None);
+    }
+    let id = sqlx::query!(
+        "INSERT INTO `catcoin_auction` (`seller_uid`, `inv_id`, `start_price`, `buy_now`, `ends_at`) VALUES (?, ?, ?, ?, ?)",
+        seller.get(),
+        inv_id,
+        start_price,
+        buy_now,
+        Utc::now() + duration
+    )
+    .execute(&mut *tx)
+    .await?
+    .last_insert_id();
+    tx.commit().await?;
+    Ok(Some(id))
+}
+
+pub async fn get(pool: &Pool<MySql>, id: u64) -> Result<Option<Auction>, Error> {
+    let row: Option<AuctionRow> = sqlx::query_as(&format!("{SELECT_AUCTION} WHERE a.id = ?"))
+        .bind(id)
+        .fetch_optional(pool)
+        .await?;
+    Ok(row.and_then(AuctionRow::into_auction))
+}
+
+/// open auctions, rarest first then soonest to end
+pub async fn get_open(pool: &Pool<MySql>, limit: i64, offset: i64) -> Result<Vec<Auction>, Error> {
+    let rows: Vec<AuctionRow> = sqlx::query_as(&format!(
+        "{SELECT_AUCTION} WHERE NOT a.settled ORDER BY r.rarity DESC, a.ends_at ASC LIMIT ? OFFSET ?"
+    ))
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows
+        .into_iter()
+        .filter_map(AuctionRow::into_auction)
+        .collect())
+}
+
+pub async fn count_open(pool: &Pool<MySql>) -> Result<i64, Error> {
+    let count = sqlx::query_scalar!("SELECT COUNT(*) FROM `catcoin_auction` WHERE NOT `settled`")
+        .fetch_one(pool)
+        .await?;
+    Ok(count)
+}
+
+/// the auction, locked until the transaction ends. None if it's settled or doesn't exist
+async fn lock(conn: &mut MySqlConnection, id: u64) -> Result<Option<Auction>, Error> {
+    let row: Option<AuctionRow> = sqlx::query_as(&format!(
+        "{SELECT_AUCTION} WHERE a.id = ? AND NOT a.settled FOR UPDATE"
+    ))
+    .bind(id)
+    .fetch_optional(&mut *conn)
+    .await?;
+    Ok(row.and_then(AuctionRow::into_auction))
+}
+
+/// refunds the current highest bid and holds the new one in its place.
+/// returns who was outbid, or None if the bidder can't afford it
+async fn replace_bid(
+    conn: &mut MySqlConnection,
+    auction: &Auction,
+    uid: serenity::UserId,
+    amount: u64,
+) -> Result<Option<Option<(serenity::UserId, u64)>>, Error> {
+    let reference = reference(auction.id);
+    let outbid = escrow::refund(&mut *conn, &reference)
+        .await?
+        .into_iter()
+        .find(|held| held.uid != uid)
+        .map(|held| (held.uid, held.amount));
+    let ttl = (auction.ends_at - Utc::now()).max(TimeDelta::zero()) + BID_GRACE;
+    if !escrow::hold_durable(&mut *conn, uid, amount, Reason::Auction, &reference, ttl).await? {
+        return Ok(None);
+    }
+    sqlx::query!(
+        "UPDATE `catcoin_auction` SET `bid` = ?, `bidder_uid` = ? WHERE `id` = ?",
+        amount,
+        uid.get(),
+        auction.id
+    )
+    .execute(&mut *conn)
+    .await?;
+    Ok(Some(outbid))
+}
+
+/// Bids on an auction, holding the catcoin until the user is outbid or the auction ends
+pub async fn bid(
+    pool: &Pool<MySql>,
+    id: u64,
+    uid: serenity::UserId,
+    amount: u64,
+) -> Result<BidOutcome, Error> {
+    let mut tx = pool.begin().await?;
+    let Some(mut auction) = lock(&mut *tx, id).await?.filter(|a| a.ends_at > Utc::now()) else {
+        return Ok(BidOutcome::Closed);
+    };
+    if auction.seller == uid {
+        return Ok(BidOutcome::OwnAuction);
+    }
+    if amount < auction.min_bid() {
+        return Ok(BidOutcome::TooLow(auction.min_bid()));
+    }
+    if let Some(price) = auction.buy_now_price().filter(|price| amount >= *price) {
+        return buy_locked(tx, auction, uid, price).await;
+    }
+    let Some(outbid) = replace_bid(&mut *tx, &auction, uid, amount).await? else {
+        tx.rollback().await?;
+        return Ok(BidOutcome::CantAfford);
+    };
+    tx.commit().await?;
+
+    auction.bid = Some(amount);
+    auction.bidder = Some(uid);
+    Ok(BidOutcome::Placed { auction, outbid })
+}
+
+/// Buys the auction outright at its buy now price
+pub async fn buy_now(
+    pool: &Pool<MySql>,
+    id: u64,
+    uid: serenity::UserId,
+) -> Result<BidOutcome, Error> {
+    let mut tx = pool.begin().await?;
+    let Some(auction) = lock(&mut *tx, id).await?.filter(|a| a.ends_at > Utc::now()) else {
+        return Ok(BidOutcome::Closed);
+    };
+    if auction.seller == uid {
+        return Ok(BidOutcome::OwnAuction);
+    }
+    let Some(price) = auction.buy_now_price() else {
+        return Ok(BidOutcome::NoBuyNow);
+    };
+    buy_locked(tx, auction, uid, price).await
+}
+
+/// holds the price from the buyer and settles the locked auction right away
+async fn buy_locked(
+    mut tx: Transaction<'_, MySql>,
+    mut auction: Auction,
+    uid: serenity::UserId,
+    price: u64,
+) -> Result<BidOutcome, Error> {
+    let Some(outbid) = replace_bid(&mut *tx, &auction, uid, price).await? else {
+        tx.rollback().await?;
+        return Ok(BidOutcome::CantAfford);
+    };
+    auction.bid = Some(price);
+    auction.bidder = Some(uid);
+    let settlement = settle_locked(&mut *tx, auction).await?;
+    tx.commit().await?;
+    Ok(BidOutcome::Bought { settlement, outbid })
+}
+
+/// pays the seller, hands the pull to the winner and closes the auction
+async fn settle_locked(conn: &mut MySqlConnection, auction: Auction) -> Result<Settlement, Error> {
+    let reference = reference(auction.id);
+    let mut winner = None;
+    let mut price = 0;
+    if let Some(bidder) = auction.bidder {
+        let moved = sqlx::query!(
+            "UPDATE `catcoin_inv` SET `uid` = ? WHERE `id` = ? AND `uid` = ?",
+            bidder.get(),
+            auction.pull.id,
+            auction.seller.get()
+        )
+        .execute(&mut *conn)
+        .await?
+        .rows_affected()
+            > 0;
+        if moved {
+            price =
+                escrow::release(&mut *conn, &reference, auction.seller, Reason::Auction).await?;
+        }
+        if price > 0 {
+            winner = Some(bidder);
+        } else {
+            // the bid already expired out of escrow, or the pull is gone
+            escrow::refund(&mut *conn, &reference).await?;
+            sqlx::query!(
+                "UPDATE `catcoin_inv` SET `uid` = ? WHERE `id` = ? AND `uid` = ?",
+                auction.seller.get(),
+                auction.pull.id,
+                bidder.get()
+            )
+            .execute(&mut *conn)
+            .await?;
+        }
+    }
+    sqlx::query!(
+        "UPDATE `catcoin_auction` SET `settled` = true WHERE `id` = ?",
+        auction.id
+    )
+    .execute(&mut *conn)
+    .await?;
+    Ok(Settlement {
+        auction,
+        winner,
+        price,
+    })
+}
+
+/// settles every auction that has ended
+pub async fn settle_ended(pool: &Pool<MySql>) -> Result<Vec<Settlement>, Error> {
+    let ids = sqlx::query_scalar!(
+        "SELECT `id` FROM `catcoin_auction` WHERE NOT `settled` AND `ends_at` <= ?",
+        Utc::now()
+    )
+    .fetch_all(pool)
+    .await?;
+    let mut settled = vec![];
+    for id in ids {
+        let mut tx = pool.begin().await?;
+        if let Some(auction) = lock(&mut *tx, id).await? {
+            settled.push(settle_locked(&mut *tx, auction).await?);
+        }
+        tx.commit().await?;
+    }
+    Ok(settled)
+}
+
+async fn notify(http: &serenity::Http, uid: serenity::UserId, content: String) {
+    let _ = uid
+        .direct_message(http, CreateMessage::new().content(content))
+        .await
+        .inspect_err(|e| log::error!("Could not DM {} about an auction: {e}", uid));
+}
+
+/// lets someone know they were outbid and got their catcoin back
+pub async fn notify_outbid(
+    http: &serenity::Http,
+    auction: &Auction,
+    uid: serenity::UserId,
+    refunded: u64,
+) {
+    notify(
+        http,
+        uid,
+        format!(
+            "You were outbid on auction `#{}` for **{}** {} `#{}`. Your **{}** {} was refunded, bid at least **{}** to get back on top.",
+            auction.id,
+            auction.pull.reward.rarity,
+            auction.pull.reward.name,
+            auction.pull.number,
+            refunded,
+            emoji("catcoin"),
+            auction.min_bid()
+        ),
+    )
+    .await;
+}
+
+/// lets the highest bidder know the auction was bought out from under them
+pub async fn notify_bought_out(
+    http: &serenity::Http,
+    auction: &Auction,
+    uid: serenity::UserId,
+    refunded: u64,
+) {
+    notify(
+        http,
+        uid,
+        format!(
+            "Auction `#{}` for **{}** {} `#{}` was bought outright. Your **{}** {} was refunded.",
+            auction.id,
+            auction.pull.reward.rarity,
+            auction.pull.reward.name,
+            auction.pull.number,
+            refunded,
+            emoji("catcoin")
+        ),
+    )
+    .await;
+}
+
+/// lets the seller and winner know how an auction ended
+pub async fn notify_settled(http: &serenity::Http, settlement: &Settlement) {
+    let auction = &settlement.auction;
+    let pull = format!(
+        "**{}** {} `#{}`",
+        auction.pull.reward.rarity, auction.pull.reward.name, auction.pull.number
+    );
+    match settlement.winner {
+        Some(winner) => {
+            notify(
+                http,
+                auction.seller,
+                format!(
+                    "Your auction `#{}` for {} sold for **{}** {}!",
+                    auction.id,
+                    pull,
+                    settlement.price,
+                    emoji("catcoin")
+                ),
+            )
+            .await;
+            notify(
+                http,
+                winner,
+                format!(
+                    "You won auction `#{}`! {} is now in your inventory.",
+                    auction.id, pull
+                ),
+            )
+            .await;
+        }
+        None => {
+            notify(
+                http,
+                auction.seller,
+                format!(
+                    "Your auction `#{}` for {} ended without a sale.",
+                    auction.id, pull
+                ),
+            )
+            .await;
+        }
+    }
+}
+
+/// settles auctions as they end
+pub fn spawn_auction_thread(pool: Pool<MySql>, http: Arc<serenity::Http>) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(SETTLE_INTERVAL);
+        loop {
+            interval.tick().await;
+            match settle_ended(&pool).await {
+                Ok(settled) => {
+                    for settlement in settled.iter() {
+                        notify_settled(&http, settlement).await;
+                    }
+                }
+                Err(e) => log::error!("Could not settle auctions: {e}"),
+            }
+        }
+    });
+}
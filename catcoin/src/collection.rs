@@ -0,0 +1,386 @@
+use std::collections::HashMap;
+
+use chrono::NaiveDateTime;
+use emoji::emoji;
+use poise::serenity_prelude as serenity;
+use sqlx::{MySql, Pool};
+
+use common::Error;
+
+use crate::{
+    drops, grant_catcoin,
+    ledger::Reason,
+    random_pulls::{Rarity, Reward},
+    thumbnails::{render_grid, thumbnail, Tile},
+};
+
+/// rewards per row of the collection grid
+const COLUMNS: u32 = 6;
+
+/// The first time a reward was ever pulled
+#[derive(Clone, Debug)]
+pub struct FirstPull {
+    pub uid: serenity::UserId,
+    pub created_at: NaiveDateTime,
+}
+
+/// A reward in the catalogue, and how many of it the user owns
+#[derive(Clone, Debug)]
+pub struct CollectionEntry {
+    pub reward: Reward,
+    pub owned: i64,
+    pub first: Option<FirstPull>,
+}
+
+impl CollectionEntry {
+    /// a short summary for discord. missing rewards keep their name a secret
+    pub fn line(&self) -> String {
+        let name = if self.owned > 0 {
+            format!("✅ **{}** x{}", self.reward.name, self.owned)
+        } else {
+            "❔ *???*".to_owned()
+        };
+        let first = match &self.first {
+            Some(first) => format!(
+                "first pulled by <@{}> <t:{}:R>",
+                first.uid,
+                first
+                    .created_at
+                    .signed_duration_since(NaiveDateTime::UNIX_EPOCH)
+                    .num_seconds()
+            ),
+            None => "never pulled".to_owned(),
+        };
+        format!("{name} - {first}")
+    }
+}
+
+/// Every reward in the catalogue, owned or not
+#[derive(Clone, Debug)]
+pub struct Collection {
+    pub uid: serenity::UserId,
+    pub entries: Vec<CollectionEntry>,
+}
+
+#[derive(sqlx::FromRow)]
+struct OwnedRow {
+    rid: i32,
+    owned: i64,
+}
+
+#[derive(sqlx::FromRow)]
+struct FirstPullRow {
+    rid: i32,
+    uid: String,
+    created_at: NaiveDateTime,
+}
+
+impl Collection {
+    pub async fn get(pool: &Pool<MySql>, uid: serenity::UserId) -> Result<Self, Error> {
+        let owned: HashMap<i32, i64> = sqlx::query_as::<_, OwnedRow>(
+            "SELECT `rid`, COUNT(*) AS `owned` FROM `catcoin_inv` WHERE `uid` = ? GROUP BY `rid`",
+        )
+        .bind(uid.get())
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .map(|r| (r.rid, r.owned))
+        .collect();
+        let mut firsts: HashMap<i32, FirstPull> = sqlx::query_as::<_, FirstPullRow>(
+            "SELECT `rid`, `uid`, `created_at` FROM `catcoin_first_pull`",
+        )
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .filter_map(|r| {
+            Some((
+                r.rid,
+                FirstPull {
+                    uid: r.uid.parse().ok()?,
+                    created_at: r.created_at,
+                },
+            ))
+        })
+        .collect();
+
+        let mut entries: Vec<CollectionEntry> = drops()
+            .iter()
+            .map(|reward| CollectionEntry {
+                reward: reward.clone(),
+                owned: owned.get(&reward.id).copied().unwrap_or(0),
+                first: firsts.remove(&reward.id),
+            })
+            .collect();
+        entries.sort_by(|a, b| a.reward.name.cmp(&b.reward.name));
+        Ok(Self { uid, entries })
+    }
+
+    /// rewards of the given rarity
+    pub fn of_rarity(&self, rarity: Rarity) -> Vec<&CollectionEntry> {
+        self.entries
+            .iter()
+            .filter(|e| e.reward.rarity == rarity)
+            .collect()
+    }
+
+    /// (owned, total) distinct rewards, for one rarity or all of them
+    pub fn completion(&self, rarity: Option<Rarity>) -> (usize, usize) {
+        let entries: Vec<&CollectionEntry> = self
+            .entries
+            .iter()
+            .filter(|e| rarity.is_none() || rarity == Some(e.reward.rarity))
+            .collect();
+        let owned = entries.iter().filter(|e| e.owned > 0).count();
+        (owned, entries.len())
+    }
+
+    /// the rewards of a rarity as a png grid, with silhouettes for the missing ones
+    pub async fn render(&self, rarity: Rarity) -> Result<Vec<u8>, Error> {
+        let mut tiles = vec![];
+        for entry in self.of_rarity(rarity) {
+            tiles.push(Tile {
+                thumbnail: thumbnail(&entry.reward.file).await?,
+                frame: None,
+                silhouette: entry.owned == 0,
+            });
+        }
+        render_grid(tiles, COLUMNS).await
+    }
+}
+
+/// A group of rewards that pays a bonus once a user owns all of them
+#[derive(Clone, Debug)]
+pub struct CatcoinSet {
+    pub id: u32,
+    pub name: String,
+    /// every reward of this rarity, or None for a themed set
+    pub rarity: Option<Rarity>,
+    pub bonus: u64,
+    pub enabled: bool,
+    /// reward ids in the set
+    pub rewards: Vec<i32>,
+    /// only completions after this pay the bonus
+    pub created_at: NaiveDateTime,
+}
+
+impl CatcoinSet {
+    /// how many of the set's rewards the collection has
+    pub fn progress(&self, collection: &Collection) -> (usize, usize) {
+        let owned = collection
+            .entries
+            .iter()
+            .filter(|e| e.owned > 0 && self.rewards.contains(&e.reward.id))
+            .count();
+        (owned, self.rewards.len())
+    }
+
+    /// a short summary for discord
+    pub fn line(&self, collection: &Collection) -> String {
+        let (owned, total) = self.progress(collection);
+        let status = if total > 0 && owned == total {
+            "✅"
+        } else {
+            "⬜"
+        };
+        format!(
+            "{} **{}** `#{}` {}/{} - **{}** {}",
+            status,
+            self.name,
+            self.id,
+            owned,
+            total,
+            self.bonus,
+            emoji("catcoin")
+        )
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct SetRow {
+    id: u32,
+    name: String,
+    rarity: Option<String>,
+    bonus: u64,
+    enabled: bool,
+    created_at: NaiveDateTime,
+}
+
+#[derive(sqlx::FromRow)]
+struct SetRewardRow {
+    set_id: u32,
+    rid: i32,
+}
+
+/// every set, with its rewards filled in
+pub async fn get_sets(
+    pool: &Pool<MySql>,
+    include_disabled: bool,
+) -> Result<Vec<CatcoinSet>, Error> {
+    let rows: Vec<SetRow> = sqlx::query_as(
+        "SELECT `id`, `name`, `rarity`, `bonus`, `enabled`, `created_at` FROM `catcoin_set` WHERE `enabled` OR ? ORDER BY `bonus` ASC, `id` ASC",
+    )
+    .bind(include_disabled)
+    .fetch_all(pool)
+    .await?;
+    let mut members: HashMap<u32, Vec<i32>> = HashMap::new();
+    for row in sqlx::query_as::<_, SetRewardRow>("SELECT `set_id`, `rid` FROM `catcoin_set_reward`")
+        .fetch_all(pool)
+        .await?
+    {
+        members.entry(row.set_id).or_default().push(row.rid);
+    }
+
+    Ok(rows
+        .into_iter()
+        .map(|row| {
+            let rarity = row.rarity.map(Rarity::from);
+            let rewards = match rarity {
+                Some(rarity) => drops()
+                    .iter()
+                    .filter(|r| r.rarity == rarity)
+                    .map(|r| r.id)
+                    .collect(),
+                None => members.remove(&row.id).unwrap_or_default(),
+            };
+            CatcoinSet {
+                id: row.id,
+                name: row.name,
+                rarity,
+                bonus: row.bonus,
+                enabled: row.enabled,
+                rewards,
+                created_at: row.created_at,
+            }
+        })
+        .collect())
+}
+
+/// creates a set, returning its id. themed sets get every reward whose name contains `matching`
+pub async fn add_set(
+    pool: &Pool<MySql>,
+    name: &str,
+    bonus: u64,
+    rarity: Option<Rarity>,
+    matching: Option<&str>,
+) -> Result<u64, Error> {
+    let mut tx = pool.begin().await?;
+    let id = sqlx::query!(
+        "INSERT INTO `catcoin_set` (`name`, `rarity`, `bonus`) VALUES (?, ?, ?)",
+        name,
+        rarity.map(|r| r.to_string()),
+        bonus
+    )
+    .execute(&mut *tx)
+    .await?
+    .last_insert_id();
+    if let Some(matching) = matching {
+        sqlx::query!(
+            r#"
+			INSERT INTO `catcoin_set_reward` (`set_id`, `rid`)
+			SELECT ?, `id` FROM `catcoin_reward` WHERE `name` LIKE CONCAT('%', ?, '%')
+		"#,
+            id,
+            matching
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await?;
+    Ok(id)
+}
+
+/// changes a set's bonus or availability. returns false if it doesn't exist
+pub async fn edit_set(
+    pool: &Pool<MySql>,
+    id: u32,
+    bonus: Option<u64>,
+    enabled: Option<bool>,
+) -> Result<bool, Error> {
+    let result = sqlx::query!(
+        r#"
+		UPDATE `catcoin_set`
+		SET `bonus` = COALESCE(?, `bonus`), `enabled` = COALESCE(?, `enabled`)
+		WHERE `id` = ?
+	"#,
+        bonus,
+        enabled,
+        id
+    )
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+#[derive(sqlx::FromRow)]
+struct OwnPullRow {
+    rid: i32,
+    pulled_at: NaiveDateTime,
+}
+
+/// Pays the user for every set they've completed and haven't been paid for yet.
+/// Only pulls the user pulled themselves count, so a set can't be passed around by trade or
+/// auction to collect its bonus again. A set pays out only if it was completed after it was
+/// created, so collections finished before a set existed aren't paid retroactively
+pub async fn award_sets(
+    pool: &Pool<MySql>,
+    uid: serenity::UserId,
+) -> Result<Vec<CatcoinSet>, Error> {
+    // reward -> when the user first pulled it themselves
+    let pulled: HashMap<i32, NaiveDateTime> = sqlx::query_as::<_, OwnPullRow>(
+        r#"
+		SELECT i.`rid`, MIN(i.`created_at`) AS `pulled_at`
+		FROM `catcoin_inv` i
+		WHERE i.`uid` = ?
+			AND NOT EXISTS (
+				SELECT 1 FROM `catcoin_trade_item` t WHERE t.`inv_id` = i.`id` AND t.`to_uid` = i.`uid`
+			)
+			AND NOT EXISTS (
+				SELECT 1 FROM `catcoin_auction` a
+				WHERE a.`inv_id` = i.`id` AND a.`bidder_uid` = i.`uid` AND a.`settled`
+			)
+		GROUP BY i.`rid`
+	"#,
+    )
+    .bind(uid.get())
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|r| (r.rid, r.pulled_at))
+    .collect();
+
+    let mut awarded = vec![];
+    for set in get_sets(pool, false).await? {
+        let completed_at = set
+            .rewards
+            .iter()
+            .map(|r| pulled.get(r).copied())
+            .collect::<Option<Vec<NaiveDateTime>>>()
+            .and_then(|times| times.into_iter().max());
+        if !completed_at.is_some_and(|at| at >= set.created_at) {
+            continue;
+        }
+        let mut tx = pool.begin().await?;
+        let inserted = sqlx::query!(
+            "INSERT IGNORE INTO `catcoin_set_completion` (`set_id`, `uid`) VALUES (?, ?)",
+            set.id,
+            uid.get()
+        )
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+        if inserted == 0 {
+            continue;
+        }
+        grant_catcoin(
+            &mut *tx,
+            uid,
+            set.bonus,
+            Reason::SetBonus,
+            Some(set.id.to_string()),
+        )
+        .await?;
+        tx.commit().await?;
+        awarded.push(set);
+    }
+    Ok(awarded)
+}
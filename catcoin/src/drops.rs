@@ -1,5 +1,6 @@
 use std::time::Duration;
 
+use chrono::TimeDelta;
 use rand::prelude::*;
 
 use poise::serenity_prelude::{
@@ -12,7 +13,11 @@ use sqlx::{MySql, Pool};
 
 use emoji::emoji;
 
-use super::{get_catcoin, grant_catcoin, spend_catcoin};
+use super::{
+    escrow, get_catcoin,
+    ledger::Reason,
+    odds::{odds, Chance},
+};
 
 const TRIP_MESSAGES: &'static [&'static str] = &[
     "meow!! >w< %u tripped and lost **%c** %e",
@@ -36,15 +41,10 @@ fn random_trip_msg(name: &str, amount: u64, catcoin_emoji: &str) -> String {
 pub async fn on_message(ctx: &Context, pool: &Pool<MySql>, msg: &Message) -> Result<(), Error> {
     let uuid = msg.id.get();
 
-    // check if drop should occur
-    {
-        let mut rng = thread_rng();
-
-        // chance to trip
-        if !rng.gen_ratio(1, 3000) {
-            return Ok(());
-        }
-    };
+    // chance to trip
+    if !odds().roll(Chance::Trip, msg.channel_id, &mut thread_rng()) {
+        return Ok(());
+    }
 
     // make sure wallet isnt empty
     let has = get_catcoin(pool, msg.author.id).await?;
@@ -61,8 +61,17 @@ pub async fn on_message(ctx: &Context, pool: &Pool<MySql>, msg: &Message) -> Res
         (has.catcoin as f32 * percent).clamp(1., 15.).abs().round() as u64
     };
 
-    // spend from wallet
-    let did_spend = spend_catcoin(pool, msg.author.id, amount).await?;
+    // hold it until someone picks it up
+    let reference = format!("trip-{}", msg.id);
+    let did_spend = escrow::hold(
+        pool,
+        msg.author.id,
+        amount,
+        Reason::Trip,
+        &reference,
+        TimeDelta::seconds(180),
+    )
+    .await?;
     if !did_spend {
         return Err("try_spend_catcoin on pre-checked value failed!".into());
     }
@@ -90,6 +99,7 @@ pub async fn on_message(ctx: &Context, pool: &Pool<MySql>, msg: &Message) -> Res
             CreateMessage::new().embed(embed).components(vec![button]),
         )
         .await?;
+    escrow::attach_message(pool, &reference, rh.channel_id, rh.id).await?;
 
     // wait for first interaction
     while let Some(mci) = ComponentInteractionCollector::new(ctx)
@@ -100,6 +110,9 @@ pub async fn on_message(ctx: &Context, pool: &Pool<MySql>, msg: &Message) -> Res
     {
         mci.create_response(ctx, serenity::CreateInteractionResponse::Acknowledge)
             .await?;
+        if escrow::release(pool, &reference, mci.user.id, Reason::Trip).await? == 0 {
+            return Ok(());
+        }
         let response = if mci.user.id == msg.author.id {
             format!(
                 "{} picked their own **{}** {} back up. ^-^",
@@ -124,11 +137,13 @@ pub async fn on_message(ctx: &Context, pool: &Pool<MySql>, msg: &Message) -> Res
                 .embeds(vec![]),
         )
         .await?;
-        grant_catcoin(pool, mci.user.id, amount).await?;
         return Ok(());
     }
 
-    rh.delete(ctx).await?;
+    // nobody picked it up, so it goes back in their pocket
+    if !escrow::refund(pool, &reference).await?.is_empty() {
+        rh.delete(ctx).await?;
+    }
 
     Ok(())
 }
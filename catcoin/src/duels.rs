@@ -7,12 +7,21 @@ use poise::serenity_prelude::{
     Message, User,
 };
 
+use chrono::TimeDelta;
 use common::Error;
 use rand::{random, thread_rng, Rng};
 use rand_distr::{Distribution, Normal};
 use sqlx::{MySql, Pool};
 
-use crate::{get_catcoin, grant_catcoin, spend_catcoin};
+use crate::{
+    escrow, get_catcoin,
+    ledger::Reason,
+    odds::{odds, Chance},
+    record_win, Game,
+};
+
+/// how long wagers stay in escrow once a duel starts
+const WAGER_TTL: TimeDelta = TimeDelta::minutes(5);
 
 use emoji::emoji;
 
@@ -21,7 +30,7 @@ pub async fn on_message(ctx: &Context, pool: &Pool<MySql>, msg: &Message) -> Res
         let mut rng = thread_rng();
 
         // should message spawn a duel?
-        if !rng.gen_ratio(1, 2500) {
+        if !odds().roll(Chance::Duel, msg.channel_id, &mut rng) {
             return Ok(());
         }
 
@@ -146,10 +155,11 @@ pub async fn spawn_duel(
         .unwrap()
         .as_secs();
 
-    let mut duel = Duel::new(wager, &uuid.to_string());
+    // also the escrow reference
+    let mut duel = Duel::new(wager, &format!("duel-{uuid}"));
 
-    let defense_uuid = format!("{uuid}-defense");
-    let attack_uuid = format!("{uuid}-attack");
+    let defense_uuid = format!("{}-defense", duel.uuid);
+    let attack_uuid = format!("{}-attack", duel.uuid);
 
     let mut msg = channel
         .send_message(
@@ -191,7 +201,7 @@ pub async fn spawn_duel(
     while let Some(mci) = ComponentInteractionCollector::new(ctx)
         .channel_id(channel)
         .timeout(Duration::from_secs(500))
-        .filter(move |mci| mci.data.custom_id.starts_with(&uuid.to_string()))
+        .filter(move |mci| mci.data.custom_id.starts_with(&format!("duel-{uuid}-")))
         .await
     {
         let user = mci.user.clone();
@@ -221,9 +231,18 @@ pub async fn spawn_duel(
 
         if duel.is_ready() {
             {
-                // try and deduct the catcoin from each user's balance
+                // try and hold the catcoin from each user's balance
                 let mut tx = pool.begin().await?;
-                if !spend_catcoin(&mut *tx, duel.defense.as_ref().unwrap().id, duel.wager).await? {
+                if !escrow::hold(
+                    &mut *tx,
+                    duel.defense.as_ref().unwrap().id,
+                    duel.wager,
+                    Reason::Duel,
+                    &duel.uuid,
+                    WAGER_TTL,
+                )
+                .await?
+                {
                     tx.rollback().await?;
                     msg.channel_id
                         .send_message(
@@ -247,7 +266,16 @@ pub async fn spawn_duel(
                         .await?;
                     continue;
                 }
-                if !spend_catcoin(&mut *tx, duel.attack.as_ref().unwrap().id, duel.wager).await? {
+                if !escrow::hold(
+                    &mut *tx,
+                    duel.attack.as_ref().unwrap().id,
+                    duel.wager,
+                    Reason::Duel,
+                    &duel.uuid,
+                    WAGER_TTL,
+                )
+                .await?
+                {
                     tx.rollback().await?;
                     msg.channel_id
                         .send_message(
@@ -297,6 +325,7 @@ pub async fn spawn_duel(
                 .add_file(CreateAttachment::path("public/catcoinflip.gif").await?),
         )
         .await?;
+    escrow::attach_message(pool, &duel.uuid, msg.channel_id, msg.id).await?;
     let secs = thread_rng().gen_range(2..=5);
     tokio::time::sleep(tokio::time::Duration::from_secs(secs)).await;
 
@@ -316,7 +345,13 @@ pub async fn spawn_duel(
         )
     };
 
-    grant_catcoin(pool, winner.id, duel.wager * 2).await?;
+    if escrow::release(pool, &duel.uuid, winner.id, Reason::Duel).await? == 0 {
+        // refunded from under us
+        return Ok(());
+    }
+    let _ = record_win(pool, winner.id, Game::Duel)
+        .await
+        .inspect_err(|e| log::error!("Could not record duel win: {e}"));
 
     let attachment = CreateAttachment::path(img).await?;
     msg.edit(
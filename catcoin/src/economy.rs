@@ -0,0 +1,322 @@
+use std::ops::Range;
+
+use chrono::{Days, NaiveDate};
+use image::{codecs::png::PngEncoder, ImageEncoder};
+use plotters::{
+    backend::{PixelFormat, RGBPixel},
+    coord::types::RangedDate,
+    prelude::*,
+};
+use sqlx::{MySql, Pool};
+
+use common::Error;
+
+use crate::ledger::Reason;
+
+/// size of the /catcoin economy chart
+const CHART_SIZE: (u32, u32) = (960, 640);
+
+/// The economy at the end of a day
+#[derive(Clone, Debug, sqlx::FromRow)]
+pub struct Snapshot {
+    pub day: NaiveDate,
+    pub supply: i64,
+    pub gini: f64,
+    pub active_wallets: u32,
+    /// catcoin the house paid out that day
+    pub minted: u64,
+    /// catcoin paid into the house that day
+    pub burned: u64,
+}
+
+/// Catcoin minted and burned for one reason
+#[derive(Clone, Debug)]
+pub struct Flow {
+    pub reason: Reason,
+    pub minted: u64,
+    pub burned: u64,
+}
+
+impl Flow {
+    /// how much the reason grew the supply by
+    pub fn net(&self) -> i64 {
+        self.minted as i64 - self.burned as i64
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct FlowRow {
+    reason: String,
+    minted: u64,
+    burned: u64,
+}
+
+/// Gini coefficient of the balances, with debts counted as empty wallets
+pub fn gini(balances: &[i64]) -> f64 {
+    let mut balances: Vec<f64> = balances.iter().map(|b| (*b).max(0) as f64).collect();
+    balances.sort_by(|a, b| a.total_cmp(b));
+    let n = balances.len() as f64;
+    let total: f64 = balances.iter().sum();
+    if total == 0.0 {
+        return 0.0;
+    }
+    let weighted: f64 = balances
+        .iter()
+        .enumerate()
+        .map(|(i, b)| (i + 1) as f64 * b)
+        .sum();
+    2.0 * weighted / (n * total) - (n + 1.0) / n
+}
+
+/// Records the economy for a day that has ended. Safe to run more than once
+pub async fn take_snapshot(pool: &Pool<MySql>, day: NaiveDate) -> Result<(), Error> {
+    let start = day.and_hms_opt(0, 0, 0).ok_or("Bad snapshot day")?;
+    let end = start
+        .checked_add_days(Days::new(1))
+        .ok_or("Bad snapshot day")?;
+
+    let balances: Vec<i64> = sqlx::query_scalar!("SELECT `catcoin` FROM `catcoin`")
+        .fetch_all(pool)
+        .await?;
+    let supply: i64 = balances.iter().sum();
+    let active_wallets = sqlx::query_scalar!(
+        r#"
+		SELECT COUNT(DISTINCT `uid`) FROM (
+			SELECT `from_uid` AS `uid` FROM `catcoin_ledger` WHERE `created_at` >= ? AND `created_at` < ?
+			UNION ALL
+			SELECT `to_uid` AS `uid` FROM `catcoin_ledger` WHERE `created_at` >= ? AND `created_at` < ?
+		) `w`
+	"#,
+        start,
+        end,
+        start,
+        end
+    )
+    .fetch_one(pool)
+    .await?;
+    let flows: Vec<FlowRow> = sqlx::query_as(
+        r#"
+		SELECT `reason`,
+			CAST(SUM(IF(`from_uid` IS NULL, `amount`, 0)) AS UNSIGNED) AS `minted`,
+			CAST(SUM(IF(`to_uid` IS NULL, `amount`, 0)) AS UNSIGNED) AS `burned`
+		FROM `catcoin_ledger`
+		WHERE `created_at` >= ? AND `created_at` < ? AND (`from_uid` IS NULL OR `to_uid` IS NULL)
+			-- escrow only holds catcoin and refunds give back what was already burned
+			AND NOT `escrow` AND `reason` <> 'Refund'
+		GROUP BY `reason`
+	"#,
+    )
+    .bind(start)
+    .bind(end)
+    .fetch_all(pool)
+    .await?;
+
+    let mut tx = pool.begin().await?;
+    sqlx::query!(
+        r#"
+		INSERT INTO `catcoin_economy_snapshot` (`day`, `supply`, `gini`, `active_wallets`) VALUES (?, ?, ?, ?)
+		ON DUPLICATE KEY UPDATE `supply` = ?, `gini` = ?, `active_wallets` = ?
+	"#,
+        day,
+        supply,
+        gini(&balances),
+        active_wallets,
+        supply,
+        gini(&balances),
+        active_wallets
+    )
+    .execute(&mut *tx)
+    .await?;
+    for flow in flows {
+        sqlx::query!(
+            r#"
+			INSERT INTO `catcoin_economy_flow` (`day`, `reason`, `minted`, `burned`) VALUES (?, ?, ?, ?)
+			ON DUPLICATE KEY UPDATE `minted` = ?, `burned` = ?
+		"#,
+            day,
+            flow.reason,
+            flow.minted,
+            flow.burned,
+            flow.minted,
+            flow.burned
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Snapshots from the last `days` days, oldest first
+pub async fn get_snapshots(pool: &Pool<MySql>, days: u32) -> Result<Vec<Snapshot>, Error> {
+    Ok(sqlx::query_as(
+        r#"
+		SELECT s.`day`, s.`supply`, s.`gini`, s.`active_wallets`,
+			CAST(COALESCE(SUM(f.`minted`), 0) AS UNSIGNED) AS `minted`,
+			CAST(COALESCE(SUM(f.`burned`), 0) AS UNSIGNED) AS `burned`
+		FROM `catcoin_economy_snapshot` s
+		LEFT JOIN `catcoin_economy_flow` f ON f.`day` = s.`day`
+		WHERE s.`day` >= DATE_SUB(CURDATE(), INTERVAL ? DAY)
+		GROUP BY s.`day`
+		ORDER BY s.`day` ASC
+	"#,
+    )
+    .bind(days)
+    .fetch_all(pool)
+    .await?)
+}
+
+/// Catcoin minted and burned per reason over the last `days` days, biggest movers first
+pub async fn get_flows(pool: &Pool<MySql>, days: u32) -> Result<Vec<Flow>, Error> {
+    let rows: Vec<FlowRow> = sqlx::query_as(
+        r#"
+		SELECT `reason`,
+			CAST(SUM(`minted`) AS UNSIGNED) AS `minted`,
+			CAST(SUM(`burned`) AS UNSIGNED) AS `burned`
+		FROM `catcoin_economy_flow`
+		WHERE `day` >= DATE_SUB(CURDATE(), INTERVAL ? DAY)
+		GROUP BY `reason`
+	"#,
+    )
+    .bind(days)
+    .fetch_all(pool)
+    .await?;
+    let mut flows: Vec<Flow> = rows
+        .into_iter()
+        .filter_map(|r| {
+            Some(Flow {
+                reason: r.reason.parse().ok()?,
+                minted: r.minted,
+                burned: r.burned,
+            })
+        })
+        .collect();
+    flows.sort_by_key(|f| std::cmp::Reverse(f.net().abs()));
+    Ok(flows)
+}
+
+/// a value range with some headroom, never empty
+fn value_range(values: impl Iterator<Item = f64> + Clone) -> Range<f64> {
+    let min = values.clone().fold(f64::INFINITY, f64::min);
+    let max = values.fold(f64::NEG_INFINITY, f64::max);
+    if !min.is_finite() || !max.is_finite() {
+        return 0.0..1.0;
+    }
+    let pad = ((max - min) * 0.1).max(1.0);
+    min - pad..max + pad
+}
+
+/// Draws supply, minted vs burned, gini and active wallets over time as a png
+pub fn draw_economy_chart(snapshots: &[Snapshot]) -> Result<Vec<u8>, Error> {
+    let (first, last) = match (snapshots.first(), snapshots.last()) {
+        (Some(first), Some(last)) => (first.day, last.day),
+        _ => return Err("No snapshots to draw.".into()),
+    };
+    let last = last.max(first + Days::new(1));
+
+    let (width, height) = CHART_SIZE;
+    let mut buf = vec![0; (width * height) as usize * RGBPixel::PIXEL_SIZE];
+    {
+        let root =
+            BitMapBackend::<RGBPixel>::with_buffer_and_format(buf.as_mut_slice(), CHART_SIZE)?
+                .into_drawing_area();
+        root.fill(&RGBColor(0x2F, 0x31, 0x36))?;
+
+        let title_style = TextStyle::from(("sans-serif", 20)).with_color(WHITE);
+        let label_style = ("sans-serif", 12).with_color(WHITE);
+
+        let panels = root.split_evenly((2, 2));
+        let charts: [(&str, Vec<(&str, RGBColor, Vec<f64>)>); 4] = [
+            (
+                "Supply",
+                vec![(
+                    "supply",
+                    YELLOW,
+                    snapshots.iter().map(|s| s.supply as f64).collect(),
+                )],
+            ),
+            (
+                "Minted / Burned",
+                vec![
+                    (
+                        "minted",
+                        GREEN,
+                        snapshots.iter().map(|s| s.minted as f64).collect(),
+                    ),
+                    (
+                        "burned",
+                        RED,
+                        snapshots.iter().map(|s| s.burned as f64).collect(),
+                    ),
+                ],
+            ),
+            (
+                "Gini",
+                vec![("gini", MAGENTA, snapshots.iter().map(|s| s.gini).collect())],
+            ),
+            (
+                "Active wallets",
+                vec![(
+                    "active",
+                    CYAN,
+                    snapshots.iter().map(|s| s.active_wallets as f64).collect(),
+                )],
+            ),
+        ];
+
+        for (panel, (title, series)) in panels.iter().zip(charts) {
+            let dates: RangedDate<NaiveDate> = (first..last).into();
+            let values = value_range(series.iter().flat_map(|(_, _, v)| v.iter().copied()));
+            let mut chart = ChartBuilder::on(panel)
+                .margin(16)
+                .caption(title, title_style.clone())
+                .x_label_area_size(24)
+                .y_label_area_size(48)
+                .build_cartesian_2d(dates, values)?;
+            chart
+                .configure_mesh()
+                .x_labels(5)
+                .y_labels(5)
+                .x_label_formatter(&|date| format!("{}", date.format("%m-%d")))
+                .label_style(label_style.clone())
+                .draw()?;
+            for (label, color, values) in series {
+                chart
+                    .draw_series(LineSeries::new(
+                        snapshots.iter().zip(values).map(|(s, v)| (s.day, v)),
+                        color.stroke_width(2),
+                    ))?
+                    .label(label)
+                    .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 16, y)], color));
+            }
+            chart
+                .configure_series_labels()
+                .label_font(label_style.clone())
+                .background_style(RGBColor(0x2F, 0x31, 0x36))
+                .border_style(WHITE)
+                .draw()?;
+        }
+
+        root.present()?;
+    }
+    let mut png = vec![];
+    PngEncoder::new(&mut png).write_image(&buf, width, height, image::ExtendedColorType::Rgb8)?;
+    Ok(png)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gini() {
+        assert_eq!(gini(&[]), 0.0);
+        assert_eq!(gini(&[0, 0, 0]), 0.0);
+        assert_eq!(gini(&[100, 100, 100, 100]), 0.0);
+        // one wallet holds everything
+        assert!((gini(&[0, 0, 0, 100]) - 0.75).abs() < 1e-9);
+        assert!((gini(&[1, 2, 3, 4]) - 0.25).abs() < 1e-9);
+        // debts count as empty wallets, and order doesn't matter
+        assert_eq!(gini(&[100, -50, 0, 0]), gini(&[0, 0, 0, 100]));
+    }
+}
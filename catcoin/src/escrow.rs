@@ -0,0 +1,268 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use chrono::{DateTime, TimeDelta, Utc};
+use emoji::emoji;
+use poise::serenity_prelude::{self as serenity, ChannelId, EditMessage, Mentionable, MessageId};
+use sqlx::{Acquire, MySql, Pool};
+
+use common::Error;
+
+use crate::{credit, debit, ledger::Reason};
+
+/// how often expired escrows are refunded
+const REFUND_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Catcoin taken from a user and held for a game or drop
+#[derive(Clone, Debug)]
+pub struct Held {
+    pub uid: serenity::UserId,
+    pub amount: u64,
+    pub reference: String,
+    pub channel_id: Option<ChannelId>,
+    pub message_id: Option<MessageId>,
+}
+
+/// takes catcoin from the user and holds it under `reference` until it's released or refunded.
+/// returns false if the user can't afford it. unreleased catcoin is refunded after `ttl`
+pub async fn hold<'c>(
+    conn: impl Acquire<'c, Database = MySql>,
+    uid: serenity::UserId,
+    amount: u64,
+    reason: Reason,
+    reference: &str,
+    ttl: TimeDelta,
+) -> Result<bool, Error> {
+    hold_inner(conn, uid, amount, reason, reference, ttl, false).await
+}
+
+/// like [`hold`], but isn't refunded when the bot restarts
+pub async fn hold_durable<'c>(
+    conn: impl Acquire<'c, Database = MySql>,
+    uid: serenity::UserId,
+    amount: u64,
+    reason: Reason,
+    reference: &str,
+    ttl: TimeDelta,
+) -> Result<bool, Error> {
+    hold_inner(conn, uid, amount, reason, reference, ttl, true).await
+}
+
+async fn hold_inner<'c>(
+    conn: impl Acquire<'c, Database = MySql>,
+    uid: serenity::UserId,
+    amount: u64,
+    reason: Reason,
+    reference: &str,
+    ttl: TimeDelta,
+    durable: bool,
+) -> Result<bool, Error> {
+    let mut tx = conn.begin().await?;
+    if !debit(
+        &mut *tx,
+        uid,
+        amount,
+        reason,
+        Some(reference.to_owned()),
+        true,
+    )
+    .await?
+    {
+        return Ok(false);
+    }
+    sqlx::query!(
+        "INSERT INTO `catcoin_escrow` (`uid`, `amount`, `reason`, `reference`, `expires_at`, `durable`) VALUES (?, ?, ?, ?, ?, ?)",
+        uid.get(),
+        amount,
+        reason.as_str(),
+        reference,
+        Utc::now() + ttl,
+        durable
+    )
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    Ok(true)
+}
+
+/// records the message showing the held catcoin, so it can be updated if it's refunded
+pub async fn attach_message(
+    pool: &Pool<MySql>,
+    reference: &str,
+    channel_id: ChannelId,
+    message_id: MessageId,
+) -> Result<(), Error> {
+    sqlx::query!(
+        "UPDATE `catcoin_escrow` SET `channel_id` = ?, `message_id` = ? WHERE `reference` = ?",
+        channel_id.get(),
+        message_id.get(),
+        reference
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// takes everything held under `reference` out of escrow
+async fn take(tx: &mut sqlx::MySqlConnection, reference: &str) -> Result<Vec<Held>, Error> {
+    let rows = sqlx::query!(
+        "SELECT `uid`, `amount`, `channel_id`, `message_id` FROM `catcoin_escrow` WHERE `reference` = ? FOR UPDATE",
+        reference
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+    sqlx::query!(
+        "DELETE FROM `catcoin_escrow` WHERE `reference` = ?",
+        reference
+    )
+    .execute(&mut *tx)
+    .await?;
+    Ok(rows
+        .into_iter()
+        .filter_map(|r| {
+            Some(Held {
+                uid: r.uid.parse().ok()?,
+                amount: r.amount,
+                reference: reference.to_owned(),
+                channel_id: r.channel_id.and_then(|c| c.parse().ok()),
+                message_id: r.message_id.and_then(|m| m.parse().ok()),
+            })
+        })
+        .collect())
+}
+
+/// pays everything held under `reference` to the winner, returning the amount.
+/// 0 if there was nothing held, e.g. it already expired
+pub async fn release<'c>(
+    conn: impl Acquire<'c, Database = MySql>,
+    reference: &str,
+    to: serenity::UserId,
+    reason: Reason,
+) -> Result<u64, Error> {
+    let mut tx = conn.begin().await?;
+    let amount: u64 = take(&mut *tx, reference)
+        .await?
+        .iter()
+        .map(|h| h.amount)
+        .sum();
+    if amount > 0 {
+        credit(
+            &mut *tx,
+            to,
+            amount,
+            reason,
+            Some(reference.to_owned()),
+            true,
+        )
+        .await?;
+    }
+    tx.commit().await?;
+    Ok(amount)
+}
+
+/// returns everything held under `reference` to whoever it was taken from
+pub async fn refund<'c>(
+    conn: impl Acquire<'c, Database = MySql>,
+    reference: &str,
+) -> Result<Vec<Held>, Error> {
+    let mut tx = conn.begin().await?;
+    let held = take(&mut *tx, reference).await?;
+    for h in &held {
+        credit(
+            &mut *tx,
+            h.uid,
+            h.amount,
+            Reason::Refund,
+            Some(reference.to_owned()),
+            true,
+        )
+        .await?;
+    }
+    tx.commit().await?;
+    Ok(held)
+}
+
+/// refunds every escrow that has expired
+pub async fn refund_expired(pool: &Pool<MySql>) -> Result<Vec<Held>, Error> {
+    let references = sqlx::query_scalar!(
+        "SELECT DISTINCT `reference` FROM `catcoin_escrow` WHERE `expires_at` < ?",
+        Utc::now()
+    )
+    .fetch_all(pool)
+    .await?;
+    refund_all(pool, references).await
+}
+
+/// refunds every non-durable escrow created before the given time
+async fn refund_created_before(
+    pool: &Pool<MySql>,
+    before: DateTime<Utc>,
+) -> Result<Vec<Held>, Error> {
+    let references = sqlx::query_scalar!(
+        "SELECT DISTINCT `reference` FROM `catcoin_escrow` WHERE `created_at` < ? AND NOT `durable`",
+        before
+    )
+    .fetch_all(pool)
+    .await?;
+    refund_all(pool, references).await
+}
+
+async fn refund_all(pool: &Pool<MySql>, references: Vec<String>) -> Result<Vec<Held>, Error> {
+    let mut refunded = vec![];
+    for reference in references {
+        refunded.extend(refund(pool, &reference).await?);
+    }
+    Ok(refunded)
+}
+
+/// lets users know their catcoin came back, on the message that held it
+async fn announce_refunds(http: &serenity::Http, refunded: Vec<Held>, why: &str) {
+    let mut messages: HashMap<(ChannelId, MessageId), Vec<Held>> = HashMap::new();
+    for held in refunded {
+        if let (Some(channel), Some(message)) = (held.channel_id, held.message_id) {
+            messages.entry((channel, message)).or_default().push(held);
+        }
+    }
+    for ((channel, message), held) in messages {
+        let lines: Vec<String> = held
+            .iter()
+            .map(|h| format!("{} **+{}** {}", h.uid.mention(), h.amount, emoji("catcoin")))
+            .collect();
+        let _ = channel
+            .edit_message(
+                http,
+                message,
+                EditMessage::new()
+                    .content(format!("{why}, refunded:\n{}", lines.join("\n")))
+                    .embeds(vec![])
+                    .components(vec![]),
+            )
+            .await
+            .inspect_err(|e| log::error!("Could not announce escrow refund: {e}"));
+    }
+}
+
+/// refunds everything left in escrow by the last run, then refunds escrows as they expire
+pub fn spawn_escrow_thread(pool: Pool<MySql>, http: Arc<serenity::Http>) {
+    let started = Utc::now();
+    tokio::spawn(async move {
+        // games in progress don't survive a restart
+        match refund_created_before(&pool, started).await {
+            Ok(refunded) => {
+                if !refunded.is_empty() {
+                    log::info!("Refunded {} escrows left from the last run", refunded.len());
+                }
+                announce_refunds(&http, refunded, "The bot restarted").await;
+            }
+            Err(e) => log::error!("Could not refund escrow on startup: {e}"),
+        }
+
+        let mut interval = tokio::time::interval(REFUND_INTERVAL);
+        loop {
+            interval.tick().await;
+            match refund_expired(&pool).await {
+                Ok(refunded) => announce_refunds(&http, refunded, "This expired").await,
+                Err(e) => log::error!("Could not refund expired escrow: {e}"),
+            }
+        }
+    });
+}
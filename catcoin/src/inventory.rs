@@ -5,7 +5,7 @@ use poise::serenity_prelude::{
 use regex::Regex;
 use sqlx::{MySql, Pool};
 
-use crate::random_pulls::Rarity;
+use crate::{random_pulls::Rarity, thumbnails::render_gallery};
 use common::Error;
 
 use emoji::emoji;
@@ -107,6 +107,14 @@ impl PaginatedInventory {
             .description(format!("{}", pulls.join("\n"))))
     }
 
+    /// the page's pulls as a gallery image. None if the page is empty
+    pub async fn render(&self) -> Result<Option<Vec<u8>>, Error> {
+        if self.pulls.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(render_gallery(&self.pulls).await?))
+    }
+
     pub async fn get(pool: &Pool<MySql>, uid: serenity::UserId) -> Result<Self, Error> {
         let res: Vec<_> = sqlx::query!(
             r#"
@@ -206,7 +214,7 @@ pub async fn get_reward_by_name(
     let res: Option<Reward> = sqlx::query_as!(
         Reward,
         r#"
-		SELECT * FROM `catcoin_reward`
+		SELECT `id`, `name`, `file`, `rarity` FROM `catcoin_reward`
 		WHERE `name` = ?
 	"#,
         reward_name
@@ -255,7 +263,7 @@ pub async fn add_to_inventory(
     number: i32,
     catcoin: u64,
 ) -> Result<(), Error> {
-    sqlx::query!(
+    let id = sqlx::query!(
         r#"
 		INSERT INTO `catcoin_inv` (`uid`, `rid`, `number`, `catcoin`)
 		VALUES (?, ?, ?, ?)
@@ -266,6 +274,63 @@ pub async fn add_to_inventory(
         catcoin
     )
     .execute(pool)
+    .await?
+    .last_insert_id();
+    // only the very first pull of a reward sticks
+    sqlx::query!(
+        r#"
+		INSERT IGNORE INTO `catcoin_first_pull` (`rid`, `uid`, `inv_id`, `created_at`)
+		VALUES (?, ?, ?, CURRENT_TIMESTAMP)
+	"#,
+        reward_id,
+        uid.get(),
+        id
+    )
+    .execute(pool)
     .await?;
     Ok(())
 }
+
+/// A user's pulls whose name matches `partial`, rarest first. for autocompletes
+pub async fn search_inventory(
+    pool: &Pool<MySql>,
+    uid: serenity::UserId,
+    partial: &str,
+) -> Result<Vec<CatcoinPull>, Error> {
+    let res: Vec<_> = sqlx::query!(
+        r#"
+		SELECT 	i.id, i.uid, i.number, i.created_at, i.updated_at, i.catcoin,
+				r.id as rid,
+				r.name, r.file, r.rarity
+		FROM `catcoin_inv` i
+		INNER JOIN `catcoin_reward` r ON i.rid = r.id
+		WHERE i.uid = ? AND r.name LIKE CONCAT('%', ?, '%')
+		ORDER BY r.rarity DESC, i.id DESC
+		LIMIT 25
+	"#,
+        uid.get(),
+        partial
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(res
+        .into_iter()
+        .map(|r| CatcoinPull {
+            id: r.id,
+            uid: r
+                .uid
+                .parse()
+                .expect("Invalid UID format in catcoin inventory."),
+            number: r.number,
+            catcoin: r.catcoin,
+            reward: Reward {
+                id: r.rid,
+                name: r.name,
+                file: r.file,
+                rarity: r.rarity.into(),
+            },
+            created_at: r.created_at,
+            updated_at: r.updated_at,
+        })
+        .collect())
+}
@@ -0,0 +1,237 @@
+use std::str::FromStr;
+
+use chrono::{DateTime, Utc};
+use emoji::emoji;
+use poise::serenity_prelude::{self as serenity, Mentionable};
+use sqlx::{MySql, MySqlConnection, Pool};
+
+use common::Error;
+
+/// Why catcoin changed hands
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Reason {
+    /// balances from before the ledger existed
+    Opening,
+    Pull,
+    Trip,
+    Drop,
+    Duel,
+    Poker,
+    Bet,
+    Stocks,
+    Transfer,
+    YapAward,
+    Achievement,
+    Refund,
+    Shop,
+    Trade,
+    Auction,
+    SetBonus,
+}
+
+impl Reason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Reason::Opening => "Opening",
+            Reason::Pull => "Pull",
+            Reason::Trip => "Trip",
+            Reason::Drop => "Drop",
+            Reason::Duel => "Duel",
+            Reason::Poker => "Poker",
+            Reason::Bet => "Bet",
+            Reason::Stocks => "Stocks",
+            Reason::Transfer => "Transfer",
+            Reason::YapAward => "YapAward",
+            Reason::Achievement => "Achievement",
+            Reason::Refund => "Refund",
+            Reason::Shop => "Shop",
+            Reason::Trade => "Trade",
+            Reason::Auction => "Auction",
+            Reason::SetBonus => "SetBonus",
+        }
+    }
+
+    /// a short description for discord
+    pub fn describe(&self) -> &'static str {
+        match self {
+            Reason::Opening => "opening balance",
+            Reason::Pull => "pull",
+            Reason::Trip => "trip",
+            Reason::Drop => "drop",
+            Reason::Duel => "duel",
+            Reason::Poker => "poker",
+            Reason::Bet => "bet",
+            Reason::Stocks => "stocks",
+            Reason::Transfer => "transfer",
+            Reason::YapAward => "yap award",
+            Reason::Achievement => "achievement",
+            Reason::Refund => "refund",
+            Reason::Shop => "shop",
+            Reason::Trade => "trade",
+            Reason::Auction => "auction",
+            Reason::SetBonus => "set bonus",
+        }
+    }
+}
+
+impl FromStr for Reason {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Opening" => Ok(Reason::Opening),
+            "Pull" => Ok(Reason::Pull),
+            "Trip" => Ok(Reason::Trip),
+            "Drop" => Ok(Reason::Drop),
+            "Duel" => Ok(Reason::Duel),
+            "Poker" => Ok(Reason::Poker),
+            "Bet" => Ok(Reason::Bet),
+            "Stocks" => Ok(Reason::Stocks),
+            "Transfer" => Ok(Reason::Transfer),
+            "YapAward" => Ok(Reason::YapAward),
+            "Achievement" => Ok(Reason::Achievement),
+            "Refund" => Ok(Reason::Refund),
+            "Shop" => Ok(Reason::Shop),
+            "Trade" => Ok(Reason::Trade),
+            "Auction" => Ok(Reason::Auction),
+            "SetBonus" => Ok(Reason::SetBonus),
+            _ => Err(format!("Unknown ledger reason {s}").into()),
+        }
+    }
+}
+
+/// A single movement of catcoin. `None` is the house, which mints and burns catcoin
+#[derive(Clone, Debug)]
+pub struct LedgerEntry {
+    pub id: u64,
+    pub from: Option<serenity::UserId>,
+    pub to: Option<serenity::UserId>,
+    pub amount: u64,
+    pub reason: Reason,
+    pub reference: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl LedgerEntry {
+    /// a one line summary from the perspective of the given user
+    pub fn line(&self, uid: serenity::UserId) -> String {
+        let incoming = self.to == Some(uid);
+        let other = if incoming { self.from } else { self.to };
+        let counterparty = other
+            .map(|u| format!(" {} {}", if incoming { "from" } else { "to" }, u.mention()))
+            .unwrap_or_default();
+        format!(
+            "<t:{}:d> **{}{}** {} {}{}",
+            self.created_at.timestamp(),
+            if incoming { "+" } else { "-" },
+            self.amount,
+            emoji("catcoin"),
+            self.reason.describe(),
+            counterparty
+        )
+    }
+}
+
+/// writes a ledger entry. should be called in the same transaction as the balance change.
+/// `escrow` entries only move catcoin in or out of escrow
+pub(crate) async fn record(
+    conn: &mut MySqlConnection,
+    from: Option<serenity::UserId>,
+    to: Option<serenity::UserId>,
+    amount: u64,
+    reason: Reason,
+    reference: Option<String>,
+    escrow: bool,
+) -> Result<(), Error> {
+    sqlx::query!(
+        "INSERT INTO `catcoin_ledger` (`from_uid`, `to_uid`, `amount`, `reason`, `reference`, `escrow`) VALUES (?, ?, ?, ?, ?, ?)",
+        from.map(|u| u.get()),
+        to.map(|u| u.get()),
+        amount,
+        reason.as_str(),
+        reference,
+        escrow
+    )
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+/// how many ledger entries involve the user
+pub async fn count_history(pool: &Pool<MySql>, uid: serenity::UserId) -> Result<i64, Error> {
+    Ok(sqlx::query_scalar!(
+        "SELECT COUNT(*) FROM `catcoin_ledger` WHERE `from_uid` = ? OR `to_uid` = ?",
+        uid.get(),
+        uid.get()
+    )
+    .fetch_one(pool)
+    .await?)
+}
+
+/// the user's ledger entries, newest first
+pub async fn get_history(
+    pool: &Pool<MySql>,
+    uid: serenity::UserId,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<LedgerEntry>, Error> {
+    let rows = sqlx::query!(
+        r#"
+		SELECT `id`, `from_uid`, `to_uid`, `amount`, `reason`, `reference`, `created_at` FROM `catcoin_ledger`
+		WHERE `from_uid` = ? OR `to_uid` = ?
+		ORDER BY `id` DESC LIMIT ? OFFSET ?
+	"#,
+        uid.get(),
+        uid.get(),
+        limit,
+        offset
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(rows
+        .into_iter()
+        .filter_map(|r| {
+            Some(LedgerEntry {
+                id: r.id,
+                from: r.from_uid.and_then(|u| u.parse().ok()),
+                to: r.to_uid.and_then(|u| u.parse().ok()),
+                amount: r.amount,
+                reason: r.reason.parse().ok()?,
+                reference: r.reference,
+                created_at: r.created_at,
+            })
+        })
+        .collect())
+}
+
+/// A wallet whose balance doesn't match its ledger entries
+#[derive(Clone, Debug, sqlx::FromRow)]
+pub struct Discrepancy {
+    pub uid: String,
+    pub balance: i64,
+    pub ledger: i64,
+}
+
+/// every wallet whose balance differs from the sum of its ledger entries
+pub async fn reconcile(pool: &Pool<MySql>) -> Result<Vec<Discrepancy>, Error> {
+    Ok(sqlx::query_as(
+        r#"
+		WITH `net` AS (
+			SELECT `uid`, CAST(SUM(`delta`) AS SIGNED) AS `ledger` FROM (
+				SELECT `to_uid` AS `uid`, CAST(`amount` AS SIGNED) AS `delta` FROM `catcoin_ledger` WHERE `to_uid` IS NOT NULL
+				UNION ALL
+				SELECT `from_uid` AS `uid`, -CAST(`amount` AS SIGNED) AS `delta` FROM `catcoin_ledger` WHERE `from_uid` IS NOT NULL
+			) `d` GROUP BY `uid`
+		)
+		SELECT w.`uid`, w.`catcoin` AS `balance`, COALESCE(n.`ledger`, 0) AS `ledger`
+		FROM `catcoin` w LEFT JOIN `net` n ON n.`uid` = w.`uid`
+		WHERE w.`catcoin` <> COALESCE(n.`ledger`, 0)
+		UNION ALL
+		SELECT n.`uid`, 0 AS `balance`, n.`ledger`
+		FROM `net` n LEFT JOIN `catcoin` w ON w.`uid` = n.`uid`
+		WHERE w.`uid` IS NULL AND n.`ledger` <> 0
+	"#,
+    )
+    .fetch_all(pool)
+    .await?)
+}
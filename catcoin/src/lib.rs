@@ -1,26 +1,51 @@
+pub mod auction;
+pub mod collection;
 pub mod drops;
 pub mod duels;
+pub mod economy;
+pub mod escrow;
 pub mod inventory;
+pub mod ledger;
+pub mod odds;
 pub mod random_pulls;
+pub mod rewards;
+pub mod shop;
+pub mod thumbnails;
+pub mod trade;
 
-use std::{collections::HashMap, sync::OnceLock};
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
 
-use common::{util::LeakyBucket, Error};
+use common::{metrics, util::LeakyBucket, Error};
 use poise::serenity_prelude::{self as serenity, UserId};
 
+use ledger::Reason;
 use random_pulls::Reward;
-use sqlx::{self, Executor, MySql, Pool};
+use sqlx::{self, Acquire, MySql, MySqlConnection, Pool};
 
-static DROPS: OnceLock<Vec<Reward>> = OnceLock::new();
+static DROPS: RwLock<Option<Arc<Vec<Reward>>>> = RwLock::new(None);
 /// Fetch all possible drops
-pub fn drops() -> &'static Vec<Reward> {
-    DROPS.get().unwrap()
+pub fn drops() -> Arc<Vec<Reward>> {
+    DROPS
+        .read()
+        .unwrap()
+        .clone()
+        .expect("Catcoin drops haven't been loaded.")
+}
+
+/// Reloads the drop table, e.g. after a reward is added
+pub async fn reload_drops(pool: &Pool<MySql>) -> Result<(), Error> {
+    let drops = get_drops(pool).await?;
+    *DROPS.write().unwrap() = Some(Arc::new(drops));
+    Ok(())
 }
 
 /// Call to load all cached catcoin data.
 pub async fn init(pool: &Pool<MySql>) -> Result<(), Error> {
-    let drops = get_drops(pool).await?;
-    DROPS.get_or_init(move || drops);
+    reload_drops(pool).await?;
+    odds::reload_odds(pool).await?;
     Ok(())
 }
 
@@ -83,31 +108,65 @@ pub async fn get_top(pool: &Pool<MySql>) -> Result<Vec<CatcoinWallet>, Error> {
     Ok(record)
 }
 
-/// Grab all catcoin rewards
+/// Grab all catcoin rewards that can still be pulled
 pub async fn get_drops(pool: &Pool<MySql>) -> Result<Vec<Reward>, Error> {
-    let rewards: Vec<Reward> = sqlx::query_as!(Reward, "SELECT * FROM `catcoin_reward`")
-        .fetch_all(pool)
-        .await?;
+    let rewards: Vec<Reward> = sqlx::query_as!(
+        Reward,
+        "SELECT `id`, `name`, `file`, `rarity` FROM `catcoin_reward` WHERE NOT `retired`"
+    )
+    .fetch_all(pool)
+    .await?;
     Ok(rewards)
 }
 
 /// Try taking `amount` catcoin from the user's wallet. return false if not enough funds.
-pub async fn spend_catcoin(
-    pool: impl Executor<'_, Database = MySql>,
+pub async fn spend_catcoin<'c>(
+    conn: impl Acquire<'c, Database = MySql>,
     from: serenity::UserId,
     amount: u64,
+    reason: Reason,
+    reference: Option<String>,
 ) -> Result<bool, Error> {
+    let spent = debit(conn, from, amount, reason, reference, false).await?;
+    if spent {
+        metrics::CATCOIN_SPENT.add(&[], amount as f64);
+    }
+    Ok(spent)
+}
+
+/// takes catcoin from a wallet without counting it as spent. `escrow` marks catcoin that's only
+/// held, so the ledger doesn't count it as burned either
+pub(crate) async fn debit<'c>(
+    conn: impl Acquire<'c, Database = MySql>,
+    from: serenity::UserId,
+    amount: u64,
+    reason: Reason,
+    reference: Option<String>,
+    escrow: bool,
+) -> Result<bool, Error> {
+    let mut tx = conn.begin().await?;
     let rc = sqlx::query!(
         "UPDATE `catcoin` SET `catcoin` = `catcoin` - ? WHERE `uid` = ? AND `catcoin` >= ?",
         amount,
         from.get(),
         amount
     )
-    .execute(pool)
+    .execute(&mut *tx)
     .await?;
     if rc.rows_affected() == 0 {
         return Ok(false);
     }
+    ledger::record(
+        &mut *tx,
+        Some(from),
+        None,
+        amount,
+        reason,
+        reference,
+        escrow,
+    )
+    .await?;
+    tx.commit().await?;
     Ok(true)
 }
 
@@ -135,11 +194,60 @@ pub async fn transact(
         "INSERT INTO `catcoin` (`uid`, `catcoin`) VALUES (?, ?) ON DUPLICATE KEY UPDATE `catcoin` = `catcoin` + ?", to.get(), amount, amount)
         .execute(&mut *tx)
         .await?;
+    // negative payments move catcoin the other way
+    let (from, to) = if amount >= 0 { (from, to) } else { (to, from) };
+    ledger::record(
+        &mut *tx,
+        Some(from),
+        Some(to),
+        amount.unsigned_abs(),
+        Reason::Transfer,
+        None,
+        false,
+    )
+    .await?;
     tx.commit().await?;
 
     Ok(true)
 }
 
+/// Move catcoin between wallets inside an existing transaction. returns false if `from` can't afford it.
+pub(crate) async fn transfer(
+    conn: &mut MySqlConnection,
+    from: serenity::UserId,
+    to: serenity::UserId,
+    amount: u64,
+    reason: Reason,
+    reference: Option<String>,
+) -> Result<bool, Error> {
+    let rc = sqlx::query!(
+        "UPDATE `catcoin` SET `catcoin` = `catcoin` - ? WHERE `uid` = ? AND `catcoin` >= ?",
+        amount,
+        from.get(),
+        amount
+    )
+    .execute(&mut *conn)
+    .await?;
+    if rc.rows_affected() == 0 {
+        return Ok(false);
+    }
+    sqlx::query!(
+        "INSERT INTO `catcoin` (`uid`, `catcoin`) VALUES (?, ?) ON DUPLICATE KEY UPDATE `catcoin` = `catcoin` + ?", to.get(), amount, amount)
+        .execute(&mut *conn)
+        .await?;
+    ledger::record(
+        &mut *conn,
+        Some(from),
+        Some(to),
+        amount,
+        reason,
+        reference,
+        false,
+    )
+    .await?;
+    Ok(true)
+}
+
 /// Add 1 to the amount of times this reward has been pulled
 pub async fn increment_and_get_pulls(pool: &Pool<MySql>, reward_id: i32) -> Result<i32, Error> {
     sqlx::query!(
@@ -163,14 +271,78 @@ pub async fn increment_and_get_pulls(pool: &Pool<MySql>, reward_id: i32) -> Resu
 }
 
 /// grant a user catcoin
-pub async fn grant_catcoin(
-    pool: impl Executor<'_, Database = MySql>,
+pub async fn grant_catcoin<'c>(
+    conn: impl Acquire<'c, Database = MySql>,
     uid: serenity::UserId,
     catcoin: u64,
+    reason: Reason,
+    reference: Option<String>,
 ) -> Result<(), Error> {
+    credit(conn, uid, catcoin, reason, reference, false).await?;
+    // refunds give back catcoin that was already counted as spent
+    if reason != Reason::Refund {
+        metrics::CATCOIN_MINTED.add(&[], catcoin as f64);
+    }
+
+    Ok(())
+}
+
+/// gives catcoin to a wallet without counting it as minted. `escrow` marks catcoin paid back out
+/// of escrow, so the ledger doesn't count it as minted either
+pub(crate) async fn credit<'c>(
+    conn: impl Acquire<'c, Database = MySql>,
+    uid: serenity::UserId,
+    catcoin: u64,
+    reason: Reason,
+    reference: Option<String>,
+    escrow: bool,
+) -> Result<(), Error> {
+    let mut tx = conn.begin().await?;
     sqlx::query!(r#"INSERT INTO `catcoin` (`uid`, `catcoin`) VALUES (?, ?) ON DUPLICATE KEY UPDATE `catcoin` = `catcoin` + ?"#, uid.get(), catcoin, catcoin)
-        .execute(pool)
+        .execute(&mut *tx)
         .await?;
+    ledger::record(
+        &mut *tx,
+        None,
+        Some(uid),
+        catcoin,
+        reason,
+        reference,
+        escrow,
+    )
+    .await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Games where one user beats another
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Game {
+    Duel,
+    Poker,
+}
+
+impl Game {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Game::Duel => "duel",
+            Game::Poker => "poker",
+        }
+    }
+}
 
+/// Count a win towards the user's lifetime wins in a game
+pub async fn record_win(
+    pool: &Pool<MySql>,
+    uid: serenity::UserId,
+    game: Game,
+) -> Result<(), Error> {
+    sqlx::query!(
+        r#"INSERT INTO `game_wins` (`uid`, `game`, `wins`) VALUES (?, ?, 1) ON DUPLICATE KEY UPDATE `wins` = `wins` + 1"#,
+        uid.get(),
+        game.as_str()
+    )
+    .execute(pool)
+    .await?;
     Ok(())
 }
@@ -0,0 +1,458 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+
+use chrono::{DateTime, Utc};
+use poise::serenity_prelude::{self as serenity, ChannelId};
+use rand::prelude::*;
+use rand_distr::Normal;
+use sqlx::{MySql, Pool};
+
+use common::Error;
+
+use crate::random_pulls::Rarity;
+
+static ODDS: RwLock<Option<Arc<Odds>>> = RwLock::new(None);
+
+/// Random things that can happen when someone sends a message
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, poise::ChoiceParameter)]
+pub enum Chance {
+    Pull,
+    Trip,
+    Duel,
+    Poker,
+}
+
+impl Chance {
+    pub const ALL: [Chance; 4] = [Chance::Pull, Chance::Trip, Chance::Duel, Chance::Poker];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Chance::Pull => "Pull",
+            Chance::Trip => "Trip",
+            Chance::Duel => "Duel",
+            Chance::Poker => "Poker",
+        }
+    }
+}
+
+impl TryFrom<&str> for Chance {
+    type Error = Error;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "Pull" => Ok(Chance::Pull),
+            "Trip" => Ok(Chance::Trip),
+            "Duel" => Ok(Chance::Duel),
+            "Poker" => Ok(Chance::Poker),
+            _ => Err(format!("Unknown catcoin chance {value}").into()),
+        }
+    }
+}
+
+/// How likely a rarity is once a pull happens, and what it pays
+#[derive(Clone, Copy, Debug)]
+pub struct RarityOdds {
+    pub rarity: Rarity,
+    pub weight: u32,
+    pub catcoin_mean: f64,
+    pub catcoin_std_dev: f64,
+}
+
+impl RarityOdds {
+    pub fn catcoin_dist(&self) -> Result<Normal<f64>, Error> {
+        Ok(Normal::new(self.catcoin_mean, self.catcoin_std_dev)?)
+    }
+}
+
+/// A boost to every chance in a channel
+#[derive(Clone, Copy, Debug)]
+pub struct Multiplier {
+    pub multiplier: f64,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// Every drop rate, as configured by mods
+#[derive(Clone, Debug)]
+pub struct Odds {
+    /// each chance as one in n messages
+    pub chances: HashMap<Chance, u32>,
+    /// most common first
+    pub rarities: Vec<RarityOdds>,
+    /// pulls without a Rare or better before one is guaranteed. 0 if off
+    pub pity: u32,
+    pub multipliers: HashMap<ChannelId, Multiplier>,
+}
+
+impl Odds {
+    /// the chance as one in n messages, before multipliers. 0 if it never happens
+    pub fn one_in(&self, chance: Chance) -> u32 {
+        self.chances.get(&chance).copied().unwrap_or(0)
+    }
+
+    /// the multiplier currently active in a channel
+    pub fn multiplier(&self, channel: ChannelId) -> f64 {
+        self.multipliers
+            .get(&channel)
+            .filter(|m| m.expires_at.is_none_or(|at| at > Utc::now()))
+            .map(|m| m.multiplier)
+            .unwrap_or(1.0)
+    }
+
+    /// how likely the chance is per message in a channel
+    pub fn probability(&self, chance: Chance, channel: ChannelId) -> f64 {
+        match self.one_in(chance) {
+            0 => 0.0,
+            one_in => (self.multiplier(channel) / one_in as f64).clamp(0.0, 1.0),
+        }
+    }
+
+    /// whether the chance happens for a message in this channel
+    pub fn roll(&self, chance: Chance, channel: ChannelId, rng: &mut impl Rng) -> bool {
+        rng.gen_bool(self.probability(chance, channel))
+    }
+
+    pub fn rarity(&self, rarity: Rarity) -> Option<&RarityOdds> {
+        self.rarities.iter().find(|r| r.rarity == rarity)
+    }
+
+    /// how likely a rarity is once a pull happens, ignoring pity
+    pub fn rarity_probability(&self, rarity: Rarity) -> f64 {
+        let total: u32 = self.rarities.iter().map(|r| r.weight).sum();
+        match (self.rarity(rarity), total) {
+            (Some(odds), 1..) => odds.weight as f64 / total as f64,
+            _ => 0.0,
+        }
+    }
+
+    /// whether the next pull is guaranteed to be Rare or better
+    pub fn pity_due(&self, since_rare: u32) -> bool {
+        self.pity > 0 && since_rare + 1 >= self.pity
+    }
+
+    /// picks a rarity by weight, only Rare or better once pity is due
+    pub fn pick_rarity(&self, since_rare: u32, rng: &mut impl Rng) -> Rarity {
+        let pity = self.pity_due(since_rare);
+        self.rarities
+            .iter()
+            .filter(|r| !pity || r.rarity >= Rarity::Rare)
+            .collect::<Vec<_>>()
+            .choose_weighted(rng, |r| r.weight)
+            .map(|r| r.rarity)
+            .unwrap_or(Rarity::Common)
+    }
+}
+
+/// The current drop rates
+pub fn odds() -> Arc<Odds> {
+    ODDS.read()
+        .unwrap()
+        .clone()
+        .expect("Catcoin odds haven't been loaded.")
+}
+
+#[derive(sqlx::FromRow)]
+struct ChanceRow {
+    event: String,
+    one_in: u32,
+}
+
+#[derive(sqlx::FromRow)]
+struct RarityOddsRow {
+    rarity: String,
+    weight: u32,
+    catcoin_mean: f64,
+    catcoin_std_dev: f64,
+}
+
+#[derive(sqlx::FromRow)]
+struct MultiplierRow {
+    channel_id: String,
+    multiplier: f64,
+    expires_at: Option<DateTime<Utc>>,
+}
+
+/// Reloads the drop rates from the database
+pub async fn reload_odds(pool: &Pool<MySql>) -> Result<(), Error> {
+    let chances: Vec<ChanceRow> = sqlx::query_as("SELECT `event`, `one_in` FROM `catcoin_chance`")
+        .fetch_all(pool)
+        .await?;
+    let rarities: Vec<RarityOddsRow> = sqlx::query_as(
+        "SELECT `rarity`, `weight`, `catcoin_mean`, `catcoin_std_dev` FROM `catcoin_rarity_odds` ORDER BY `rarity` ASC",
+    )
+    .fetch_all(pool)
+    .await?;
+    let pity = sqlx::query_scalar!("SELECT `pulls` FROM `catcoin_pity_config` WHERE `id` = 1")
+        .fetch_optional(pool)
+        .await?
+        .unwrap_or(0);
+    let multipliers: Vec<MultiplierRow> = sqlx::query_as(
+        "SELECT `channel_id`, `multiplier`, `expires_at` FROM `catcoin_channel_multiplier` WHERE `expires_at` IS NULL OR `expires_at` > ?",
+    )
+    .bind(Utc::now())
+    .fetch_all(pool)
+    .await?;
+
+    let odds = Odds {
+        chances: chances
+            .into_iter()
+            .filter_map(|r| Some((Chance::try_from(r.event.as_str()).ok()?, r.one_in)))
+            .collect(),
+        rarities: rarities
+            .into_iter()
+            .map(|r| RarityOdds {
+                rarity: r.rarity.into(),
+                weight: r.weight,
+                catcoin_mean: r.catcoin_mean,
+                catcoin_std_dev: r.catcoin_std_dev,
+            })
+            .collect(),
+        pity,
+        multipliers: multipliers
+            .into_iter()
+            .filter_map(|r| {
+                Some((
+                    r.channel_id.parse().ok()?,
+                    Multiplier {
+                        multiplier: r.multiplier,
+                        expires_at: r.expires_at,
+                    },
+                ))
+            })
+            .collect(),
+    };
+    *ODDS.write().unwrap() = Some(Arc::new(odds));
+    Ok(())
+}
+
+/// sets a chance to one in `one_in` messages
+pub async fn set_chance(pool: &Pool<MySql>, chance: Chance, one_in: u32) -> Result<(), Error> {
+    sqlx::query!(
+        "INSERT INTO `catcoin_chance` (`event`, `one_in`) VALUES (?, ?) ON DUPLICATE KEY UPDATE `one_in` = ?",
+        chance.as_str(),
+        one_in,
+        one_in
+    )
+    .execute(pool)
+    .await?;
+    reload_odds(pool).await
+}
+
+/// changes how likely a rarity is, or what it pays
+pub async fn set_rarity_odds(
+    pool: &Pool<MySql>,
+    rarity: Rarity,
+    weight: Option<u32>,
+    catcoin_mean: Option<f64>,
+    catcoin_std_dev: Option<f64>,
+) -> Result<(), Error> {
+    sqlx::query!(
+        r#"
+		UPDATE `catcoin_rarity_odds`
+		SET `weight` = COALESCE(?, `weight`),
+			`catcoin_mean` = COALESCE(?, `catcoin_mean`),
+			`catcoin_std_dev` = COALESCE(?, `catcoin_std_dev`)
+		WHERE `rarity` = ?
+	"#,
+        weight,
+        catcoin_mean,
+        catcoin_std_dev,
+        rarity.to_string()
+    )
+    .execute(pool)
+    .await?;
+    reload_odds(pool).await
+}
+
+/// sets how many pulls without a Rare or better guarantee one. 0 turns pity off
+pub async fn set_pity(pool: &Pool<MySql>, pulls: u32) -> Result<(), Error> {
+    sqlx::query!(
+        "INSERT INTO `catcoin_pity_config` (`id`, `pulls`) VALUES (1, ?) ON DUPLICATE KEY UPDATE `pulls` = ?",
+        pulls,
+        pulls
+    )
+    .execute(pool)
+    .await?;
+    reload_odds(pool).await
+}
+
+/// boosts every chance in a channel, until `expires_at` if given
+pub async fn set_multiplier(
+    pool: &Pool<MySql>,
+    channel: ChannelId,
+    multiplier: f64,
+    expires_at: Option<DateTime<Utc>>,
+) -> Result<(), Error> {
+    sqlx::query!(
+        "INSERT INTO `catcoin_channel_multiplier` (`channel_id`, `multiplier`, `expires_at`) VALUES (?, ?, ?) ON DUPLICATE KEY UPDATE `multiplier` = ?, `expires_at` = ?",
+        channel.get(),
+        multiplier,
+        expires_at,
+        multiplier,
+        expires_at
+    )
+    .execute(pool)
+    .await?;
+    reload_odds(pool).await
+}
+
+/// puts a channel back to normal odds
+pub async fn remove_multiplier(pool: &Pool<MySql>, channel: ChannelId) -> Result<(), Error> {
+    sqlx::query!(
+        "DELETE FROM `catcoin_channel_multiplier` WHERE `channel_id` = ?",
+        channel.get()
+    )
+    .execute(pool)
+    .await?;
+    reload_odds(pool).await
+}
+
+/// pulls the user has had since their last Rare or better
+pub async fn get_pity(pool: &Pool<MySql>, uid: serenity::UserId) -> Result<u32, Error> {
+    let pulls = sqlx::query_scalar!(
+        "SELECT `pulls` FROM `catcoin_pity` WHERE `uid` = ?",
+        uid.get()
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(pulls.unwrap_or(0))
+}
+
+/// counts a pull towards the user's pity, or resets it for a Rare or better
+pub async fn record_pity(
+    pool: &Pool<MySql>,
+    uid: serenity::UserId,
+    rarity: Rarity,
+) -> Result<(), Error> {
+    let reset = rarity >= Rarity::Rare;
+    sqlx::query!(
+        r#"
+		INSERT INTO `catcoin_pity` (`uid`, `pulls`) VALUES (?, ?)
+		ON DUPLICATE KEY UPDATE `pulls` = IF(?, 0, `pulls` + 1)
+	"#,
+        uid.get(),
+        if reset { 0 } else { 1 },
+        reset
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+
+    fn test_odds(pity: u32) -> Odds {
+        let rarity = |rarity, weight| RarityOdds {
+            rarity,
+            weight,
+            catcoin_mean: 0.0,
+            catcoin_std_dev: 1.0,
+        };
+        Odds {
+            chances: HashMap::from([(Chance::Pull, 100), (Chance::Trip, 0)]),
+            rarities: vec![
+                rarity(Rarity::Common, 90),
+                rarity(Rarity::Rare, 9),
+                rarity(Rarity::Fluffy, 1),
+                rarity(Rarity::Peak, 0),
+            ],
+            pity,
+            multipliers: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_probability() {
+        let mut odds = test_odds(0);
+        let channel = ChannelId::new(1);
+        assert_eq!(odds.probability(Chance::Pull, channel), 0.01);
+        // 0 and missing chances never happen
+        assert_eq!(odds.probability(Chance::Trip, channel), 0.0);
+        assert_eq!(odds.probability(Chance::Duel, channel), 0.0);
+
+        odds.multipliers.insert(
+            channel,
+            Multiplier {
+                multiplier: 2.0,
+                expires_at: None,
+            },
+        );
+        assert_eq!(odds.probability(Chance::Pull, channel), 0.02);
+        assert_eq!(odds.probability(Chance::Pull, ChannelId::new(2)), 0.01);
+
+        // expired multipliers don't count
+        odds.multipliers.insert(
+            channel,
+            Multiplier {
+                multiplier: 2.0,
+                expires_at: Some(Utc::now() - chrono::Duration::minutes(1)),
+            },
+        );
+        assert_eq!(odds.probability(Chance::Pull, channel), 0.01);
+    }
+
+    #[test]
+    fn test_multiplier_clamp() {
+        let mut odds = test_odds(0);
+        let channel = ChannelId::new(1);
+        for multiplier in [1000.0, -5.0] {
+            odds.multipliers.insert(
+                channel,
+                Multiplier {
+                    multiplier,
+                    expires_at: None,
+                },
+            );
+            let p = odds.probability(Chance::Pull, channel);
+            assert!((0.0..=1.0).contains(&p));
+            // rolling must not panic on an out of range probability
+            odds.roll(Chance::Pull, channel, &mut StdRng::seed_from_u64(0));
+        }
+        odds.multipliers.insert(
+            channel,
+            Multiplier {
+                multiplier: 1000.0,
+                expires_at: None,
+            },
+        );
+        assert_eq!(odds.probability(Chance::Pull, channel), 1.0);
+    }
+
+    #[test]
+    fn test_pity_due() {
+        let off = test_odds(0);
+        assert!(!off.pity_due(0));
+        assert!(!off.pity_due(1000));
+
+        let odds = test_odds(30);
+        assert!(!odds.pity_due(0));
+        assert!(!odds.pity_due(28));
+        // the 30th pull without a Rare is guaranteed one
+        assert!(odds.pity_due(29));
+        assert!(odds.pity_due(100));
+    }
+
+    #[test]
+    fn test_pick_rarity() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let odds = test_odds(30);
+        let picks: Vec<Rarity> = (0..1000).map(|_| odds.pick_rarity(0, &mut rng)).collect();
+        assert!(picks.contains(&Rarity::Common));
+        // weight 0 is never picked
+        assert!(!picks.contains(&Rarity::Peak));
+
+        // pity only picks Rare or better
+        assert!((0..1000).all(|_| odds.pick_rarity(29, &mut rng) >= Rarity::Rare));
+
+        // no rarities falls back to Common
+        let empty = Odds {
+            rarities: vec![],
+            ..test_odds(0)
+        };
+        assert_eq!(empty.pick_rarity(0, &mut rng), Rarity::Common);
+    }
+}
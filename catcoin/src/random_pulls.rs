@@ -6,16 +6,20 @@ use poise::serenity_prelude::{
 };
 use sqlx::{MySql, Pool};
 
-use crate::{drops, inventory::add_to_inventory};
+use crate::{
+    collection::award_sets,
+    drops,
+    inventory::add_to_inventory,
+    odds::{get_pity, odds, record_pity, Chance},
+    thumbnails::thumbnail,
+};
 use common::Error;
-use image::{codecs::png::PngEncoder, imageops::FilterType, ImageEncoder, ImageReader};
 use rand::prelude::*;
-use rand_distr::Normal;
 
-use super::{grant_catcoin, increment_and_get_pulls};
+use super::{grant_catcoin, increment_and_get_pulls, ledger::Reason};
 
 /// Reward rarities
-#[derive(PartialEq, PartialOrd, Eq, Ord, Clone, Copy, Debug)]
+#[derive(PartialEq, PartialOrd, Eq, Ord, Clone, Copy, Debug, poise::ChoiceParameter)]
 pub enum Rarity {
     Common,
     Rare,
@@ -57,15 +61,8 @@ impl Display for Rarity {
 }
 
 impl Rarity {
-    pub fn pick(rng: &mut impl Rng) -> Self {
-        let rarity = rng.gen_range(0..=100);
-        match rarity {
-            0..=68 => Rarity::Common,
-            69..=94 => Rarity::Rare,
-            95..=99 => Rarity::Fluffy,
-            _ => Rarity::Peak,
-        }
-    }
+    /// every rarity, most common first
+    pub const ALL: [Rarity; 4] = [Rarity::Common, Rarity::Rare, Rarity::Fluffy, Rarity::Peak];
 
     pub fn color(&self) -> Color {
         match self {
@@ -75,15 +72,6 @@ impl Rarity {
             Rarity::Peak => Color::from_rgb(233, 138, 153),
         }
     }
-
-    pub fn reward_dist(&self) -> Normal<f32> {
-        match self {
-            Rarity::Common => Normal::new(2.0, 0.5).unwrap(),
-            Rarity::Rare => Normal::new(10.0, 2.0).unwrap(),
-            Rarity::Fluffy => Normal::new(80.0, 5.0).unwrap(),
-            Rarity::Peak => Normal::new(300.0, 50.0).unwrap(),
-        }
-    }
 }
 
 #[derive(Clone, Debug, sqlx::FromRow)]
@@ -96,22 +84,30 @@ pub struct Reward {
 
 /// Rarely drops goodies
 pub async fn on_message(ctx: &Context, pool: &Pool<MySql>, message: &Message) -> Result<(), Error> {
+    let odds = odds();
+    if !odds.roll(Chance::Pull, message.channel_id, &mut thread_rng()) {
+        return Ok(());
+    }
+    let pity = get_pity(pool, message.author.id).await?;
+
     let (rarity, catcoins, reward) = {
         let mut rng = thread_rng();
 
-        // chance to pull
-        if !rng.gen_ratio(1, 500) {
-            return Ok(());
-        }
-
-        let rarity = Rarity::pick(&mut rng);
+        let rarity = odds.pick_rarity(pity, &mut rng);
         let reward = drops()
             .iter()
             .filter(|reward| reward.rarity == rarity)
             .choose(&mut rng)
+            .cloned()
             .ok_or("No rewards available")?;
 
-        let catcoins = rarity.reward_dist().sample(&mut rng).round().abs() as u64;
+        let catcoins = odds
+            .rarity(rarity)
+            .ok_or("No odds for rarity")?
+            .catcoin_dist()?
+            .sample(&mut rng)
+            .round()
+            .abs() as u64;
         (rarity, catcoins, reward)
     };
 
@@ -119,19 +115,20 @@ pub async fn on_message(ctx: &Context, pool: &Pool<MySql>, message: &Message) ->
 
     let pulls = increment_and_get_pulls(pool, reward.id).await?;
     add_to_inventory(pool, message.author.id, reward.id, pulls, catcoins).await?;
-    grant_catcoin(pool, message.author.id, catcoins).await?;
-
-    let image = ImageReader::open(&reward.file)?.decode()?;
-    let smaller = image.resize(125, 125, FilterType::Lanczos3);
-    let mut data: Vec<u8> = vec![];
-    PngEncoder::new(&mut data).write_image(
-        smaller.as_bytes(),
-        smaller.width(),
-        smaller.height(),
-        smaller.color().into(),
-    )?;
+    record_pity(pool, message.author.id, rarity).await?;
+    grant_catcoin(
+        pool,
+        message.author.id,
+        catcoins,
+        Reason::Pull,
+        Some(message.id.to_string()),
+    )
+    .await?;
+    let sets = award_sets(pool, message.author.id).await?;
+
+    let thumbnail = thumbnail(&reward.file).await?;
     let attachment = CreateAttachment::bytes(
-        data.as_slice(),
+        thumbnail.png.as_slice(),
         Path::new(&reward.file)
             .file_name()
             .unwrap()
@@ -143,7 +140,19 @@ pub async fn on_message(ctx: &Context, pool: &Pool<MySql>, message: &Message) ->
             ":bangbang: {} Pull: {} #{} :sparkles:",
             rarity, reward.name, pulls
         ))
-        .description(format!("{} **+{}**", emoji("catcoin"), catcoins))
+        .description(
+            std::iter::once(format!("{} **+{}**", emoji("catcoin"), catcoins))
+                .chain(sets.iter().map(|set| {
+                    format!(
+                        "Completed **{}**! {} **+{}**",
+                        set.name,
+                        emoji("catcoin"),
+                        set.bonus
+                    )
+                }))
+                .collect::<Vec<String>>()
+                .join("\n"),
+        )
         //.footer(CreateEmbedFooter::new("/catcoin balance"))
         .attachment(&attachment.filename)
         .color(reward.rarity.color());
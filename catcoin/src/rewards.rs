@@ -0,0 +1,172 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, BufWriter},
+    path::Path,
+};
+
+use image::{DynamicImage, ImageFormat};
+use sqlx::{MySql, Pool};
+
+use common::Error;
+
+use crate::{
+    random_pulls::{Rarity, Reward},
+    reload_drops,
+};
+
+/// where uploaded reward images are stored
+const REWARD_DIR: &str = "public";
+
+/// Decodes an uploaded reward image off the async executor. None if it isn't a valid image
+pub async fn decode_image(data: Vec<u8>) -> Result<Option<DynamicImage>, Error> {
+    Ok(tokio::task::spawn_blocking(move || image::load_from_memory(&data).ok()).await?)
+}
+
+/// the file name a reward's image is stored under, based on its name
+fn slug(name: &str) -> String {
+    let slug = name
+        .to_lowercase()
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<&str>>()
+        .join("_");
+    if slug.is_empty() {
+        "reward".to_owned()
+    } else {
+        slug
+    }
+}
+
+/// saves the image as a png under a new file, returning where it was stored.
+/// the file is claimed before it's written, so rewards with the same name can't overwrite each other
+fn save_image_blocking(name: &str, image: DynamicImage) -> Result<String, Error> {
+    let slug = slug(name);
+    let mut path = Path::new(REWARD_DIR).join(format!("{slug}.png"));
+    let mut n = 2;
+    let file = loop {
+        match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(file) => break file,
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                path = Path::new(REWARD_DIR).join(format!("{slug}_{n}.png"));
+                n += 1;
+            }
+            Err(e) => return Err(e.into()),
+        }
+    };
+    if let Err(e) = image.write_to(&mut BufWriter::new(file), ImageFormat::Png) {
+        let _ = fs::remove_file(&path);
+        return Err(e.into());
+    }
+    Ok(path.to_str().ok_or("Invalid reward path")?.to_owned())
+}
+
+async fn save_image(name: &str, image: DynamicImage) -> Result<String, Error> {
+    let name = name.to_owned();
+    tokio::task::spawn_blocking(move || save_image_blocking(&name, image)).await?
+}
+
+/// Rewards whose name contains `partial`, retired ones included. (reward, retired)
+pub async fn search_rewards(
+    pool: &Pool<MySql>,
+    partial: &str,
+    limit: i64,
+) -> Result<Vec<(Reward, bool)>, Error> {
+    let rows = sqlx::query!(
+        r#"
+		SELECT `id`, `name`, `file`, `rarity`, `retired` FROM `catcoin_reward`
+		WHERE `name` LIKE CONCAT('%', ?, '%')
+		ORDER BY `name` ASC LIMIT ?
+	"#,
+        partial,
+        limit
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(rows
+        .into_iter()
+        .map(|r| {
+            (
+                Reward {
+                    id: r.id,
+                    name: r.name,
+                    file: r.file,
+                    rarity: r.rarity.into(),
+                },
+                r.retired,
+            )
+        })
+        .collect())
+}
+
+/// Adds a reward to the drop table, returning its id
+pub async fn add_reward(
+    pool: &Pool<MySql>,
+    name: &str,
+    rarity: Rarity,
+    image: DynamicImage,
+) -> Result<u64, Error> {
+    let file = save_image(name, image).await?;
+    let id = sqlx::query!(
+        "INSERT INTO `catcoin_reward` (`name`, `file`, `rarity`) VALUES (?, ?, ?)",
+        name,
+        file,
+        rarity.to_string()
+    )
+    .execute(pool)
+    .await?
+    .last_insert_id();
+    reload_drops(pool).await?;
+    Ok(id)
+}
+
+/// Changes a reward's name, rarity or image. returns false if it doesn't exist
+pub async fn edit_reward(
+    pool: &Pool<MySql>,
+    id: i32,
+    name: Option<&str>,
+    rarity: Option<Rarity>,
+    image: Option<DynamicImage>,
+) -> Result<bool, Error> {
+    let Some(current) =
+        sqlx::query_scalar!("SELECT `name` FROM `catcoin_reward` WHERE `id` = ?", id)
+            .fetch_optional(pool)
+            .await?
+    else {
+        return Ok(false);
+    };
+    let file = match image {
+        Some(image) => Some(save_image(name.unwrap_or(&current), image).await?),
+        None => None,
+    };
+    sqlx::query!(
+        r#"
+		UPDATE `catcoin_reward`
+		SET `name` = COALESCE(?, `name`),
+			`rarity` = COALESCE(?, `rarity`),
+			`file` = COALESCE(?, `file`)
+		WHERE `id` = ?
+	"#,
+        name,
+        rarity.map(|r| r.to_string()),
+        file,
+        id
+    )
+    .execute(pool)
+    .await?;
+    reload_drops(pool).await?;
+    Ok(true)
+}
+
+/// Stops a reward from being pulled, or brings it back. Owned pulls are kept.
+/// returns false if it doesn't exist
+pub async fn retire_reward(pool: &Pool<MySql>, id: i32, retired: bool) -> Result<bool, Error> {
+    let result = sqlx::query!(
+        "UPDATE `catcoin_reward` SET `retired` = ? WHERE `id` = ?",
+        retired,
+        id
+    )
+    .execute(pool)
+    .await?;
+    reload_drops(pool).await?;
+    Ok(result.rows_affected() > 0)
+}
@@ -0,0 +1,387 @@
+use std::str::FromStr;
+
+use chrono::{DateTime, TimeDelta, Utc};
+use emoji::emoji;
+use poise::serenity_prelude::{self as serenity, RoleId};
+use sqlx::{MySql, Pool};
+
+use common::Error;
+
+use crate::{grant_catcoin, ledger::Reason, spend_catcoin};
+
+/// What a shop item does once it's bought
+#[derive(Clone, Copy, Debug, PartialEq, Eq, poise::ChoiceParameter)]
+pub enum ItemKind {
+    #[name = "Color role"]
+    ColorRole,
+    #[name = "Profile background"]
+    ProfileBackground,
+    #[name = "Relay tag"]
+    RelayTag,
+    #[name = "Map nomination"]
+    MapNomination,
+    #[name = "Reserved slot"]
+    ReservedSlot,
+}
+
+impl ItemKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ItemKind::ColorRole => "ColorRole",
+            ItemKind::ProfileBackground => "ProfileBackground",
+            ItemKind::RelayTag => "RelayTag",
+            ItemKind::MapNomination => "MapNomination",
+            ItemKind::ReservedSlot => "ReservedSlot",
+        }
+    }
+
+    /// what the buyer has to provide, if anything
+    pub fn input_hint(&self) -> Option<&'static str> {
+        match self {
+            ItemKind::ColorRole => Some("a hex color, e.g. `#ff66cc`"),
+            ItemKind::ProfileBackground => Some("an image url"),
+            ItemKind::RelayTag => Some("the tag, up to 12 characters"),
+            ItemKind::MapNomination => Some("the map to nominate"),
+            ItemKind::ReservedSlot => None,
+        }
+    }
+
+    /// whether the perk is tied to the buyer's linked steam account
+    pub fn needs_steam(&self) -> bool {
+        matches!(self, ItemKind::RelayTag | ItemKind::ReservedSlot)
+    }
+}
+
+impl FromStr for ItemKind {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ColorRole" => Ok(ItemKind::ColorRole),
+            "ProfileBackground" => Ok(ItemKind::ProfileBackground),
+            "RelayTag" => Ok(ItemKind::RelayTag),
+            "MapNomination" => Ok(ItemKind::MapNomination),
+            "ReservedSlot" => Ok(ItemKind::ReservedSlot),
+            _ => Err(format!("Unknown shop item kind {s}").into()),
+        }
+    }
+}
+
+/// Something for sale in the shop
+#[derive(Clone, Debug)]
+pub struct ShopItem {
+    pub id: u32,
+    pub name: String,
+    pub description: String,
+    pub kind: ItemKind,
+    pub price: u64,
+    /// how long the perk lasts. None for one-time items
+    pub duration_days: Option<u32>,
+    pub enabled: bool,
+}
+
+impl ShopItem {
+    /// a short summary for discord
+    pub fn line(&self) -> String {
+        let duration = self
+            .duration_days
+            .map(|d| format!(" for {d} days"))
+            .unwrap_or_default();
+        format!(
+            "**{}** `#{}` - **{}** {}{}\n{}",
+            self.name,
+            self.id,
+            self.price,
+            emoji("catcoin"),
+            duration,
+            self.description
+        )
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct ShopItemRow {
+    id: u32,
+    name: String,
+    description: String,
+    kind: String,
+    price: u64,
+    duration_days: Option<u32>,
+    enabled: bool,
+}
+
+impl ShopItemRow {
+    fn into_item(self) -> Option<ShopItem> {
+        Some(ShopItem {
+            id: self.id,
+            name: self.name,
+            description: self.description,
+            kind: self.kind.parse().ok()?,
+            price: self.price,
+            duration_days: self.duration_days,
+            enabled: self.enabled,
+        })
+    }
+}
+
+/// A perk a user bought
+#[derive(Clone, Debug)]
+pub struct Purchase {
+    pub id: u64,
+    pub uid: serenity::UserId,
+    pub item_id: u32,
+    pub kind: ItemKind,
+    pub price: u64,
+    /// e.g. the role color, relay tag or map
+    pub input: Option<String>,
+    pub role_id: Option<RoleId>,
+    pub steamid: Option<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+#[derive(sqlx::FromRow)]
+struct PurchaseRow {
+    id: u64,
+    uid: String,
+    item_id: u32,
+    kind: String,
+    price: u64,
+    input: Option<String>,
+    role_id: Option<String>,
+    steamid: Option<String>,
+    expires_at: Option<DateTime<Utc>>,
+}
+
+impl PurchaseRow {
+    fn into_purchase(self) -> Option<Purchase> {
+        Some(Purchase {
+            id: self.id,
+            uid: self.uid.parse().ok()?,
+            item_id: self.item_id,
+            kind: self.kind.parse().ok()?,
+            price: self.price,
+            input: self.input,
+            role_id: self.role_id.and_then(|r| r.parse().ok()),
+            steamid: self.steamid,
+            expires_at: self.expires_at,
+        })
+    }
+}
+
+const SELECT_PURCHASE: &str = "SELECT `id`, `uid`, `item_id`, `kind`, `price`, `input`, `role_id`, `steamid`, `expires_at` FROM `catcoin_purchase`";
+
+/// every item in the shop, cheapest first
+pub async fn get_items(pool: &Pool<MySql>, include_disabled: bool) -> Result<Vec<ShopItem>, Error> {
+    let rows: Vec<ShopItemRow> = sqlx::query_as(
+        r#"
+		SELECT `id`, `name`, `description`, `kind`, `price`, `duration_days`, `enabled` FROM `catcoin_shop_item`
+		WHERE `enabled` OR ?
+		ORDER BY `price` ASC, `id` ASC
+	"#,
+    )
+    .bind(include_disabled)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows
+        .into_iter()
+        .filter_map(ShopItemRow::into_item)
+        .collect())
+}
+
+pub async fn get_item(pool: &Pool<MySql>, id: u32) -> Result<Option<ShopItem>, Error> {
+    let row: Option<ShopItemRow> = sqlx::query_as(
+        "SELECT `id`, `name`, `description`, `kind`, `price`, `duration_days`, `enabled` FROM `catcoin_shop_item` WHERE `id` = ?",
+    )
+    .bind(id)
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.and_then(ShopItemRow::into_item))
+}
+
+/// puts a new item up for sale, returning its id
+pub async fn add_item(
+    pool: &Pool<MySql>,
+    name: &str,
+    description: &str,
+    kind: ItemKind,
+    price: u64,
+    duration_days: Option<u32>,
+) -> Result<u64, Error> {
+    let result = sqlx::query!(
+        "INSERT INTO `catcoin_shop_item` (`name`, `description`, `kind`, `price`, `duration_days`) VALUES (?, ?, ?, ?, ?)",
+        name,
+        description,
+        kind.as_str(),
+        price,
+        duration_days
+    )
+    .execute(pool)
+    .await?;
+    Ok(result.last_insert_id())
+}
+
+/// changes an item's price or availability. returns false if it doesn't exist
+pub async fn edit_item(
+    pool: &Pool<MySql>,
+    id: u32,
+    price: Option<u64>,
+    enabled: Option<bool>,
+) -> Result<bool, Error> {
+    let result = sqlx::query!(
+        r#"
+		UPDATE `catcoin_shop_item`
+		SET `price` = COALESCE(?, `price`), `enabled` = COALESCE(?, `enabled`)
+		WHERE `id` = ?
+	"#,
+        price,
+        enabled,
+        id
+    )
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// takes payment for the item and records the purchase. None if the user can't afford it
+pub async fn buy(
+    pool: &Pool<MySql>,
+    uid: serenity::UserId,
+    item: &ShopItem,
+    input: Option<String>,
+    steamid: Option<String>,
+) -> Result<Option<Purchase>, Error> {
+    let expires_at = item
+        .duration_days
+        .map(|days| Utc::now() + TimeDelta::days(days.into()));
+
+    let mut tx = pool.begin().await?;
+    let id = sqlx::query!(
+        r#"
+		INSERT INTO `catcoin_purchase` (`uid`, `item_id`, `kind`, `price`, `input`, `steamid`, `expires_at`, `expired`)
+		VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+	"#,
+        uid.get(),
+        item.id,
+        item.kind.as_str(),
+        item.price,
+        input,
+        steamid,
+        expires_at,
+        // one-time items are used up right away
+        expires_at.is_none()
+    )
+    .execute(&mut *tx)
+    .await?
+    .last_insert_id();
+
+    if !spend_catcoin(
+        &mut *tx,
+        uid,
+        item.price,
+        Reason::Shop,
+        Some(id.to_string()),
+    )
+    .await?
+    {
+        tx.rollback().await?;
+        return Ok(None);
+    }
+    tx.commit().await?;
+
+    Ok(Some(Purchase {
+        id,
+        uid,
+        item_id: item.id,
+        kind: item.kind,
+        price: item.price,
+        input,
+        role_id: None,
+        steamid,
+        expires_at,
+    }))
+}
+
+/// undoes a purchase whose perk couldn't be given
+pub async fn refund(pool: &Pool<MySql>, purchase: &Purchase) -> Result<(), Error> {
+    let mut tx = pool.begin().await?;
+    sqlx::query!("DELETE FROM `catcoin_purchase` WHERE `id` = ?", purchase.id)
+        .execute(&mut *tx)
+        .await?;
+    grant_catcoin(
+        &mut *tx,
+        purchase.uid,
+        purchase.price,
+        Reason::Refund,
+        Some(purchase.id.to_string()),
+    )
+    .await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+/// records the discord role created for a purchase
+pub async fn set_role(pool: &Pool<MySql>, id: u64, role_id: RoleId) -> Result<(), Error> {
+    sqlx::query!(
+        "UPDATE `catcoin_purchase` SET `role_id` = ? WHERE `id` = ?",
+        role_id.get(),
+        id
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// the user's perk of the given kind that hasn't expired yet
+pub async fn get_active(
+    pool: &Pool<MySql>,
+    uid: serenity::UserId,
+    kind: ItemKind,
+) -> Result<Option<Purchase>, Error> {
+    let row: Option<PurchaseRow> = sqlx::query_as(&format!(
+        "{SELECT_PURCHASE} WHERE `uid` = ? AND `kind` = ? AND NOT `expired` ORDER BY `id` DESC LIMIT 1"
+    ))
+    .bind(uid.get())
+    .bind(kind.as_str())
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.and_then(PurchaseRow::into_purchase))
+}
+
+/// every perk of the given kind that hasn't expired yet
+pub async fn get_all_active(pool: &Pool<MySql>, kind: ItemKind) -> Result<Vec<Purchase>, Error> {
+    let rows: Vec<PurchaseRow> = sqlx::query_as(&format!(
+        "{SELECT_PURCHASE} WHERE `kind` = ? AND NOT `expired`"
+    ))
+    .bind(kind.as_str())
+    .fetch_all(pool)
+    .await?;
+    Ok(rows
+        .into_iter()
+        .filter_map(PurchaseRow::into_purchase)
+        .collect())
+}
+
+/// perks that have run out but haven't been taken away yet
+pub async fn get_expired(pool: &Pool<MySql>) -> Result<Vec<Purchase>, Error> {
+    let rows: Vec<PurchaseRow> = sqlx::query_as(&format!(
+        "{SELECT_PURCHASE} WHERE NOT `expired` AND `expires_at` <= ?"
+    ))
+    .bind(Utc::now())
+    .fetch_all(pool)
+    .await?;
+    Ok(rows
+        .into_iter()
+        .filter_map(PurchaseRow::into_purchase)
+        .collect())
+}
+
+/// records that the perk was taken away
+pub async fn mark_expired(pool: &Pool<MySql>, id: u64) -> Result<(), Error> {
+    sqlx::query!(
+        "UPDATE `catcoin_purchase` SET `expired` = true WHERE `id` = ?",
+        id
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,125 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, LazyLock, Mutex},
+};
+
+use image::{
+    codecs::png::PngEncoder, imageops, imageops::FilterType, ExtendedColorType, ImageEncoder,
+    ImageReader, Rgba, RgbaImage,
+};
+
+use common::Error;
+
+use crate::inventory::CatcoinPull;
+
+/// size of a pull thumbnail
+pub const THUMBNAIL_SIZE: u32 = 125;
+/// width of the rarity colored frame around each pull in a gallery
+const FRAME: u32 = 4;
+/// pulls per row of a gallery
+const GALLERY_COLUMNS: u32 = 5;
+
+/// reward file -> its thumbnail
+static THUMBNAILS: LazyLock<Mutex<HashMap<String, Arc<Thumbnail>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// A reward image shrunk down for discord
+pub struct Thumbnail {
+    pub image: RgbaImage,
+    pub png: Vec<u8>,
+}
+
+fn encode_png(image: &RgbaImage) -> Result<Vec<u8>, Error> {
+    let mut data: Vec<u8> = vec![];
+    PngEncoder::new(&mut data).write_image(
+        image.as_raw(),
+        image.width(),
+        image.height(),
+        ExtendedColorType::Rgba8,
+    )?;
+    Ok(data)
+}
+
+fn render_thumbnail(file: &str) -> Result<Thumbnail, Error> {
+    let image = ImageReader::open(file)?
+        .decode()?
+        .resize(THUMBNAIL_SIZE, THUMBNAIL_SIZE, FilterType::Lanczos3)
+        .to_rgba8();
+    let png = encode_png(&image)?;
+    Ok(Thumbnail { image, png })
+}
+
+/// The thumbnail for a reward image, rendered on first use
+pub async fn thumbnail(file: &str) -> Result<Arc<Thumbnail>, Error> {
+    let cached = THUMBNAILS.lock().unwrap().get(file).cloned();
+    if let Some(thumbnail) = cached {
+        return Ok(thumbnail);
+    }
+    let owned = file.to_owned();
+    let thumbnail = Arc::new(tokio::task::spawn_blocking(move || render_thumbnail(&owned)).await??);
+    THUMBNAILS
+        .lock()
+        .unwrap()
+        .insert(file.to_owned(), thumbnail.clone());
+    Ok(thumbnail)
+}
+
+/// One image in a grid
+pub(crate) struct Tile {
+    pub thumbnail: Arc<Thumbnail>,
+    /// color of the frame around it, if any
+    pub frame: Option<Rgba<u8>>,
+    /// blacked out, keeping its shape
+    pub silhouette: bool,
+}
+
+/// Draws tiles as a png grid off the async executor
+pub(crate) async fn render_grid(tiles: Vec<Tile>, columns: u32) -> Result<Vec<u8>, Error> {
+    tokio::task::spawn_blocking(move || {
+        let size = THUMBNAIL_SIZE + FRAME * 2;
+        let count = tiles.len().max(1) as u32;
+        let mut canvas = RgbaImage::new(count.min(columns) * size, count.div_ceil(columns) * size);
+        for (i, tile) in tiles.iter().enumerate() {
+            let i = i as u32;
+            let (x, y) = ((i % columns) * size, (i / columns) * size);
+            if let Some(frame) = tile.frame {
+                for dx in 0..size {
+                    for dy in 0..size {
+                        if dx < FRAME || dy < FRAME || dx >= size - FRAME || dy >= size - FRAME {
+                            canvas.put_pixel(x + dx, y + dy, frame);
+                        }
+                    }
+                }
+            }
+            let mut image = tile.thumbnail.image.clone();
+            if tile.silhouette {
+                for pixel in image.pixels_mut() {
+                    let alpha = pixel.0[3];
+                    pixel.0 = [24, 24, 24, alpha];
+                }
+            }
+            imageops::overlay(
+                &mut canvas,
+                &image,
+                (x + FRAME + (THUMBNAIL_SIZE - image.width()) / 2).into(),
+                (y + FRAME + (THUMBNAIL_SIZE - image.height()) / 2).into(),
+            );
+        }
+        encode_png(&canvas)
+    })
+    .await?
+}
+
+/// Draws pulls as a png grid, each framed in its rarity's color
+pub async fn render_gallery(pulls: &[CatcoinPull]) -> Result<Vec<u8>, Error> {
+    let mut tiles = vec![];
+    for pull in pulls {
+        let color = pull.reward.rarity.color();
+        tiles.push(Tile {
+            thumbnail: thumbnail(&pull.reward.file).await?,
+            frame: Some(Rgba([color.r(), color.g(), color.b(), 255])),
+            silhouette: false,
+        });
+    }
+    render_grid(tiles, GALLERY_COLUMNS).await
+}
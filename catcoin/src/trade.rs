@@ -0,0 +1,152 @@
+use chrono::{DateTime, Utc};
+use poise::serenity_prelude as serenity;
+use sqlx::{MySql, MySqlConnection, Pool};
+
+use common::Error;
+
+use crate::{ledger::Reason, transfer};
+
+/// What one side of a trade hands over
+#[derive(Clone, Debug)]
+pub struct TradeOffer {
+    pub uid: serenity::UserId,
+    /// `catcoin_inv` ids
+    pub pulls: Vec<i32>,
+    pub catcoin: u64,
+}
+
+impl TradeOffer {
+    pub fn new(uid: serenity::UserId) -> Self {
+        Self {
+            uid,
+            pulls: vec![],
+            catcoin: 0,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pulls.is_empty() && self.catcoin == 0
+    }
+}
+
+/// A time a pull changed hands
+#[derive(Clone, Debug)]
+pub struct PullTransfer {
+    pub trade_id: u64,
+    pub from: serenity::UserId,
+    pub to: serenity::UserId,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(sqlx::FromRow)]
+struct PullTransferRow {
+    trade_id: u64,
+    from_uid: String,
+    to_uid: String,
+    created_at: DateTime<Utc>,
+}
+
+/// Swap both offers in one transaction, returning the trade id.
+/// None if either side no longer owns a pull or can't afford their catcoin.
+pub async fn execute(
+    pool: &Pool<MySql>,
+    from: &TradeOffer,
+    to: &TradeOffer,
+) -> Result<Option<u64>, Error> {
+    let mut tx = pool.begin().await?;
+    let id = sqlx::query!(
+        "INSERT INTO `catcoin_trade` (`from_uid`, `to_uid`, `from_catcoin`, `to_catcoin`) VALUES (?, ?, ?, ?)",
+        from.uid.get(),
+        to.uid.get(),
+        from.catcoin,
+        to.catcoin
+    )
+    .execute(&mut *tx)
+    .await?
+    .last_insert_id();
+
+    for (giver, taker) in [(from, to), (to, from)] {
+        if !hand_over(&mut *tx, id, giver, taker.uid).await? {
+            tx.rollback().await?;
+            return Ok(None);
+        }
+    }
+    tx.commit().await?;
+
+    Ok(Some(id))
+}
+
+/// moves one side's pulls and catcoin to the other. false if anything is missing
+async fn hand_over(
+    conn: &mut MySqlConnection,
+    trade_id: u64,
+    giver: &TradeOffer,
+    taker: serenity::UserId,
+) -> Result<bool, Error> {
+    for pull in giver.pulls.iter() {
+        // pulls up for auction can't be traded away
+        let rc = sqlx::query!(
+            r#"
+			UPDATE `catcoin_inv` SET `uid` = ?
+			WHERE `id` = ? AND `uid` = ?
+			AND NOT EXISTS (SELECT 1 FROM `catcoin_auction` a WHERE a.`inv_id` = `catcoin_inv`.`id` AND NOT a.`settled`)
+		"#,
+            taker.get(),
+            pull,
+            giver.uid.get()
+        )
+        .execute(&mut *conn)
+        .await?;
+        if rc.rows_affected() == 0 {
+            return Ok(false);
+        }
+        sqlx::query!(
+            "INSERT INTO `catcoin_trade_item` (`trade_id`, `inv_id`, `from_uid`, `to_uid`) VALUES (?, ?, ?, ?)",
+            trade_id,
+            pull,
+            giver.uid.get(),
+            taker.get()
+        )
+        .execute(&mut *conn)
+        .await?;
+    }
+    if giver.catcoin > 0 {
+        return transfer(
+            conn,
+            giver.uid,
+            taker,
+            giver.catcoin,
+            Reason::Trade,
+            Some(trade_id.to_string()),
+        )
+        .await;
+    }
+    Ok(true)
+}
+
+/// Every trade a pull has been part of, oldest first
+pub async fn get_pull_history(pool: &Pool<MySql>, inv_id: i32) -> Result<Vec<PullTransfer>, Error> {
+    let rows: Vec<PullTransferRow> = sqlx::query_as(
+        r#"
+		SELECT ti.`trade_id`, ti.`from_uid`, ti.`to_uid`, t.`created_at`
+		FROM `catcoin_trade_item` ti
+		INNER JOIN `catcoin_trade` t ON ti.`trade_id` = t.`id`
+		WHERE ti.`inv_id` = ?
+		ORDER BY ti.`id` ASC
+	"#,
+    )
+    .bind(inv_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows
+        .into_iter()
+        .filter_map(|r| {
+            Some(PullTransfer {
+                trade_id: r.trade_id,
+                from: r.from_uid.parse().ok()?,
+                to: r.to_uid.parse().ok()?,
+                created_at: r.created_at,
+            })
+        })
+        .collect())
+}
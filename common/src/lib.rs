@@ -1,4 +1,5 @@
 pub type Error = Box<dyn std::error::Error + Send + Sync>;
 
 pub mod discord;
+pub mod metrics;
 pub mod util;
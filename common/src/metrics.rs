@@ -0,0 +1,215 @@
+use std::{collections::BTreeMap, fmt::Write, sync::Mutex, time::Duration};
+
+/// The prometheus type of a metric
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kind {
+    Counter,
+    Gauge,
+    /// a running sum and count of observations, e.g. latencies
+    Summary,
+}
+
+impl Kind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Kind::Counter => "counter",
+            Kind::Gauge => "gauge",
+            Kind::Summary => "summary",
+        }
+    }
+}
+
+/// A metric exported at /metrics
+pub struct Metric {
+    pub name: &'static str,
+    pub kind: Kind,
+    pub help: &'static str,
+}
+
+pub static RCON_LATENCY: Metric = Metric {
+    name: "rcon_latency_seconds",
+    kind: Kind::Summary,
+    help: "Time taken by rcon commands",
+};
+pub static RCON_ERRORS: Metric = Metric {
+    name: "rcon_errors_total",
+    kind: Kind::Counter,
+    help: "Failed rcon commands",
+};
+pub static SERVER_ONLINE: Metric = Metric {
+    name: "server_online",
+    kind: Kind::Gauge,
+    help: "Whether the last rcon command to the server succeeded",
+};
+pub static LOG_PACKETS_RECEIVED: Metric = Metric {
+    name: "log_packets_received_total",
+    kind: Kind::Counter,
+    help: "Log packets and lines received",
+};
+pub static LOG_PACKETS_PARSED: Metric = Metric {
+    name: "log_packets_parsed_total",
+    kind: Kind::Counter,
+    help: "Log lines broadcast to subscribers",
+};
+pub static LOG_PACKETS_DROPPED: Metric = Metric {
+    name: "log_packets_dropped_total",
+    kind: Kind::Counter,
+    help: "Log packets that could not be parsed or authenticated",
+};
+pub static RELAY_MESSAGES: Metric = Metric {
+    name: "relay_messages_total",
+    kind: Kind::Counter,
+    help: "Messages posted to server relay channels",
+};
+pub static DISCORD_ERRORS: Metric = Metric {
+    name: "discord_errors_total",
+    kind: Kind::Counter,
+    help: "Errors from discord commands, events and api calls",
+};
+pub static CATCOIN_MINTED: Metric = Metric {
+    name: "catcoin_minted_total",
+    kind: Kind::Counter,
+    help: "Catcoin granted to users",
+};
+pub static CATCOIN_SPENT: Metric = Metric {
+    name: "catcoin_spent_total",
+    kind: Kind::Counter,
+    help: "Catcoin spent by users",
+};
+pub static TASK_LAST_RUN: Metric = Metric {
+    name: "task_last_run_timestamp_seconds",
+    kind: Kind::Gauge,
+    help: "Unix time a background task last completed",
+};
+pub static DB_LATENCY: Metric = Metric {
+    name: "db_query_latency_seconds",
+    kind: Kind::Summary,
+    help: "Time taken by database queries",
+};
+
+/// every series of one metric, keyed by rendered labels
+struct Family {
+    metric: &'static Metric,
+    /// value (or sum for summaries), and observation count
+    series: BTreeMap<String, (f64, u64)>,
+}
+
+static REGISTRY: Mutex<BTreeMap<&'static str, Family>> = Mutex::new(BTreeMap::new());
+
+/// renders labels as `{a="b",c="d"}`
+fn render_labels(labels: &[(&str, &str)]) -> String {
+    if labels.is_empty() {
+        return String::new();
+    }
+    let labels: Vec<String> = labels
+        .iter()
+        .map(|(k, v)| {
+            let v = v
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"")
+                .replace('\n', "\\n");
+            format!("{k}=\"{v}\"")
+        })
+        .collect();
+    format!("{{{}}}", labels.join(","))
+}
+
+impl Metric {
+    fn update(&'static self, labels: &[(&str, &str)], f: impl FnOnce(&mut (f64, u64))) {
+        let mut registry = REGISTRY.lock().unwrap_or_else(|e| e.into_inner());
+        let family = registry.entry(self.name).or_insert_with(|| Family {
+            metric: self,
+            series: BTreeMap::new(),
+        });
+        f(family.series.entry(render_labels(labels)).or_default());
+    }
+
+    /// adds one to a counter
+    pub fn inc(&'static self, labels: &[(&str, &str)]) {
+        self.add(labels, 1.);
+    }
+
+    /// adds to a counter
+    pub fn add(&'static self, labels: &[(&str, &str)], value: f64) {
+        self.update(labels, |(v, _)| *v += value);
+    }
+
+    /// sets a gauge
+    pub fn set(&'static self, labels: &[(&str, &str)], value: f64) {
+        self.update(labels, |(v, _)| *v = value);
+    }
+
+    /// records an observation, e.g. a latency in seconds
+    pub fn observe(&'static self, labels: &[(&str, &str)], value: f64) {
+        self.update(labels, |(sum, count)| {
+            *sum += value;
+            *count += 1;
+        });
+    }
+
+    /// sets a gauge to the current unix time
+    pub fn set_now(&'static self, labels: &[(&str, &str)]) {
+        self.set(labels, chrono::Utc::now().timestamp() as f64);
+    }
+}
+
+/// records a completed run of a background db flush
+pub fn flushed(task: &str, elapsed: Duration) {
+    DB_LATENCY.observe(&[("query", task)], elapsed.as_secs_f64());
+    TASK_LAST_RUN.set_now(&[("task", task)]);
+}
+
+/// every recorded metric in the prometheus text format
+pub fn render() -> String {
+    let registry = REGISTRY.lock().unwrap_or_else(|e| e.into_inner());
+    let mut out = String::new();
+    for family in registry.values() {
+        let Metric { name, kind, help } = family.metric;
+        let _ = writeln!(out, "# HELP {name} {help}");
+        let _ = writeln!(out, "# TYPE {name} {}", kind.as_str());
+        for (labels, (value, count)) in &family.series {
+            match kind {
+                Kind::Summary => {
+                    let _ = writeln!(out, "{name}_sum{labels} {value}");
+                    let _ = writeln!(out, "{name}_count{labels} {count}");
+                }
+                _ => {
+                    let _ = writeln!(out, "{name}{labels} {value}");
+                }
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static TEST_COUNTER: Metric = Metric {
+        name: "test_counter_total",
+        kind: Kind::Counter,
+        help: "A test counter",
+    };
+    static TEST_SUMMARY: Metric = Metric {
+        name: "test_latency_seconds",
+        kind: Kind::Summary,
+        help: "A test summary",
+    };
+
+    #[test]
+    fn renders_metrics() {
+        TEST_COUNTER.inc(&[("server", "#4")]);
+        TEST_COUNTER.add(&[("server", "#4")], 2.);
+        TEST_COUNTER.inc(&[("server", "say \"hi\"")]);
+        TEST_SUMMARY.observe(&[], 0.5);
+        TEST_SUMMARY.observe(&[], 0.25);
+
+        let out = render();
+        assert!(out.contains("# TYPE test_counter_total counter\n"));
+        assert!(out.contains("test_counter_total{server=\"#4\"} 3\n"));
+        assert!(out.contains("test_counter_total{server=\"say \\\"hi\\\"\"} 1\n"));
+        assert!(out.contains("test_latency_seconds_sum 0.75\n"));
+        assert!(out.contains("test_latency_seconds_count 2\n"));
+    }
+}
@@ -1,13 +1,24 @@
 use std::{sync::Arc, time::Duration};
 
 use common::Error;
-use poise::serenity_prelude::{ChannelId, CreateMessage, Http};
+use poise::serenity_prelude::{ChannelId, CreateAllowedMentions, CreateMessage, Http};
 use serde::{Deserialize, Serialize};
 use sqlx::{MySql, Pool};
 
-const API_BASE: &'static str = "http://logs.tf/api/v1";
+pub mod stats;
+pub mod summary;
 
-pub fn init(pool: &Pool<MySql>, uploader: u64, http: Arc<Http>, channel_id: ChannelId) {
+use stats::{get_linked_uids, record_pug_stats};
+use summary::{fetch_log, LogJson};
+
+/// polls the uploader's latest logs, posting a summary of each new one and recording pug stats
+pub fn init(
+    pool: &Pool<MySql>,
+    base_url: String,
+    uploader: u64,
+    http: Arc<Http>,
+    channel_id: ChannelId,
+) {
     let pool = pool.clone();
     tokio::spawn(async move {
         let mut last_id: i64 = sqlx::query!("SELECT `id` FROM `logstf_lastposted` LIMIT 1")
@@ -19,7 +30,7 @@ pub fn init(pool: &Pool<MySql>, uploader: u64, http: Arc<Http>, channel_id: Chan
         let mut interval = tokio::time::interval(Duration::from_secs(60));
         loop {
             interval.tick().await;
-            let mut logs = match fetch_last_logs(uploader).await {
+            let mut logs = match fetch_last_logs(&base_url, uploader).await {
                 Ok(logs) => logs,
                 Err(e) => {
                     log::error!("Could not fetch logs: {e}");
@@ -31,8 +42,16 @@ pub fn init(pool: &Pool<MySql>, uploader: u64, http: Arc<Http>, channel_id: Chan
             for log in &logs {
                 if log.id > last_id {
                     last_id = log.id;
+                    let url = log.url(&base_url);
+                    let message = match fetch_log(&base_url, log.id).await {
+                        Ok(json) => summarize(&pool, &json, &url).await,
+                        Err(e) => {
+                            log::error!("Could not fetch log {}: {e}", log.id);
+                            CreateMessage::new().content(url)
+                        }
+                    };
                     let _ = channel_id
-                        .send_message(&http, CreateMessage::new().content(log.url()))
+                        .send_message(&http, message)
                         .await
                         .inspect_err(|e| log::error!("Could not send logstf message: {e}"));
                 }
@@ -46,14 +65,29 @@ pub fn init(pool: &Pool<MySql>, uploader: u64, http: Arc<Http>, channel_id: Chan
     });
 }
 
+/// records the log's pug stats and builds the summary message
+async fn summarize(pool: &Pool<MySql>, json: &LogJson, url: &str) -> CreateMessage {
+    let _ = record_pug_stats(pool, json)
+        .await
+        .inspect_err(|e| log::error!("Could not record pug stats: {e}"));
+    let linked = get_linked_uids(pool, json.players.keys().cloned().collect())
+        .await
+        .inspect_err(|e| log::error!("Could not fetch linked profiles: {e}"))
+        .unwrap_or_default();
+    CreateMessage::new()
+        .content(url)
+        .embed(json.to_embed(url, &linked))
+        .allowed_mentions(CreateAllowedMentions::new().empty_users().empty_roles())
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 struct Log {
     id: i64,
 }
 
 impl Log {
-    fn url(&self) -> String {
-        format!("https://logs.tf/{}", self.id)
+    fn url(&self, base_url: &str) -> String {
+        format!("{}/{}", base_url, self.id)
     }
 }
 
@@ -62,8 +96,8 @@ struct LogResponse {
     logs: Vec<Log>,
 }
 
-async fn fetch_last_logs(uploader: u64) -> Result<Vec<Log>, Error> {
-    let response = reqwest::get(format!("{API_BASE}/log?uploader={uploader}&limit=4"))
+async fn fetch_last_logs(base_url: &str, uploader: u64) -> Result<Vec<Log>, Error> {
+    let response = reqwest::get(format!("{base_url}/api/v1/log?uploader={uploader}&limit=4"))
         .await?
         .json::<LogResponse>()
         .await?;
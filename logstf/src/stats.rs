@@ -0,0 +1,112 @@
+use std::collections::HashMap;
+
+use sqlx::{MySql, Pool, QueryBuilder};
+
+use common::Error;
+
+use crate::summary::LogJson;
+
+/// Cumulative stats of a player across all posted pug logs
+#[derive(sqlx::FromRow, Clone, Debug)]
+pub struct PugStats {
+    pub steamid: String,
+    pub name: String,
+    pub games: i32,
+    pub wins: i32,
+    pub losses: i32,
+    pub kills: i32,
+    pub deaths: i32,
+    pub assists: i32,
+    pub damage: i64,
+    pub heals: i64,
+    pub ubers: i32,
+    pub playtime: i64,
+}
+
+impl PugStats {
+    pub fn dpm(&self) -> f32 {
+        self.damage as f32 / (self.playtime.max(1) as f32 / 60.0)
+    }
+
+    pub fn kd(&self) -> f32 {
+        self.kills as f32 / self.deaths.max(1) as f32
+    }
+
+    pub fn winrate(&self) -> f32 {
+        self.wins as f32 / self.games.max(1) as f32
+    }
+}
+
+/// adds every player's stats from the log to their pug totals
+pub async fn record_pug_stats(pool: &Pool<MySql>, log: &LogJson) -> Result<(), Error> {
+    if log.players.is_empty() {
+        return Ok(());
+    }
+    let mut qb = QueryBuilder::<MySql>::new(
+        "INSERT INTO `pug_stats` (`steamid`, `name`, `games`, `wins`, `losses`, `kills`, `deaths`, `assists`, `damage`, `heals`, `ubers`, `playtime`) ",
+    );
+    qb.push_values(&log.players, |mut b, (steamid, p)| {
+        let outcome = log.player_outcome(p);
+        b.push_bind(steamid)
+            .push_bind(log.name(steamid))
+            .push_bind(1)
+            .push_bind((outcome == Some(true)) as i32)
+            .push_bind((outcome == Some(false)) as i32)
+            .push_bind(p.kills)
+            .push_bind(p.deaths)
+            .push_bind(p.assists)
+            .push_bind(p.dmg)
+            .push_bind(p.heal)
+            .push_bind(p.ubers)
+            .push_bind(p.class_stats.iter().map(|c| c.total_time).sum::<i64>());
+    });
+    qb.push(
+        r#" ON DUPLICATE KEY UPDATE
+        `name` = VALUES(`name`),
+        `games` = `games` + VALUES(`games`),
+        `wins` = `wins` + VALUES(`wins`),
+        `losses` = `losses` + VALUES(`losses`),
+        `kills` = `kills` + VALUES(`kills`),
+        `deaths` = `deaths` + VALUES(`deaths`),
+        `assists` = `assists` + VALUES(`assists`),
+        `damage` = `damage` + VALUES(`damage`),
+        `heals` = `heals` + VALUES(`heals`),
+        `ubers` = `ubers` + VALUES(`ubers`),
+        `playtime` = `playtime` + VALUES(`playtime`)"#,
+    );
+    qb.build().execute(pool).await?;
+    Ok(())
+}
+
+/// fetch the pug stats of every player with at least `min_games` games
+pub async fn get_pug_stats(pool: &Pool<MySql>, min_games: i32) -> Result<Vec<PugStats>, Error> {
+    Ok(sqlx::query_as!(
+        PugStats,
+        "SELECT * FROM `pug_stats` WHERE `games` >= ?",
+        min_games
+    )
+    .fetch_all(pool)
+    .await?)
+}
+
+/// maps steamid3s to the discord uids of the profiles they are linked to
+pub async fn get_linked_uids(
+    pool: &Pool<MySql>,
+    steamids: Vec<String>,
+) -> Result<HashMap<String, String>, Error> {
+    if steamids.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let mut qb =
+        QueryBuilder::<MySql>::new("SELECT `uid`, `steamid` FROM `profiles` WHERE `steamid` IN ");
+    qb.push_tuples(steamids, |mut b, steamid| {
+        b.push_bind(steamid);
+    });
+    Ok(qb
+        .build_query_as::<(String, String)>()
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .map(|(uid, steamid)| (steamid, uid))
+        .collect())
+}
@@ -0,0 +1,237 @@
+use std::collections::HashMap;
+
+use poise::serenity_prelude::{Color, CreateEmbed, CreateEmbedFooter, Timestamp};
+use serde::{Deserialize, Serialize};
+
+use common::{util::hhmmss, Error};
+
+/// The parts of a logs.tf json log that we use
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct LogJson {
+    pub teams: Teams,
+    pub length: i64,
+    pub players: HashMap<String, PlayerStats>,
+    pub names: HashMap<String, String>,
+    pub info: Info,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Teams {
+    #[serde(rename = "Red")]
+    pub red: TeamStats,
+    #[serde(rename = "Blue")]
+    pub blue: TeamStats,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct TeamStats {
+    pub score: i32,
+    pub kills: i32,
+    pub dmg: i64,
+    pub charges: i32,
+    pub drops: i32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct PlayerStats {
+    pub team: String,
+    pub kills: i32,
+    pub deaths: i32,
+    pub assists: i32,
+    pub dmg: i64,
+    pub dapm: i64,
+    pub ubers: i32,
+    pub drops: i32,
+    pub heal: i64,
+    pub class_stats: Vec<ClassStats>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct ClassStats {
+    #[serde(rename = "type")]
+    pub class: String,
+    pub total_time: i64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct Info {
+    pub map: String,
+    pub title: String,
+    pub date: i64,
+}
+
+/// Which team won a log
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    Red,
+    Blue,
+    Tie,
+}
+
+impl PlayerStats {
+    pub fn kd(&self) -> f32 {
+        self.kills as f32 / self.deaths.max(1) as f32
+    }
+
+    /// the class played the longest
+    pub fn main_class(&self) -> Option<&str> {
+        self.class_stats
+            .iter()
+            .max_by_key(|c| c.total_time)
+            .map(|c| c.class.as_str())
+    }
+}
+
+impl LogJson {
+    pub fn outcome(&self) -> Outcome {
+        match self.teams.red.score.cmp(&self.teams.blue.score) {
+            std::cmp::Ordering::Greater => Outcome::Red,
+            std::cmp::Ordering::Less => Outcome::Blue,
+            std::cmp::Ordering::Equal => Outcome::Tie,
+        }
+    }
+
+    /// whether the player's team won, lost or tied
+    pub fn player_outcome(&self, player: &PlayerStats) -> Option<bool> {
+        match (self.outcome(), player.team.as_str()) {
+            (Outcome::Tie, _) => None,
+            (Outcome::Red, team) => Some(team == "Red"),
+            (Outcome::Blue, team) => Some(team == "Blue"),
+        }
+    }
+
+    pub fn name(&self, steamid: &str) -> &str {
+        self.names
+            .get(steamid)
+            .map(|s| s.as_str())
+            .unwrap_or(steamid)
+    }
+
+    /// the player with the highest value of the given stat
+    pub fn top_by<T: PartialOrd>(
+        &self,
+        f: impl Fn(&PlayerStats) -> T,
+    ) -> Option<(&String, &PlayerStats)> {
+        self.players.iter().max_by(|a, b| {
+            f(a.1)
+                .partial_cmp(&f(b.1))
+                .unwrap_or(std::cmp::Ordering::Equal)
+        })
+    }
+
+    /// builds the summary embed. `linked` maps steamid3s to discord user ids
+    pub fn to_embed(&self, url: &str, linked: &HashMap<String, String>) -> CreateEmbed {
+        let display = |steamid: &str| match linked.get(steamid) {
+            Some(uid) => format!("**{}** (<@{}>)", self.name(steamid), uid),
+            None => format!("**{}**", self.name(steamid)),
+        };
+        let team_summary = |team: &TeamStats| {
+            format!(
+                "Kills: `{}`\nDamage: `{}`\nUbers: `{}`\nDrops: `{}`",
+                team.kills, team.dmg, team.charges, team.drops
+            )
+        };
+
+        let mut top = vec![];
+        if let Some((sid, p)) = self.top_by(|p| p.dapm) {
+            top.push(format!("DPM: {} `{}`", display(sid), p.dapm));
+        }
+        if let Some((sid, p)) = self.top_by(|p| p.kd()) {
+            top.push(format!("K/D: {} `{:.2}`", display(sid), p.kd()));
+        }
+        if let Some((sid, p)) = self.top_by(|p| p.heal).filter(|(_, p)| p.heal > 0) {
+            top.push(format!("Heals: {} `{}`", display(sid), p.heal));
+        }
+        if let Some((sid, p)) = self.top_by(|p| p.ubers).filter(|(_, p)| p.ubers > 0) {
+            top.push(format!("Ubers: {} `{}`", display(sid), p.ubers));
+        }
+
+        let color = match self.outcome() {
+            Outcome::Red => Color::RED,
+            Outcome::Blue => Color::BLUE,
+            Outcome::Tie => Color::LIGHT_GREY,
+        };
+
+        let mut embed = CreateEmbed::new()
+            .title(&self.info.title)
+            .url(url)
+            .color(color)
+            .description(format!(
+                "`{}` | **RED {} - {} BLU** | `{}`",
+                self.info.map,
+                self.teams.red.score,
+                self.teams.blue.score,
+                hhmmss(self.length.max(0) as u64)
+            ))
+            .field("RED", team_summary(&self.teams.red), true)
+            .field("BLU", team_summary(&self.teams.blue), true)
+            .field("Top performers", top.join("\n"), false)
+            .footer(CreateEmbedFooter::new(format!(
+                "{} players",
+                self.players.len()
+            )));
+        if let Ok(ts) = Timestamp::from_unix_timestamp(self.info.date) {
+            embed = embed.timestamp(ts);
+        }
+        embed
+    }
+}
+
+/// fetch the full json of a log
+pub async fn fetch_log(base_url: &str, id: i64) -> Result<LogJson, Error> {
+    Ok(reqwest::get(format!("{base_url}/json/{id}"))
+        .await?
+        .error_for_status()?
+        .json::<LogJson>()
+        .await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    const FIXTURE: &str = include_str!("../fixtures/log.json");
+
+    #[test]
+    fn test_parse_fixture() {
+        let log: LogJson = serde_json::from_str(FIXTURE).unwrap();
+        assert_eq!(log.info.map, "koth_product_final");
+        assert_eq!(log.outcome(), Outcome::Red);
+        assert_eq!(log.top_by(|p| p.dapm).unwrap().0, "[U:1:2001]");
+        assert_eq!(log.top_by(|p| p.heal).unwrap().0, "[U:1:1002]");
+        let medic = &log.players["[U:1:1002]"];
+        assert_eq!(medic.main_class(), Some("medic"));
+        assert_eq!(log.player_outcome(medic), Some(true));
+        assert_eq!(log.player_outcome(&log.players["[U:1:2001]"]), Some(false));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_from_stand_in() {
+        // serves the fixture to a single request, in place of logs.tf
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut sock, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 1024];
+            let _ = sock.read(&mut buf).await.unwrap();
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                FIXTURE.len(),
+                FIXTURE
+            );
+            sock.write_all(response.as_bytes()).await.unwrap();
+        });
+
+        let log = fetch_log(&format!("http://{addr}"), 1234).await.unwrap();
+        assert_eq!(log.teams.red.score, 3);
+        assert_eq!(log.name("[U:1:1001]"), "kitty");
+    }
+}
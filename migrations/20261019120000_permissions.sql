@@ -0,0 +1,12 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS `command_permissions` (
+	`command` varchar(64) NOT NULL,
+	`rid` varchar(32) NOT NULL,
+	PRIMARY KEY (`command`, `rid`)
+);
+
+CREATE TABLE IF NOT EXISTS `rcon_permissions` (
+	`server` varchar(64) NOT NULL,
+	`rid` varchar(32) NOT NULL,
+	PRIMARY KEY (`server`, `rid`)
+);
@@ -0,0 +1,8 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS `demo_pins` (
+	`server` varchar(64) NOT NULL,
+	`name` varchar(255) NOT NULL,
+	`reason` varchar(255) NOT NULL,
+	`created_at` DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+	PRIMARY KEY (`server`, `name`)
+);
@@ -0,0 +1,15 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS `pug_stats` (
+	`steamid` varchar(32) PRIMARY KEY NOT NULL,
+	`name` varchar(64) NOT NULL,
+	`games` INT NOT NULL DEFAULT 0,
+	`wins` INT NOT NULL DEFAULT 0,
+	`losses` INT NOT NULL DEFAULT 0,
+	`kills` INT NOT NULL DEFAULT 0,
+	`deaths` INT NOT NULL DEFAULT 0,
+	`assists` INT NOT NULL DEFAULT 0,
+	`damage` BIGINT NOT NULL DEFAULT 0,
+	`heals` BIGINT NOT NULL DEFAULT 0,
+	`ubers` INT NOT NULL DEFAULT 0,
+	`playtime` BIGINT NOT NULL DEFAULT 0
+);
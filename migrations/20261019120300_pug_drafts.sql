@@ -0,0 +1,31 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS `pug_drafts` (
+	`id` INT AUTO_INCREMENT PRIMARY KEY NOT NULL,
+	`red_captain` varchar(32), -- NULL when teams were balanced by rating
+	`blu_captain` varchar(32),
+	`winner` varchar(8), -- red / blu / tie, NULL until the result is in
+	`red_score` INT,
+	`blu_score` INT,
+	`created_at` TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+	`finished_at` TIMESTAMP NULL
+);
+
+CREATE TABLE IF NOT EXISTS `pug_draft_players` (
+	`draft_id` INT NOT NULL,
+	`uid` varchar(32) NOT NULL,
+	`team` varchar(8) NOT NULL, -- red / blu
+	`pick` INT, -- pick order, NULL for captains and balanced teams
+	`rating_before` DOUBLE,
+	`rating_after` DOUBLE,
+	CONSTRAINT `pug_draft_players_pk` PRIMARY KEY (`draft_id`, `uid`),
+	FOREIGN KEY (`draft_id`) REFERENCES `pug_drafts`(`id`) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS `pug_ratings` (
+	`uid` varchar(32) PRIMARY KEY NOT NULL,
+	`rating` DOUBLE NOT NULL DEFAULT 1500,
+	`games` INT NOT NULL DEFAULT 0,
+	`wins` INT NOT NULL DEFAULT 0,
+	`losses` INT NOT NULL DEFAULT 0,
+	`ties` INT NOT NULL DEFAULT 0
+);
@@ -0,0 +1,29 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS `rivalries` (
+	`lt_steamid` varchar(32) NOT NULL,
+	`gt_steamid` varchar(32) NOT NULL,
+	`lt_kills` INT NOT NULL DEFAULT 0,
+	`gt_kills` INT NOT NULL DEFAULT 0,
+	`lt_dominations` INT NOT NULL DEFAULT 0,
+	`gt_dominations` INT NOT NULL DEFAULT 0,
+	`lt_revenges` INT NOT NULL DEFAULT 0,
+	`gt_revenges` INT NOT NULL DEFAULT 0,
+	`leader` varchar(32), -- steamid with more dominations, kept through ties
+	`updated_at` TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+	CONSTRAINT `rivalries_pk` PRIMARY KEY (`lt_steamid`, `gt_steamid`)
+);
+
+CREATE TABLE IF NOT EXISTS `rivalry_players` (
+	`steamid` varchar(32) PRIMARY KEY NOT NULL,
+	`name` varchar(64) NOT NULL
+);
+
+-- carry over the net domination scores. a negative score means lt dominated gt more
+INSERT IGNORE INTO `rivalries` (`lt_steamid`, `gt_steamid`, `lt_dominations`, `gt_dominations`, `leader`)
+SELECT
+	`lt_steamid`,
+	`gt_steamid`,
+	GREATEST(-`score`, 0),
+	GREATEST(`score`, 0),
+	CASE WHEN `score` < 0 THEN `lt_steamid` WHEN `score` > 0 THEN `gt_steamid` ELSE NULL END
+FROM `domination`;
@@ -0,0 +1,20 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS `achievements` (
+	`uid` varchar(32) NOT NULL,
+	`achievement` varchar(64) NOT NULL,
+	`unlocked_at` TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+	CONSTRAINT `achievements_pk` PRIMARY KEY (`uid`, `achievement`)
+);
+
+CREATE TABLE IF NOT EXISTS `game_wins` (
+	`uid` varchar(32) NOT NULL,
+	`game` varchar(16) NOT NULL,
+	`wins` INT NOT NULL DEFAULT 0,
+	CONSTRAINT `game_wins_pk` PRIMARY KEY (`uid`, `game`)
+);
+
+-- yapawards resets daily, so keep a running total alongside it
+ALTER TABLE `yapawards`
+ADD COLUMN `lifetime` BIGINT NOT NULL DEFAULT 0;
+
+UPDATE `yapawards` SET `lifetime` = `count`;
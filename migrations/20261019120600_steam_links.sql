@@ -0,0 +1,35 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS `steam_link_codes` (
+	`code` varchar(8) PRIMARY KEY NOT NULL,
+	`steamid64` BIGINT UNSIGNED NOT NULL,
+	`expires_at` TIMESTAMP NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS `steam_link_history` (
+	`id` INT PRIMARY KEY NOT NULL AUTO_INCREMENT,
+	`uid` varchar(32) NOT NULL, -- the discord user whose link changed
+	`steamid` varchar(32) NOT NULL,
+	`action` ENUM ('Link', 'Unlink', 'ForceLink', 'ForceUnlink') NOT NULL,
+	`by_uid` varchar(32) NOT NULL, -- who made the change
+	`created_at` TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+	INDEX `steam_link_history_uid` (`uid`),
+	INDEX `steam_link_history_steamid` (`steamid`)
+);
+
+-- a steam account may only be linked to one discord user. keep the most recent of any duplicates.
+-- by_uid 1 is the bot itself
+INSERT INTO `steam_link_history` (`uid`, `steamid`, `action`, `by_uid`)
+SELECT `uid`, `steamid`, 'ForceUnlink', '1' FROM (
+	SELECT `uid`, `steamid`, ROW_NUMBER() OVER (PARTITION BY `steamid` ORDER BY `updated_at` DESC, `uid` ASC) AS `n`
+	FROM `profiles` WHERE `steamid` IS NOT NULL
+) d WHERE d.`n` > 1;
+
+UPDATE `profiles` p
+JOIN (
+	SELECT `uid`, ROW_NUMBER() OVER (PARTITION BY `steamid` ORDER BY `updated_at` DESC, `uid` ASC) AS `n`
+	FROM `profiles` WHERE `steamid` IS NOT NULL
+) d ON d.`uid` = p.`uid`
+SET p.`steamid` = NULL
+WHERE d.`n` > 1;
+
+CREATE UNIQUE INDEX `profiles_steamid_unique` ON `profiles` (`steamid`);
@@ -0,0 +1,8 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS `map_stats` (
+	`map` varchar(64) PRIMARY KEY NOT NULL,
+	`seconds_played` BIGINT NOT NULL DEFAULT 0, -- time spent on the map with players online
+	`player_seconds` BIGINT NOT NULL DEFAULT 0, -- summed over every player online
+	`peak_players` INT NOT NULL DEFAULT 0,
+	`last_played` TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
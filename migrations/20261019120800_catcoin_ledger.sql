@@ -0,0 +1,20 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS `catcoin_ledger` (
+	`id` BIGINT UNSIGNED PRIMARY KEY NOT NULL AUTO_INCREMENT,
+	-- NULL is the house, which mints and burns catcoin
+	`from_uid` varchar(32) NULL,
+	`to_uid` varchar(32) NULL,
+	`amount` BIGINT UNSIGNED NOT NULL,
+	`reason` ENUM ('Opening', 'Pull', 'Trip', 'Drop', 'Duel', 'Poker', 'Bet', 'Stocks', 'Transfer', 'YapAward', 'Achievement', 'Refund') NOT NULL,
+	-- e.g. the message or game the entry belongs to
+	`reference` varchar(64) NULL,
+	`created_at` TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+	INDEX `catcoin_ledger_from` (`from_uid`, `id`),
+	INDEX `catcoin_ledger_to` (`to_uid`, `id`)
+);
+
+-- opening balances, so existing wallets reconcile against the ledger
+INSERT INTO `catcoin_ledger` (`from_uid`, `to_uid`, `amount`, `reason`)
+SELECT NULL, `uid`, `catcoin`, 'Opening' FROM `catcoin` WHERE `catcoin` > 0;
+INSERT INTO `catcoin_ledger` (`from_uid`, `to_uid`, `amount`, `reason`)
+SELECT `uid`, NULL, -`catcoin`, 'Opening' FROM `catcoin` WHERE `catcoin` < 0;
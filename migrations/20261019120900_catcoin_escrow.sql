@@ -0,0 +1,14 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS `catcoin_escrow` (
+	`id` BIGINT UNSIGNED PRIMARY KEY NOT NULL AUTO_INCREMENT,
+	`uid` varchar(32) NOT NULL, -- who the catcoin is held for
+	`amount` BIGINT UNSIGNED NOT NULL,
+	`reason` varchar(16) NOT NULL, -- the ledger reason of the hold
+	`reference` varchar(64) NOT NULL, -- the game or drop holding it
+	`channel_id` varchar(32) NULL,
+	`message_id` varchar(32) NULL,
+	`created_at` TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+	`expires_at` TIMESTAMP NOT NULL,
+	INDEX `catcoin_escrow_reference` (`reference`),
+	INDEX `catcoin_escrow_expires_at` (`expires_at`)
+);
@@ -0,0 +1,38 @@
+-- Add migration script here
+ALTER TABLE `catcoin_ledger` MODIFY `reason` ENUM ('Opening', 'Pull', 'Trip', 'Drop', 'Duel', 'Poker', 'Bet', 'Stocks', 'Transfer', 'YapAward', 'Achievement', 'Refund', 'Shop') NOT NULL;
+
+CREATE TABLE IF NOT EXISTS `catcoin_shop_item` (
+	`id` INT UNSIGNED PRIMARY KEY NOT NULL AUTO_INCREMENT,
+	`name` varchar(64) NOT NULL,
+	`description` varchar(255) NOT NULL,
+	`kind` ENUM ('ColorRole', 'ProfileBackground', 'RelayTag', 'MapNomination', 'ReservedSlot') NOT NULL,
+	`price` BIGINT UNSIGNED NOT NULL,
+	-- how long the perk lasts, NULL for one-time items
+	`duration_days` INT UNSIGNED NULL,
+	`enabled` BOOLEAN NOT NULL DEFAULT true,
+	`created_at` TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE IF NOT EXISTS `catcoin_purchase` (
+	`id` BIGINT UNSIGNED PRIMARY KEY NOT NULL AUTO_INCREMENT,
+	`uid` varchar(32) NOT NULL,
+	`item_id` INT UNSIGNED NOT NULL,
+	`kind` ENUM ('ColorRole', 'ProfileBackground', 'RelayTag', 'MapNomination', 'ReservedSlot') NOT NULL,
+	`price` BIGINT UNSIGNED NOT NULL, -- what was paid, in case the price changes later
+	`input` varchar(255) NULL, -- e.g. the role color, relay tag or map
+	`role_id` varchar(32) NULL, -- the discord role created for the perk
+	`steamid` varchar(32) NULL, -- the steam account given the perk
+	`created_at` TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+	`expires_at` TIMESTAMP NULL,
+	`expired` BOOLEAN NOT NULL DEFAULT false,
+	INDEX `catcoin_purchase_uid` (`uid`, `kind`),
+	INDEX `catcoin_purchase_expires_at` (`expired`, `expires_at`),
+	FOREIGN KEY (`item_id`) REFERENCES `catcoin_shop_item` (`id`)
+);
+
+INSERT INTO `catcoin_shop_item` (`name`, `description`, `kind`, `price`, `duration_days`) VALUES
+	('Custom color', 'A role in the color of your choice', 'ColorRole', 500, 7),
+	('Profile background', 'An image behind your /profile card', 'ProfileBackground', 300, 30),
+	('Relay tag', 'A tag before your name in the server chat relay', 'RelayTag', 250, 14),
+	('Map nomination', 'Nominate a map for the next vote on a server', 'MapNomination', 100, NULL),
+	('Reserved slot', 'A reserved slot on the servers for your linked steam account', 'ReservedSlot', 1000, 30);
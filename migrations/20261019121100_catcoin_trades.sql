@@ -0,0 +1,24 @@
+-- Add migration script here
+ALTER TABLE `catcoin_ledger` MODIFY `reason` ENUM ('Opening', 'Pull', 'Trip', 'Drop', 'Duel', 'Poker', 'Bet', 'Stocks', 'Transfer', 'YapAward', 'Achievement', 'Refund', 'Shop', 'Trade') NOT NULL;
+
+CREATE TABLE IF NOT EXISTS `catcoin_trade` (
+	`id` BIGINT UNSIGNED PRIMARY KEY NOT NULL AUTO_INCREMENT,
+	`from_uid` varchar(32) NOT NULL, -- who started the trade
+	`to_uid` varchar(32) NOT NULL,
+	`from_catcoin` BIGINT UNSIGNED NOT NULL DEFAULT 0,
+	`to_catcoin` BIGINT UNSIGNED NOT NULL DEFAULT 0,
+	`created_at` TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+	INDEX `catcoin_trade_from` (`from_uid`),
+	INDEX `catcoin_trade_to` (`to_uid`)
+);
+
+-- every pull that changed hands, so numbered pulls keep their history
+CREATE TABLE IF NOT EXISTS `catcoin_trade_item` (
+	`id` BIGINT UNSIGNED PRIMARY KEY NOT NULL AUTO_INCREMENT,
+	`trade_id` BIGINT UNSIGNED NOT NULL,
+	`inv_id` INT NOT NULL,
+	`from_uid` varchar(32) NOT NULL,
+	`to_uid` varchar(32) NOT NULL,
+	INDEX `catcoin_trade_item_inv` (`inv_id`),
+	FOREIGN KEY (`trade_id`) REFERENCES `catcoin_trade` (`id`)
+);
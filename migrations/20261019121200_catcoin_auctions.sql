@@ -0,0 +1,20 @@
+-- Add migration script here
+ALTER TABLE `catcoin_ledger` MODIFY `reason` ENUM ('Opening', 'Pull', 'Trip', 'Drop', 'Duel', 'Poker', 'Bet', 'Stocks', 'Transfer', 'YapAward', 'Achievement', 'Refund', 'Shop', 'Trade', 'Auction') NOT NULL;
+
+-- durable escrows, like auction bids, are kept across restarts
+ALTER TABLE `catcoin_escrow` ADD `durable` BOOLEAN NOT NULL DEFAULT false;
+
+CREATE TABLE IF NOT EXISTS `catcoin_auction` (
+	`id` BIGINT UNSIGNED PRIMARY KEY NOT NULL AUTO_INCREMENT,
+	`seller_uid` varchar(32) NOT NULL,
+	`inv_id` INT NOT NULL, -- the pull up for auction
+	`start_price` BIGINT UNSIGNED NOT NULL,
+	`buy_now` BIGINT UNSIGNED NULL,
+	`bid` BIGINT UNSIGNED NULL, -- the highest bid, held in escrow
+	`bidder_uid` varchar(32) NULL,
+	`created_at` TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+	`ends_at` TIMESTAMP NOT NULL,
+	`settled` BOOLEAN NOT NULL DEFAULT false,
+	INDEX `catcoin_auction_inv` (`inv_id`, `settled`),
+	INDEX `catcoin_auction_ends_at` (`settled`, `ends_at`)
+);
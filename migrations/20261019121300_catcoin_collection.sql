@@ -0,0 +1,48 @@
+-- Add migration script here
+ALTER TABLE `catcoin_ledger` MODIFY `reason` ENUM ('Opening', 'Pull', 'Trip', 'Drop', 'Duel', 'Poker', 'Bet', 'Stocks', 'Transfer', 'YapAward', 'Achievement', 'Refund', 'Shop', 'Trade', 'Auction', 'SetBonus') NOT NULL;
+
+-- who pulled each reward first
+CREATE TABLE IF NOT EXISTS `catcoin_first_pull` (
+	`rid` INT PRIMARY KEY NOT NULL,
+	`uid` varchar(32) NOT NULL,
+	`inv_id` INT NOT NULL,
+	`created_at` DATETIME NOT NULL,
+	FOREIGN KEY (`rid`) REFERENCES `catcoin_reward` (`id`)
+);
+
+INSERT IGNORE INTO `catcoin_first_pull` (`rid`, `uid`, `inv_id`, `created_at`)
+	SELECT `rid`, `uid`, `id`, `created_at` FROM `catcoin_inv` ORDER BY `created_at` ASC, `id` ASC;
+
+-- sets of rewards that pay a bonus once a user owns all of them.
+-- a set with a rarity is every reward of that rarity, otherwise it's the rewards in catcoin_set_reward
+CREATE TABLE IF NOT EXISTS `catcoin_set` (
+	`id` INT UNSIGNED PRIMARY KEY NOT NULL AUTO_INCREMENT,
+	`name` varchar(64) NOT NULL,
+	`rarity` ENUM ('Common', 'Rare', 'Fluffy', 'Peak') NULL,
+	`bonus` BIGINT UNSIGNED NOT NULL,
+	`enabled` BOOLEAN NOT NULL DEFAULT true,
+	`created_at` TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE IF NOT EXISTS `catcoin_set_reward` (
+	`set_id` INT UNSIGNED NOT NULL,
+	`rid` INT NOT NULL,
+	PRIMARY KEY (`set_id`, `rid`),
+	FOREIGN KEY (`set_id`) REFERENCES `catcoin_set` (`id`),
+	FOREIGN KEY (`rid`) REFERENCES `catcoin_reward` (`id`)
+);
+
+-- bonuses already paid, each user gets a set's bonus once
+CREATE TABLE IF NOT EXISTS `catcoin_set_completion` (
+	`set_id` INT UNSIGNED NOT NULL,
+	`uid` varchar(32) NOT NULL,
+	`created_at` TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+	PRIMARY KEY (`set_id`, `uid`),
+	FOREIGN KEY (`set_id`) REFERENCES `catcoin_set` (`id`)
+);
+
+INSERT INTO `catcoin_set` (`name`, `rarity`, `bonus`) VALUES
+	('Every Common', 'Common', 100),
+	('Every Rare', 'Rare', 500),
+	('Every Fluffy', 'Fluffy', 2000),
+	('Every Peak', 'Peak', 5000);
@@ -0,0 +1,47 @@
+-- Add migration script here
+-- how likely each random event is per message, as one in `one_in`
+CREATE TABLE IF NOT EXISTS `catcoin_chance` (
+	`event` ENUM ('Pull', 'Trip', 'Duel', 'Poker') PRIMARY KEY NOT NULL,
+	`one_in` INT UNSIGNED NOT NULL
+);
+
+INSERT INTO `catcoin_chance` (`event`, `one_in`) VALUES
+	('Pull', 500),
+	('Trip', 3000),
+	('Duel', 2500),
+	('Poker', 2000);
+
+-- how likely each rarity is once a pull happens, and how much catcoin it pays
+CREATE TABLE IF NOT EXISTS `catcoin_rarity_odds` (
+	`rarity` ENUM ('Common', 'Rare', 'Fluffy', 'Peak') PRIMARY KEY NOT NULL,
+	`weight` INT UNSIGNED NOT NULL,
+	`catcoin_mean` DOUBLE NOT NULL,
+	`catcoin_std_dev` DOUBLE NOT NULL
+);
+
+INSERT INTO `catcoin_rarity_odds` (`rarity`, `weight`, `catcoin_mean`, `catcoin_std_dev`) VALUES
+	('Common', 69, 2, 0.5),
+	('Rare', 26, 10, 2),
+	('Fluffy', 5, 80, 5),
+	('Peak', 1, 300, 50);
+
+-- guarantees a Rare or better after this many pulls without one. 0 turns pity off
+CREATE TABLE IF NOT EXISTS `catcoin_pity_config` (
+	`id` TINYINT UNSIGNED PRIMARY KEY NOT NULL DEFAULT 1,
+	`pulls` INT UNSIGNED NOT NULL
+);
+
+INSERT INTO `catcoin_pity_config` (`id`, `pulls`) VALUES (1, 0);
+
+-- pulls each user has had since their last Rare or better
+CREATE TABLE IF NOT EXISTS `catcoin_pity` (
+	`uid` varchar(32) PRIMARY KEY NOT NULL,
+	`pulls` INT UNSIGNED NOT NULL DEFAULT 0
+);
+
+-- multiplies every chance in a channel, e.g. for event weekends
+CREATE TABLE IF NOT EXISTS `catcoin_channel_multiplier` (
+	`channel_id` varchar(32) PRIMARY KEY NOT NULL,
+	`multiplier` DOUBLE NOT NULL,
+	`expires_at` TIMESTAMP NULL
+);
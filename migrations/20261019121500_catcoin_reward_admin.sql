@@ -0,0 +1,3 @@
+-- Add migration script here
+-- retired rewards stay in inventories but can't be pulled anymore
+ALTER TABLE `catcoin_reward` ADD COLUMN `retired` BOOLEAN NOT NULL DEFAULT FALSE;
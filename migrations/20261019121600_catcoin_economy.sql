@@ -0,0 +1,21 @@
+-- Add migration script here
+-- the state of the catcoin economy at the end of each day
+CREATE TABLE IF NOT EXISTS `catcoin_economy_snapshot` (
+	`day` DATE PRIMARY KEY NOT NULL,
+	-- total catcoin across every wallet
+	`supply` BIGINT NOT NULL,
+	-- inequality of wallet balances, 0 is perfectly equal and 1 is one wallet holding everything
+	`gini` DOUBLE NOT NULL,
+	-- wallets with a ledger entry that day
+	`active_wallets` INT UNSIGNED NOT NULL,
+	`created_at` TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- catcoin minted by and burned into the house each day, by reason
+CREATE TABLE IF NOT EXISTS `catcoin_economy_flow` (
+	`day` DATE NOT NULL,
+	`reason` ENUM ('Opening', 'Pull', 'Trip', 'Drop', 'Duel', 'Poker', 'Bet', 'Stocks', 'Transfer', 'YapAward', 'Achievement', 'Refund', 'Shop', 'Trade', 'Auction', 'SetBonus') NOT NULL,
+	`minted` BIGINT UNSIGNED NOT NULL,
+	`burned` BIGINT UNSIGNED NOT NULL,
+	PRIMARY KEY (`day`, `reason`)
+);
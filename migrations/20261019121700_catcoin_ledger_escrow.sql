@@ -0,0 +1,3 @@
+-- Add migration script here
+-- entries that only move catcoin in or out of escrow, which neither mint nor burn it
+ALTER TABLE `catcoin_ledger` ADD COLUMN `escrow` BOOLEAN NOT NULL DEFAULT false;
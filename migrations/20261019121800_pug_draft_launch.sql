@@ -0,0 +1,4 @@
+-- Add migration script here
+ALTER TABLE `pug_drafts`
+	ADD COLUMN `map` varchar(64), -- NULL until the pug is set up on the server
+	ADD COLUMN `launched_at` TIMESTAMP NULL;
@@ -0,0 +1,10 @@
+-- Add migration script here
+-- achievements whose existing progress has been unlocked. the first unlock of an achievement
+-- backfills everyone who already qualified, without rewards or announcements
+CREATE TABLE IF NOT EXISTS `achievement_backfills` (
+	`achievement` varchar(64) PRIMARY KEY NOT NULL,
+	`backfilled_at` TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+INSERT IGNORE INTO `achievement_backfills` (`achievement`)
+SELECT DISTINCT `achievement` FROM `achievements`;
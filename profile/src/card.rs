@@ -0,0 +1,470 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+};
+
+use achievements::Unlock;
+use catcoin::{inventory::CatcoinPull, random_pulls::Rarity, CatcoinWallet};
+use image::{codecs::png::PngEncoder, imageops::FilterType, DynamicImage, ImageEncoder};
+use plotters::{
+    backend::{PixelFormat, RGBPixel},
+    prelude::*,
+};
+use poise::serenity_prelude as serenity;
+use pugs::PugRating;
+use steam::SteamProfileData;
+
+use common::{util::hhmmss, Error};
+
+use crate::UserProfile;
+
+const WIDTH: u32 = 800;
+const HEIGHT: u32 = 400;
+const CACHE_DIR: &str = "cache/cards";
+const BACKGROUND: RGBColor = RGBColor(0x2F, 0x31, 0x36);
+const MUTED: RGBColor = RGBColor(0xB9, 0xBB, 0xBE);
+/// hosts a shop background can be fetched from
+const BACKGROUND_HOSTS: [&str; 2] = ["cdn.discordapp.com", "media.discordapp.net"];
+/// biggest image the card will download
+const MAX_IMAGE_BYTES: usize = 8 * 1024 * 1024;
+/// biggest image the card will decode, in pixels per side
+const MAX_IMAGE_SIDE: u32 = 4096;
+/// left and right margin of the badge row
+const BADGE_MARGIN: i32 = 32;
+const BADGE_GAP: i32 = 10;
+
+/// Everything drawn on a card. A card is re-rendered only when this changes
+#[derive(Hash, Clone, Debug)]
+struct CardData {
+    uid: String,
+    name: String,
+    title: String,
+    description: Option<String>,
+    color: Option<u32>,
+    avatar_url: String,
+    /// discord emoji ids of the class icons
+    classes: Vec<String>,
+    favorite_map: Option<String>,
+    seeding: Option<(i64, i64)>,
+    catcoin: i64,
+    pug_rating: Option<i64>,
+    /// (image file, rarity, pull number)
+    pulls: Vec<(String, String, i32)>,
+    /// names of the newest achievements that fit on the card
+    badges: Vec<String>,
+    /// image url bought from the catcoin shop
+    background: Option<String>,
+}
+
+impl CardData {
+    fn path(&self) -> PathBuf {
+        let mut hasher = DefaultHasher::new();
+        self.hash(&mut hasher);
+        Path::new(CACHE_DIR).join(format!("{}-{:016x}.png", self.uid, hasher.finish()))
+    }
+}
+
+/// the id of a custom discord emoji like `<:scout:1234>`
+fn emoji_id(emoji: &str) -> Option<String> {
+    emoji
+        .trim_end_matches('>')
+        .rsplit(':')
+        .next()
+        .filter(|id| id.chars().all(|c| c.is_ascii_digit()))
+        .map(str::to_owned)
+}
+
+/// Whether the url can be used as a profile background, i.e. a discord attachment
+pub fn is_background_url(url: &str) -> bool {
+    reqwest::Url::parse(url).is_ok_and(|url| {
+        url.scheme() == "https"
+            && url
+                .host_str()
+                .is_some_and(|host| BACKGROUND_HOSTS.contains(&host))
+    })
+}
+
+async fn fetch_image(url: &str) -> Result<DynamicImage, Error> {
+    let mut response = reqwest::get(url).await?.error_for_status()?;
+    if response
+        .content_length()
+        .is_some_and(|len| len > MAX_IMAGE_BYTES as u64)
+    {
+        return Err("Image is too big".into());
+    }
+    let mut bytes = vec![];
+    while let Some(chunk) = response.chunk().await? {
+        if bytes.len() + chunk.len() > MAX_IMAGE_BYTES {
+            return Err("Image is too big".into());
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    tokio::task::spawn_blocking(move || {
+        let mut limits = image::Limits::default();
+        limits.max_image_width = Some(MAX_IMAGE_SIDE);
+        limits.max_image_height = Some(MAX_IMAGE_SIDE);
+        let mut reader =
+            image::ImageReader::new(std::io::Cursor::new(bytes)).with_guessed_format()?;
+        reader.limits(limits);
+        Ok(reader.decode()?)
+    })
+    .await?
+}
+
+/// fetches a discord emoji as an image, caching it on disk
+async fn fetch_emoji(id: &str) -> Result<DynamicImage, Error> {
+    let path = Path::new(CACHE_DIR).join("emoji").join(format!("{id}.png"));
+    if let Ok(image) = image::open(&path) {
+        return Ok(image);
+    }
+    let image = fetch_image(&format!("https://cdn.discordapp.com/emojis/{id}.png")).await?;
+    tokio::fs::create_dir_all(path.parent().unwrap()).await?;
+    image.save(&path)?;
+    Ok(image)
+}
+
+fn rarity_color(rarity: &str) -> RGBColor {
+    let (r, g, b) = Rarity::from(rarity).color().tuple();
+    RGBColor(r, g, b)
+}
+
+/// how wide a badge is drawn
+fn badge_width(name: &str) -> i32 {
+    16 + name.chars().count() as i32 * 9
+}
+
+/// the names of the newest unlocked achievements that fit in the badge row
+fn badges(unlocked: &[Unlock]) -> Vec<String> {
+    let mut badges = vec![];
+    let mut x = BADGE_MARGIN;
+    for unlock in unlocked.iter().rev() {
+        let width = badge_width(unlock.achievement.name);
+        if x + width > WIDTH as i32 - BADGE_MARGIN {
+            break;
+        }
+        badges.push(unlock.achievement.name.to_owned());
+        x += width + BADGE_GAP;
+    }
+    badges
+}
+
+impl UserProfile {
+    /// renders the profile as a png card, reusing the cached render if nothing changed
+    pub async fn to_card(
+        &self,
+        user: &serenity::User,
+        nickname: String,
+        steam_data: Option<SteamProfileData>,
+        catcoin: CatcoinWallet,
+        pug_rating: Option<PugRating>,
+        pulls: Vec<CatcoinPull>,
+        unlocked: Vec<Unlock>,
+        background: Option<String>,
+    ) -> Result<Vec<u8>, Error> {
+        let data = CardData {
+            uid: self.uid.clone(),
+            title: self.title.replace("%", &nickname),
+            name: nickname,
+            description: self.description.clone(),
+            color: self.color,
+            avatar_url: user
+                .static_avatar_url()
+                .unwrap_or(user.default_avatar_url()),
+            classes: self
+                .get_classes()
+                .into_iter()
+                .filter_map(|c| emoji_id(c.emoji()))
+                .collect(),
+            favorite_map: self.favorite_map.clone(),
+            seeding: steam_data.as_ref().and_then(|s| s.seederboard),
+            catcoin: catcoin.catcoin,
+            pug_rating: pug_rating.as_ref().map(|r| r.rating.round() as i64),
+            pulls: pulls
+                .iter()
+                .take(3)
+                .map(|p| (p.reward.file.clone(), p.reward.rarity.to_string(), p.number))
+                .collect(),
+            badges: badges(&unlocked),
+            background,
+        };
+
+        let path = data.path();
+        if let Ok(png) = tokio::fs::read(&path).await {
+            return Ok(png);
+        }
+
+        let avatar = fetch_image(&data.avatar_url)
+            .await
+            .unwrap_or_else(|_| DynamicImage::new_rgb8(128, 128));
+        let background = match &data.background {
+            Some(url) if is_background_url(url) => fetch_image(url).await.ok(),
+            _ => None,
+        };
+        let mut classes = vec![];
+        for id in &data.classes {
+            if let Ok(icon) = fetch_emoji(id).await {
+                classes.push(icon);
+            }
+        }
+
+        let png = {
+            let data = data.clone();
+            tokio::task::spawn_blocking(move || draw_card(&data, avatar, classes, background))
+                .await??
+        };
+
+        // replace any older render of this profile
+        tokio::fs::create_dir_all(CACHE_DIR).await?;
+        if let Ok(mut dir) = tokio::fs::read_dir(CACHE_DIR).await {
+            let prefix = format!("{}-", data.uid);
+            while let Ok(Some(entry)) = dir.next_entry().await {
+                if entry.file_name().to_string_lossy().starts_with(&prefix) {
+                    let _ = tokio::fs::remove_file(entry.path()).await;
+                }
+            }
+        }
+        tokio::fs::write(&path, &png).await?;
+        Ok(png)
+    }
+}
+
+fn draw_card(
+    data: &CardData,
+    avatar: DynamicImage,
+    classes: Vec<DynamicImage>,
+    background: Option<DynamicImage>,
+) -> Result<Vec<u8>, Error> {
+    let accent = data
+        .color
+        .map(|c| RGBColor((c >> 16) as u8, (c >> 8) as u8, c as u8))
+        .unwrap_or(RGBColor(0x58, 0x65, 0xF2));
+
+    let mut buf = vec![0; (WIDTH * HEIGHT) as usize * RGBPixel::PIXEL_SIZE];
+    {
+        let root =
+            BitMapBackend::<RGBPixel>::with_buffer_and_format(buf.as_mut_slice(), (WIDTH, HEIGHT))?
+                .into_drawing_area();
+        match background {
+            Some(background) => {
+                // dimmed so the text stays readable
+                let background: BitMapElement<_> = (
+                    (0, 0),
+                    DynamicImage::ImageRgb8(
+                        background
+                            .resize_to_fill(WIDTH, HEIGHT, FilterType::Triangle)
+                            .brighten(-80)
+                            .to_rgb8(),
+                    ),
+                )
+                    .into();
+                root.draw(&background)?;
+            }
+            None => root.fill(&BACKGROUND)?,
+        }
+        root.draw(&Rectangle::new(
+            [(0, 0), (WIDTH as i32, 12)],
+            accent.filled(),
+        ))?;
+
+        // avatar
+        root.draw(&Rectangle::new([(28, 36), (164, 172)], accent.filled()))?;
+        let avatar: BitMapElement<_> = (
+            (32, 40),
+            avatar.resize_exact(128, 128, FilterType::Lanczos3),
+        )
+            .into();
+        root.draw(&avatar)?;
+
+        // name, title, bio
+        let text = |size: u32, color: &RGBColor| ("sans-serif", size).into_font().color(color);
+        root.draw(&Text::new(
+            data.name.chars().take(28).collect::<String>(),
+            (190, 36),
+            text(36, &WHITE),
+        ))?;
+        root.draw(&Text::new(
+            data.title.chars().take(50).collect::<String>(),
+            (190, 82),
+            text(20, &accent),
+        ))?;
+        if let Some(description) = &data.description {
+            let line: String = description
+                .lines()
+                .next()
+                .unwrap_or("")
+                .chars()
+                .take(60)
+                .collect();
+            root.draw(&Text::new(line, (190, 112), text(16, &MUTED)))?;
+        }
+
+        // class icons
+        for (i, icon) in classes.into_iter().enumerate() {
+            let icon: BitMapElement<_> = (
+                (190 + i as i32 * 40, 140),
+                DynamicImage::ImageRgb8(icon.resize_exact(32, 32, FilterType::Lanczos3).to_rgb8()),
+            )
+                .into();
+            root.draw(&icon)?;
+        }
+
+        // stats
+        let mut stats = vec![format!("Catcoin: {}", data.catcoin)];
+        if let Some((rank, seconds)) = data.seeding {
+            stats.push(format!(
+                "Seeding: #{} ({})",
+                rank,
+                hhmmss(seconds.try_into().unwrap_or(0))
+            ));
+        }
+        if let Some(rating) = data.pug_rating {
+            stats.push(format!("Pug rating: {}", rating));
+        }
+        if let Some(map) = &data.favorite_map {
+            stats.push(format!("Favorite map: {}", map));
+        }
+        for (i, stat) in stats.iter().enumerate() {
+            root.draw(&Text::new(
+                stat.as_str(),
+                (32, 200 + i as i32 * 28),
+                text(20, &WHITE),
+            ))?;
+        }
+
+        // top pulls
+        for (i, (file, rarity, number)) in data.pulls.iter().enumerate() {
+            let Ok(pull) = image::open(file) else {
+                continue;
+            };
+            let (x, y) = (440 + i as i32 * 116, 200);
+            root.draw(&Rectangle::new(
+                [(x - 4, y - 4), (x + 100, y + 100)],
+                rarity_color(rarity).filled(),
+            ))?;
+            let pull: BitMapElement<_> = (
+                (x, y),
+                DynamicImage::ImageRgb8(pull.resize_exact(96, 96, FilterType::Lanczos3).to_rgb8()),
+            )
+                .into();
+            root.draw(&pull)?;
+            root.draw(&Text::new(
+                format!("#{}", number),
+                (x, y + 106),
+                text(16, &MUTED),
+            ))?;
+        }
+
+        // badges
+        let mut x = BADGE_MARGIN;
+        for badge in &data.badges {
+            let width = badge_width(badge);
+            root.draw(&Rectangle::new(
+                [(x, 350), (x + width, 380)],
+                accent.filled(),
+            ))?;
+            root.draw(&Text::new(badge.as_str(), (x + 8, 356), text(16, &WHITE)))?;
+            x += width + BADGE_GAP;
+        }
+
+        root.present()?;
+    }
+    let mut png = vec![];
+    PngEncoder::new(&mut png).write_image(&buf, WIDTH, HEIGHT, image::ExtendedColorType::Rgb8)?;
+    Ok(png)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_emoji_id() {
+        assert_eq!(
+            emoji_id("<:scout:1219161898642051094>").as_deref(),
+            Some("1219161898642051094")
+        );
+        assert_eq!(emoji_id("🐱"), None);
+    }
+
+    #[test]
+    fn test_is_background_url() {
+        assert!(is_background_url(
+            "https://cdn.discordapp.com/attachments/1/2/cat.png"
+        ));
+        assert!(is_background_url(
+            "https://media.discordapp.net/attachments/1/2/cat.png?width=800"
+        ));
+        assert!(!is_background_url("http://cdn.discordapp.com/cat.png"));
+        assert!(!is_background_url("https://169.254.169.254/latest"));
+        assert!(!is_background_url(
+            "https://cdn.discordapp.com.evil.example/cat.png"
+        ));
+        assert!(!is_background_url("not a url"));
+    }
+
+    fn unlock(id: &str) -> Unlock {
+        Unlock {
+            achievement: achievements::Achievement::get(id).unwrap(),
+            unlocked_at: chrono::Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_badges() {
+        assert!(badges(&[]).is_empty());
+        // newest first
+        assert_eq!(
+            badges(&[unlock("seed_1h"), unlock("pulls_1")]),
+            vec!["First Pull", "Sprout"]
+        );
+        // only as many as fit on the card
+        let unlocked: Vec<Unlock> = achievements::ACHIEVEMENTS
+            .iter()
+            .map(|a| unlock(a.id))
+            .collect();
+        let fit = badges(&unlocked);
+        assert!(!fit.is_empty() && fit.len() < unlocked.len());
+        let width: i32 = fit.iter().map(|b| badge_width(b) + BADGE_GAP).sum();
+        assert!(BADGE_MARGIN + width - BADGE_GAP <= WIDTH as i32 - BADGE_MARGIN);
+    }
+
+    fn card_data() -> CardData {
+        CardData {
+            uid: "1234".to_owned(),
+            name: "cat".to_owned(),
+            title: "meow".to_owned(),
+            description: None,
+            color: None,
+            avatar_url: "https://cdn.discordapp.com/embed/avatars/0.png".to_owned(),
+            classes: vec![],
+            favorite_map: None,
+            seeding: None,
+            catcoin: 10,
+            pug_rating: None,
+            pulls: vec![],
+            badges: vec![],
+            background: None,
+        }
+    }
+
+    #[test]
+    fn test_card_path() {
+        let data = card_data();
+        let path = data.path();
+        assert_eq!(path, card_data().path());
+        assert!(path.starts_with(CACHE_DIR));
+        assert!(path
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .starts_with("1234-"));
+        // any change renders a new card
+        let mut changed = card_data();
+        changed.catcoin += 1;
+        assert_ne!(path, changed.path());
+        let mut changed = card_data();
+        changed.badges.push("Sprout".to_owned());
+        assert_ne!(path, changed.path());
+    }
+}
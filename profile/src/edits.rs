@@ -10,14 +10,16 @@ use poise::{
 use regex::Regex;
 use sqlx::{self, MySql, Pool};
 
-use api::ApiState;
 use common::{
     discord::{execute_modal_generic, get_steam_link_content},
     Error,
 };
 use tf2::TF2Class;
 
-use crate::get_user_profile;
+use crate::{
+    get_user_profile,
+    link::{unlink_steam, LinkAction},
+};
 
 #[derive(Debug, Modal)]
 #[name = "Edit user description"]
@@ -202,7 +204,7 @@ pub async fn dispatch_profile_edit(
     ctx: &serenity::Context,
     mci: &ComponentInteraction,
     pool: &Pool<MySql>,
-    api_state: &ApiState,
+    link_url: &str,
     choice: &str,
 ) -> Result<(), Error> {
     match choice {
@@ -220,7 +222,7 @@ pub async fn dispatch_profile_edit(
             open_class_select_menu(ctx, pool, mci).await?;
         }
         "link-steam" => {
-            let (embed, components) = get_steam_link_content(&api_state.link_url());
+            let (embed, components) = get_steam_link_content(link_url);
             mci.create_response(
                 ctx,
                 CreateInteractionResponse::Message(
@@ -269,12 +271,14 @@ pub async fn dispatch_profile_edit(
             .await?;
         }
         "unlink-steam" => {
-            update_profile_column::<Option<String>>(mci.user.id, "steamid", None, pool).await?;
+            unlink_steam(pool, mci.user.id, LinkAction::Unlink, mci.user.id).await?;
             mci.create_response(
                 &ctx,
                 CreateInteractionResponse::Message(
                     CreateInteractionResponseMessage::new()
-                        .content("Unlinked your steam. Do /link again if you change your mind ^-^")
+                        .content(
+                            "Unlinked your steam. Do /link steam again if you change your mind ^-^",
+                        )
                         .ephemeral(true),
                 ),
             )
@@ -1,3 +1,4 @@
+use achievements::Unlock;
 use catcoin::CatcoinWallet;
 use chrono::{NaiveDateTime, Utc};
 use poise::serenity_prelude::{self as serenity, CreateEmbedFooter, Mentionable};
@@ -9,12 +10,15 @@ use common::{
     Error,
 };
 use emoji::emoji;
+use pugs::PugRating;
 use stats::psychostats;
 use tf2::TF2Class;
 
 use self::vote::Votes;
 
+pub mod card;
 pub mod edits;
+pub mod link;
 pub mod vote;
 
 #[allow(dead_code)]
@@ -77,6 +81,8 @@ impl UserProfile {
         votes: Votes,
         steam_data: Option<SteamProfileData>,
         catcoin: CatcoinWallet,
+        pug_rating: Option<PugRating>,
+        unlocked: Vec<Unlock>,
     ) -> Result<serenity::CreateEmbed, Error> {
         let user = serenity::UserId::new(self.uid.parse()?)
             .to_user(ctx)
@@ -192,9 +198,29 @@ impl UserProfile {
         } else {
             // link footer
             e = e.footer(CreateEmbedFooter::new(
-                "For more stats, link your steam! /link",
+                "For more stats, link your steam! /link steam",
             ));
         }
+        // pug rating
+        if let Some(pug_rating) = pug_rating {
+            e = e.field(
+                "Pug Rating 🏆",
+                format!("`{:.0}`\n_{}_", pug_rating.rating, pug_rating.record()),
+                true,
+            );
+        }
+        // achievement badges
+        if !unlocked.is_empty() {
+            e = e.field(
+                "Badges 🏅",
+                unlocked
+                    .iter()
+                    .map(|u| u.achievement.emoji)
+                    .collect::<Vec<&str>>()
+                    .join(""),
+                true,
+            );
+        }
         // fav map
         if let Some(map) = &self.favorite_map {
             e = e.field("Favorite Map", format!("🗺️ {map}"), true);
@@ -244,15 +270,22 @@ pub async fn get_user_profile(
     pool: &Pool<MySql>,
     uid: serenity::UserId,
 ) -> Result<UserProfile, Error> {
-    let prof = sqlx::query_as!(
+    let prof = find_user_profile(pool, uid).await?;
+    Ok(prof.unwrap_or_else(|| UserProfile::new(uid.get().to_string())))
+}
+
+/// retrieve a profile by discord user id, if the user has one
+pub async fn find_user_profile(
+    pool: &Pool<MySql>,
+    uid: serenity::UserId,
+) -> Result<Option<UserProfile>, Error> {
+    Ok(sqlx::query_as!(
         UserProfile,
         "SELECT * FROM `profiles` WHERE `uid` = ?",
         uid.get()
     )
     .fetch_optional(pool)
-    .await?;
-
-    Ok(prof.unwrap_or_else(|| UserProfile::new(uid.get().to_string())))
+    .await?)
 }
 
 pub async fn get_user_profiles(
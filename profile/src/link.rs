@@ -0,0 +1,239 @@
+use std::str::FromStr;
+
+use chrono::{DateTime, Utc};
+use poise::serenity_prelude::{self as serenity, Mentionable};
+use sqlx::{self, MySql, Pool};
+
+use common::Error;
+
+/// `by_uid` of changes made by the bot itself, e.g. when migrating duplicate links
+pub const SYSTEM_UID: u64 = 1;
+
+/// A change to a user's steam link
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LinkAction {
+    Link,
+    Unlink,
+    /// linked by a mod
+    ForceLink,
+    /// unlinked by a mod
+    ForceUnlink,
+}
+
+impl LinkAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LinkAction::Link => "Link",
+            LinkAction::Unlink => "Unlink",
+            LinkAction::ForceLink => "ForceLink",
+            LinkAction::ForceUnlink => "ForceUnlink",
+        }
+    }
+
+    pub fn emoji(&self) -> &'static str {
+        match self {
+            LinkAction::Link => "🔗",
+            LinkAction::Unlink => "✂️",
+            LinkAction::ForceLink => "🛡️🔗",
+            LinkAction::ForceUnlink => "🛡️✂️",
+        }
+    }
+}
+
+impl FromStr for LinkAction {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Link" => Ok(LinkAction::Link),
+            "Unlink" => Ok(LinkAction::Unlink),
+            "ForceLink" => Ok(LinkAction::ForceLink),
+            "ForceUnlink" => Ok(LinkAction::ForceUnlink),
+            _ => Err(format!("Unknown link action {s}").into()),
+        }
+    }
+}
+
+/// An entry in the steam link history
+#[derive(Clone, Debug)]
+pub struct LinkEvent {
+    pub uid: serenity::UserId,
+    pub steamid: String,
+    pub action: LinkAction,
+    pub by: serenity::UserId,
+    pub created_at: DateTime<Utc>,
+}
+
+impl LinkEvent {
+    /// a one line summary for discord
+    pub fn line(&self) -> String {
+        let by = if self.by.get() == SYSTEM_UID {
+            "system".to_owned()
+        } else {
+            self.by.mention().to_string()
+        };
+        format!(
+            "<t:{}:d> {} {} `{}` by {}",
+            self.created_at.timestamp(),
+            self.action.emoji(),
+            self.uid.mention(),
+            self.steamid,
+            by
+        )
+    }
+}
+
+/// the discord user a steam account is linked to
+pub async fn get_linked_user(
+    pool: &Pool<MySql>,
+    steamid: &str,
+) -> Result<Option<serenity::UserId>, Error> {
+    let uid = sqlx::query_scalar!("SELECT `uid` FROM `profiles` WHERE `steamid` = ?", steamid)
+        .fetch_optional(pool)
+        .await?;
+    Ok(uid.and_then(|uid| uid.parse().ok()))
+}
+
+/// links a steam account (steamid3) to a discord user, replacing any previous link.
+/// fails if the account is linked to someone else, unless it's a [`LinkAction::ForceLink`],
+/// which detaches it from them first. linking an account that's already yours does nothing
+pub async fn link_steam(
+    pool: &Pool<MySql>,
+    uid: serenity::UserId,
+    steamid: &str,
+    action: LinkAction,
+    by: serenity::UserId,
+) -> Result<(), Error> {
+    let mut tx = pool.begin().await?;
+    let owner = sqlx::query_scalar!(
+        "SELECT `uid` FROM `profiles` WHERE `steamid` = ? FOR UPDATE",
+        steamid
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+    match owner {
+        Some(owner) if owner == uid.to_string() => return Ok(()),
+        Some(owner) if action == LinkAction::ForceLink => {
+            let owner: serenity::UserId = owner.parse()?;
+            sqlx::query!(
+                "UPDATE `profiles` SET `steamid` = NULL WHERE `uid` = ?",
+                owner.get()
+            )
+            .execute(&mut *tx)
+            .await?;
+            record(&mut *tx, owner, steamid, LinkAction::ForceUnlink, by).await?;
+        }
+        Some(_) => return Err("That steam account is already linked to another user.".into()),
+        None => (),
+    }
+
+    let previous = sqlx::query_scalar!(
+        "SELECT `steamid` FROM `profiles` WHERE `uid` = ? FOR UPDATE",
+        uid.get()
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    .flatten();
+    if let Some(previous) = previous {
+        let unlink = match action {
+            LinkAction::ForceLink => LinkAction::ForceUnlink,
+            _ => LinkAction::Unlink,
+        };
+        record(&mut *tx, uid, &previous, unlink, by).await?;
+    }
+
+    sqlx::query!(
+        "INSERT INTO `profiles` (`uid`, `steamid`) VALUES (?, ?) ON DUPLICATE KEY UPDATE `steamid` = ?",
+        uid.get(),
+        steamid,
+        steamid
+    )
+    .execute(&mut *tx)
+    .await?;
+    record(&mut *tx, uid, steamid, action, by).await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+/// removes a user's steam link, returning the steamid3 that was unlinked
+pub async fn unlink_steam(
+    pool: &Pool<MySql>,
+    uid: serenity::UserId,
+    action: LinkAction,
+    by: serenity::UserId,
+) -> Result<Option<String>, Error> {
+    let mut tx = pool.begin().await?;
+    let steamid = sqlx::query_scalar!(
+        "SELECT `steamid` FROM `profiles` WHERE `uid` = ? FOR UPDATE",
+        uid.get()
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    .flatten();
+    let Some(steamid) = steamid else {
+        return Ok(None);
+    };
+    sqlx::query!(
+        "UPDATE `profiles` SET `steamid` = NULL WHERE `uid` = ?",
+        uid.get()
+    )
+    .execute(&mut *tx)
+    .await?;
+    record(&mut *tx, uid, &steamid, action, by).await?;
+    tx.commit().await?;
+    Ok(Some(steamid))
+}
+
+async fn record(
+    tx: &mut sqlx::MySqlConnection,
+    uid: serenity::UserId,
+    steamid: &str,
+    action: LinkAction,
+    by: serenity::UserId,
+) -> Result<(), Error> {
+    sqlx::query!(
+        "INSERT INTO `steam_link_history` (`uid`, `steamid`, `action`, `by_uid`) VALUES (?, ?, ?, ?)",
+        uid.get(),
+        steamid,
+        action.as_str(),
+        by.get()
+    )
+    .execute(tx)
+    .await?;
+    Ok(())
+}
+
+/// the link history of a discord user and every steam account they've linked, newest first
+pub async fn get_link_history(
+    pool: &Pool<MySql>,
+    uid: serenity::UserId,
+    limit: i64,
+) -> Result<Vec<LinkEvent>, Error> {
+    let rows = sqlx::query!(
+        r#"
+		SELECT `uid`, `steamid`, `action`, `by_uid`, `created_at` FROM `steam_link_history`
+		WHERE `uid` = ? OR `steamid` IN (SELECT `steamid` FROM `steam_link_history` WHERE `uid` = ?)
+		ORDER BY `id` DESC LIMIT ?
+	"#,
+        uid.get(),
+        uid.get(),
+        limit
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(rows
+        .into_iter()
+        .filter_map(|r| {
+            Some(LinkEvent {
+                uid: r.uid.parse().ok()?,
+                steamid: r.steamid,
+                action: r.action.parse().ok()?,
+                by: r
+                    .by_uid
+                    .parse()
+                    .unwrap_or(serenity::UserId::new(SYSTEM_UID)),
+                created_at: r.created_at,
+            })
+        })
+        .collect())
+}
@@ -0,0 +1,346 @@
+use std::{collections::HashMap, str::FromStr};
+
+use chrono::{TimeDelta, Utc};
+use poise::serenity_prelude::{Color, CreateEmbed, Mentionable, UserId};
+use sqlx::{MySql, Pool, QueryBuilder};
+
+use common::Error;
+
+pub mod rating;
+pub mod results;
+
+pub use results::spawn_result_tracker;
+
+use rating::{delta, DEFAULT_RATING};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Team {
+    Red,
+    Blu,
+}
+
+impl Team {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Team::Red => "red",
+            Team::Blu => "blu",
+        }
+    }
+}
+
+impl FromStr for Team {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "red" => Ok(Team::Red),
+            "blu" => Ok(Team::Blu),
+            _ => Err(format!("Unknown team {s}").into()),
+        }
+    }
+}
+
+/// A player's pug rating and record
+#[derive(sqlx::FromRow, Clone, Debug)]
+pub struct PugRating {
+    pub uid: String,
+    pub rating: f64,
+    pub games: i32,
+    pub wins: i32,
+    pub losses: i32,
+    pub ties: i32,
+}
+
+impl PugRating {
+    pub fn record(&self) -> String {
+        format!("{}W / {}L / {}T", self.wins, self.losses, self.ties)
+    }
+}
+
+/// How a player's rating moved after a pug
+#[derive(Clone, Debug)]
+pub struct RatingChange {
+    pub uid: UserId,
+    pub team: Team,
+    pub before: f64,
+    pub after: f64,
+}
+
+/// stores a finished draft. captains are `None` for teams balanced by rating. returns the draft id
+pub async fn save_draft(
+    pool: &Pool<MySql>,
+    captains: Option<(UserId, UserId)>,
+    picks: &[UserId],
+    red: &[UserId],
+    blu: &[UserId],
+) -> Result<i32, Error> {
+    let mut tx = pool.begin().await?;
+    let id = sqlx::query!(
+        "INSERT INTO `pug_drafts` (`red_captain`, `blu_captain`) VALUES (?, ?)",
+        captains.map(|c| c.0.get()),
+        captains.map(|c| c.1.get()),
+    )
+    .execute(&mut *tx)
+    .await?
+    .last_insert_id() as i32;
+
+    let players = red
+        .iter()
+        .map(|uid| (uid, Team::Red))
+        .chain(blu.iter().map(|uid| (uid, Team::Blu)));
+    let mut qb = QueryBuilder::<MySql>::new(
+        "INSERT INTO `pug_draft_players` (`draft_id`, `uid`, `team`, `pick`) ",
+    );
+    qb.push_values(players, |mut b, (uid, team)| {
+        b.push_bind(id)
+            .push_bind(uid.get())
+            .push_bind(team.as_str())
+            .push_bind(picks.iter().position(|p| p == uid).map(|i| i as i32 + 1));
+    });
+    qb.build().execute(&mut *tx).await?;
+
+    tx.commit().await?;
+    Ok(id)
+}
+
+/// the most recent draft without a result, created within the given time
+pub async fn latest_open_draft(
+    pool: &Pool<MySql>,
+    within: TimeDelta,
+) -> Result<Option<i32>, Error> {
+    Ok(sqlx::query!(
+        "SELECT `id` FROM `pug_drafts` WHERE `winner` IS NULL AND `created_at` > ? ORDER BY `id` DESC LIMIT 1",
+        Utc::now() - within
+    )
+    .fetch_optional(pool)
+    .await?
+    .map(|r| r.id))
+}
+
+/// marks a draft as set up on the pug server with the given map
+pub async fn mark_launched(pool: &Pool<MySql>, draft_id: i32, map: &str) -> Result<(), Error> {
+    sqlx::query!(
+        "UPDATE `pug_drafts` SET `map` = ?, `launched_at` = ? WHERE `id` = ?",
+        map,
+        Utc::now(),
+        draft_id
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// the most recently launched draft, if it is still without a result, was launched on the given
+/// map and within the given time. older launches were replaced on the server by it
+pub async fn launched_draft(
+    pool: &Pool<MySql>,
+    map: &str,
+    within: TimeDelta,
+) -> Result<Option<i32>, Error> {
+    let latest = sqlx::query!(
+        "SELECT `id`, `map`, `winner`, `launched_at` FROM `pug_drafts` WHERE `launched_at` IS NOT NULL ORDER BY `launched_at` DESC, `id` DESC LIMIT 1"
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(latest
+        .filter(|r| r.winner.is_none() && r.map.as_deref() == Some(map))
+        .filter(|r| r.launched_at.is_some_and(|at| at > Utc::now() - within))
+        .map(|r| r.id))
+}
+
+/// records the result of a draft and updates the ratings of everyone in it. `None` is a tie
+pub async fn record_result(
+    pool: &Pool<MySql>,
+    draft_id: i32,
+    winner: Option<Team>,
+    scores: Option<(i32, i32)>,
+) -> Result<Vec<RatingChange>, Error> {
+    let mut tx = pool.begin().await?;
+    let draft = sqlx::query!(
+        "SELECT `winner` FROM `pug_drafts` WHERE `id` = ? FOR UPDATE",
+        draft_id
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or(format!("Pug #{} does not exist.", draft_id))?;
+    if draft.winner.is_some() {
+        return Err(format!("Pug #{} already has a result.", draft_id).into());
+    }
+
+    let players: Vec<(UserId, Team, f64)> = sqlx::query!(
+        r#"SELECT p.`uid`, p.`team`, r.`rating` AS `rating?`
+        FROM `pug_draft_players` p
+        LEFT JOIN `pug_ratings` r ON r.`uid` = p.`uid`
+        WHERE p.`draft_id` = ?"#,
+        draft_id
+    )
+    .fetch_all(&mut *tx)
+    .await?
+    .into_iter()
+    .filter_map(|r| {
+        Some((
+            UserId::new(r.uid.parse().ok()?),
+            r.team.parse().ok()?,
+            r.rating.unwrap_or(DEFAULT_RATING),
+        ))
+    })
+    .collect();
+    let ratings = |team: Team| -> Vec<f64> {
+        players
+            .iter()
+            .filter(|p| p.1 == team)
+            .map(|p| p.2)
+            .collect()
+    };
+    let (red, blu) = (ratings(Team::Red), ratings(Team::Blu));
+
+    let mut changes = vec![];
+    for (uid, team, before) in players.iter().copied() {
+        let score = match winner {
+            Some(w) if w == team => 1.0,
+            Some(_) => 0.0,
+            None => 0.5,
+        };
+        let after = match team {
+            Team::Red => before + delta(&red, &blu, score),
+            Team::Blu => before + delta(&blu, &red, score),
+        };
+        sqlx::query!(
+            "UPDATE `pug_draft_players` SET `rating_before` = ?, `rating_after` = ? WHERE `draft_id` = ? AND `uid` = ?",
+            before,
+            after,
+            draft_id,
+            uid.get()
+        )
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query!(
+            r#"
+            INSERT INTO `pug_ratings` (`uid`, `rating`, `games`, `wins`, `losses`, `ties`)
+            VALUES (?, ?, 1, ?, ?, ?)
+            ON DUPLICATE KEY UPDATE
+                `rating` = VALUES(`rating`),
+                `games` = `games` + 1,
+                `wins` = `wins` + VALUES(`wins`),
+                `losses` = `losses` + VALUES(`losses`),
+                `ties` = `ties` + VALUES(`ties`)"#,
+            uid.get(),
+            after,
+            (score == 1.0) as i32,
+            (score == 0.0) as i32,
+            (score == 0.5) as i32
+        )
+        .execute(&mut *tx)
+        .await?;
+        changes.push(RatingChange {
+            uid,
+            team,
+            before,
+            after,
+        });
+    }
+
+    sqlx::query!(
+        "UPDATE `pug_drafts` SET `winner` = ?, `red_score` = ?, `blu_score` = ?, `finished_at` = CURRENT_TIMESTAMP WHERE `id` = ?",
+        winner.map(|t| t.as_str()).unwrap_or("tie"),
+        scores.map(|s| s.0),
+        scores.map(|s| s.1),
+        draft_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+    Ok(changes)
+}
+
+/// a player's rating, if they have played a rated pug
+pub async fn get_rating(pool: &Pool<MySql>, uid: UserId) -> Result<Option<PugRating>, Error> {
+    Ok(sqlx::query_as!(
+        PugRating,
+        "SELECT * FROM `pug_ratings` WHERE `uid` = ?",
+        uid.get()
+    )
+    .fetch_optional(pool)
+    .await?)
+}
+
+/// the ratings of the given players, unrated players get the default rating
+pub async fn get_ratings(
+    pool: &Pool<MySql>,
+    uids: &[UserId],
+) -> Result<HashMap<UserId, f64>, Error> {
+    let mut ratings: HashMap<UserId, f64> = uids.iter().map(|u| (*u, DEFAULT_RATING)).collect();
+    if uids.is_empty() {
+        return Ok(ratings);
+    }
+    let mut qb =
+        QueryBuilder::<MySql>::new("SELECT `uid`, `rating` FROM `pug_ratings` WHERE `uid` IN ");
+    qb.push_tuples(uids, |mut b, uid| {
+        b.push_bind(uid.get());
+    });
+    for (uid, rating) in qb.build_query_as::<(String, f64)>().fetch_all(pool).await? {
+        if let Ok(uid) = uid.parse() {
+            ratings.insert(UserId::new(uid), rating);
+        }
+    }
+    Ok(ratings)
+}
+
+/// the highest rated players with at least `min_games` pugs
+pub async fn get_leaderboard(
+    pool: &Pool<MySql>,
+    min_games: i32,
+    limit: i64,
+) -> Result<Vec<PugRating>, Error> {
+    Ok(sqlx::query_as!(
+        PugRating,
+        "SELECT * FROM `pug_ratings` WHERE `games` >= ? ORDER BY `rating` DESC LIMIT ?",
+        min_games,
+        limit
+    )
+    .fetch_all(pool)
+    .await?)
+}
+
+/// summarizes a pug result and everyone's rating changes
+pub fn result_embed(
+    draft_id: i32,
+    winner: Option<Team>,
+    scores: Option<(i32, i32)>,
+    changes: &[RatingChange],
+) -> CreateEmbed {
+    let title = match winner {
+        Some(Team::Red) => format!("Pug #{}: 🔴 RED wins!", draft_id),
+        Some(Team::Blu) => format!("Pug #{}: 🔵 BLU wins!", draft_id),
+        None => format!("Pug #{}: Tie!", draft_id),
+    };
+    let list = |team: Team| {
+        changes
+            .iter()
+            .filter(|c| c.team == team)
+            .map(|c| {
+                format!(
+                    "{} `{:.0}` ({:+.0})",
+                    c.uid.mention(),
+                    c.after,
+                    c.after - c.before
+                )
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    };
+    let mut e = CreateEmbed::new()
+        .title(title)
+        .field("🔴 RED", list(Team::Red), true)
+        .field("🔵 BLU", list(Team::Blu), true)
+        .color(match winner {
+            Some(Team::Red) => Color::RED,
+            Some(Team::Blu) => Color::BLUE,
+            None => Color::LIGHT_GREY,
+        });
+    if let Some((red, blu)) = scores {
+        e = e.description(format!("🔴 `{}` - `{}` 🔵", red, blu));
+    }
+    e
+}
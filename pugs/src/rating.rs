@@ -0,0 +1,101 @@
+/// rating every player starts at
+pub const DEFAULT_RATING: f64 = 1500.0;
+/// max rating change from a single game
+const K: f64 = 32.0;
+
+/// chance of a team rated `a` beating one rated `b`
+pub fn expected(a: f64, b: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf((b - a) / 400.0))
+}
+
+/// a team's rating is the average of its players
+pub fn team_rating(ratings: &[f64]) -> f64 {
+    if ratings.is_empty() {
+        return DEFAULT_RATING;
+    }
+    ratings.iter().sum::<f64>() / ratings.len() as f64
+}
+
+/// the rating change of each player on a team. `score` is 1 for a win, 0.5 for a tie and 0 for a loss
+pub fn delta(team: &[f64], opponents: &[f64], score: f64) -> f64 {
+    K * (score - expected(team_rating(team), team_rating(opponents)))
+}
+
+/// splits players into two teams (sizes differing by at most one) with as close total ratings as possible
+pub fn balance<T: Clone>(players: &[(T, f64)]) -> (Vec<(T, f64)>, Vec<(T, f64)>) {
+    let mut sorted = players.to_vec();
+    sorted.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+
+    // greedily hand the best remaining player to the weaker team
+    let cap = (sorted.len() + 1) / 2;
+    let (mut red, mut blu): (Vec<(T, f64)>, Vec<(T, f64)>) = (vec![], vec![]);
+    for p in sorted {
+        let red_sum: f64 = red.iter().map(|p| p.1).sum();
+        let blu_sum: f64 = blu.iter().map(|p| p.1).sum();
+        if red.len() < cap && (red_sum <= blu_sum || blu.len() >= cap) {
+            red.push(p);
+        } else {
+            blu.push(p);
+        }
+    }
+
+    // then swap pairs while it narrows the gap
+    loop {
+        let diff: f64 = red.iter().map(|p| p.1).sum::<f64>() - blu.iter().map(|p| p.1).sum::<f64>();
+        let mut best: Option<(usize, usize, f64)> = None;
+        for (i, r) in red.iter().enumerate() {
+            for (j, b) in blu.iter().enumerate() {
+                let new = (diff - 2.0 * (r.1 - b.1)).abs();
+                if new < diff.abs() - 1e-9 && best.as_ref().map_or(true, |b| new < b.2) {
+                    best = Some((i, j, new));
+                }
+            }
+        }
+        let Some((i, j, _)) = best else {
+            break;
+        };
+        std::mem::swap(&mut red[i], &mut blu[j]);
+    }
+
+    (red, blu)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expected() {
+        assert_eq!(expected(1500.0, 1500.0), 0.5);
+        assert!((expected(1900.0, 1500.0) - 0.909).abs() < 0.001);
+        assert!((expected(1500.0, 1900.0) + expected(1900.0, 1500.0) - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_delta() {
+        let even = [1500.0, 1500.0];
+        assert_eq!(delta(&even, &even, 1.0), 16.0);
+        assert_eq!(delta(&even, &even, 0.0), -16.0);
+        assert_eq!(delta(&even, &even, 0.5), 0.0);
+        // upsets are worth more
+        assert!(delta(&[1300.0], &[1700.0], 1.0) > delta(&[1700.0], &[1300.0], 1.0));
+    }
+
+    #[test]
+    fn test_balance() {
+        let players: Vec<(u32, f64)> = [1800.0, 1600.0, 1500.0, 1500.0, 1400.0, 1200.0]
+            .into_iter()
+            .enumerate()
+            .map(|(i, r)| (i as u32, r))
+            .collect();
+        let (red, blu) = balance(&players);
+        assert_eq!(red.len(), 3);
+        assert_eq!(blu.len(), 3);
+        let sum = |t: &Vec<(u32, f64)>| t.iter().map(|p| p.1).sum::<f64>();
+        assert_eq!(sum(&red), sum(&blu));
+
+        let (red, blu) = balance(&[(0, 2000.0), (1, 1000.0), (2, 1500.0)]);
+        assert_eq!(red.len() + blu.len(), 3);
+        assert!(red.len().abs_diff(blu.len()) <= 1);
+    }
+}
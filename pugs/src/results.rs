@@ -0,0 +1,152 @@
+use std::{net::SocketAddr, sync::Arc};
+
+use chrono::TimeDelta;
+use poise::serenity_prelude::{ChannelId, CreateAllowedMentions, CreateMessage, Http};
+use sqlx::{MySql, Pool};
+use tf2::logs::LogReceiver;
+
+use crate::{launched_draft, record_result, result_embed, Team};
+
+/// how long after a pug is launched its result can still come in from the server
+const MATCH_WINDOW_HOURS: i64 = 4;
+
+/// parses the `Team "Red" final score "3" with "6" players` line logged at game over
+pub fn parse_final_score(line: &str) -> Option<(Team, i32)> {
+    let (team, rest) = line.strip_prefix("Team \"")?.split_once('"')?;
+    let team = match team {
+        "Red" => Team::Red,
+        "Blue" => Team::Blu,
+        _ => return None,
+    };
+    let (score, _) = rest.strip_prefix(" final score \"")?.split_once('"')?;
+    Some((team, score.parse().ok()?))
+}
+
+/// parses the `Started map "cp_process_final" (CRC "...")` line logged on map change
+pub fn parse_started_map(line: &str) -> Option<&str> {
+    let (map, _) = line.strip_prefix("Started map \"")?.split_once('"')?;
+    Some(map)
+}
+
+/// A line of interest from the pug server's logs
+enum PugEvent {
+    MapStarted(String),
+    FinalScore(Team, i32),
+}
+
+/// watches the pug server's logs for game over, recording the result of the pug launched on
+/// the map that was played
+pub async fn spawn_result_tracker(
+    log_receiver: LogReceiver,
+    pug_server: SocketAddr,
+    pool: Pool<MySql>,
+    http: Arc<Http>,
+    channel: ChannelId,
+) {
+    let (tx, mut rx) = tokio::sync::mpsc::channel(10);
+    log_receiver
+        .subscribe(Box::new(move |from, msg, _parsed| {
+            if from != pug_server {
+                return;
+            }
+            let event = if let Some(map) = parse_started_map(&msg.message) {
+                PugEvent::MapStarted(map.to_owned())
+            } else if let Some((team, score)) = parse_final_score(&msg.message) {
+                PugEvent::FinalScore(team, score)
+            } else {
+                return;
+            };
+            let tx = tx.clone();
+            tokio::spawn(async move {
+                let _ = tx.send(event).await;
+            });
+        }))
+        .await;
+
+    tokio::spawn(async move {
+        let mut map: Option<String> = None;
+        let (mut red, mut blu): (Option<i32>, Option<i32>) = (None, None);
+        while let Some(event) = rx.recv().await {
+            match event {
+                PugEvent::MapStarted(started) => {
+                    map = Some(started);
+                    (red, blu) = (None, None);
+                    continue;
+                }
+                PugEvent::FinalScore(Team::Red, score) => red = Some(score),
+                PugEvent::FinalScore(Team::Blu, score) => blu = Some(score),
+            }
+            let (Some(red_score), Some(blu_score)) = (red, blu) else {
+                continue;
+            };
+            (red, blu) = (None, None);
+            // the map is unknown until the first map change after startup
+            let Some(map) = &map else {
+                continue;
+            };
+
+            let window = TimeDelta::try_hours(MATCH_WINDOW_HOURS).unwrap();
+            let draft_id = match launched_draft(&pool, map, window).await {
+                Ok(Some(id)) => id,
+                Ok(None) => continue,
+                Err(e) => {
+                    log::error!("Could not fetch open pug draft: {e}");
+                    continue;
+                }
+            };
+            let winner = match red_score.cmp(&blu_score) {
+                std::cmp::Ordering::Greater => Some(Team::Red),
+                std::cmp::Ordering::Less => Some(Team::Blu),
+                std::cmp::Ordering::Equal => None,
+            };
+            let scores = Some((red_score, blu_score));
+            let changes = match record_result(&pool, draft_id, winner, scores).await {
+                Ok(changes) => changes,
+                Err(e) => {
+                    log::error!("Could not record pug #{} result: {e}", draft_id);
+                    continue;
+                }
+            };
+            let _ = channel
+                .send_message(
+                    &http,
+                    CreateMessage::new()
+                        .embed(result_embed(draft_id, winner, scores, &changes))
+                        .allowed_mentions(CreateAllowedMentions::new().empty_users()),
+                )
+                .await
+                .inspect_err(|e| log::error!("Could not post pug result: {e}"));
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_final_score() {
+        assert_eq!(
+            parse_final_score(r#"Team "Red" final score "3" with "6" players"#),
+            Some((Team::Red, 3))
+        );
+        assert_eq!(
+            parse_final_score(r#"Team "Blue" final score "0" with "6" players"#),
+            Some((Team::Blu, 0))
+        );
+        assert_eq!(
+            parse_final_score(r#"Team "Red" current score "3" with "6" players"#),
+            None
+        );
+        assert_eq!(parse_final_score(r#"World triggered "Game_Over""#), None);
+    }
+
+    #[test]
+    fn test_parse_started_map() {
+        assert_eq!(
+            parse_started_map(r#"Started map "cp_process_final" (CRC "1234")"#),
+            Some("cp_process_final")
+        );
+        assert_eq!(parse_started_map(r#"Loading map "cp_process_final""#), None);
+    }
+}
@@ -0,0 +1,106 @@
+use std::collections::HashMap;
+use std::f64::consts::PI;
+
+use image::{codecs::png::PngEncoder, ImageEncoder};
+use plotters::{
+    backend::{PixelFormat, RGBPixel},
+    prelude::*,
+    style::text_anchor::{HPos, Pos, VPos},
+};
+
+use common::Error;
+
+use crate::Rivalry;
+
+const SIZE: u32 = 800;
+
+/// draws the players of the given rivalries in a ring, linked by how many times they've killed each other
+pub fn draw_rivalry_graph(rivalries: &[Rivalry]) -> Result<Vec<u8>, Error> {
+    // every player in the graph, in order of appearance
+    let mut players: Vec<(String, String)> = vec![];
+    for r in rivalries {
+        for (steamid, name) in [(&r.lt_steamid, &r.lt_name), (&r.gt_steamid, &r.gt_name)] {
+            if !players.iter().any(|p| &p.0 == steamid) {
+                players.push((steamid.clone(), name.clone().unwrap_or(steamid.clone())));
+            }
+        }
+    }
+    let center = (SIZE / 2) as f64;
+    let radius = center - 120.0;
+    let positions: HashMap<&String, (i32, i32)> = players
+        .iter()
+        .enumerate()
+        .map(|(i, (steamid, _))| {
+            let angle = 2.0 * PI * i as f64 / players.len().max(1) as f64 - PI / 2.0;
+            (
+                steamid,
+                (
+                    (center + radius * angle.cos()) as i32,
+                    (center + radius * angle.sin()) as i32,
+                ),
+            )
+        })
+        .collect();
+    let max_kills = rivalries
+        .iter()
+        .map(|r| r.total_kills())
+        .max()
+        .unwrap_or(1)
+        .max(1);
+
+    let mut buf = vec![0; (SIZE * SIZE) as usize * RGBPixel::PIXEL_SIZE];
+    {
+        let root =
+            BitMapBackend::<RGBPixel>::with_buffer_and_format(buf.as_mut_slice(), (SIZE, SIZE))?
+                .into_drawing_area();
+        root.fill(&RGBColor(0x2F, 0x31, 0x36))?;
+        root.draw(&Text::new(
+            "Top rivalries",
+            (20, 20),
+            ("sans-serif", 28).into_font().color(&WHITE),
+        ))?;
+
+        let label = ("sans-serif", 14)
+            .into_font()
+            .color(&WHITE)
+            .pos(Pos::new(HPos::Center, VPos::Center));
+        for r in rivalries {
+            let (a, b) = (positions[&r.lt_steamid], positions[&r.gt_steamid]);
+            // the line leans towards the color of whoever leads the kills
+            let color = match r.lt_kills.cmp(&r.gt_kills) {
+                std::cmp::Ordering::Greater => RGBColor(0xE0, 0x5A, 0x5A),
+                std::cmp::Ordering::Less => RGBColor(0x5A, 0x9B, 0xE0),
+                std::cmp::Ordering::Equal => RGBColor(0xB0, 0xB0, 0xB0),
+            };
+            let width = 1 + (7 * r.total_kills() / max_kills) as u32;
+            root.draw(&PathElement::new(vec![a, b], color.stroke_width(width)))?;
+            root.draw(&Text::new(
+                format!("{} - {}", r.lt_kills, r.gt_kills),
+                ((a.0 + b.0) / 2, (a.1 + b.1) / 2),
+                label.clone(),
+            ))?;
+        }
+
+        for (steamid, name) in &players {
+            let pos = positions[steamid];
+            root.draw(&Circle::new(pos, 8, WHITE.filled()))?;
+            // push the name outwards from the ring
+            let (dx, dy) = (pos.0 as f64 - center, pos.1 as f64 - center);
+            let len = (dx * dx + dy * dy).sqrt().max(1.0);
+            root.draw(&Text::new(
+                name.chars().take(20).collect::<String>(),
+                (
+                    pos.0 + (dx / len * 40.0) as i32,
+                    pos.1 + (dy / len * 24.0) as i32,
+                ),
+                label.clone(),
+            ))?;
+        }
+
+        root.present()?;
+    }
+    let mut png_buf = vec![];
+    let e = PngEncoder::new(&mut png_buf);
+    e.write_image(&buf, SIZE, SIZE, image::ExtendedColorType::Rgb8)?;
+    Ok(png_buf)
+}
@@ -0,0 +1,265 @@
+use std::collections::HashMap;
+
+use sqlx::{MySql, Pool, QueryBuilder};
+
+use common::Error;
+
+pub mod graph;
+pub mod parse;
+mod tracker;
+
+pub use tracker::spawn_rivalry_tracker;
+
+/// dominations both ways before a lead change is called out
+pub const FLIP_MIN_DOMINATIONS: i32 = 5;
+
+const SELECT_RIVALRIES: &str = r#"
+    SELECT r.*, lp.`name` AS `lt_name`, gp.`name` AS `gt_name`
+    FROM `rivalries` r
+    LEFT JOIN `rivalry_players` lp ON lp.`steamid` = r.`lt_steamid`
+    LEFT JOIN `rivalry_players` gp ON gp.`steamid` = r.`gt_steamid`
+"#;
+
+/// The history between two players. `lt` is the lesser steamid of the pair
+#[derive(sqlx::FromRow, Clone, Debug)]
+pub struct Rivalry {
+    pub lt_steamid: String,
+    pub gt_steamid: String,
+    pub lt_name: Option<String>,
+    pub gt_name: Option<String>,
+    pub lt_kills: i32,
+    pub gt_kills: i32,
+    pub lt_dominations: i32,
+    pub gt_dominations: i32,
+    pub lt_revenges: i32,
+    pub gt_revenges: i32,
+    pub leader: Option<String>,
+}
+
+/// A rivalry as seen by one of the players
+#[derive(Clone, Debug)]
+pub struct Side {
+    pub steamid: String,
+    pub name: String,
+    pub rival_steamid: String,
+    pub rival_name: String,
+    pub kills: i32,
+    pub deaths: i32,
+    pub dominations: i32,
+    pub dominated: i32,
+    pub revenges: i32,
+    pub revenged: i32,
+}
+
+impl Rivalry {
+    /// the rivalry from the given player's point of view
+    pub fn side(&self, steamid: &str) -> Side {
+        let name = |n: &Option<String>, s: &String| n.clone().unwrap_or(s.clone());
+        let (lt, gt) = (
+            name(&self.lt_name, &self.lt_steamid),
+            name(&self.gt_name, &self.gt_steamid),
+        );
+        if steamid == self.lt_steamid {
+            Side {
+                steamid: self.lt_steamid.clone(),
+                name: lt,
+                rival_steamid: self.gt_steamid.clone(),
+                rival_name: gt,
+                kills: self.lt_kills,
+                deaths: self.gt_kills,
+                dominations: self.lt_dominations,
+                dominated: self.gt_dominations,
+                revenges: self.lt_revenges,
+                revenged: self.gt_revenges,
+            }
+        } else {
+            Side {
+                steamid: self.gt_steamid.clone(),
+                name: gt,
+                rival_steamid: self.lt_steamid.clone(),
+                rival_name: lt,
+                kills: self.gt_kills,
+                deaths: self.lt_kills,
+                dominations: self.gt_dominations,
+                dominated: self.lt_dominations,
+                revenges: self.gt_revenges,
+                revenged: self.lt_revenges,
+            }
+        }
+    }
+
+    pub fn total_kills(&self) -> i32 {
+        self.lt_kills + self.gt_kills
+    }
+
+    pub fn total_dominations(&self) -> i32 {
+        self.lt_dominations + self.gt_dominations
+    }
+}
+
+/// orders a pair of steamids as (lt, gt)
+fn pair<'a>(a: &'a str, b: &'a str) -> (&'a str, &'a str) {
+    if a < b {
+        (a, b)
+    } else {
+        (b, a)
+    }
+}
+
+/// adds kills between players. keyed by (killer, victim)
+pub async fn record_kills(
+    pool: &Pool<MySql>,
+    kills: &HashMap<(String, String), i32>,
+) -> Result<(), Error> {
+    let mut pairs: HashMap<(&str, &str), (i32, i32)> = HashMap::new();
+    for ((killer, victim), count) in kills {
+        let key = pair(killer, victim);
+        let entry = pairs.entry(key).or_default();
+        if key.0 == killer.as_str() {
+            entry.0 += count;
+        } else {
+            entry.1 += count;
+        }
+    }
+    if pairs.is_empty() {
+        return Ok(());
+    }
+    let mut qb = QueryBuilder::<MySql>::new(
+        "INSERT INTO `rivalries` (`lt_steamid`, `gt_steamid`, `lt_kills`, `gt_kills`) ",
+    );
+    qb.push_values(pairs, |mut b, ((lt, gt), (lt_kills, gt_kills))| {
+        b.push_bind(lt)
+            .push_bind(gt)
+            .push_bind(lt_kills)
+            .push_bind(gt_kills);
+    });
+    qb.push(
+        r#" ON DUPLICATE KEY UPDATE
+        `lt_kills` = `lt_kills` + VALUES(`lt_kills`),
+        `gt_kills` = `gt_kills` + VALUES(`gt_kills`)"#,
+    );
+    qb.build().execute(pool).await?;
+    Ok(())
+}
+
+/// remembers the latest name of each steamid
+pub async fn record_names(
+    pool: &Pool<MySql>,
+    names: &HashMap<String, String>,
+) -> Result<(), Error> {
+    if names.is_empty() {
+        return Ok(());
+    }
+    let mut qb = QueryBuilder::<MySql>::new("INSERT INTO `rivalry_players` (`steamid`, `name`) ");
+    qb.push_values(names, |mut b, (steamid, name)| {
+        b.push_bind(steamid).push_bind(name);
+    });
+    qb.push(" ON DUPLICATE KEY UPDATE `name` = VALUES(`name`)");
+    qb.build().execute(pool).await?;
+    Ok(())
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Action {
+    Domination,
+    Revenge,
+}
+
+/// records a domination or revenge. returns the rivalry if its leader flipped
+pub async fn record_action(
+    pool: &Pool<MySql>,
+    from: &str,
+    against: &str,
+    action: Action,
+) -> Result<Option<Rivalry>, Error> {
+    let (lt, gt) = pair(from, against);
+    let column = match (action, from == lt) {
+        (Action::Domination, true) => "lt_dominations",
+        (Action::Domination, false) => "gt_dominations",
+        (Action::Revenge, true) => "lt_revenges",
+        (Action::Revenge, false) => "gt_revenges",
+    };
+    let mut tx = pool.begin().await?;
+    sqlx::query(&format!(
+        "INSERT INTO `rivalries` (`lt_steamid`, `gt_steamid`, `{column}`) VALUES (?, ?, 1) ON DUPLICATE KEY UPDATE `{column}` = `{column}` + 1"
+    ))
+    .bind(lt)
+    .bind(gt)
+    .execute(&mut *tx)
+    .await?;
+    let rivalry = sqlx::query_as::<_, Rivalry>(&format!(
+        "{SELECT_RIVALRIES} WHERE r.`lt_steamid` = ? AND r.`gt_steamid` = ? FOR UPDATE"
+    ))
+    .bind(lt)
+    .bind(gt)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let leader = match rivalry.lt_dominations.cmp(&rivalry.gt_dominations) {
+        std::cmp::Ordering::Greater => Some(rivalry.lt_steamid.clone()),
+        std::cmp::Ordering::Less => Some(rivalry.gt_steamid.clone()),
+        std::cmp::Ordering::Equal => rivalry.leader.clone(),
+    };
+    let mut flipped = None;
+    if leader != rivalry.leader {
+        sqlx::query!(
+            "UPDATE `rivalries` SET `leader` = ? WHERE `lt_steamid` = ? AND `gt_steamid` = ?",
+            leader,
+            lt,
+            gt
+        )
+        .execute(&mut *tx)
+        .await?;
+        if rivalry.leader.is_some() && rivalry.total_dominations() >= FLIP_MIN_DOMINATIONS {
+            flipped = Some(Rivalry { leader, ..rivalry });
+        }
+    }
+    tx.commit().await?;
+    Ok(flipped)
+}
+
+/// every rivalry a player is part of
+pub async fn get_rivalries(pool: &Pool<MySql>, steamid: &str) -> Result<Vec<Rivalry>, Error> {
+    Ok(sqlx::query_as::<_, Rivalry>(&format!(
+        "{SELECT_RIVALRIES} WHERE r.`lt_steamid` = ? OR r.`gt_steamid` = ?"
+    ))
+    .bind(steamid)
+    .bind(steamid)
+    .fetch_all(pool)
+    .await?)
+}
+
+/// the rivalry between two players
+pub async fn get_rivalry(pool: &Pool<MySql>, a: &str, b: &str) -> Result<Option<Rivalry>, Error> {
+    let (lt, gt) = pair(a, b);
+    Ok(sqlx::query_as::<_, Rivalry>(&format!(
+        "{SELECT_RIVALRIES} WHERE r.`lt_steamid` = ? AND r.`gt_steamid` = ?"
+    ))
+    .bind(lt)
+    .bind(gt)
+    .fetch_optional(pool)
+    .await?)
+}
+
+/// the rivalries with the most kills
+pub async fn get_top_rivalries(pool: &Pool<MySql>, limit: i64) -> Result<Vec<Rivalry>, Error> {
+    Ok(sqlx::query_as::<_, Rivalry>(&format!(
+        "{SELECT_RIVALRIES} ORDER BY (r.`lt_kills` + r.`gt_kills`) DESC LIMIT ?"
+    ))
+    .bind(limit)
+    .fetch_all(pool)
+    .await?)
+}
+
+/// players whose last seen name contains `partial`, as (steamid, name)
+pub async fn search_players(
+    pool: &Pool<MySql>,
+    partial: &str,
+) -> Result<Vec<(String, String)>, Error> {
+    Ok(sqlx::query_as::<_, (String, String)>(
+        "SELECT `steamid`, `name` FROM `rivalry_players` WHERE `name` LIKE ? LIMIT 25",
+    )
+    .bind(format!("%{}%", partial))
+    .fetch_all(pool)
+    .await?)
+}
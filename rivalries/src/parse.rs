@@ -0,0 +1,56 @@
+/// A player as they appear in a log line
+#[derive(Clone, Debug, PartialEq)]
+pub struct LogPlayer {
+    pub name: String,
+    pub steamid: String,
+}
+
+/// parses `Name<12><[U:1:123]><Red>`. bots are ignored
+fn parse_player(s: &str) -> Option<LogPlayer> {
+    let mut parts = s.strip_suffix('>')?.rsplitn(4, '<');
+    let _team = parts.next()?;
+    let steamid = parts.next()?.strip_suffix('>')?;
+    let _uid = parts.next()?;
+    let name = parts.next()?;
+    if !steamid.starts_with("[U:") {
+        return None;
+    }
+    Some(LogPlayer {
+        name: name.to_owned(),
+        steamid: steamid.to_owned(),
+    })
+}
+
+/// parses a `"A<..>" killed "B<..>" with "weapon"` line into the killer and victim
+pub fn parse_kill(line: &str) -> Option<(LogPlayer, LogPlayer)> {
+    let (attacker, rest) = line.strip_prefix('"')?.split_once("\" killed \"")?;
+    let (victim, _) = rest.split_once("\" with \"")?;
+    let attacker = parse_player(attacker)?;
+    let victim = parse_player(victim)?;
+    if attacker.steamid == victim.steamid {
+        return None;
+    }
+    Some((attacker, victim))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_kill() {
+        let (a, v) = parse_kill(r#""kitty <3<12><[U:1:1234]><Red>" killed "dog<13><[U:1:5678]><Blue>" with "scattergun" (attacker_position "1 2 3") (victim_position "4 5 6")"#).unwrap();
+        assert_eq!(a.name, "kitty <3");
+        assert_eq!(a.steamid, "[U:1:1234]");
+        assert_eq!(v.name, "dog");
+        assert_eq!(v.steamid, "[U:1:5678]");
+
+        assert!(parse_kill(
+            r#""Bot<3><BOT><Red>" killed "dog<13><[U:1:5678]><Blue>" with "minigun""#
+        )
+        .is_none());
+        assert!(
+            parse_kill(r#""dog<13><[U:1:5678]><Blue>" committed suicide with "world""#).is_none()
+        );
+    }
+}
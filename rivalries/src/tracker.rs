@@ -0,0 +1,138 @@
+use std::{collections::HashMap, net::SocketAddr, sync::Arc, time::Duration};
+
+use poise::serenity_prelude::{CreateAllowedMentions, CreateMessage, Http};
+use sqlx::{MySql, Pool};
+use srcds_log_parser::MessageType;
+use tf2::{logs::LogReceiver, Server};
+
+use common::util::strip_markdown;
+
+use crate::{parse::parse_kill, record_action, record_kills, record_names, Action, Rivalry};
+
+/// how often buffered kills are written to the db
+const FLUSH_INTERVAL: Duration = Duration::from_secs(60);
+
+enum Event {
+    Kill {
+        killer: (String, String),
+        victim: (String, String),
+    },
+    Action {
+        from: (String, String),
+        against: (String, String),
+        action: Action,
+    },
+}
+
+/// tracks kills, dominations and revenges between players, calling out rivalries that flip
+pub async fn spawn_rivalry_tracker(
+    log_receiver: LogReceiver,
+    servers: HashMap<SocketAddr, Server>,
+    pool: Pool<MySql>,
+    http: Arc<Http>,
+) {
+    let (tx, mut rx) = tokio::sync::mpsc::channel(1000);
+    log_receiver
+        .subscribe(Box::new(move |from, msg, parsed| {
+            let event = match parsed {
+                MessageType::InterPlayerAction {
+                    from: user,
+                    against,
+                    action,
+                } => Event::Action {
+                    from: (user.steamid.clone(), user.name.clone()),
+                    against: (against.steamid.clone(), against.name.clone()),
+                    action: match action.as_str() {
+                        "domination" => Action::Domination,
+                        "revenge" => Action::Revenge,
+                        _ => return,
+                    },
+                },
+                _ => {
+                    let Some((killer, victim)) = parse_kill(&msg.message) else {
+                        return;
+                    };
+                    Event::Kill {
+                        killer: (killer.steamid, killer.name),
+                        victim: (victim.steamid, victim.name),
+                    }
+                }
+            };
+            let tx = tx.clone();
+            tokio::spawn(async move {
+                let _ = tx.send((from, event)).await;
+            });
+        }))
+        .await;
+
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(FLUSH_INTERVAL);
+        let mut kills: HashMap<(String, String), i32> = HashMap::new();
+        let mut names: HashMap<String, String> = HashMap::new();
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {
+                    let _ = record_names(&pool, &names)
+                        .await
+                        .inspect_err(|e| log::error!("Could not record rivalry names: {e}"));
+                    let _ = record_kills(&pool, &kills)
+                        .await
+                        .inspect_err(|e| log::error!("Could not record rivalry kills: {e}"));
+                    kills.clear();
+                    names.clear();
+                }
+                Some((addr, event)) = rx.recv() => match event {
+                    Event::Kill { killer, victim } => {
+                        *kills.entry((killer.0.clone(), victim.0.clone())).or_default() += 1;
+                        names.insert(killer.0, killer.1);
+                        names.insert(victim.0, victim.1);
+                    }
+                    Event::Action { from, against, action } => {
+                        names.insert(from.0.clone(), from.1);
+                        names.insert(against.0.clone(), against.1);
+                        let flipped = match record_action(&pool, &from.0, &against.0, action).await {
+                            Ok(flipped) => flipped,
+                            Err(e) => {
+                                log::error!("Could not record rivalry {action:?}: {e}");
+                                continue;
+                            }
+                        };
+                        let (Some(rivalry), Some(server)) = (flipped, servers.get(&addr)) else {
+                            continue;
+                        };
+                        let Some(channel) = server.log_channel else {
+                            continue;
+                        };
+                        let _ = channel
+                            .send_message(
+                                &http,
+                                CreateMessage::new()
+                                    .content(flip_callout(&rivalry, &names))
+                                    .allowed_mentions(CreateAllowedMentions::new().empty_users().empty_roles()),
+                            )
+                            .await
+                            .inspect_err(|e| log::error!("Could not post rivalry flip: {e}"));
+                    }
+                },
+            }
+        }
+    });
+}
+
+/// the relay message for a rivalry whose leader just changed
+fn flip_callout(rivalry: &Rivalry, names: &HashMap<String, String>) -> String {
+    let Some(leader) = &rivalry.leader else {
+        return String::new();
+    };
+    let side = rivalry.side(leader);
+    let name = |steamid: &String, fallback: &String| {
+        strip_markdown(names.get(steamid).unwrap_or(fallback))
+    };
+    format!(
+        ":arrows_counterclockwise: **{}** has turned the tables on **{}**! Dominations: `{} - {}`",
+        name(&side.steamid, &side.name),
+        name(&side.rival_steamid, &side.rival_name),
+        side.dominations,
+        side.dominated
+    )
+}
@@ -1,3 +1,4 @@
+use common::metrics;
 use sqlx::{MySql, Pool};
 use srcds_log_parser::MessageType;
 use std::{collections::HashMap, net::SocketAddr, sync::Arc};
@@ -78,10 +79,13 @@ pub async fn spawn_tracker(
                 // try flushing to db
                 if last_flush.elapsed().as_secs() >= 10 {
                     last_flush = Instant::now();
-                    let _ = stracker
+                    let flushed = stracker
                         .flush_cache_to_db()
                         .await
                         .inspect_err(|e| log::error!("Could not flush seeder cache to db: {e}"));
+                    if flushed.is_ok() {
+                        metrics::flushed("seederboard_flush", last_flush.elapsed());
+                    }
                 }
 
                 // try resynchronizing
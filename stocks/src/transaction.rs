@@ -39,7 +39,15 @@ impl Transaction {
         match (async {
             // try remove catcoin from user's account
             print!("Deducting catcoin... ");
-            if !catcoin::spend_catcoin(&mut *tx, uid, catcoin_cost as u64).await? {
+            if !catcoin::spend_catcoin(
+                &mut *tx,
+                uid,
+                catcoin_cost as u64,
+                catcoin::ledger::Reason::Stocks,
+                Some(company.tag.clone()),
+            )
+            .await?
+            {
                 return Err(Into::<Error>::into(
                     "You do not have enough catcoin to buy this!",
                 ));
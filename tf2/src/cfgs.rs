@@ -0,0 +1,291 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+
+use common::Error;
+
+use crate::Server;
+
+/// where server cfgs live
+pub const CFG_DIR: &str = "tf/cfg";
+
+fn default_include() -> Vec<String> {
+    ["rgl_", "etf2l_", "ugc_", "tfcl_", "ozf_", "mge", "arena"]
+        .into_iter()
+        .map(str::to_owned)
+        .collect()
+}
+
+/// Optional friendly info about a cfg
+#[derive(Deserialize, Clone, Debug, Default)]
+pub struct CfgMeta {
+    pub name: Option<String>,
+    pub category: Option<String>,
+    /// map name patterns the cfg is for. inferred from the cfg name when empty
+    #[serde(default)]
+    pub maps: Vec<String>,
+    /// picked before the other cfgs in its category when launching a pug
+    #[serde(default)]
+    pub preferred: bool,
+}
+
+/// Which cfgs on the server are offered for pugs, and how they're shown
+#[derive(Deserialize, Clone, Debug)]
+pub struct CfgConfig {
+    /// cfg name prefixes to offer. cfgs listed in `cfgs` are always offered
+    #[serde(default = "default_include")]
+    pub include: Vec<String>,
+    #[serde(default)]
+    pub cfgs: HashMap<String, CfgMeta>,
+}
+
+impl Default for CfgConfig {
+    fn default() -> Self {
+        Self {
+            include: default_include(),
+            cfgs: HashMap::new(),
+        }
+    }
+}
+
+impl CfgConfig {
+    /// loads the config from a json file
+    pub fn load(path: &str) -> Result<Self, Error> {
+        Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+    }
+}
+
+/// A cfg available on the pug server
+#[derive(Clone, Debug)]
+pub struct PugCfg {
+    /// file name without `.cfg`
+    pub name: String,
+    pub label: String,
+    pub category: Option<String>,
+    pub maps: Vec<String>,
+    pub preferred: bool,
+}
+
+impl PugCfg {
+    /// whether the cfg is meant for the given map
+    pub fn allows_map(&self, map: &str) -> bool {
+        self.maps.is_empty() || self.maps.iter().any(|m| map.contains(m.as_str()))
+    }
+
+    pub fn display(&self) -> String {
+        match &self.category {
+            Some(category) => format!("{} [{}]", self.label, category),
+            None => self.label.clone(),
+        }
+    }
+}
+
+/// guesses the maps a cfg is for from the gamemode in its name
+fn infer_maps(cfg: &str) -> Vec<String> {
+    let words: Vec<&str> = cfg.split('_').collect();
+    let has = |w: &str| words.iter().any(|word| word.eq_ignore_ascii_case(w));
+    let maps: &[&str] = if has("ultiduo") || has("ud") {
+        &["ultiduo"]
+    } else if has("koth") {
+        &["koth_"]
+    } else if has("5cp") || has("cp") {
+        &["cp_"]
+    } else if has("pl") || has("payload") {
+        &["pl_"]
+    } else if has("ctf") {
+        &["ctf_"]
+    } else if has("pass") {
+        &["pass_"]
+    } else if has("mge") {
+        &["mge"]
+    } else if has("arena") {
+        &["arena_"]
+    } else {
+        &[]
+    };
+    maps.iter().map(|m| m.to_string()).collect()
+}
+
+/// Cached index of the cfgs on the pug server
+pub struct CfgIndex {
+    config: CfgConfig,
+    cfgs: Vec<PugCfg>,
+    pub refreshed: Option<DateTime<Utc>>,
+}
+
+impl CfgIndex {
+    pub fn new(config: CfgConfig) -> Self {
+        Self {
+            config,
+            cfgs: vec![],
+            refreshed: None,
+        }
+    }
+
+    /// rebuilds the index from the file names in the cfg directory
+    pub fn update(&mut self, files: Vec<String>) {
+        let mut cfgs: Vec<PugCfg> = files
+            .into_iter()
+            .filter_map(|f| f.strip_suffix(".cfg").map(str::to_owned))
+            .filter(|name| {
+                self.config.cfgs.contains_key(name)
+                    || self.config.include.iter().any(|p| name.starts_with(p))
+            })
+            .map(|name| {
+                let meta = self.config.cfgs.get(&name).cloned().unwrap_or_default();
+                PugCfg {
+                    label: meta.name.unwrap_or(name.clone()),
+                    category: meta.category,
+                    maps: if meta.maps.is_empty() {
+                        infer_maps(&name)
+                    } else {
+                        meta.maps
+                    },
+                    preferred: meta.preferred,
+                    name,
+                }
+            })
+            .collect();
+        cfgs.sort_by(|a, b| a.category.cmp(&b.category).then(a.name.cmp(&b.name)));
+        self.cfgs = cfgs;
+        self.refreshed = Some(Utc::now());
+    }
+
+    pub fn all(&self) -> &[PugCfg] {
+        &self.cfgs
+    }
+
+    pub fn get(&self, name: &str) -> Option<&PugCfg> {
+        self.cfgs.iter().find(|c| c.name == name)
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+
+    /// the cfg in a category that's meant for the map, preferred cfgs first
+    pub fn find(&self, category: &str, map: &str) -> Option<&PugCfg> {
+        let mut matches: Vec<&PugCfg> = self
+            .cfgs
+            .iter()
+            .filter(|c| {
+                c.category
+                    .as_deref()
+                    .is_some_and(|c| c.eq_ignore_ascii_case(category))
+                    && c.allows_map(map)
+            })
+            .collect();
+        matches.sort_by_key(|c| !c.preferred);
+        matches.first().copied()
+    }
+}
+
+/// lists the cfg file names on the server
+pub async fn list_cfgs(server: &Server) -> Result<Vec<String>, Error> {
+    Ok(server
+        .files
+        .list_dir(CFG_DIR)
+        .await?
+        .into_iter()
+        .filter(|e| !e.is_dir)
+        .map(|e| e.name)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn files(names: &[&str]) -> Vec<String> {
+        names.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_infer_maps() {
+        assert_eq!(infer_maps("rgl_6s_koth_scrim"), vec!["koth_"]);
+        assert_eq!(infer_maps("rgl_6s_5cp_scrim"), vec!["cp_"]);
+        assert_eq!(infer_maps("tfcl_UD_ultiduo"), vec!["ultiduo"]);
+        assert_eq!(infer_maps("etf2l_hl_stopwatch_pl"), vec!["pl_"]);
+        assert!(infer_maps("rgl_off").is_empty());
+    }
+
+    #[test]
+    fn test_update() {
+        let mut config = CfgConfig::default();
+        config.cfgs.insert(
+            "rgl_6s_koth_scrim".to_owned(),
+            CfgMeta {
+                name: Some("RGL 6s KOTH scrim".to_owned()),
+                category: Some("6s".to_owned()),
+                maps: vec![],
+                preferred: false,
+            },
+        );
+        config
+            .cfgs
+            .insert("my_league".to_owned(), CfgMeta::default());
+        let mut index = CfgIndex::new(config);
+        index.update(files(&[
+            "server.cfg",
+            "rgl_6s_koth_scrim.cfg",
+            "rgl_off.cfg",
+            "my_league.cfg",
+            "rgl_notes.txt",
+        ]));
+
+        let names: Vec<&str> = index.all().iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["my_league", "rgl_off", "rgl_6s_koth_scrim"]);
+        let koth = index.get("rgl_6s_koth_scrim").unwrap();
+        assert_eq!(koth.label, "RGL 6s KOTH scrim");
+        assert!(koth.allows_map("koth_product_final"));
+        assert!(!koth.allows_map("cp_process_f12"));
+        assert!(index.get("rgl_off").unwrap().allows_map("cp_process_f12"));
+        assert_eq!(
+            index.find("6s", "koth_bagel").map(|c| c.name.as_str()),
+            Some("rgl_6s_koth_scrim")
+        );
+        assert!(index.find("6s", "cp_gullywash").is_none());
+    }
+
+    #[test]
+    fn test_find_preferred() {
+        let mut config = CfgConfig::default();
+        for (name, preferred) in [
+            ("rgl_6s_5cp_match_pro", false),
+            ("rgl_6s_5cp_scrim", true),
+            ("rgl_6s_koth_bo5", false),
+            ("rgl_6s_koth_scrim", false),
+        ] {
+            config.cfgs.insert(
+                name.to_owned(),
+                CfgMeta {
+                    category: Some("6s".to_owned()),
+                    preferred,
+                    ..Default::default()
+                },
+            );
+        }
+        let mut index = CfgIndex::new(config);
+        index.update(files(&[
+            "rgl_6s_5cp_match_pro.cfg",
+            "rgl_6s_5cp_scrim.cfg",
+            "rgl_6s_koth_bo5.cfg",
+            "rgl_6s_koth_scrim.cfg",
+        ]));
+        // the preferred cfg wins over the ones sorted before it
+        assert_eq!(
+            index
+                .find("6s", "cp_process_final")
+                .map(|c| c.name.as_str()),
+            Some("rgl_6s_5cp_scrim")
+        );
+        // without one, the first match
+        assert_eq!(
+            index
+                .find("6s", "koth_product_final")
+                .map(|c| c.name.as_str()),
+            Some("rgl_6s_koth_bo5")
+        );
+    }
+}
@@ -0,0 +1,76 @@
+use chrono::{DateTime, Utc};
+
+use common::Error;
+
+use crate::Server;
+
+/// Fields of interest from the header of a source engine `.dem` file
+#[derive(Debug, Clone)]
+pub struct DemoHeader {
+    pub server_name: String,
+    pub map: String,
+    /// seconds of recorded playback. zero while the demo is still being recorded
+    pub playback_time: f32,
+    pub ticks: i32,
+}
+
+impl DemoHeader {
+    /// size of the HL2DEMO header in bytes
+    pub const SIZE: usize = 1072;
+
+    pub fn parse(bytes: &[u8]) -> Result<Self, Error> {
+        if bytes.len() < Self::SIZE || &bytes[..8] != b"HL2DEMO\0" {
+            return Err("Not a valid demo file".into());
+        }
+        let string = |offset: usize| {
+            let field = &bytes[offset..offset + 260];
+            let end = field.iter().position(|&c| c == 0).unwrap_or(field.len());
+            String::from_utf8_lossy(&field[..end]).into_owned()
+        };
+        let word = |offset: usize| -> [u8; 4] { bytes[offset..offset + 4].try_into().unwrap() };
+        Ok(Self {
+            server_name: string(16),
+            map: string(536),
+            playback_time: f32::from_le_bytes(word(1056)),
+            ticks: i32::from_le_bytes(word(1060)),
+        })
+    }
+}
+
+/// A demo recorded on a server
+#[derive(Debug, Clone)]
+pub struct Demo {
+    pub name: String,
+    pub size: u64,
+    pub modified: Option<DateTime<Utc>>,
+}
+
+impl Demo {
+    pub fn path(&self, dir: &str) -> String {
+        format!("{}/{}", dir.trim_end_matches('/'), self.name)
+    }
+}
+
+/// lists the demos in the server's demo directory, newest first
+pub async fn list_demos(server: &Server, dir: &str) -> Result<Vec<Demo>, Error> {
+    let mut demos: Vec<Demo> = server
+        .files
+        .list_dir(dir)
+        .await?
+        .into_iter()
+        .filter(|e| !e.is_dir && e.name.ends_with(".dem"))
+        .map(|e| Demo {
+            name: e.name,
+            size: e.size,
+            modified: e.modified,
+        })
+        .collect();
+    demos.sort_by(|a, b| b.modified.cmp(&a.modified).then(b.name.cmp(&a.name)));
+    Ok(demos)
+}
+
+/// reads just the header of a demo on the server
+pub async fn read_header(server: &Server, path: &str) -> Result<DemoHeader, Error> {
+    let bytes = server.files.fetch_file_head(path, DemoHeader::SIZE).await?;
+    DemoHeader::parse(&bytes)
+}
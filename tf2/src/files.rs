@@ -1,5 +1,16 @@
+use chrono::{DateTime, Utc};
 use common::Error;
 use poise::serenity_prelude::async_trait;
+use std::path::Path;
+
+/// A single entry of a directory listing
+#[derive(Debug, Clone)]
+pub struct FileEntry {
+    pub name: String,
+    pub size: u64,
+    pub modified: Option<DateTime<Utc>>,
+    pub is_dir: bool,
+}
 
 #[async_trait]
 pub trait ServerFiles: Send + Sync {
@@ -9,6 +20,27 @@ pub trait ServerFiles: Send + Sync {
     /// upload the contents of a file on the server.
     async fn upload_file(&self, path: &str, contents: &[u8]) -> Result<(), Error>;
 
+    /// list the entries of a directory on the server.
+    async fn list_dir(&self, path: &str) -> Result<Vec<FileEntry>, Error>;
+
+    /// delete a file on the server.
+    async fn delete_file(&self, path: &str) -> Result<(), Error>;
+
+    /// download at most the first `len` bytes of a file on the server.
+    async fn fetch_file_head(&self, path: &str, len: usize) -> Result<Vec<u8>, Error> {
+        let mut bytes = self.fetch_file(path).await?;
+        bytes.truncate(len);
+        Ok(bytes)
+    }
+
+    /// download a file on the server to a local path without holding it in memory.
+    /// returns the size of the file
+    async fn download_file(&self, path: &str, local: &Path) -> Result<u64, Error> {
+        let bytes = self.fetch_file(path).await?;
+        tokio::fs::write(local, &bytes).await?;
+        Ok(bytes.len() as u64)
+    }
+
     /// download the contents of a file and split it into lines, trimming whitespace
     async fn fetch_file_lines(&self, path: &str) -> Result<Vec<String>, Error> {
         self.fetch_file(path).await.map(|bytes| {
@@ -41,4 +73,16 @@ pub trait ServerFiles: Send + Sync {
         self.upload_file(path, lines.join("\n").as_bytes()).await?;
         Ok(!exists)
     }
+
+    /// removes every line starting with the given text. returns true if any were removed
+    async fn remove_lines(&self, path: &str, starts_with: &str) -> Result<bool, Error> {
+        let mut lines = self.fetch_file_lines(path).await?;
+        let len = lines.len();
+        lines.retain(|line| !line.starts_with(starts_with));
+        if lines.len() == len {
+            return Ok(false);
+        }
+        self.upload_file(path, lines.join("\n").as_bytes()).await?;
+        Ok(true)
+    }
 }
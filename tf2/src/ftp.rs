@@ -1,7 +1,12 @@
-use crate::files::ServerFiles;
+use crate::files::{FileEntry, ServerFiles};
 use ftp::FtpStream;
 use poise::serenity_prelude::async_trait;
-use std::{io::Cursor, net::SocketAddr};
+use std::{
+    fs::File,
+    io::{self, Cursor, Read},
+    net::SocketAddr,
+    path::Path,
+};
 
 use common::Error;
 
@@ -18,13 +23,21 @@ impl ServerFtp {
         Self { addr, creds }
     }
 
-    /// run an operation on the ftp stream
-    fn exec<T>(&self, f: impl FnOnce(&mut FtpStream) -> Result<T, Error>) -> Result<T, Error> {
-        let mut ftp = FtpStream::connect(self.addr)?;
-        ftp.login(&self.creds.0, &self.creds.1)?;
-        let r = f(&mut ftp);
-        ftp.quit()?;
-        r
+    /// run an operation on the ftp stream, off the async runtime
+    async fn exec<T: Send + 'static>(
+        &self,
+        f: impl FnOnce(&mut FtpStream) -> Result<T, Error> + Send + 'static,
+    ) -> Result<T, Error> {
+        let addr = self.addr;
+        let (user, password) = self.creds.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut ftp = FtpStream::connect(addr)?;
+            ftp.login(&user, &password)?;
+            let r = f(&mut ftp);
+            ftp.quit()?;
+            r
+        })
+        .await?
     }
 }
 
@@ -32,11 +45,76 @@ impl ServerFtp {
 impl ServerFiles for ServerFtp {
     /// download the contents of a file on the server.
     async fn fetch_file(&self, path: &str) -> Result<Vec<u8>, Error> {
-        self.exec(|ftp| Ok(ftp.simple_retr(path)?.into_inner()))
+        let path = path.to_owned();
+        self.exec(move |ftp| Ok(ftp.simple_retr(&path)?.into_inner()))
+            .await
     }
 
     /// upload the contents of a file on the server.
     async fn upload_file(&self, path: &str, contents: &[u8]) -> Result<(), Error> {
-        self.exec(|ftp| Ok(ftp.put(path, &mut Cursor::new(contents))?))
+        let path = path.to_owned();
+        let contents = contents.to_vec();
+        self.exec(move |ftp| Ok(ftp.put(&path, &mut Cursor::new(contents))?))
+            .await
+    }
+
+    /// list the entries of a directory on the server.
+    async fn list_dir(&self, path: &str) -> Result<Vec<FileEntry>, Error> {
+        let path = path.to_owned();
+        self.exec(move |ftp| {
+            let mut entries = vec![];
+            for name in ftp.nlst(Some(&path))? {
+                // some servers return the full path, others just the name
+                let name = name.rsplit('/').next().unwrap_or(&name).to_owned();
+                let full = format!("{}/{}", path.trim_end_matches('/'), name);
+                // SIZE is not defined for directories, and some servers refuse it with a 550
+                let size = ftp.size(&full).ok().flatten();
+                entries.push(FileEntry {
+                    modified: ftp.mdtm(&full).ok().flatten(),
+                    size: size.unwrap_or(0) as u64,
+                    // a directory, or something we can't tell the size of
+                    is_dir: size.is_none(),
+                    name,
+                });
+            }
+            Ok(entries)
+        })
+        .await
+    }
+
+    /// delete a file on the server.
+    async fn delete_file(&self, path: &str) -> Result<(), Error> {
+        let path = path.to_owned();
+        self.exec(move |ftp| Ok(ftp.rm(&path)?)).await
+    }
+
+    /// download at most the first `len` bytes of a file on the server.
+    async fn fetch_file_head(&self, path: &str, len: usize) -> Result<Vec<u8>, Error> {
+        let path = path.to_owned();
+        self.exec(move |ftp| {
+            let mut stream = ftp.get(&path)?;
+            let mut contents = Vec::new();
+            (&mut stream).take(len as u64).read_to_end(&mut contents)?;
+            // closing the data connection early aborts the transfer, so the server
+            // answers 426 instead of 226 unless the whole file was already sent
+            drop(stream);
+            ftp.read_response_in(&[226, 250, 426, 451])?;
+            Ok(contents)
+        })
+        .await
+    }
+
+    /// download a file on the server to a local path without holding it in memory.
+    async fn download_file(&self, path: &str, local: &Path) -> Result<u64, Error> {
+        let path = path.to_owned();
+        let local = local.to_owned();
+        self.exec(move |ftp| {
+            Ok(ftp.retr(&path, |reader| {
+                File::create(&local)
+                    .and_then(|mut file| io::copy(reader, &mut file))
+                    .map_err(ftp::FtpError::ConnectionError)
+            })?)
+        })
+        .await
     }
 }
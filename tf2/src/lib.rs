@@ -1,13 +1,17 @@
+pub mod cfgs;
 mod class;
+pub mod demos;
 mod files;
 pub mod ftp;
 pub mod logs;
 mod rcon;
+pub mod report;
 mod server;
 pub mod sftp;
 pub mod wacky;
 
 pub use class::TF2Class;
+pub use files::{FileEntry, ServerFiles};
 pub use ftp::ServerFtp;
 pub use rcon::{banid, rcon_user_output, GameState, NextMap, Player, RconController, TimeLeft};
 pub use server::{Server, ServerBuilder};
@@ -1,21 +1,24 @@
 use std::{
+    collections::HashMap,
     net::{Ipv4Addr, SocketAddr},
     sync::Arc,
 };
 
-use chrono::Utc;
+use chrono::{NaiveDateTime, Utc};
 use tokio::{net::UdpSocket, sync::RwLock};
 
 use srcds_log_parser::{LogMessage, MessageType};
 
 mod discord;
+mod history;
 mod util;
 
-pub use discord::spawn_log_thread;
+pub use discord::{spawn_log_thread, RelayTags};
+pub use history::ChatHistory;
 
 use util::as_discord_message;
 
-use common::Error;
+use common::{metrics, Error};
 
 type LogCallback = Box<dyn Fn(SocketAddr, &LogMessage, &MessageType) + Send + Sync + 'static>;
 
@@ -23,11 +26,17 @@ type LogCallback = Box<dyn Fn(SocketAddr, &LogMessage, &MessageType) + Send + Sy
 #[derive(Clone)]
 pub struct LogReceiver {
     callbacks: Arc<RwLock<Vec<LogCallback>>>,
+    /// server address -> name, used to label metrics
+    server_names: Arc<HashMap<SocketAddr, String>>,
 }
 
 impl LogReceiver {
     /// create and bind a udp socket to listen to srcds logs
-    pub async fn connect(addr: Ipv4Addr, port: u16) -> Result<Self, Error> {
+    pub async fn connect(
+        addr: Ipv4Addr,
+        port: u16,
+        server_names: HashMap<SocketAddr, String>,
+    ) -> Result<Self, Error> {
         let sock = Arc::new(UdpSocket::bind((addr, port)).await?);
         let callbacks = Arc::new(RwLock::new(Vec::new()));
 
@@ -35,7 +44,10 @@ impl LogReceiver {
             .ok()
             .and_then(|p| if p.len() > 0 { Some(p) } else { None });
 
-        let lr = LogReceiver { callbacks };
+        let lr = LogReceiver {
+            callbacks,
+            server_names: Arc::new(server_names),
+        };
 
         let _task = {
             let sock = sock.clone();
@@ -44,16 +56,22 @@ impl LogReceiver {
                 let mut buf = [0u8; 1024];
                 loop {
                     let (len, from) = sock.recv_from(&mut buf).await.unwrap();
+                    let server = lr.server_label(from);
+                    metrics::LOG_PACKETS_RECEIVED.inc(&[("server", server), ("source", "udp")]);
                     let message = match LogMessage::from_bytes(&buf[..len]) {
                         Ok(m) => m,
                         Err(e) => {
                             log::info!(
                                 "Could not parse packet from {from:?} with len {len}: {e:?}"
                             );
+                            metrics::LOG_PACKETS_DROPPED
+                                .inc(&[("server", server), ("reason", "parse")]);
                             continue;
                         }
                     };
                     if expected_password.is_some() && message.secret != expected_password {
+                        metrics::LOG_PACKETS_DROPPED
+                            .inc(&[("server", server), ("reason", "password")]);
                         continue;
                     }
 
@@ -65,12 +83,29 @@ impl LogReceiver {
         Ok(lr)
     }
 
+    /// the name of the server at `addr`, or "unknown" so senders can't add metric series
+    fn server_label(&self, addr: SocketAddr) -> &str {
+        self.server_names
+            .get(&addr)
+            .map(String::as_str)
+            .unwrap_or("unknown")
+    }
+
     pub async fn subscribe(&self, cb: LogCallback) {
         self.callbacks.write().await.push(cb);
     }
 
+    /// broadcasts a log line received some other way than udp, as if it came from the given server.
+    /// accepts lines with or without the `L mm/dd/yyyy - hh:mm:ss: ` prefix
+    pub async fn ingest_line(&self, from: SocketAddr, line: &str) {
+        metrics::LOG_PACKETS_RECEIVED
+            .inc(&[("server", self.server_label(from)), ("source", "http")]);
+        self.broadcast_message(from, parse_log_line(line)).await;
+    }
+
     async fn broadcast_message(&self, from: SocketAddr, msg: LogMessage) {
         let parsed = MessageType::from_message(msg.message.as_str());
+        metrics::LOG_PACKETS_PARSED.inc(&[("server", self.server_label(from))]);
         for cb in self.callbacks.read().await.iter() {
             cb(from.clone(), &msg, &parsed);
         }
@@ -91,3 +126,38 @@ impl LogReceiver {
         .await;
     }
 }
+
+/// parses a single srcds log line, defaulting to the current time if it has no timestamp
+fn parse_log_line(line: &str) -> LogMessage {
+    let line = line.trim_end_matches(['\0', '\n', '\r']);
+    let stamped = line.strip_prefix("L ").and_then(|rest| {
+        let (timestamp, message) = rest.split_once(": ")?;
+        let timestamp = NaiveDateTime::parse_from_str(timestamp, "%m/%d/%Y - %H:%M:%S").ok()?;
+        Some((timestamp, message))
+    });
+    let (timestamp, message) = stamped.unwrap_or((Utc::now().naive_utc(), line));
+    LogMessage {
+        timestamp,
+        message: message.to_owned(),
+        secret: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_log_lines() {
+        let msg =
+            parse_log_line("L 10/19/2026 - 12:30:05: \"cat<2><[U:1:1]><Red>\" say \"hi: there\"\n");
+        assert_eq!(
+            msg.timestamp,
+            NaiveDateTime::parse_from_str("2026-10-19 12:30:05", "%Y-%m-%d %H:%M:%S").unwrap()
+        );
+        assert_eq!(msg.message, "\"cat<2><[U:1:1]><Red>\" say \"hi: there\"");
+
+        let msg = parse_log_line("World triggered \"Round_Start\"");
+        assert_eq!(msg.message, "World triggered \"Round_Start\"");
+    }
+}
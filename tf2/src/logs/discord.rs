@@ -1,13 +1,13 @@
 use super::{as_discord_message, LogReceiver};
 use crate::{banid, Server};
-use common::Error;
+use common::{metrics, Error};
 use poise::serenity_prelude::{self as serenity, CreateAllowedMentions};
 use serenity::CreateMessage;
 use sqlx::{MySql, Pool};
 use std::collections::HashMap;
 use std::net::SocketAddr;
 use std::sync::Arc;
-use tokio::time;
+use tokio::{sync::RwLock, time};
 
 use srcds_log_parser::MessageType;
 
@@ -25,12 +25,16 @@ pub async fn ban_noobs(parsed: &MessageType, server: &Server) -> Result<(), Erro
     Ok(())
 }
 
+/// steamid -> tag shown before the player's name in the chat relay
+pub type RelayTags = Arc<RwLock<HashMap<String, String>>>;
+
 /// receives logs from the tf2 server & posts them in a channel
 pub async fn spawn_log_thread(
     log_receiver: LogReceiver,
     servers: HashMap<SocketAddr, Server>,
     pool: Pool<MySql>,
     ctx: Arc<serenity::Http>,
+    tags: RelayTags,
 ) {
     let mut interval = time::interval(time::Duration::from_secs(3));
     let (msg_in, mut msg_out) = tokio::sync::mpsc::channel(1000);
@@ -80,7 +84,13 @@ pub async fn spawn_log_thread(
                     log::info!("Could not ban noobs: {:?}", e);
                 });
 
-                let dm = as_discord_message(&parsed, dom_score);
+                let tag = match &parsed {
+                    MessageType::ChatMessage { from, .. } => {
+                        tags.read().await.get(&from.steamid).cloned()
+                    }
+                    _ => None,
+                };
+                let dm = as_discord_message(&parsed, dom_score, tag.as_deref());
 
                 if let Some(dm) = dm {
                     let v = output.entry(from).or_insert_with(|| "".to_owned());
@@ -114,6 +124,9 @@ pub async fn spawn_log_thread(
                     .await
                 {
                     log::info!("Could not send message to logs channel: {:?}", e);
+                    metrics::DISCORD_ERRORS.inc(&[("kind", "relay")]);
+                } else {
+                    metrics::RELAY_MESSAGES.inc(&[("server", &addr.to_string())]);
                 }
             }
         }
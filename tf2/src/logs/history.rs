@@ -0,0 +1,61 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+
+use chrono::NaiveDateTime;
+
+use super::{as_discord_message, LogReceiver};
+
+/// In-memory ring buffer of the most recent relay lines per server
+#[derive(Clone)]
+pub struct ChatHistory {
+    capacity: usize,
+    lines: Arc<Mutex<HashMap<SocketAddr, VecDeque<(NaiveDateTime, String)>>>>,
+}
+
+impl ChatHistory {
+    /// subscribe to the log receiver, keeping the last `capacity` lines of each server
+    pub async fn subscribe(log_receiver: &LogReceiver, capacity: usize) -> Self {
+        let history = Self {
+            capacity,
+            lines: Arc::new(Mutex::new(HashMap::new())),
+        };
+        {
+            let history = history.clone();
+            log_receiver
+                .subscribe(Box::new(move |from, msg, parsed| {
+                    if parsed.is_unknown() {
+                        return;
+                    }
+                    if let Some(line) = as_discord_message(parsed, None, None) {
+                        history.push(from, msg.timestamp, line);
+                    }
+                }))
+                .await;
+        }
+        history
+    }
+
+    fn push(&self, from: SocketAddr, timestamp: NaiveDateTime, line: String) {
+        let mut lines = self.lines.lock().unwrap();
+        let buf = lines.entry(from).or_default();
+        if buf.len() >= self.capacity {
+            buf.pop_front();
+        }
+        buf.push_back((timestamp, line));
+    }
+
+    /// the last `n` lines received from the server, oldest first
+    pub fn last(&self, from: SocketAddr, n: usize) -> Vec<(NaiveDateTime, String)> {
+        let lines = self.lines.lock().unwrap();
+        let Some(buf) = lines.get(&from) else {
+            return vec![];
+        };
+        buf.iter()
+            .skip(buf.len().saturating_sub(n))
+            .cloned()
+            .collect()
+    }
+}
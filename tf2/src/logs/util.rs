@@ -2,7 +2,12 @@ use srcds_log_parser::MessageType;
 
 use common::util::strip_markdown;
 
-pub fn as_discord_message(msg: &MessageType, dom_score: Option<i32>) -> Option<String> {
+/// formats a log message for discord. `tag` is shown before the name of whoever sent a chat message
+pub fn as_discord_message(
+    msg: &MessageType,
+    dom_score: Option<i32>,
+    tag: Option<&str>,
+) -> Option<String> {
     let dominator_dom_score = dom_score
         .map(|s| format!(" **({})**", s))
         .unwrap_or("".to_owned());
@@ -11,7 +16,9 @@ pub fn as_discord_message(msg: &MessageType, dom_score: Option<i32>) -> Option<S
         .unwrap_or("".to_owned());
     match msg {
         MessageType::ChatMessage { from, message, .. } => format!(
-            "**{}** :  {}",
+            "{}**{}** :  {}",
+            tag.map(|t| format!("[{}] ", strip_markdown(t)))
+                .unwrap_or_default(),
             strip_markdown(&from.name),
             strip_markdown(&message)
         )
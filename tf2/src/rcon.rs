@@ -1,6 +1,7 @@
 use std::{net::SocketAddr, time};
 
 use common::{
+    metrics,
     util::{hhmmss, remove_backticks},
     Error,
 };
@@ -150,9 +151,18 @@ impl RconController {
 
     /// run an rcon command and return the output
     pub async fn run(&mut self, cmd: &str) -> Result<String, Error> {
+        let server = self.address.to_string();
+        let labels = [("server", server.as_str())];
+        let start = time::Instant::now();
         match self.connection.cmd(cmd).await {
-            Ok(msg) => Ok(msg),
+            Ok(msg) => {
+                metrics::RCON_LATENCY.observe(&labels, start.elapsed().as_secs_f64());
+                metrics::SERVER_ONLINE.set(&labels, 1.);
+                Ok(msg)
+            }
             Err(e) => {
+                metrics::RCON_ERRORS.inc(&labels);
+                metrics::SERVER_ONLINE.set(&labels, 0.);
                 self.reconnect().await?;
                 Err(format!("Failed to connect, retrying. Error {}", e))?
             }
@@ -0,0 +1,115 @@
+use crate::Player;
+
+/// A player report sent through in-game chat
+#[derive(Debug, Clone, PartialEq)]
+pub struct Report {
+    /// the name of the reported player, none for a general `@admin` call
+    pub target: Option<String>,
+    pub reason: String,
+}
+
+/// parses `!report <name> <reason>`, `!report "<name with spaces>" <reason>` or `@admin <reason>`
+pub fn parse_report(message: &str) -> Option<Report> {
+    let message = message.trim();
+    if let Some(rest) = strip_prefix_ci(message, "@admin")
+        .filter(|rest| rest.is_empty() || rest.starts_with(char::is_whitespace))
+    {
+        return Some(Report {
+            target: None,
+            reason: rest.trim().to_owned(),
+        });
+    }
+    let rest = strip_prefix_ci(message, "!report ")?.trim_start();
+    let (target, reason) = if let Some(quoted) = rest.strip_prefix('"') {
+        quoted.split_once('"')?
+    } else {
+        rest.split_once(' ').unwrap_or((rest, ""))
+    };
+    if target.is_empty() {
+        return None;
+    }
+    Some(Report {
+        target: Some(target.to_owned()),
+        reason: reason.trim().to_owned(),
+    })
+}
+
+fn strip_prefix_ci<'a>(s: &'a str, prefix: &str) -> Option<&'a str> {
+    let head = s.get(..prefix.len())?;
+    if head.eq_ignore_ascii_case(prefix) {
+        Some(&s[prefix.len()..])
+    } else {
+        None
+    }
+}
+
+/// finds the player best matching the given name. exact matches beat prefixes beat substrings
+pub fn find_player<'a>(players: &'a [Player], name: &str) -> Option<&'a Player> {
+    let name = name.to_lowercase();
+    let lower = |p: &Player| p.name.to_lowercase();
+    players
+        .iter()
+        .find(|p| lower(p) == name)
+        .or_else(|| players.iter().find(|p| lower(p).starts_with(&name)))
+        .or_else(|| {
+            let mut matches = players.iter().filter(|p| lower(p).contains(&name));
+            let first = matches.next();
+            // ambiguous substring matches resolve to nobody
+            if matches.next().is_some() {
+                None
+            } else {
+                first
+            }
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn player(name: &str) -> Player {
+        Player {
+            name: name.to_owned(),
+            connected: Duration::from_secs(0),
+            id: "[U:1:1]".to_owned(),
+        }
+    }
+
+    #[test]
+    fn test_parse_report() {
+        assert_eq!(
+            parse_report("!report kitty aimbot lol"),
+            Some(Report {
+                target: Some("kitty".to_owned()),
+                reason: "aimbot lol".to_owned()
+            })
+        );
+        assert_eq!(
+            parse_report("!REPORT \"tiny kitty\" spamming mic"),
+            Some(Report {
+                target: Some("tiny kitty".to_owned()),
+                reason: "spamming mic".to_owned()
+            })
+        );
+        assert_eq!(
+            parse_report("@admin help"),
+            Some(Report {
+                target: None,
+                reason: "help".to_owned()
+            })
+        );
+        assert_eq!(parse_report("@administrator"), None);
+        assert_eq!(parse_report("!report"), None);
+        assert_eq!(parse_report("gg"), None);
+    }
+
+    #[test]
+    fn test_find_player() {
+        let players = vec![player("cat"), player("catgirl"), player("dogcat")];
+        assert_eq!(find_player(&players, "CAT").unwrap().name, "cat");
+        assert_eq!(find_player(&players, "catg").unwrap().name, "catgirl");
+        assert_eq!(find_player(&players, "dog").unwrap().name, "dogcat");
+        assert!(find_player(&players, "at").is_none());
+    }
+}
@@ -18,6 +18,8 @@ pub struct ServerBuilder {
     pub show_status: bool,
     pub control_mapfile: bool,
     pub wacky_server: bool,
+    /// secret used to sign logs sent over http instead of udp
+    pub ingest_secret: Option<String>,
 }
 
 impl ServerBuilder {
@@ -37,6 +39,7 @@ impl ServerBuilder {
             show_status: self.show_status,
             control_mapfile: self.control_mapfile,
             wacky_server: self.wacky_server,
+            ingest_secret: self.ingest_secret,
         })
     }
 }
@@ -55,6 +58,7 @@ pub struct Server {
     pub show_status: bool,
     pub control_mapfile: bool,
     pub wacky_server: bool,
+    pub ingest_secret: Option<String>,
 }
 
 impl Server {
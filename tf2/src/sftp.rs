@@ -1,10 +1,12 @@
 use std::{
-    io::{Read, Write},
+    fs::File,
+    io::{self, Read, Write},
     net::{SocketAddr, TcpStream},
     path::Path,
 };
 
-use crate::files::ServerFiles;
+use crate::files::{FileEntry, ServerFiles};
+use chrono::DateTime;
 use common::Error;
 use poise::serenity_prelude::async_trait;
 use ssh2::{Session, Sftp};
@@ -69,4 +71,60 @@ impl ServerFiles for ServerSftp {
         })
         .await?
     }
+
+    /// list the entries of a directory on the server.
+    async fn list_dir(&self, path: &str) -> Result<Vec<FileEntry>, Error> {
+        let sftp = self.connect().await?;
+        let path = path.to_owned();
+        tokio::task::spawn_blocking(move || -> Result<Vec<FileEntry>, Error> {
+            Ok(sftp
+                .readdir(Path::new(&path))?
+                .into_iter()
+                .filter_map(|(path, stat)| {
+                    Some(FileEntry {
+                        name: path.file_name()?.to_string_lossy().into_owned(),
+                        size: stat.size.unwrap_or(0),
+                        modified: stat
+                            .mtime
+                            .and_then(|t| DateTime::from_timestamp(t as i64, 0)),
+                        is_dir: stat.is_dir(),
+                    })
+                })
+                .collect())
+        })
+        .await?
+    }
+
+    /// delete a file on the server.
+    async fn delete_file(&self, path: &str) -> Result<(), Error> {
+        let sftp = self.connect().await?;
+        let path = path.to_owned();
+        tokio::task::spawn_blocking(move || Ok(sftp.unlink(Path::new(&path))?)).await?
+    }
+
+    /// download at most the first `len` bytes of a file on the server.
+    async fn fetch_file_head(&self, path: &str, len: usize) -> Result<Vec<u8>, Error> {
+        let sftp = self.connect().await?;
+        let path = path.to_owned();
+        tokio::task::spawn_blocking(move || -> Result<Vec<u8>, Error> {
+            let mut contents = Vec::new();
+            sftp.open(Path::new(&path))?
+                .take(len as u64)
+                .read_to_end(&mut contents)?;
+            Ok(contents)
+        })
+        .await?
+    }
+
+    /// download a file on the server to a local path without holding it in memory.
+    async fn download_file(&self, path: &str, local: &Path) -> Result<u64, Error> {
+        let sftp = self.connect().await?;
+        let path = path.to_owned();
+        let local = local.to_owned();
+        tokio::task::spawn_blocking(move || -> Result<u64, Error> {
+            let mut file = File::create(local)?;
+            Ok(io::copy(&mut sftp.open(Path::new(&path))?, &mut file)?)
+        })
+        .await?
+    }
 }
@@ -1,4 +1,8 @@
-use std::{collections::HashMap, sync::Arc, time::Duration};
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use emoji::emoji;
 use poise::serenity_prelude::{
@@ -8,7 +12,7 @@ use sqlx::{MySql, Pool, QueryBuilder};
 use tokio::sync::RwLock;
 use tokio_cron_scheduler::{Job, JobBuilder};
 
-use common::Error;
+use common::{metrics, Error};
 
 const CATCOIN_AWARD_AMOUNT: u64 = 3;
 
@@ -37,11 +41,13 @@ impl YapTracker {
         if self.cache.is_empty() {
             return Ok(());
         }
-        let mut qb = QueryBuilder::new("INSERT INTO `yapawards` (`uid`, `count`)");
+        let mut qb = QueryBuilder::new("INSERT INTO `yapawards` (`uid`, `count`, `lifetime`)");
         qb.push_values(self.cache.iter(), |mut b, (from, count)| {
-            b.push_bind(from.to_string()).push_bind(count);
+            b.push_bind(from.to_string())
+                .push_bind(count)
+                .push_bind(count);
         });
-        qb.push("ON DUPLICATE KEY UPDATE `count` = `count` + VALUES(`count`)");
+        qb.push("ON DUPLICATE KEY UPDATE `count` = `count` + VALUES(`count`), `lifetime` = `lifetime` + VALUES(`lifetime`)");
         let q = qb.build();
         q.execute(db).await?;
         self.cache.clear();
@@ -117,12 +123,16 @@ pub fn init(tracker: Arc<RwLock<YapTracker>>, pool: &Pool<MySql>) {
         loop {
             interval.tick().await;
 
-            let _ = tracker
+            let start = Instant::now();
+            let flushed = tracker
                 .write()
                 .await
                 .flush_to_db(&db)
                 .await
                 .inspect_err(|e| log::error!("could not flush yap to db: {e:?}"));
+            if flushed.is_ok() {
+                metrics::flushed("yap_flush", start.elapsed());
+            }
         }
     });
 }
@@ -148,8 +158,14 @@ pub fn start_job(http: Arc<serenity::Http>, channel: ChannelId, db: Pool<MySql>)
                 };
 
                 if let Some(x) = awards.top10.get(3) {
-                    let _ = catcoin::grant_catcoin(&db, x.0, CATCOIN_AWARD_AMOUNT)
-                        .await
+                    let _ = catcoin::grant_catcoin(
+                        &db,
+                        x.0,
+                        CATCOIN_AWARD_AMOUNT,
+                        catcoin::ledger::Reason::YapAward,
+                        None,
+                    )
+                    .await
                         .inspect_err(|e| log::error!("Could not grant yap catcoin: {e}"));
                     let _ = channel
                         .send_message(